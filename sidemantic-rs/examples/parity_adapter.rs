@@ -667,6 +667,9 @@ fn metric_aggregation_name(aggregation: Option<&Aggregation>) -> &'static str {
         Some(Aggregation::StddevPop) => "stddev_pop",
         Some(Aggregation::Variance) => "variance",
         Some(Aggregation::VariancePop) => "variance_pop",
+        Some(Aggregation::Percentile(_)) => "percentile",
+        Some(Aggregation::ApproxPercentile(_)) => "approx_percentile",
+        Some(Aggregation::ApproxCountDistinct) => "approx_count_distinct",
         Some(Aggregation::Expression) | None => "sum",
     }
 }
@@ -8,6 +8,8 @@
 #define SIDEMANTIC_H
 
 #include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
 
 #ifdef __cplusplus
 extern "C" {
@@ -20,6 +22,17 @@ typedef struct {
     bool was_rewritten; /* Whether the query was rewritten (false = passthrough) */
 } SidemanticRewriteResult;
 
+/*
+ * Create an isolated session with its own models. Returns an opaque id for
+ * the sidemantic_session_* functions; release it with sidemantic_session_free().
+ * The functions without a session argument use a shared default session.
+ */
+uint64_t sidemantic_session_new(void);
+void sidemantic_session_free(uint64_t session);
+char *sidemantic_session_load_yaml(uint64_t session, const char *yaml);
+char *sidemantic_session_define(uint64_t session, const char *definition_sql, const char *db_path, bool replace);
+SidemanticRewriteResult sidemantic_session_rewrite(uint64_t session, const char *sql);
+
 /*
  * Load semantic models from YAML string.
  *
@@ -117,6 +130,25 @@ bool sidemantic_is_model_for_context(const char *context, const char *table_name
 char *sidemantic_list_models(void);
 char *sidemantic_list_models_for_context(const char *context);
 
+/*
+ * Describe registered models as JSON: {"models": [{"name", "table",
+ * "primary_key", "description", "dimensions", "metrics"}, ...]}.
+ *
+ * Caller must free the returned string with sidemantic_free().
+ */
+char *sidemantic_describe(void);
+char *sidemantic_describe_for_context(const char *context);
+
+/*
+ * Serialize the full semantic graph as JSON: {"models", "metrics",
+ * "table_calculations", "parameters"}, with every model's dimensions,
+ * metrics, relationships, and segments.
+ *
+ * Caller must free the returned string with sidemantic_free().
+ */
+char *sidemantic_describe_json(void);
+char *sidemantic_describe_json_for_context(const char *context);
+
 /*
  * Rewrite a SQL query using semantic definitions.
  *
@@ -126,6 +158,51 @@ char *sidemantic_list_models_for_context(const char *context);
 SidemanticRewriteResult sidemantic_rewrite(const char *sql);
 SidemanticRewriteResult sidemantic_rewrite_for_context(const char *context, const char *sql);
 
+/*
+ * Drop one model from the session and, when db_path is given, from its
+ * definitions file. Errors if the model is not loaded or other models or
+ * metrics still reference it.
+ *
+ * Returns NULL on success, error message on failure.
+ * Caller must free the returned string with sidemantic_free().
+ */
+char *sidemantic_drop_model(const char *model_name, const char *db_path);
+char *sidemantic_drop_model_for_context(const char *context, const char *model_name, const char *db_path);
+
+/*
+ * Dry-run a MODEL definition against a copy of the current graph without
+ * storing it or writing any file. `replace` follows sidemantic_define: when
+ * false, a model with the same name is an error. Only errors introduced by
+ * the definition are reported.
+ *
+ * Returns NULL if valid, error message otherwise.
+ * Caller must free the returned string with sidemantic_free().
+ */
+char *sidemantic_validate(const char *definition_sql, bool replace);
+char *sidemantic_validate_for_context(const char *context, const char *definition_sql, bool replace);
+
+/*
+ * Compile a JSON-encoded semantic query ({"metrics", "dimensions",
+ * "filters", "segments", "order_by", "limit"}) into SQL. Unknown keys
+ * are rejected.
+ *
+ * Returns a SidemanticRewriteResult struct.
+ * Caller must free with sidemantic_free_result().
+ */
+SidemanticRewriteResult sidemantic_compile_query(const char *query_json);
+SidemanticRewriteResult sidemantic_compile_query_for_context(const char *context, const char *query_json);
+
+/*
+ * Rewrite several queries while taking the graph lock once. `queries` holds
+ * `len` bytes of NUL-separated queries (newline-separated if it contains no
+ * NUL). Returns a JSON array with one {"sql", "error", "was_rewritten"}
+ * object per entry; a null buffer yields a single error object.
+ *
+ * Caller must free the returned string with sidemantic_free().
+ */
+char *sidemantic_rewrite_batch(const char *queries, size_t len);
+char *sidemantic_rewrite_batch_for_context(const char *context, const char *queries, size_t len);
+
 /*
  * Free a string returned by sidemantic functions.
  */
@@ -132,11 +132,13 @@ impl CubeDefinition {
             name: self.name,
             table: self.sql_table,
             sql: self.sql,
+            columns: None,
             source_uri: None,
             extends: None,
             primary_key: primary_key.clone(),
             primary_key_columns: vec![primary_key],
             unique_keys: None,
+            grain: Vec::new(),
             dimensions: self
                 .dimensions
                 .into_iter()
@@ -147,8 +149,9 @@ impl CubeDefinition {
             segments: self
                 .segments
                 .into_iter()
-                .map(|s| s.into_segment())
+                .map(|s| s.into_segment(&self_name))
                 .collect(),
+            default_filters: Vec::new(),
             pre_aggregations: Vec::new(),
             default_time_dimension: None,
             default_grain: None,
@@ -156,6 +159,7 @@ impl CubeDefinition {
             description: self.description,
             metadata: None,
             meta: None,
+            tags: Vec::new(),
         }
     }
 }
@@ -184,8 +188,13 @@ impl CubeDimension {
             meta: None,
             format: None,
             value_format_name: None,
+            value_labels: None,
+            null_label: None,
             parent: None,
             window: None,
+            reference: None,
+            cardinality_hint: None,
+            tags: Vec::new(),
             public: true,
         }
     }
@@ -240,6 +249,8 @@ impl CubeMeasure {
             window_expression: None,
             window_frame: None,
             window_order: None,
+            window_periods: None,
+            window_grain: None,
             base_metric: None,
             comparison_type: None,
             time_offset: None,
@@ -261,15 +272,21 @@ impl CubeMeasure {
             value_format_name: None,
             drill_fields: None,
             non_additive_dimension: None,
+            non_additive_window: Default::default(),
+            tags: Vec::new(),
             public: true,
         }
     }
 }
 
 impl CubeSegment {
-    fn into_segment(self) -> Segment {
-        // Convert ${CUBE} to {model} for our segment format
-        let sql = self.sql.replace("${CUBE}", "{model}");
+    fn into_segment(self, cube_name: &str) -> Segment {
+        // Convert ${CUBE} (and self references like ${orders}) to {model} so
+        // Segment::get_sql can substitute the query alias.
+        let sql = self
+            .sql
+            .replace("${CUBE}", "{model}")
+            .replace(&format!("${{{cube_name}}}"), "{model}");
 
         Segment {
             name: self.name,
@@ -481,6 +498,32 @@ cubes:
         assert_eq!(orders.segments[0].sql, "{model}.status = 'completed'");
     }
 
+    #[test]
+    fn test_cube_segment_self_reference_uses_model_placeholder() {
+        let yaml = r#"
+cubes:
+  - name: orders
+    sql_table: orders
+    segments:
+      - name: large
+        sql: "${orders}.amount > 100 AND ${CUBE}.status != 'void'"
+        description: Large orders
+"#;
+        let config: CubeConfig = serde_yaml::from_str(yaml).unwrap();
+        let models = config.into_models();
+        let segment = &models[0].segments[0];
+        assert_eq!(segment.name, "large");
+        assert_eq!(
+            segment.sql,
+            "{model}.amount > 100 AND {model}.status != 'void'"
+        );
+        assert_eq!(segment.description.as_deref(), Some("Large orders"));
+        assert_eq!(
+            segment.get_sql("orders_cte"),
+            "orders_cte.amount > 100 AND orders_cte.status != 'void'"
+        );
+    }
+
     #[test]
     fn test_strip_cube_placeholder() {
         assert_eq!(strip_cube_placeholder("${CUBE}.status"), "status");
@@ -0,0 +1,796 @@
+//! LookML adapter: imports `view:` and `explore:` definitions (`.lkml`) into
+//! the semantic graph.
+//!
+//! Views become [`Model`]s: `dimension`s and time `dimension_group`s become
+//! dimensions and `measure`s become metrics. `explore` joins become
+//! relationships on the joining view. `${TABLE}.col` and `${field}`
+//! references in `sql:` are rewritten to plain SQL.
+//!
+//! Only a subset of LookML is understood: refinements (`view: +name`),
+//! `extends`, liquid templating and non-time dimension groups are not.
+
+use std::collections::{HashMap, HashSet};
+
+use regex::Regex;
+
+use crate::core::{
+    Aggregation, Dimension, DimensionType, Metric, MetricType, Model, Relationship,
+    RelationshipType,
+};
+use crate::error::{Result, SidemanticError};
+
+use super::{Adapter, ParsedDocument};
+
+/// Adapter for importing LookML views and explores.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LookMLAdapter;
+
+impl LookMLAdapter {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Adapter for LookMLAdapter {
+    fn parse_document(&self, content: &str) -> Result<ParsedDocument> {
+        let root = Parser::new(content).parse_body(false)?;
+
+        let views: Vec<View<'_>> = root
+            .iter()
+            .filter_map(|(key, value)| match (key.as_str(), value) {
+                (
+                    "view",
+                    LkValue::Block {
+                        name: Some(name),
+                        body,
+                    },
+                ) if !name.starts_with('+') => Some(View::new(name, body)),
+                _ => None,
+            })
+            .collect();
+        let views_by_name: HashMap<&str, &View<'_>> =
+            views.iter().map(|view| (view.name, view)).collect();
+
+        let mut doc = ParsedDocument {
+            explicit_relationships: true,
+            ..Default::default()
+        };
+        for view in &views {
+            doc.models.push(view.to_model()?);
+        }
+        for (key, value) in &root {
+            if let (
+                "explore",
+                LkValue::Block {
+                    name: Some(name),
+                    body,
+                },
+            ) = (key.as_str(), value)
+            {
+                doc.relationships
+                    .extend(explore_relationships(name, body, &views_by_name)?);
+            }
+        }
+        Ok(doc)
+    }
+}
+
+// =============================================================================
+// LookML syntax
+// =============================================================================
+
+/// A parsed LookML value.
+#[derive(Debug, Clone, PartialEq)]
+enum LkValue {
+    /// A bare token, quoted string or `;;`-terminated SQL expression
+    Scalar(String),
+    /// `[a, b]`, or `[field: "value", ...]` for filters
+    List(Vec<(Option<String>, String)>),
+    /// `key: name { ... }` or `key: { ... }`
+    Block {
+        name: Option<String>,
+        body: Vec<(String, LkValue)>,
+    },
+}
+
+/// Keys whose value is raw text terminated by `;;`.
+fn is_expression_key(key: &str) -> bool {
+    key.starts_with("sql") || key.ends_with("_sql") || key == "html" || key == "expression"
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Self { src, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn error(&self, message: &str) -> SidemanticError {
+        let line = self.src[..self.pos].matches('\n').count() + 1;
+        SidemanticError::Validation(format!("LookML parse error on line {line}: {message}"))
+    }
+
+    /// Skip whitespace and `#` comments.
+    fn skip_trivia(&mut self) {
+        loop {
+            let trimmed = self.rest().trim_start();
+            self.pos = self.src.len() - trimmed.len();
+            if trimmed.starts_with('#') {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        self.skip_trivia();
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{c}'")))
+        }
+    }
+
+    fn parse_body(&mut self, nested: bool) -> Result<Vec<(String, LkValue)>> {
+        let mut pairs = Vec::new();
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                None if nested => return Err(self.error("unterminated block")),
+                None => return Ok(pairs),
+                Some('}') if nested => {
+                    self.pos += 1;
+                    return Ok(pairs);
+                }
+                _ => {}
+            }
+            let key = self.parse_token()?;
+            self.expect(':')?;
+            let value = if is_expression_key(&key) {
+                self.parse_expression()?
+            } else {
+                self.parse_value()?
+            };
+            pairs.push((key, value));
+        }
+    }
+
+    fn parse_token(&mut self) -> Result<String> {
+        let len = self
+            .rest()
+            .find(|c: char| c.is_whitespace() || matches!(c, ':' | '{' | '}' | '[' | ']' | ','))
+            .unwrap_or(self.rest().len());
+        if len == 0 {
+            return Err(self.error("expected a name"));
+        }
+        let token = self.rest()[..len].to_string();
+        self.pos += len;
+        Ok(token)
+    }
+
+    fn parse_expression(&mut self) -> Result<LkValue> {
+        let Some(end) = self.rest().find(";;") else {
+            return Err(self.error("expected ';;' after SQL expression"));
+        };
+        let sql = self.rest()[..end].trim().to_string();
+        self.pos += end + 2;
+        Ok(LkValue::Scalar(sql))
+    }
+
+    fn parse_string(&mut self) -> Result<String> {
+        self.pos += 1;
+        let mut out = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Ok(out);
+                }
+                '\\' => {
+                    if let Some((_, escaped)) = chars.next() {
+                        out.push(escaped);
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    fn parse_atom(&mut self) -> Result<String> {
+        self.skip_trivia();
+        if self.peek() == Some('"') {
+            self.parse_string()
+        } else {
+            self.parse_token()
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<LkValue> {
+        self.skip_trivia();
+        match self.peek() {
+            Some('{') => {
+                self.pos += 1;
+                Ok(LkValue::Block {
+                    name: None,
+                    body: self.parse_body(true)?,
+                })
+            }
+            Some('[') => {
+                self.pos += 1;
+                self.parse_list()
+            }
+            _ => {
+                let atom = self.parse_atom()?;
+                self.skip_trivia();
+                if self.peek() == Some('{') {
+                    self.pos += 1;
+                    Ok(LkValue::Block {
+                        name: Some(atom),
+                        body: self.parse_body(true)?,
+                    })
+                } else {
+                    Ok(LkValue::Scalar(atom))
+                }
+            }
+        }
+    }
+
+    fn parse_list(&mut self) -> Result<LkValue> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(LkValue::List(items));
+                }
+                Some(',') => self.pos += 1,
+                None => return Err(self.error("unterminated list")),
+                _ => {
+                    let atom = self.parse_atom()?;
+                    self.skip_trivia();
+                    if self.peek() == Some(':') {
+                        self.pos += 1;
+                        items.push((Some(atom), self.parse_atom()?));
+                    } else {
+                        items.push((None, atom));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn scalar<'a>(body: &'a [(String, LkValue)], key: &str) -> Option<&'a str> {
+    body.iter().find_map(|(k, v)| match v {
+        LkValue::Scalar(s) if k == key => Some(s.as_str()),
+        _ => None,
+    })
+}
+
+fn list<'a>(body: &'a [(String, LkValue)], key: &str) -> &'a [(Option<String>, String)] {
+    body.iter()
+        .find_map(|(k, v)| match v {
+            LkValue::List(items) if k == key => Some(items.as_slice()),
+            _ => None,
+        })
+        .unwrap_or(&[])
+}
+
+fn blocks<'a>(
+    body: &'a [(String, LkValue)],
+    key: &'a str,
+) -> impl Iterator<Item = (&'a str, &'a [(String, LkValue)])> + 'a {
+    body.iter().filter_map(move |(k, v)| match v {
+        LkValue::Block {
+            name: Some(name),
+            body,
+        } if k == key => Some((name.as_str(), body.as_slice())),
+        _ => None,
+    })
+}
+
+fn is_yes(body: &[(String, LkValue)], key: &str) -> bool {
+    scalar(body, key) == Some("yes")
+}
+
+// =============================================================================
+// Conversion to Core Types
+// =============================================================================
+
+struct View<'a> {
+    name: &'a str,
+    body: &'a [(String, LkValue)],
+    /// Dimension (and dimension group) name -> raw LookML SQL
+    field_sql: HashMap<&'a str, &'a str>,
+    dimension_groups: Vec<&'a str>,
+    measures: HashSet<&'a str>,
+}
+
+impl<'a> View<'a> {
+    fn new(name: &'a str, body: &'a [(String, LkValue)]) -> Self {
+        let mut field_sql = HashMap::new();
+        for (dim_name, dim_body) in blocks(body, "dimension") {
+            field_sql.insert(dim_name, scalar(dim_body, "sql").unwrap_or(""));
+        }
+        let mut dimension_groups = Vec::new();
+        for (group_name, group_body) in blocks(body, "dimension_group") {
+            field_sql.insert(group_name, scalar(group_body, "sql").unwrap_or(""));
+            dimension_groups.push(group_name);
+        }
+        let measures = blocks(body, "measure").map(|(name, _)| name).collect();
+        Self {
+            name,
+            body,
+            field_sql,
+            dimension_groups,
+            measures,
+        }
+    }
+
+    /// Rewrite `${TABLE}.col` to `col`, `${field}` / `${view.field}` on this
+    /// view to the field's SQL (measures to their metric name), and
+    /// `${other_view.field}` to `other_view.field`.
+    fn normalize_sql(&self, sql: &str) -> String {
+        self.normalize_sql_inner(sql, &mut HashSet::new())
+    }
+
+    fn normalize_sql_inner(&self, sql: &str, visiting: &mut HashSet<&'a str>) -> String {
+        let reference =
+            Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)(?:\.([A-Za-z_][A-Za-z0-9_]*))?\}")
+                .expect("valid LookML reference regex");
+        let sql = sql.replace("${TABLE}.", "").replace("${TABLE}", "");
+        reference
+            .replace_all(&sql, |caps: &regex::Captures| {
+                let (view, field) = match caps.get(2) {
+                    Some(field) => (&caps[1], field.as_str()),
+                    None => (self.name, &caps[1]),
+                };
+                if view != self.name {
+                    return format!("{view}.{field}");
+                }
+                self.field_expression(field, visiting)
+            })
+            .into_owned()
+    }
+
+    fn field_expression(&self, field: &str, visiting: &mut HashSet<&'a str>) -> String {
+        if self.measures.contains(field) {
+            return field.to_string();
+        }
+        // `${created_date}` refers to a timeframe of dimension group `created`.
+        let field_key = self
+            .field_sql
+            .get_key_value(field)
+            .map(|(key, _)| *key)
+            .or_else(|| {
+                self.dimension_groups.iter().copied().find(|group| {
+                    field
+                        .strip_prefix(*group)
+                        .is_some_and(|suffix| suffix.starts_with('_'))
+                })
+            });
+        let Some(field_key) = field_key else {
+            return field.to_string();
+        };
+        let raw = self.field_sql[field_key];
+        if raw.is_empty() || !visiting.insert(field_key) {
+            return field_key.to_string();
+        }
+        let expr = self.normalize_sql_inner(raw, visiting);
+        visiting.remove(field_key);
+        if is_identifier(&expr) {
+            expr
+        } else {
+            format!("({expr})")
+        }
+    }
+
+    /// Column a join key reference resolves to on this view.
+    fn key_column(&self, field: &str) -> String {
+        let expr = self.field_expression(field, &mut HashSet::new());
+        if is_identifier(&expr) {
+            expr
+        } else {
+            field.to_string()
+        }
+    }
+
+    fn primary_key(&self) -> String {
+        blocks(self.body, "dimension")
+            .find(|(_, body)| is_yes(body, "primary_key"))
+            .map(|(name, _)| self.key_column(name))
+            .unwrap_or_else(|| "id".to_string())
+    }
+
+    fn to_model(&self) -> Result<Model> {
+        let mut model = Model::new(self.name, self.primary_key());
+        model.table = scalar(self.body, "sql_table_name").map(str::to_string);
+        model.sql = self.body.iter().find_map(|(key, value)| match value {
+            LkValue::Block { body, .. } if key == "derived_table" => {
+                scalar(body, "sql").map(|sql| self.normalize_sql(sql))
+            }
+            _ => None,
+        });
+        model.label = scalar(self.body, "label").map(str::to_string);
+        model.description = scalar(self.body, "description").map(str::to_string);
+
+        for (name, body) in blocks(self.body, "dimension") {
+            let r#type = match scalar(body, "type") {
+                Some("number") => DimensionType::Numeric,
+                Some("yesno") => DimensionType::Boolean,
+                Some("date" | "date_time" | "time") => DimensionType::Time,
+                _ => DimensionType::Categorical,
+            };
+            let mut dimension = Dimension {
+                r#type,
+                sql: scalar(body, "sql").map(|sql| self.normalize_sql(sql)),
+                ..Dimension::new(name)
+            };
+            dimension.label = scalar(body, "label").map(str::to_string);
+            dimension.description = scalar(body, "description").map(str::to_string);
+            dimension.public = !is_yes(body, "hidden");
+            model.dimensions.push(dimension);
+        }
+
+        for (name, body) in blocks(self.body, "dimension_group") {
+            model.dimensions.push(self.time_dimension(name, body)?);
+        }
+
+        for (name, body) in blocks(self.body, "measure") {
+            model.metrics.push(self.metric(name, body)?);
+        }
+
+        Ok(model)
+    }
+
+    fn time_dimension(&self, name: &str, body: &[(String, LkValue)]) -> Result<Dimension> {
+        if let Some(other) = scalar(body, "type").filter(|t| *t != "time") {
+            return Err(SidemanticError::Validation(format!(
+                "LookML dimension_group '{}.{name}' has unsupported type '{other}'",
+                self.name
+            )));
+        }
+        const GRAINS: [&str; 8] = [
+            "second", "minute", "hour", "day", "week", "month", "quarter", "year",
+        ];
+        let timeframes: Vec<&str> = list(body, "timeframes")
+            .iter()
+            .map(|(_, timeframe)| match timeframe.as_str() {
+                "date" => "day",
+                other => other,
+            })
+            .collect();
+        let supported: Vec<String> = GRAINS
+            .iter()
+            .filter(|grain| timeframes.contains(*grain))
+            .map(|grain| grain.to_string())
+            .collect();
+
+        let mut dimension = Dimension::time(name);
+        dimension.sql = scalar(body, "sql").map(|sql| self.normalize_sql(sql));
+        dimension.granularity = Some(
+            supported
+                .first()
+                .cloned()
+                .unwrap_or_else(|| "day".to_string()),
+        );
+        if !supported.is_empty() {
+            dimension.supported_granularities = Some(supported);
+        }
+        dimension.label = scalar(body, "label").map(str::to_string);
+        dimension.description = scalar(body, "description").map(str::to_string);
+        dimension.public = !is_yes(body, "hidden");
+        Ok(dimension)
+    }
+
+    fn metric(&self, name: &str, body: &[(String, LkValue)]) -> Result<Metric> {
+        let sql = scalar(body, "sql").map(|sql| self.normalize_sql(sql));
+        let measure_type = scalar(body, "type").unwrap_or("count");
+        let agg = match measure_type {
+            "count" => Aggregation::Count,
+            "count_distinct" => Aggregation::CountDistinct,
+            "sum" => Aggregation::Sum,
+            "average" => Aggregation::Avg,
+            "min" => Aggregation::Min,
+            "max" => Aggregation::Max,
+            "median" => Aggregation::Median,
+            "percentile" => {
+                let percentile = scalar(body, "percentile")
+                    .and_then(|p| p.parse::<f64>().ok())
+                    .ok_or_else(|| {
+                        SidemanticError::Validation(format!(
+                            "LookML measure '{}.{name}' of type percentile requires 'percentile'",
+                            self.name
+                        ))
+                    })?;
+                Aggregation::Percentile(percentile / 100.0)
+            }
+            "number" => Aggregation::Expression,
+            other => {
+                return Err(SidemanticError::Validation(format!(
+                    "LookML measure '{}.{name}' has unsupported type '{other}'",
+                    self.name
+                )))
+            }
+        };
+
+        let mut metric = if agg == Aggregation::Expression {
+            Metric {
+                r#type: MetricType::Derived,
+                agg: None,
+                sql,
+                ..Metric::new(name)
+            }
+        } else {
+            Metric {
+                agg: Some(agg),
+                // A bare `type: count` counts rows.
+                sql: sql.filter(|_| measure_type != "count"),
+                ..Metric::new(name)
+            }
+        };
+        metric.label = scalar(body, "label").map(str::to_string);
+        metric.description = scalar(body, "description").map(str::to_string);
+        metric.value_format_name = scalar(body, "value_format_name").map(str::to_string);
+        metric.public = !is_yes(body, "hidden");
+        for (field, value) in list(body, "filters") {
+            let Some(field) = field else { continue };
+            let field = field.rsplit('.').next().unwrap_or(field);
+            let column = self.field_expression(field, &mut HashSet::new());
+            metric.filters.push(filter_condition(&column, value));
+        }
+        Ok(metric)
+    }
+}
+
+/// Translate a basic LookML filter expression (`value`, `-value`, `>10`,
+/// `NULL`, `-NULL`) on `column` into SQL.
+fn filter_condition(column: &str, value: &str) -> String {
+    let value = value.trim();
+    let numeric = Regex::new(r"^(>=|<=|<>|!=|>|<|=)?\s*(-?\d+(?:\.\d+)?)$").unwrap();
+    if value.eq_ignore_ascii_case("NULL") {
+        return format!("{column} IS NULL");
+    }
+    if value.eq_ignore_ascii_case("-NULL") {
+        return format!("{column} IS NOT NULL");
+    }
+    if let Some(caps) = numeric.captures(value) {
+        let op = caps.get(1).map_or("=", |m| m.as_str());
+        return format!("{column} {op} {}", &caps[2]);
+    }
+    if value.eq_ignore_ascii_case("yes") || value.eq_ignore_ascii_case("no") {
+        let truth = value.eq_ignore_ascii_case("yes");
+        return format!("{column} = {}", if truth { "TRUE" } else { "FALSE" });
+    }
+    let (negated, literal) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    };
+    let quoted = format!("'{}'", literal.replace('\'', "''"));
+    if negated {
+        format!("{column} <> {quoted}")
+    } else {
+        format!("{column} = {quoted}")
+    }
+}
+
+fn is_identifier(sql: &str) -> bool {
+    let mut chars = sql.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Relationships declared by an explore's joins, keyed by joining view.
+fn explore_relationships(
+    explore: &str,
+    body: &[(String, LkValue)],
+    views: &HashMap<&str, &View<'_>>,
+) -> Result<Vec<(String, Relationship)>> {
+    let base = scalar(body, "from")
+        .or_else(|| scalar(body, "view_name"))
+        .unwrap_or(explore);
+    let reference = Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\.([A-Za-z_][A-Za-z0-9_]*)\}")
+        .expect("valid LookML reference regex");
+    let key_column = |view: &str, field: &str| match views.get(view) {
+        Some(view) => view.key_column(field),
+        None => field.to_string(),
+    };
+
+    let mut relationships = Vec::new();
+    for (join_name, join) in blocks(body, "join") {
+        let target = scalar(join, "from").unwrap_or(join_name);
+        let r#type = match scalar(join, "relationship").unwrap_or("many_to_one") {
+            "many_to_one" => RelationshipType::ManyToOne,
+            "one_to_one" => RelationshipType::OneToOne,
+            "one_to_many" => RelationshipType::OneToMany,
+            "many_to_many" => RelationshipType::ManyToMany,
+            other => {
+                return Err(SidemanticError::Validation(format!(
+                    "LookML explore '{explore}' join '{join_name}' has unsupported relationship '{other}'"
+                )))
+            }
+        };
+        let mut rel = Relationship::new(target);
+        rel.r#type = r#type.clone();
+
+        if let Some(foreign_key) = scalar(join, "foreign_key") {
+            let foreign_key =
+                key_column(base, foreign_key.rsplit('.').next().unwrap_or(foreign_key));
+            // A target view from another file gets its primary key once the
+            // loader has every model.
+            rel = match views.get(target) {
+                Some(view) => rel.with_keys(foreign_key, view.primary_key()),
+                None => {
+                    rel.foreign_key_columns = Some(vec![foreign_key.clone()]);
+                    rel.foreign_key = Some(foreign_key);
+                    rel
+                }
+            };
+            relationships.push((base.to_string(), rel));
+            continue;
+        }
+
+        let Some(sql_on) = scalar(join, "sql_on") else {
+            return Err(SidemanticError::Validation(format!(
+                "LookML explore '{explore}' join '{join_name}' needs 'sql_on' or 'foreign_key'"
+            )));
+        };
+        // The joining side is whichever other view the condition references.
+        let source = reference
+            .captures_iter(sql_on)
+            .map(|caps| caps.get(1).unwrap().as_str())
+            .find(|view| *view != join_name && *view != target)
+            .unwrap_or(base)
+            .to_string();
+
+        let refs: Vec<(String, String)> = reference
+            .captures_iter(sql_on)
+            .map(|caps| (caps[1].to_string(), caps[2].to_string()))
+            .collect();
+        let residual: String = reference
+            .replace_all(sql_on, "@")
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        let is_target = |view: &str| view == join_name || view == target;
+        let simple_keys = match refs.as_slice() {
+            [(a, a_field), (b, b_field)] if residual == "@=@" => {
+                if *a == source && is_target(b.as_str()) {
+                    Some((key_column(a, a_field), key_column(target, b_field)))
+                } else if is_target(a.as_str()) && *b == source {
+                    Some((key_column(b, b_field), key_column(target, a_field)))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+
+        rel = match (simple_keys, &r#type) {
+            (Some((from_col, to_col)), RelationshipType::ManyToOne) => {
+                rel.with_keys(from_col, to_col)
+            }
+            (Some((from_col, to_col)), RelationshipType::OneToMany) => {
+                // FK lives on the joined view, local key on this one.
+                rel.with_keys(to_col, from_col)
+            }
+            _ => {
+                let condition = reference.replace_all(sql_on, |caps: &regex::Captures| {
+                    let (side, view) = if caps[1] == source {
+                        ("from", &caps[1])
+                    } else if is_target(&caps[1]) {
+                        ("to", target)
+                    } else {
+                        return caps[0].to_string();
+                    };
+                    format!("{{{side}}}.{}", key_column(view, &caps[2]))
+                });
+                rel.with_condition(condition)
+            }
+        };
+        relationships.push((source, rel));
+    }
+    Ok(relationships)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lookml_syntax() {
+        let lookml = r#"
+# comment
+view: events {
+  derived_table: {
+    sql: SELECT * FROM raw.events WHERE kind <> 'test' ;;
+  }
+  dimension: id { primary_key: yes }
+  link: { label: "Docs" url: "https://example.com" }
+  measure: total { type: sum sql: ${TABLE}.value ;; filters: [id: ">10", kind: "-test"] }
+}
+"#;
+        let root = Parser::new(lookml).parse_body(false).unwrap();
+        assert_eq!(root.len(), 1);
+
+        let doc = LookMLAdapter::new().parse_document(lookml).unwrap();
+        let events = &doc.models[0];
+        assert_eq!(events.name, "events");
+        assert_eq!(
+            events.sql.as_deref(),
+            Some("SELECT * FROM raw.events WHERE kind <> 'test'")
+        );
+        assert_eq!(
+            events.get_metric("total").unwrap().filters,
+            vec!["id > 10".to_string(), "kind <> 'test'".to_string()]
+        );
+
+        let err = LookMLAdapter::new()
+            .parse_document("view: broken {\n  dimension: id {\n")
+            .unwrap_err();
+        assert!(err.to_string().contains("unterminated block"), "{err}");
+    }
+
+    #[test]
+    fn test_explore_joins_become_relationships() {
+        // Views live in another file; joins still resolve by name.
+        let lookml = r#"
+explore: orders {
+  join: users {
+    relationship: many_to_one
+    foreign_key: user_id
+  }
+  join: order_items {
+    relationship: one_to_many
+    sql_on: ${orders.id} = ${order_items.order_id} ;;
+  }
+  join: promotions {
+    relationship: many_to_one
+    sql_on: ${orders.promo_code} = ${promotions.code}
+      AND ${orders.created_raw} BETWEEN ${promotions.start_date} AND ${promotions.end_date} ;;
+  }
+}
+"#;
+        let doc = LookMLAdapter::new().parse_document(lookml).unwrap();
+        assert!(doc.models.is_empty());
+        let rels: HashMap<&str, &Relationship> = doc
+            .relationships
+            .iter()
+            .map(|(source, rel)| {
+                assert_eq!(source, "orders");
+                (rel.name.as_str(), rel)
+            })
+            .collect();
+
+        assert_eq!(rels["users"].foreign_key.as_deref(), Some("user_id"));
+        assert_eq!(rels["users"].primary_key, None);
+        let items = rels["order_items"];
+        assert_eq!(items.r#type, RelationshipType::OneToMany);
+        assert_eq!(items.foreign_key.as_deref(), Some("order_id"));
+        assert_eq!(items.primary_key.as_deref(), Some("id"));
+        assert_eq!(
+            rels["promotions"].sql.as_deref(),
+            Some(
+                "{from}.promo_code = {to}.code\n      AND {from}.created_raw BETWEEN {to}.start_date AND {to}.end_date"
+            )
+        );
+    }
+}
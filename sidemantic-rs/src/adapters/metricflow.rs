@@ -0,0 +1,793 @@
+//! dbt MetricFlow adapter: imports Semantic Layer YAML (`semantic_models:`)
+//! into the semantic graph.
+//!
+//! Semantic models become [`Model`]s, measures become simple metrics on their
+//! semantic model, and foreign entities become `many_to_one` relationships to
+//! the semantic model whose primary entity they name. Top-level `metrics` are
+//! attached to the model owning the measure (or first input metric) they are
+//! built on, with references qualified as `model.name`.
+
+use std::collections::HashMap;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::core::{Aggregation, Dimension, DimensionType, Metric, Model, Relationship, TimeGrain};
+use crate::error::{Result, SidemanticError};
+
+use super::{Adapter, ForeignEntity, ParsedDocument};
+
+/// Adapter for importing dbt MetricFlow semantic definitions.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MetricFlowAdapter;
+
+impl MetricFlowAdapter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Parse MetricFlow YAML content into core models.
+    pub fn parse_models(&self, content: &str) -> Result<Vec<Model>> {
+        Ok(self.parse_document(content)?.models)
+    }
+}
+
+impl Adapter for MetricFlowAdapter {
+    fn parse_document(&self, content: &str) -> Result<ParsedDocument> {
+        let config: MetricFlowConfig = serde_yaml::from_str(content).map_err(|e| {
+            SidemanticError::Validation(format!("MetricFlow YAML parse error: {e}"))
+        })?;
+        config.into_document()
+    }
+}
+
+// =============================================================================
+// MetricFlow Format Schema
+// =============================================================================
+
+/// Root schema for MetricFlow YAML files
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct MetricFlowConfig {
+    #[serde(default)]
+    pub semantic_models: Vec<MfSemanticModel>,
+    #[serde(default)]
+    pub metrics: Vec<MfMetric>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MfSemanticModel {
+    pub name: String,
+    /// dbt model reference, e.g. `ref('orders')`
+    pub model: Option<String>,
+    pub label: Option<String>,
+    pub description: Option<String>,
+    pub defaults: Option<MfDefaults>,
+    #[serde(default)]
+    pub entities: Vec<MfEntity>,
+    #[serde(default)]
+    pub dimensions: Vec<MfDimension>,
+    #[serde(default)]
+    pub measures: Vec<MfMeasure>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MfDefaults {
+    pub agg_time_dimension: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MfEntity {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub entity_type: String,
+    pub expr: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MfDimension {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub dim_type: Option<String>,
+    pub expr: Option<serde_yaml::Value>,
+    pub label: Option<String>,
+    pub description: Option<String>,
+    pub type_params: Option<MfDimensionTypeParams>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MfDimensionTypeParams {
+    pub time_granularity: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MfMeasure {
+    pub name: String,
+    pub agg: Option<String>,
+    pub expr: Option<serde_yaml::Value>,
+    pub label: Option<String>,
+    pub description: Option<String>,
+    pub agg_params: Option<MfAggParams>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MfAggParams {
+    pub percentile: Option<f64>,
+    #[serde(default)]
+    pub use_approximate_percentile: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MfMetric {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub metric_type: Option<String>,
+    pub label: Option<String>,
+    pub description: Option<String>,
+    pub filter: Option<String>,
+    #[serde(default)]
+    pub type_params: MfMetricTypeParams,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct MfMetricTypeParams {
+    pub measure: Option<MfInput>,
+    pub numerator: Option<MfInput>,
+    pub denominator: Option<MfInput>,
+    pub expr: Option<String>,
+    #[serde(default)]
+    pub metrics: Vec<MfInput>,
+    pub window: Option<String>,
+    pub grain_to_date: Option<String>,
+    pub cumulative_type_params: Option<MfCumulativeTypeParams>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MfCumulativeTypeParams {
+    pub window: Option<String>,
+    pub grain_to_date: Option<String>,
+}
+
+/// A measure or metric input: either a bare name or a mapping with modifiers.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum MfInput {
+    Name(String),
+    Spec {
+        name: String,
+        alias: Option<String>,
+        filter: Option<String>,
+        offset_window: Option<String>,
+        offset_to_grain: Option<String>,
+    },
+}
+
+impl MfInput {
+    fn name(&self) -> &str {
+        match self {
+            MfInput::Name(name) | MfInput::Spec { name, .. } => name,
+        }
+    }
+
+    /// Name the input is referenced by in a derived expression.
+    fn reference_name(&self) -> &str {
+        match self {
+            MfInput::Spec {
+                alias: Some(alias), ..
+            } => alias,
+            _ => self.name(),
+        }
+    }
+
+    /// Reject input modifiers that have no Sidemantic equivalent.
+    fn ensure_plain(&self, metric_name: &str) -> Result<()> {
+        let MfInput::Spec {
+            name,
+            filter,
+            offset_window,
+            offset_to_grain,
+            ..
+        } = self
+        else {
+            return Ok(());
+        };
+        let modifier = if offset_window.is_some() {
+            "offset_window"
+        } else if offset_to_grain.is_some() {
+            "offset_to_grain"
+        } else if filter.is_some() {
+            "filter"
+        } else {
+            return Ok(());
+        };
+        Err(SidemanticError::Validation(format!(
+            "MetricFlow metric '{metric_name}' uses '{modifier}' on input '{name}', which is not supported"
+        )))
+    }
+}
+
+// =============================================================================
+// Conversion to Core Types
+// =============================================================================
+
+impl MetricFlowConfig {
+    /// Convert to list of core Model types
+    pub fn into_models(self) -> Result<Vec<Model>> {
+        Ok(self.into_document()?.models)
+    }
+
+    /// Convert to a parsed document. Foreign entities whose primary model is
+    /// declared in another file are left for the loader to resolve.
+    pub fn into_document(self) -> Result<ParsedDocument> {
+        // Primary entity name -> (model name, key column)
+        let primary_entities: HashMap<String, (String, String)> = self
+            .semantic_models
+            .iter()
+            .flat_map(|sm| {
+                sm.entities
+                    .iter()
+                    .filter(|entity| entity.entity_type == "primary")
+                    .map(|entity| {
+                        (
+                            entity.name.clone(),
+                            (sm.name.clone(), entity_column(entity).to_string()),
+                        )
+                    })
+            })
+            .collect();
+        let foreign_entities: Vec<ForeignEntity> = self
+            .semantic_models
+            .iter()
+            .flat_map(|sm| {
+                sm.entities
+                    .iter()
+                    .filter(|entity| {
+                        entity.entity_type == "foreign"
+                            && !primary_entities.contains_key(&entity.name)
+                    })
+                    .map(|entity| ForeignEntity {
+                        model: sm.name.clone(),
+                        entity: entity.name.clone(),
+                        column: entity_column(entity).to_string(),
+                    })
+            })
+            .collect();
+
+        let mut context = FilterContext {
+            entities: primary_entities
+                .iter()
+                .map(|(entity, (model, _))| (entity.clone(), model.clone()))
+                .collect(),
+            ..Default::default()
+        };
+        for sm in &self.semantic_models {
+            for entity in &sm.entities {
+                context
+                    .entities
+                    .entry(entity.name.clone())
+                    .or_insert_with(|| sm.name.clone());
+            }
+            for dim in &sm.dimensions {
+                let expr = dim
+                    .expr
+                    .as_ref()
+                    .and_then(yaml_scalar)
+                    .unwrap_or_else(|| dim.name.clone());
+                context
+                    .dimensions
+                    .insert((sm.name.clone(), dim.name.clone()), expr);
+            }
+        }
+
+        let mut models = Vec::with_capacity(self.semantic_models.len());
+        // Measure/metric name -> owning model name
+        let mut owners: HashMap<String, String> = HashMap::new();
+        for sm in self.semantic_models {
+            let model = sm.into_model(&primary_entities)?;
+            for metric in &model.metrics {
+                owners.insert(metric.name.clone(), model.name.clone());
+            }
+            models.push(model);
+        }
+
+        // Derived metrics may reference metrics declared after them, so keep
+        // converting until no pending metric can be resolved.
+        let mut pending = self.metrics;
+        while !pending.is_empty() {
+            let mut unresolved = Vec::new();
+            let before = pending.len();
+            for mf_metric in pending {
+                match mf_metric.resolve(&models, &owners, &context)? {
+                    Some((owner, metric)) => {
+                        let model = models
+                            .iter_mut()
+                            .find(|model| model.name == owner)
+                            .expect("owner is a parsed semantic model");
+                        owners.insert(metric.name.clone(), owner);
+                        if let Some(existing) =
+                            model.metrics.iter_mut().find(|m| m.name == metric.name)
+                        {
+                            *existing = metric;
+                        } else {
+                            model.metrics.push(metric);
+                        }
+                    }
+                    None => unresolved.push(mf_metric),
+                }
+            }
+            if unresolved.len() == before {
+                let names: Vec<&str> = unresolved.iter().map(|m| m.name.as_str()).collect();
+                return Err(SidemanticError::Validation(format!(
+                    "MetricFlow metrics reference unknown measures or metrics: {}",
+                    names.join(", ")
+                )));
+            }
+            pending = unresolved;
+        }
+
+        Ok(ParsedDocument {
+            models,
+            explicit_relationships: true,
+            primary_entities: primary_entities
+                .into_iter()
+                .map(|(entity, (model, _))| (entity, model))
+                .collect(),
+            foreign_entities,
+            ..Default::default()
+        })
+    }
+}
+
+impl MfSemanticModel {
+    fn into_model(self, primary_entities: &HashMap<String, (String, String)>) -> Result<Model> {
+        let primary_key = self
+            .entities
+            .iter()
+            .find(|entity| entity.entity_type == "primary")
+            .map(|entity| entity_column(entity).to_string())
+            .unwrap_or_else(|| "id".to_string());
+
+        let mut model = Model::new(&self.name, primary_key);
+        model.table = self.model.as_deref().map(table_from_dbt_ref);
+        model.label = self.label;
+        model.description = self.description;
+        model.default_time_dimension = self.defaults.and_then(|d| d.agg_time_dimension);
+
+        for entity in self.entities.iter().filter(|e| e.entity_type == "foreign") {
+            let Some((target, target_key)) = primary_entities.get(&entity.name) else {
+                continue;
+            };
+            if *target == self.name {
+                continue;
+            }
+            model.relationships.push(
+                Relationship::many_to_one(target.clone())
+                    .with_keys(entity_column(entity), target_key.clone()),
+            );
+        }
+
+        model.dimensions = self
+            .dimensions
+            .into_iter()
+            .map(MfDimension::into_dimension)
+            .collect();
+        model.metrics = self
+            .measures
+            .into_iter()
+            .map(MfMeasure::into_metric)
+            .collect::<Result<_>>()?;
+
+        Ok(model)
+    }
+}
+
+impl MfDimension {
+    fn into_dimension(self) -> Dimension {
+        let mut dimension = match self.dim_type.as_deref() {
+            Some("time") => Dimension::time(&self.name),
+            _ => Dimension::categorical(&self.name),
+        };
+        if dimension.r#type == DimensionType::Time {
+            let granularity = self
+                .type_params
+                .and_then(|params| params.time_granularity)
+                .unwrap_or_else(|| "day".to_string());
+            dimension = dimension.with_granularity(granularity);
+        }
+        dimension.sql = self.expr.as_ref().and_then(yaml_scalar);
+        dimension.label = self.label;
+        dimension.description = self.description;
+        dimension
+    }
+}
+
+impl MfMeasure {
+    fn into_metric(self) -> Result<Metric> {
+        let expr = self.expr.as_ref().and_then(yaml_scalar);
+        let agg_name = self.agg.as_deref().unwrap_or("sum").to_ascii_lowercase();
+        let (agg, sql) = match agg_name.as_str() {
+            "sum" => (Aggregation::Sum, expr),
+            "sum_boolean" => (
+                Aggregation::Sum,
+                expr.map(|e| format!("CASE WHEN {e} THEN 1 ELSE 0 END")),
+            ),
+            "count" => (Aggregation::Count, expr),
+            "count_distinct" => (Aggregation::CountDistinct, expr),
+            "average" | "avg" => (Aggregation::Avg, expr),
+            "min" => (Aggregation::Min, expr),
+            "max" => (Aggregation::Max, expr),
+            "median" => (Aggregation::Median, expr),
+            "percentile" => {
+                let params = self.agg_params.as_ref();
+                let Some(fraction) = params.and_then(|p| p.percentile) else {
+                    return Err(SidemanticError::Validation(format!(
+                        "MetricFlow measure '{}' uses 'percentile' without agg_params.percentile",
+                        self.name
+                    )));
+                };
+                let agg = if params.is_some_and(|p| p.use_approximate_percentile) {
+                    Aggregation::ApproxPercentile(fraction)
+                } else {
+                    Aggregation::Percentile(fraction)
+                };
+                (agg, expr)
+            }
+            other => {
+                return Err(SidemanticError::Validation(format!(
+                    "MetricFlow measure '{}' uses unsupported aggregation '{other}'",
+                    self.name
+                )))
+            }
+        };
+
+        let mut metric = Metric::new(self.name);
+        metric.agg = Some(agg);
+        metric.sql = sql;
+        metric.label = self.label;
+        metric.description = self.description;
+        Ok(metric)
+    }
+}
+
+impl MfMetric {
+    /// Convert to a metric and the model it belongs on, or `None` when an
+    /// input has not been converted yet.
+    fn resolve(
+        &self,
+        models: &[Model],
+        owners: &HashMap<String, String>,
+        context: &FilterContext,
+    ) -> Result<Option<(String, Metric)>> {
+        let params = &self.type_params;
+        let (owner, mut metric) = match self.metric_type.as_deref().unwrap_or("simple") {
+            "simple" => {
+                let measure = self.required_input(params.measure.as_ref(), "measure")?;
+                measure.ensure_plain(&self.name)?;
+                let Some(owner) = owners.get(measure.name()) else {
+                    return Ok(None);
+                };
+                // A simple metric is its measure under a (possibly new) name.
+                let mut metric = models
+                    .iter()
+                    .find(|model| model.name == *owner)
+                    .and_then(|model| model.get_metric(measure.name()))
+                    .cloned()
+                    .expect("owned measure exists on its model");
+                metric.name = self.name.clone();
+                (owner.clone(), metric)
+            }
+            "ratio" => {
+                let numerator = self.required_input(params.numerator.as_ref(), "numerator")?;
+                let denominator =
+                    self.required_input(params.denominator.as_ref(), "denominator")?;
+                numerator.ensure_plain(&self.name)?;
+                denominator.ensure_plain(&self.name)?;
+                let (Some(num_owner), Some(den_owner)) =
+                    (owners.get(numerator.name()), owners.get(denominator.name()))
+                else {
+                    return Ok(None);
+                };
+                (
+                    num_owner.clone(),
+                    Metric::ratio(
+                        &self.name,
+                        format!("{num_owner}.{}", numerator.name()),
+                        format!("{den_owner}.{}", denominator.name()),
+                    ),
+                )
+            }
+            "derived" => {
+                let expr = params.expr.as_deref().ok_or_else(|| {
+                    SidemanticError::Validation(format!(
+                        "MetricFlow derived metric '{}' requires type_params.expr",
+                        self.name
+                    ))
+                })?;
+                let mut qualified: HashMap<&str, String> = HashMap::new();
+                let mut first_owner = None;
+                for input in &params.metrics {
+                    input.ensure_plain(&self.name)?;
+                    let Some(owner) = owners.get(input.name()) else {
+                        return Ok(None);
+                    };
+                    first_owner.get_or_insert_with(|| owner.clone());
+                    qualified.insert(input.reference_name(), format!("{owner}.{}", input.name()));
+                }
+                let Some(owner) = first_owner else {
+                    return Err(SidemanticError::Validation(format!(
+                        "MetricFlow derived metric '{}' requires type_params.metrics",
+                        self.name
+                    )));
+                };
+                let identifier = Regex::new(r"\b[A-Za-z_][A-Za-z0-9_]*\b").unwrap();
+                let sql = identifier.replace_all(expr, |caps: &regex::Captures| {
+                    qualified
+                        .get(&caps[0])
+                        .cloned()
+                        .unwrap_or_else(|| caps[0].to_string())
+                });
+                (owner, Metric::derived(&self.name, sql))
+            }
+            "cumulative" => {
+                let measure = self.required_input(params.measure.as_ref(), "measure")?;
+                measure.ensure_plain(&self.name)?;
+                let Some(owner) = owners.get(measure.name()) else {
+                    return Ok(None);
+                };
+                let nested = params.cumulative_type_params.as_ref();
+                let mut metric =
+                    Metric::cumulative(&self.name, format!("{owner}.{}", measure.name()));
+                metric.window = params
+                    .window
+                    .clone()
+                    .or_else(|| nested.and_then(|p| p.window.clone()));
+                metric.grain_to_date = params
+                    .grain_to_date
+                    .as_deref()
+                    .or_else(|| nested.and_then(|p| p.grain_to_date.as_deref()))
+                    .map(|grain| parse_time_grain(&self.name, grain))
+                    .transpose()?;
+                (owner.clone(), metric)
+            }
+            other => {
+                return Err(SidemanticError::Validation(format!(
+                    "MetricFlow metric '{}' has unsupported type '{other}'",
+                    self.name
+                )))
+            }
+        };
+
+        if self.label.is_some() {
+            metric.label = self.label.clone();
+        }
+        if self.description.is_some() {
+            metric.description = self.description.clone();
+        }
+        if let Some(filter) = &self.filter {
+            metric.filters.push(context.render_filter(filter, &owner));
+        }
+        Ok(Some((owner, metric)))
+    }
+
+    fn required_input<'a>(&self, input: Option<&'a MfInput>, key: &str) -> Result<&'a MfInput> {
+        input.ok_or_else(|| {
+            SidemanticError::Validation(format!(
+                "MetricFlow metric '{}' requires type_params.{key}",
+                self.name
+            ))
+        })
+    }
+}
+
+/// Lookups needed to translate MetricFlow Jinja filters.
+#[derive(Debug, Default)]
+struct FilterContext {
+    /// Entity name -> model declaring it as primary, else the first declaring it
+    entities: HashMap<String, String>,
+    /// (model, dimension) -> SQL expression
+    dimensions: HashMap<(String, String), String>,
+}
+
+impl FilterContext {
+    /// Rewrite `{{ Dimension('entity__dim') }}`, `{{ TimeDimension(...) }}` and
+    /// `{{ Entity('entity') }}` references into SQL.
+    ///
+    /// Dimensions on the owning model become their column expression; those on
+    /// other models are qualified as `model.dimension`.
+    fn render_filter(&self, filter: &str, owner: &str) -> String {
+        let jinja = Regex::new(
+            r#"\{\{\s*(Dimension|TimeDimension|Entity)\(\s*['"]([^'"]+)['"][^)]*\)\s*\}\}"#,
+        )
+        .unwrap();
+        jinja
+            .replace_all(filter, |caps: &regex::Captures| {
+                let reference = &caps[2];
+                if &caps[1] == "Entity" {
+                    return reference.to_string();
+                }
+                let (entity, dimension) = reference.rsplit_once("__").unwrap_or(("", reference));
+                let model = self
+                    .entities
+                    .get(entity)
+                    .map(String::as_str)
+                    .unwrap_or(owner);
+                if model == owner {
+                    self.dimensions
+                        .get(&(model.to_string(), dimension.to_string()))
+                        .cloned()
+                        .unwrap_or_else(|| dimension.to_string())
+                } else {
+                    format!("{model}.{dimension}")
+                }
+            })
+            .into_owned()
+    }
+}
+
+fn entity_column(entity: &MfEntity) -> &str {
+    entity.expr.as_deref().unwrap_or(&entity.name)
+}
+
+/// Physical table for a dbt `ref('name')` / `source('schema', 'name')` call;
+/// anything else is used verbatim.
+fn table_from_dbt_ref(reference: &str) -> String {
+    let args: Vec<&str> = Regex::new(r#"['"]([^'"]+)['"]"#)
+        .unwrap()
+        .captures_iter(reference)
+        .map(|caps| caps.get(1).unwrap().as_str())
+        .collect();
+    let trimmed = reference.trim();
+    if trimmed.starts_with("ref(") {
+        if let Some(name) = args.last() {
+            return name.to_string();
+        }
+    } else if trimmed.starts_with("source(") && !args.is_empty() {
+        return args.join(".");
+    }
+    trimmed.to_string()
+}
+
+fn parse_time_grain(metric_name: &str, grain: &str) -> Result<TimeGrain> {
+    match grain.to_ascii_lowercase().as_str() {
+        "day" => Ok(TimeGrain::Day),
+        "week" => Ok(TimeGrain::Week),
+        "month" => Ok(TimeGrain::Month),
+        "quarter" => Ok(TimeGrain::Quarter),
+        "year" => Ok(TimeGrain::Year),
+        other => Err(SidemanticError::Validation(format!(
+            "MetricFlow metric '{metric_name}' has unsupported grain_to_date '{other}'"
+        ))),
+    }
+}
+
+/// Render a scalar YAML value (MetricFlow allows e.g. `expr: 1`) as SQL text.
+fn yaml_scalar(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        serde_yaml::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YAML: &str = r#"
+semantic_models:
+  - name: orders
+    model: ref('orders')
+    entities:
+      - name: order
+        type: primary
+        expr: order_id
+      - name: customer
+        type: foreign
+        expr: customer_id
+    dimensions:
+      - name: ordered_at
+        type: time
+        expr: created_at
+    measures:
+      - name: p95_amount
+        agg: percentile
+        expr: amount
+        agg_params:
+          percentile: 0.95
+          use_approximate_percentile: true
+      - name: discounted_orders
+        agg: sum_boolean
+        expr: is_discounted
+  - name: customers
+    model: ref('customers')
+    entities:
+      - name: customer
+        type: primary
+        expr: id
+    dimensions:
+      - name: region
+        type: categorical
+        expr: region_code
+"#;
+
+    #[test]
+    fn test_parse_metricflow_measures_and_filters() {
+        let yaml = format!(
+            r#"{YAML}
+metrics:
+  - name: emea_discounted_orders
+    type: simple
+    filter: "{{{{ Dimension('customer__region') }}}} = 'EMEA' AND {{{{ TimeDimension('order__ordered_at', 'day') }}}} >= '2024-01-01'"
+    type_params:
+      measure:
+        name: discounted_orders
+"#
+        );
+
+        let models = MetricFlowAdapter::new().parse_models(&yaml).unwrap();
+        let orders = models.iter().find(|m| m.name == "orders").unwrap();
+
+        assert_eq!(
+            orders
+                .get_dimension("ordered_at")
+                .unwrap()
+                .granularity
+                .as_deref(),
+            Some("day")
+        );
+        assert_eq!(
+            orders.get_metric("p95_amount").unwrap().agg,
+            Some(Aggregation::ApproxPercentile(0.95))
+        );
+        assert_eq!(
+            orders
+                .get_metric("discounted_orders")
+                .unwrap()
+                .sql
+                .as_deref(),
+            Some("CASE WHEN is_discounted THEN 1 ELSE 0 END")
+        );
+
+        // Own-model dimensions render as their expression; joined ones are qualified.
+        let metric = orders.get_metric("emea_discounted_orders").unwrap();
+        assert_eq!(
+            metric.filters,
+            vec!["customers.region = 'EMEA' AND created_at >= '2024-01-01'".to_string()]
+        );
+
+        let rel = orders.get_relationship("customers").unwrap();
+        assert_eq!(rel.primary_key.as_deref(), Some("id"));
+    }
+
+    #[test]
+    fn test_metricflow_rejects_unsupported_inputs() {
+        let yaml = format!(
+            r#"{YAML}
+metrics:
+  - name: discounted_orders_wow
+    type: derived
+    type_params:
+      expr: discounted_orders - last_week
+      metrics:
+        - discounted_orders
+        - name: discounted_orders
+          alias: last_week
+          offset_window: 1 week
+"#
+        );
+        let err = MetricFlowAdapter::new().parse_models(&yaml).unwrap_err();
+        assert!(err.to_string().contains("offset_window"), "{err}");
+
+        let yaml = format!(
+            r#"{YAML}
+metrics:
+  - name: orphan
+    type: simple
+    type_params:
+      measure: missing_measure
+"#
+        );
+        let err = MetricFlowAdapter::new().parse_models(&yaml).unwrap_err();
+        assert!(err.to_string().contains("orphan"), "{err}");
+    }
+}
@@ -6,15 +6,19 @@
 //!
 //! The config loader dispatches to these adapters by detected format. Native
 //! Sidemantic YAML/SQL remains built into the loader; everything else
-//! (Cube, OSI, and future importers) lives here.
+//! (Cube, LookML, MetricFlow, OSI, and future importers) lives here.
 
-use crate::core::{Metric, Model, Parameter, SemanticGraph};
+use crate::core::{Metric, Model, Parameter, Relationship, SemanticGraph};
 use crate::error::{Result, SidemanticError};
 
 pub mod cube;
+pub mod lookml;
+pub mod metricflow;
 pub mod osi;
 
 pub use cube::CubeAdapter;
+pub use lookml::LookMLAdapter;
+pub use metricflow::MetricFlowAdapter;
 pub use osi::OsiAdapter;
 
 /// Result of parsing a single external-format document.
@@ -33,6 +37,28 @@ pub struct ParsedDocument {
     /// When true, the format specifies relationships explicitly and the loader
     /// must not infer foreign-key relationships for these models.
     pub explicit_relationships: bool,
+    /// Relationships declared apart from their source model (e.g. LookML
+    /// explore joins), keyed by source model name. The loader attaches them
+    /// once every document has been parsed, so the model may live elsewhere.
+    pub relationships: Vec<(String, Relationship)>,
+    /// Entities a model is the primary source of, as (entity, model) pairs
+    /// (MetricFlow primary entities).
+    pub primary_entities: Vec<(String, String)>,
+    /// Foreign keys naming an entity whose primary model is not in this
+    /// document. The loader turns them into relationships once every
+    /// document has been parsed.
+    pub foreign_entities: Vec<ForeignEntity>,
+}
+
+/// A foreign key declared by entity name rather than target model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignEntity {
+    /// Model declaring the foreign key
+    pub model: String,
+    /// Entity the key refers to
+    pub entity: String,
+    /// Foreign key column on `model`
+    pub column: String,
 }
 
 /// A reusable importer/exporter for an external semantic-layer format.
@@ -22,8 +22,8 @@ use serde_yaml::{Mapping as YamlMap, Value as Yaml};
 
 use crate::config::schema::metric_from_sql_expression;
 use crate::core::{
-    Aggregation, Dimension, DimensionType, Metric, MetricType, Model, Relationship,
-    RelationshipType, SemanticGraph,
+    Dimension, DimensionType, Metric, MetricType, Model, Relationship, RelationshipType,
+    SemanticGraph,
 };
 use crate::error::{Result, SidemanticError};
 
@@ -376,8 +376,13 @@ fn parse_field(field_def: &Json) -> Option<Dimension> {
         meta: build_meta(field),
         format: None,
         value_format_name: None,
+        value_labels: None,
+        null_label: None,
         parent: None,
         window: None,
+        reference: None,
+        cardinality_hint: None,
+        tags: Vec::new(),
         public: true,
     })
 }
@@ -398,12 +403,13 @@ fn parse_metric(metric_def: &Json) -> Option<Metric> {
         .get("description")
         .and_then(Json::as_str)
         .map(String::from);
-    Some(metric_from_sql_expression(
+    metric_from_sql_expression(
         name.to_string(),
         Some(expression),
         description,
         build_meta(metric),
-    ))
+    )
+    .ok()
 }
 
 /// Extract a SQL expression from an OSI `expression` definition, preferring
@@ -491,6 +497,7 @@ fn add_relationship_to_model(rel_def: &Json, models: &mut [Model]) {
         related_foreign_key: None,
         related_foreign_key_columns: None,
         sql: None,
+        join_type: None,
         metadata: if metadata.is_empty() {
             None
         } else {
@@ -819,11 +826,7 @@ fn build_metric_expression(metric: &Metric, model_name: Option<&str>) -> Option<
                     inner = format!("{model_name}.{inner}");
                 }
             }
-            if *agg == Aggregation::CountDistinct {
-                Some(format!("COUNT(DISTINCT {inner})"))
-            } else {
-                Some(format!("{}({inner})", agg.as_sql()))
-            }
+            Some(agg.call_sql(&inner))
         }
     }
 }
@@ -0,0 +1,369 @@
+//! High-level Rust entry point wrapping a [`SemanticGraph`].
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, PoisonError};
+
+use crate::config::load_from_string;
+use crate::core::{Model, SemanticGraph};
+use crate::error::Result;
+use crate::sql::{QueryRewriter, SemanticQuery, SqlGenerator};
+
+/// Stateful wrapper around a semantic graph with an optional compile cache.
+///
+/// ```
+/// use sidemantic::api::Sidemantic;
+/// use sidemantic::sql::SemanticQuery;
+///
+/// let mut sidemantic = Sidemantic::new();
+/// sidemantic
+///     .add_yaml(
+///         r#"
+/// models:
+///   - name: orders
+///     table: orders
+///     primary_key: order_id
+///     dimensions:
+///       - name: status
+///         type: categorical
+///     metrics:
+///       - name: revenue
+///         agg: sum
+///         sql: amount
+/// "#,
+///     )
+///     .unwrap();
+///
+/// let query = SemanticQuery::new()
+///     .with_metrics(vec!["orders.revenue".into()])
+///     .with_dimensions(vec!["orders.status".into()]);
+/// let sql = sidemantic.compile(&query).unwrap();
+/// assert!(sql.contains("SUM(orders_cte.revenue_raw)"));
+///
+/// let sql = sidemantic
+///     .rewrite("SELECT orders.revenue, orders.status FROM orders")
+///     .unwrap();
+/// assert!(sql.contains("GROUP BY"));
+/// ```
+#[derive(Debug, Default)]
+pub struct Sidemantic {
+    graph: SemanticGraph,
+    cache: Option<Mutex<CompileCache>>,
+}
+
+impl Sidemantic {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wrap an existing graph.
+    pub fn from_graph(graph: SemanticGraph) -> Self {
+        Self { graph, cache: None }
+    }
+
+    /// Enable an LRU cache of compiled SQL holding up to `capacity` queries.
+    pub fn with_compile_cache(mut self, capacity: usize) -> Self {
+        self.cache = Some(Mutex::new(CompileCache::new(capacity)));
+        self
+    }
+
+    pub fn graph(&self) -> &SemanticGraph {
+        &self.graph
+    }
+
+    /// Mutable access to the graph. Clears the compile cache, since the
+    /// caller may replace the graph outright and a fresh graph's version can
+    /// collide with the cached one.
+    pub fn graph_mut(&mut self) -> &mut SemanticGraph {
+        if let Some(cache) = self.cache.as_mut() {
+            cache
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clear();
+        }
+        &mut self.graph
+    }
+
+    pub fn add_model(&mut self, model: Model) -> Result<()> {
+        self.graph.add_model(model)
+    }
+
+    pub fn remove_model(&mut self, name: &str) -> Result<Model> {
+        self.graph.remove_model(name)
+    }
+
+    /// Load models, metrics, and parameters from a YAML document (any format
+    /// `load_from_string` detects) into the graph. Fails without changing the
+    /// graph if a name is already defined.
+    pub fn add_yaml(&mut self, yaml: &str) -> Result<()> {
+        let graph = load_from_string(yaml)?;
+        self.graph.merge(graph)
+    }
+
+    /// Rewrite a SQL query written against semantic models into executable SQL.
+    pub fn rewrite(&self, sql: &str) -> Result<String> {
+        QueryRewriter::new(&self.graph).rewrite(sql)
+    }
+
+    /// Compile a semantic query to SQL, reusing cached SQL when available.
+    pub fn compile(&self, query: &SemanticQuery) -> Result<String> {
+        let Some(cache) = self.cache.as_ref() else {
+            return SqlGenerator::new(&self.graph).generate(query);
+        };
+
+        let version = self.graph.version();
+        if let Some(sql) = lock_cache(cache).get(query, version) {
+            return Ok(sql);
+        }
+
+        let sql = SqlGenerator::new(&self.graph).generate(query)?;
+        lock_cache(cache).insert(query.clone(), version, sql.clone());
+        Ok(sql)
+    }
+}
+
+fn lock_cache(cache: &Mutex<CompileCache>) -> std::sync::MutexGuard<'_, CompileCache> {
+    cache.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Bounded least-recently-used cache of compiled SQL for one graph version.
+///
+/// Each entry carries the tick of its last use, and `recency` maps ticks back
+/// to queries, so hits and evictions cost O(log capacity) without scanning.
+#[derive(Debug)]
+struct CompileCache {
+    capacity: usize,
+    graph_version: u64,
+    entries: HashMap<SemanticQuery, (String, u64)>,
+    recency: BTreeMap<u64, SemanticQuery>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl CompileCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            graph_version: 0,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    fn get(&mut self, query: &SemanticQuery, graph_version: u64) -> Option<String> {
+        self.sync_version(graph_version);
+        self.tick += 1;
+        let Some((sql, last_used)) = self.entries.get_mut(query) else {
+            self.misses += 1;
+            return None;
+        };
+        self.hits += 1;
+        let previous = std::mem::replace(last_used, self.tick);
+        let sql = sql.clone();
+        self.promote(previous);
+        Some(sql)
+    }
+
+    fn insert(&mut self, query: SemanticQuery, graph_version: u64, sql: String) {
+        self.sync_version(graph_version);
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        if let Some((cached, last_used)) = self.entries.get_mut(&query) {
+            *cached = sql;
+            let previous = std::mem::replace(last_used, self.tick);
+            self.promote(previous);
+            return;
+        }
+        self.recency.insert(self.tick, query.clone());
+        self.entries.insert(query, (sql, self.tick));
+        while self.entries.len() > self.capacity {
+            let Some((_, evicted)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&evicted);
+        }
+    }
+
+    /// Drop every entry compiled against an older graph.
+    fn sync_version(&mut self, graph_version: u64) {
+        if self.graph_version != graph_version {
+            self.clear();
+            self.graph_version = graph_version;
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    /// Move the entry last used at `previous` to the current tick.
+    fn promote(&mut self, previous: u64) {
+        if let Some(query) = self.recency.remove(&previous) {
+            self.recency.insert(self.tick, query);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Dimension, Metric};
+
+    fn create_sidemantic() -> Sidemantic {
+        let mut sidemantic = Sidemantic::new().with_compile_cache(2);
+        sidemantic
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_dimension(Dimension::categorical("status"))
+                    .with_metric(Metric::sum("revenue", "amount")),
+            )
+            .unwrap();
+        sidemantic
+    }
+
+    fn cache_stats(sidemantic: &Sidemantic) -> (u64, u64, usize) {
+        let cache = lock_cache(sidemantic.cache.as_ref().unwrap());
+        (cache.hits, cache.misses, cache.entries.len())
+    }
+
+    fn revenue_by_status() -> SemanticQuery {
+        SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.status".into()])
+    }
+
+    #[test]
+    fn test_compile_cache_hit_returns_same_sql() {
+        let sidemantic = create_sidemantic();
+        let query = revenue_by_status();
+
+        let first = sidemantic.compile(&query).unwrap();
+        let second = sidemantic.compile(&query).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(cache_stats(&sidemantic), (1, 1, 1));
+    }
+
+    #[test]
+    fn test_compile_cache_miss_for_different_query() {
+        let sidemantic = create_sidemantic();
+
+        sidemantic.compile(&revenue_by_status()).unwrap();
+        sidemantic
+            .compile(&SemanticQuery::new().with_metrics(vec!["orders.revenue".into()]))
+            .unwrap();
+
+        assert_eq!(cache_stats(&sidemantic), (0, 2, 2));
+    }
+
+    #[test]
+    fn test_compile_cache_evicts_least_recently_used() {
+        let sidemantic = create_sidemantic();
+        let a = revenue_by_status();
+        let b = SemanticQuery::new().with_metrics(vec!["orders.revenue".into()]);
+        let c = revenue_by_status().with_limit(10);
+
+        sidemantic.compile(&a).unwrap();
+        sidemantic.compile(&b).unwrap();
+        sidemantic.compile(&a).unwrap();
+        sidemantic.compile(&c).unwrap();
+
+        let cache = lock_cache(sidemantic.cache.as_ref().unwrap());
+        assert!(cache.entries.contains_key(&a));
+        assert!(!cache.entries.contains_key(&b));
+        assert!(cache.entries.contains_key(&c));
+        assert_eq!(cache.recency.len(), cache.entries.len());
+    }
+
+    #[test]
+    fn test_compile_cache_invalidates_after_model_change() {
+        let mut sidemantic = create_sidemantic();
+        let query = revenue_by_status();
+
+        let before = sidemantic.compile(&query).unwrap();
+        sidemantic.remove_model("orders").unwrap();
+        sidemantic
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders_v2")
+                    .with_dimension(Dimension::categorical("status"))
+                    .with_metric(Metric::sum("revenue", "amount")),
+            )
+            .unwrap();
+        let after = sidemantic.compile(&query).unwrap();
+
+        assert_ne!(before, after);
+        assert!(after.contains("FROM orders_v2"), "{after}");
+        assert_eq!(cache_stats(&sidemantic), (0, 2, 1));
+    }
+
+    #[test]
+    fn test_compile_cache_invalidates_after_graph_replacement() {
+        let mut sidemantic = create_sidemantic();
+        let query = revenue_by_status();
+
+        let before = sidemantic.compile(&query).unwrap();
+        let mut replacement = SemanticGraph::new();
+        replacement
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders_v2")
+                    .with_dimension(Dimension::categorical("status"))
+                    .with_metric(Metric::sum("revenue", "amount")),
+            )
+            .unwrap();
+        // A fresh graph can land on the same version number as the old one.
+        assert_eq!(replacement.version(), sidemantic.graph().version());
+        *sidemantic.graph_mut() = replacement;
+        let after = sidemantic.compile(&query).unwrap();
+
+        assert_ne!(before, after);
+        assert!(after.contains("FROM orders_v2"), "{after}");
+    }
+
+    #[test]
+    fn test_add_yaml_rejects_duplicate_models() {
+        let yaml = r#"
+models:
+  - name: orders
+    table: orders
+    primary_key: order_id
+    metrics:
+      - name: revenue
+        agg: sum
+        sql: amount
+"#;
+        let mut sidemantic = Sidemantic::new();
+        sidemantic.add_yaml(yaml).unwrap();
+        assert!(sidemantic.graph().get_model("orders").is_some());
+
+        let err = sidemantic.add_yaml(yaml).unwrap_err();
+        assert!(err.to_string().contains("model 'orders' already exists"));
+    }
+
+    #[test]
+    fn test_compile_without_cache() {
+        let mut sidemantic = Sidemantic::new();
+        sidemantic
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_metric(Metric::sum("revenue", "amount")),
+            )
+            .unwrap();
+
+        let sql = sidemantic
+            .compile(&SemanticQuery::new().with_metrics(vec!["orders.revenue".into()]))
+            .unwrap();
+
+        assert!(sql.contains("SUM(orders_cte.revenue_raw)"), "{sql}");
+        assert!(sidemantic.cache.is_none());
+    }
+}
@@ -153,8 +153,8 @@ fn demo_segments() {
         .with_table("orders")
         .with_dimension(Dimension::categorical("status"))
         .with_metric(Metric::sum("revenue", "amount"))
-        .with_segment(Segment::new("completed", "{model}.status = 'completed'"))
-        .with_segment(Segment::new("high_value", "{model}.amount > 100"));
+        .with_segment(Segment::new("completed").with_sql("{model}.status = 'completed'"))
+        .with_segment(Segment::new("high_value").with_sql("{model}.amount > 100"));
 
     graph.add_model(orders).unwrap();
 
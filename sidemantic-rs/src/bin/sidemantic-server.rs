@@ -630,6 +630,15 @@ fn dimension_json(dimension: &sidemantic::Dimension) -> JsonValue {
     if let Some(value_format_name) = &dimension.value_format_name {
         entry.insert("value_format_name".to_string(), json!(value_format_name));
     }
+    if let Some(value_labels) = &dimension.value_labels {
+        entry.insert("value_labels".to_string(), json!(value_labels));
+    }
+    if let Some(null_label) = &dimension.null_label {
+        entry.insert("null_label".to_string(), json!(null_label));
+    }
+    if let Some(cardinality_hint) = dimension.cardinality_hint {
+        entry.insert("cardinality_hint".to_string(), json!(cardinality_hint));
+    }
     if let Some(parent) = &dimension.parent {
         entry.insert("parent".to_string(), json!(parent));
     }
@@ -2,12 +2,15 @@
 
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use polyglot_sql::DialectType;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use crate::adapters::{Adapter, CubeAdapter, OsiAdapter};
+use crate::adapters::{
+    Adapter, CubeAdapter, ForeignEntity, LookMLAdapter, MetricFlowAdapter, OsiAdapter,
+};
 use crate::core::{
     extract_dependencies, resolve_model_inheritance, Metric, Model, Parameter, Relationship,
     RelationshipType, SemanticGraph,
@@ -30,8 +33,18 @@ struct ParsedConfig {
     graph_metrics: Vec<Metric>,
     /// Graph-level metadata payload (OSI import state).
     graph_metadata: Option<serde_json::Value>,
+    /// Default SQL dialect declared at the top level (native format).
+    dialect: Option<DialectType>,
+    /// Flatten one-hop related dimensions onto each model (native format).
+    flatten: bool,
     /// When true, relationships are declared explicitly; skip FK inference.
     explicit_relationships: bool,
+    /// Relationships declared apart from their source model (LookML explores).
+    relationships: Vec<(String, Relationship)>,
+    /// (entity, model) pairs for entities a model is the primary source of.
+    primary_entities: Vec<(String, String)>,
+    /// Foreign keys to entities declared in other files (MetricFlow).
+    foreign_entities: Vec<ForeignEntity>,
 }
 
 #[derive(Debug)]
@@ -49,6 +62,14 @@ pub struct LoadedModelSource {
     pub source_file: Option<String>,
 }
 
+/// A file, or part of one, that `load_from_directory_lenient` skipped, with
+/// the reason.
+#[derive(Debug)]
+pub struct LoadError {
+    pub path: PathBuf,
+    pub error: SidemanticError,
+}
+
 /// Detected config format
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConfigFormat {
@@ -56,15 +77,23 @@ pub enum ConfigFormat {
     Sidemantic,
     /// Cube.js format (cubes: key)
     Cube,
+    /// dbt MetricFlow format (top-level semantic_models: key)
+    MetricFlow,
+    /// LookML views and explores (`.lkml` files, view: / explore: blocks)
+    LookML,
     /// OSI (Open Semantic Interchange) format (semantic_model: / ontology_mappings: key)
     Osi,
+    /// Native Sidemantic SQL (MODEL statements or SQL + YAML frontmatter)
+    SidemanticSql,
 }
 
 impl ConfigFormat {
     fn source_label(self) -> &'static str {
         match self {
-            ConfigFormat::Sidemantic => "Sidemantic",
+            ConfigFormat::Sidemantic | ConfigFormat::SidemanticSql => "Sidemantic",
             ConfigFormat::Cube => "Cube",
+            ConfigFormat::MetricFlow => "MetricFlow",
+            ConfigFormat::LookML => "LookML",
             ConfigFormat::Osi => "OSI",
         }
     }
@@ -80,19 +109,14 @@ pub fn load_from_file(path: impl AsRef<Path>) -> Result<SemanticGraph> {
 /// Supported file extensions:
 /// - `.yml` / `.yaml` (native/cube YAML)
 /// - `.sql` (MODEL statements or SQL + YAML frontmatter)
+/// - `.lkml` (LookML views and explores)
 pub fn load_from_file_with_metadata(path: impl AsRef<Path>) -> Result<LoadedGraphMetadata> {
     let path = path.as_ref();
     let content = fs::read_to_string(path)
         .map_err(|e| SidemanticError::Validation(format!("Failed to read file: {e}")))?;
 
-    let ext = path
-        .extension()
-        .and_then(|value| value.to_str())
-        .map(str::to_ascii_lowercase);
-    match ext.as_deref() {
-        Some("sql") => load_from_sql_string_with_metadata(&content),
-        _ => load_from_string_with_metadata(&content),
-    }
+    let format = detect_file_format(path, &content).unwrap_or_else(|| detect_format(&content));
+    load_content_with_metadata(&content, format)
 }
 
 /// Load a semantic graph from a YAML string
@@ -102,7 +126,10 @@ pub fn load_from_string(content: &str) -> Result<SemanticGraph> {
 
 /// Load a semantic graph from YAML with parsing metadata used by Python bridge.
 pub fn load_from_string_with_metadata(content: &str) -> Result<LoadedGraphMetadata> {
-    let format = detect_format(content);
+    load_content_with_metadata(content, detect_format(content))
+}
+
+fn load_content_with_metadata(content: &str, format: ConfigFormat) -> Result<LoadedGraphMetadata> {
     let parsed = parse_content_with_extends(content, format)?;
     let ParsedConfig {
         models,
@@ -111,6 +138,9 @@ pub fn load_from_string_with_metadata(content: &str) -> Result<LoadedGraphMetada
         top_level_parameters,
         graph_metrics,
         graph_metadata,
+        dialect,
+        flatten,
+        relationships,
         ..
     } = parsed;
     let model_order: Vec<String> = models.iter().map(|model| model.name.clone()).collect();
@@ -129,7 +159,8 @@ pub fn load_from_string_with_metadata(content: &str) -> Result<LoadedGraphMetada
         .collect();
 
     // Resolve inheritance
-    let models_map = collect_unique_models(models)?;
+    let mut models_map = collect_unique_models(models)?;
+    attach_relationships(&mut models_map, relationships)?;
     let mut resolved_models = resolve_model_inheritance(models_map, &extends_map)?;
     if !resolved_models.is_empty() && !top_level_metrics.is_empty() {
         assign_top_level_metrics(&mut resolved_models, top_level_metrics.clone())?;
@@ -149,6 +180,12 @@ pub fn load_from_string_with_metadata(content: &str) -> Result<LoadedGraphMetada
     if let Some(metadata) = graph_metadata {
         graph.set_metadata(metadata);
     }
+    if let Some(dialect) = dialect {
+        graph.set_dialect(dialect);
+    }
+    if flatten {
+        graph.flatten_foreign_dimensions();
+    }
 
     let mut reported_metrics = top_level_metrics;
     reported_metrics.extend(graph_metrics);
@@ -357,83 +394,21 @@ fn parse_sql_content(content: &str) -> Result<ParsedConfig> {
 
 /// Load a semantic graph from SQL content with metadata.
 pub fn load_from_sql_string_with_metadata(content: &str) -> Result<LoadedGraphMetadata> {
-    let parsed = parse_sql_content(content)?;
-    let ParsedConfig {
-        models,
-        extends_map,
-        top_level_metrics,
-        top_level_parameters,
-        graph_metadata,
-        ..
-    } = parsed;
-    let model_order: Vec<String> = models.iter().map(|model| model.name.clone()).collect();
-    let original_model_metrics: HashMap<String, Vec<String>> = models
-        .iter()
-        .map(|model| {
-            (
-                model.name.clone(),
-                model
-                    .metrics
-                    .iter()
-                    .map(|metric| metric.name.clone())
-                    .collect::<Vec<_>>(),
-            )
-        })
-        .collect();
-
-    let models_map = collect_unique_models(models)?;
-    let mut resolved_models = resolve_model_inheritance(models_map, &extends_map)?;
-    if !resolved_models.is_empty() && !top_level_metrics.is_empty() {
-        assign_top_level_metrics(&mut resolved_models, top_level_metrics.clone())?;
-    }
-
-    let mut graph = SemanticGraph::new();
-    add_resolved_models_in_order(&mut graph, resolved_models, &model_order)?;
-    for metric in top_level_metrics.iter().cloned() {
-        if graph.get_metric(&metric.name).is_none() {
-            graph.add_metric(metric)?;
-        }
-    }
-    for parameter in top_level_parameters {
-        graph.add_parameter(parameter)?;
-    }
-    if let Some(metadata) = graph_metadata {
-        graph.set_metadata(metadata);
-    }
-
-    let model_sources = model_order
-        .iter()
-        .map(|model_name| {
-            (
-                model_name.clone(),
-                LoadedModelSource {
-                    source_format: "Sidemantic".to_string(),
-                    source_file: None,
-                },
-            )
-        })
-        .collect();
-
-    Ok(LoadedGraphMetadata {
-        graph,
-        model_order,
-        top_level_metrics,
-        original_model_metrics,
-        model_sources,
-    })
+    load_content_with_metadata(content, ConfigFormat::SidemanticSql)
 }
 
 /// Load all semantic model files from a directory into a semantic graph.
 ///
 /// This function:
-/// 1. Recursively finds all `.yml`/`.yaml`/`.sql` files
-/// 2. Auto-detects only native Sidemantic YAML vs Cube YAML for YAML files
+/// 1. Recursively finds all `.yml`/`.yaml`/`.sql`/`.lkml` files
+/// 2. Detects each file's format individually (native SQL and LookML by
+///    extension; native, Cube, MetricFlow, or OSI YAML by content), so formats can be mixed
 /// 3. Parses and collects all models
 /// 4. Infers relationships from FK naming conventions
 /// 5. Returns a unified SemanticGraph
 ///
-/// External formats supported by the Python package (LookML, MetricFlow, Hex,
-/// Rill, Malloy, and similar) must be converted to native YAML/SQL before using
+/// External formats supported by the Python package (Hex, Rill, Malloy, and
+/// similar) must be converted to native YAML/SQL before using
 /// the Rust runtime loader.
 pub fn load_from_directory(dir: impl AsRef<Path>) -> Result<SemanticGraph> {
     Ok(load_from_directory_with_metadata(dir)?.graph)
@@ -441,8 +416,54 @@ pub fn load_from_directory(dir: impl AsRef<Path>) -> Result<SemanticGraph> {
 
 /// Load all YAML files from a directory into a semantic graph with metadata.
 pub fn load_from_directory_with_metadata(dir: impl AsRef<Path>) -> Result<LoadedGraphMetadata> {
+    load_directory(dir.as_ref(), None)
+}
+
+/// Load a directory like [`load_from_directory`], skipping files that fail to
+/// read or parse, define an already-loaded model, or declare a conflicting
+/// dialect. Each skipped file is reported as a [`LoadError`].
+///
+/// Errors that only surface once the valid files are combined skip just the
+/// offending part: a model with a broken `extends` chain or that fails to
+/// register, or the relationships, top-level metrics, graph metrics or
+/// parameters a file declares. Each is reported against its file.
+pub fn load_from_directory_lenient(dir: impl AsRef<Path>) -> (SemanticGraph, Vec<LoadError>) {
     let dir = dir.as_ref();
+    let mut errors = Vec::new();
+    match load_directory(dir, Some(&mut errors)) {
+        Ok(loaded) => (loaded.graph, errors),
+        Err(error) => {
+            errors.push(LoadError {
+                path: dir.to_path_buf(),
+                error,
+            });
+            (SemanticGraph::new(), errors)
+        }
+    }
+}
+
+/// Record `error` against `path` when loading leniently, otherwise fail.
+fn skip_file(
+    errors: &mut Option<&mut Vec<LoadError>>,
+    path: &Path,
+    error: SidemanticError,
+) -> Result<()> {
+    match errors {
+        Some(errors) => {
+            errors.push(LoadError {
+                path: path.to_path_buf(),
+                error,
+            });
+            Ok(())
+        }
+        None => Err(error),
+    }
+}
 
+fn load_directory(
+    dir: &Path,
+    mut errors: Option<&mut Vec<LoadError>>,
+) -> Result<LoadedGraphMetadata> {
     if !dir.is_dir() {
         return Err(SidemanticError::Validation(format!(
             "Path is not a directory: {}",
@@ -452,115 +473,122 @@ pub fn load_from_directory_with_metadata(dir: impl AsRef<Path>) -> Result<Loaded
 
     let mut all_models: HashMap<String, Model> = HashMap::new();
     let mut all_extends_map: HashMap<String, String> = HashMap::new();
-    let mut all_top_level_metrics: Vec<Metric> = Vec::new();
-    let mut all_top_level_parameters: Vec<Parameter> = Vec::new();
-    let mut all_graph_metrics: Vec<Metric> = Vec::new();
+    // Cross-file declarations, grouped by the file declaring them.
+    let mut top_level_metric_groups: Vec<(PathBuf, Vec<Metric>)> = Vec::new();
+    let mut parameter_groups: Vec<(PathBuf, Vec<Parameter>)> = Vec::new();
+    let mut graph_metric_groups: Vec<(PathBuf, Vec<Metric>)> = Vec::new();
+    let mut relationship_groups: Vec<(PathBuf, Vec<(String, Relationship)>)> = Vec::new();
+    let mut foreign_entity_groups: Vec<(PathBuf, Vec<ForeignEntity>)> = Vec::new();
+    // Entity name -> model it is the primary entity of, across all files.
+    let mut primary_entity_models: HashMap<String, String> = HashMap::new();
+    let mut model_paths: HashMap<String, PathBuf> = HashMap::new();
     let mut model_order: Vec<String> = Vec::new();
     let mut model_sources: HashMap<String, LoadedModelSource> = HashMap::new();
     // Models whose format declares relationships explicitly (e.g. OSI); these
     // are excluded from foreign-key relationship inference.
     let mut explicit_rel_models: HashSet<String> = HashSet::new();
     let mut merged_graph_metadata: Option<serde_json::Value> = None;
+    let mut merged_dialect: Option<DialectType> = None;
+    let mut merged_flatten = false;
 
     // Recursively find and parse model files.
     for entry in walkdir(dir)? {
         let path = entry;
-        let ext = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .map(str::to_ascii_lowercase);
-
-        match ext.as_deref() {
-            Some("yml") | Some("yaml") => {
-                let content = fs::read_to_string(&path).map_err(|e| {
-                    SidemanticError::Validation(format!("Failed to read {}: {}", path.display(), e))
-                })?;
+        if file_extension(&path).is_none() {
+            continue;
+        }
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                let error = SidemanticError::Validation(format!(
+                    "Failed to read {}: {}",
+                    path.display(),
+                    e
+                ));
+                skip_file(&mut errors, &path, error)?;
+                continue;
+            }
+        };
+        let Some(format) = detect_file_format(&path, &content) else {
+            continue;
+        };
 
-                let format = detect_format(&content);
-                let parsed = parse_content(&content, format)?;
-                let source_format = format.source_label();
-                let source_file = path
-                    .strip_prefix(dir)
-                    .ok()
-                    .map(|value| value.to_string_lossy().to_string());
-                let ParsedConfig {
-                    models,
-                    extends_map,
-                    top_level_metrics,
-                    top_level_parameters,
-                    graph_metrics,
-                    graph_metadata,
-                    explicit_relationships,
-                } = parsed;
-
-                for model in models {
-                    if all_models.contains_key(&model.name) {
-                        return Err(SidemanticError::Validation(format!(
-                            "Duplicate model '{}' found while loading directory",
-                            model.name
-                        )));
-                    }
-                    if explicit_relationships {
-                        explicit_rel_models.insert(model.name.clone());
-                    }
-                    model_order.push(model.name.clone());
-                    model_sources.insert(
-                        model.name.clone(),
-                        LoadedModelSource {
-                            source_format: source_format.to_string(),
-                            source_file: source_file.clone(),
-                        },
-                    );
-                    all_models.insert(model.name.clone(), model);
-                }
-                all_extends_map.extend(extends_map);
-                all_top_level_metrics.extend(top_level_metrics);
-                all_top_level_parameters.extend(top_level_parameters);
-                all_graph_metrics.extend(graph_metrics);
-                merge_graph_metadata(&mut merged_graph_metadata, graph_metadata);
+        let parsed = match parse_content(&content, format) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                skip_file(&mut errors, &path, error)?;
+                continue;
             }
-            Some("sql") => {
-                let content = fs::read_to_string(&path).map_err(|e| {
-                    SidemanticError::Validation(format!("Failed to read {}: {}", path.display(), e))
-                })?;
-                let parsed = parse_sql_content(&content)?;
-                let source_file = path
-                    .strip_prefix(dir)
-                    .ok()
-                    .map(|value| value.to_string_lossy().to_string());
-                let ParsedConfig {
-                    models,
-                    extends_map,
-                    top_level_metrics,
-                    top_level_parameters,
-                    graph_metadata,
-                    ..
-                } = parsed;
-
-                for model in models {
-                    if all_models.contains_key(&model.name) {
-                        return Err(SidemanticError::Validation(format!(
-                            "Duplicate model '{}' found while loading directory",
-                            model.name
-                        )));
-                    }
-                    model_order.push(model.name.clone());
-                    model_sources.insert(
-                        model.name.clone(),
-                        LoadedModelSource {
-                            source_format: "Sidemantic".to_string(),
-                            source_file: source_file.clone(),
-                        },
-                    );
-                    all_models.insert(model.name.clone(), model);
-                }
-                all_extends_map.extend(extends_map);
-                all_top_level_metrics.extend(top_level_metrics);
-                all_top_level_parameters.extend(top_level_parameters);
-                merge_graph_metadata(&mut merged_graph_metadata, graph_metadata);
+        };
+        let source_format = format.source_label();
+        let source_file = path
+            .strip_prefix(dir)
+            .ok()
+            .map(|value| value.to_string_lossy().to_string());
+        let ParsedConfig {
+            models,
+            extends_map,
+            top_level_metrics,
+            top_level_parameters,
+            graph_metrics,
+            graph_metadata,
+            dialect,
+            flatten,
+            explicit_relationships,
+            relationships,
+            primary_entities,
+            foreign_entities,
+        } = parsed;
+
+        let mut file_models: HashSet<&str> = HashSet::new();
+        if let Some(model) = models.iter().find(|model| {
+            all_models.contains_key(&model.name) || !file_models.insert(model.name.as_str())
+        }) {
+            let error = SidemanticError::Validation(format!(
+                "Duplicate model '{}' found while loading directory",
+                model.name
+            ));
+            skip_file(&mut errors, &path, error)?;
+            continue;
+        }
+        if let Some(dialect) = dialect {
+            if merged_dialect.is_some_and(|existing| existing != dialect) {
+                let error = SidemanticError::Validation(format!(
+                    "Conflicting dialects found while loading directory ({})",
+                    path.display()
+                ));
+                skip_file(&mut errors, &path, error)?;
+                continue;
             }
-            _ => {}
+            merged_dialect = Some(dialect);
+        }
+        merged_flatten |= flatten;
+
+        for model in models {
+            if explicit_relationships {
+                explicit_rel_models.insert(model.name.clone());
+            }
+            model_order.push(model.name.clone());
+            model_paths.insert(model.name.clone(), path.clone());
+            model_sources.insert(
+                model.name.clone(),
+                LoadedModelSource {
+                    source_format: source_format.to_string(),
+                    source_file: source_file.clone(),
+                },
+            );
+            all_models.insert(model.name.clone(), model);
+        }
+        all_extends_map.extend(extends_map);
+        top_level_metric_groups.push((path.clone(), top_level_metrics));
+        parameter_groups.push((path.clone(), top_level_parameters));
+        graph_metric_groups.push((path.clone(), graph_metrics));
+        relationship_groups.push((path.clone(), relationships));
+        foreign_entity_groups.push((path.clone(), foreign_entities));
+        for (entity, model) in primary_entities {
+            primary_entity_models.entry(entity).or_insert(model);
         }
+        merge_graph_metadata(&mut merged_graph_metadata, graph_metadata);
     }
 
     let original_model_metrics: HashMap<String, Vec<String>> = all_models
@@ -577,29 +605,82 @@ pub fn load_from_directory_with_metadata(dir: impl AsRef<Path>) -> Result<Loaded
         })
         .collect();
 
+    // Foreign entities name their target by entity, which may be the primary
+    // entity of a model from any file.
+    for (path, foreign_entities) in foreign_entity_groups {
+        let relationships =
+            foreign_entity_relationships(&all_models, &primary_entity_models, foreign_entities);
+        relationship_groups.push((path, relationships));
+    }
+
     // Infer relationships from FK naming conventions (skip formats that
     // declare relationships explicitly, e.g. OSI).
+    for (path, relationships) in relationship_groups {
+        if let Err(error) = attach_relationships(&mut all_models, relationships) {
+            skip_file(&mut errors, &path, error)?;
+        }
+    }
     infer_relationships(&mut all_models, &explicit_rel_models);
+    if errors.is_some() {
+        for (model_name, error) in unresolvable_models(&all_models, &all_extends_map) {
+            all_models.remove(&model_name);
+            skip_file(&mut errors, &model_paths[&model_name], error)?;
+        }
+    }
     let mut resolved_models = resolve_model_inheritance(all_models, &all_extends_map)?;
+    let top_level_metric_groups =
+        accepted_groups(top_level_metric_groups, &mut errors, |metrics| {
+            assign_top_level_metrics(&mut resolved_models.clone(), metrics.to_vec())
+        })?;
+    let all_top_level_metrics: Vec<Metric> = top_level_metric_groups
+        .iter()
+        .flat_map(|(_, metrics)| metrics.iter().cloned())
+        .collect();
     if !resolved_models.is_empty() && !all_top_level_metrics.is_empty() {
         assign_top_level_metrics(&mut resolved_models, all_top_level_metrics.clone())?;
     }
 
     // Build the graph
     let mut graph = SemanticGraph::new();
-    add_resolved_models_in_order(&mut graph, resolved_models, &model_order)?;
-    for metric in all_top_level_metrics.iter().cloned() {
-        if graph.get_metric(&metric.name).is_none() {
-            graph.add_metric(metric)?;
+    for model in models_in_order(resolved_models, &model_order) {
+        let path = model_paths[&model.name].clone();
+        if let Err(error) = graph.add_model(model) {
+            skip_file(&mut errors, &path, error)?;
+        }
+    }
+    for (path, metrics) in &top_level_metric_groups {
+        for metric in metrics {
+            if graph.get_metric(&metric.name).is_none() {
+                if let Err(error) = graph.add_metric(metric.clone()) {
+                    skip_file(&mut errors, path, error)?;
+                }
+            }
         }
     }
+    let graph_metric_groups = accepted_groups(graph_metric_groups, &mut errors, |metrics| {
+        register_graph_metrics(&mut graph.clone(), metrics)
+    })?;
+    let all_graph_metrics: Vec<Metric> = graph_metric_groups
+        .into_iter()
+        .flat_map(|(_, metrics)| metrics)
+        .collect();
     register_graph_metrics(&mut graph, &all_graph_metrics)?;
-    for parameter in all_top_level_parameters {
-        graph.add_parameter(parameter)?;
+    for (path, parameters) in parameter_groups {
+        for parameter in parameters {
+            if let Err(error) = graph.add_parameter(parameter) {
+                skip_file(&mut errors, &path, error)?;
+            }
+        }
     }
     if let Some(metadata) = merged_graph_metadata {
         graph.set_metadata(metadata);
     }
+    if let Some(dialect) = merged_dialect {
+        graph.set_dialect(dialect);
+    }
+    if merged_flatten {
+        graph.flatten_foreign_dimensions();
+    }
 
     let mut reported_metrics = all_top_level_metrics;
     reported_metrics.extend(all_graph_metrics);
@@ -649,8 +730,43 @@ fn deep_merge_json(target: &mut serde_json::Value, incoming: serde_json::Value)
     }
 }
 
+/// Detect the format of a file on disk: `.sql` files are native SQL, `.lkml`
+/// files are LookML, and `.yml`/`.yaml` files are classified by content via
+/// [`detect_format`].
+///
+/// Returns `None` for files that are not semantic model files.
+fn detect_file_format(path: &Path, content: &str) -> Option<ConfigFormat> {
+    match file_extension(path)? {
+        "sql" => Some(ConfigFormat::SidemanticSql),
+        "lkml" => Some(ConfigFormat::LookML),
+        _ => Some(detect_format(content)),
+    }
+}
+
+/// Normalized extension of a semantic model file, or `None` for other files.
+fn file_extension(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    ["sql", "yml", "yaml", "lkml"]
+        .into_iter()
+        .find(|candidate| *candidate == ext)
+}
+
 /// Detect the config format from content
 fn detect_format(content: &str) -> ConfigFormat {
+    // Check for LookML view/explore blocks
+    let lookml_block = Regex::new(r"(?m)^\s*(?:view|explore)\s*:\s*\+?\w+\s*\{").unwrap();
+    if lookml_block.is_match(content) {
+        return ConfigFormat::LookML;
+    }
+
+    // Check for a top-level MetricFlow semantic_models: key
+    if content
+        .lines()
+        .any(|line| line.trim_end().starts_with("semantic_models:"))
+    {
+        return ConfigFormat::MetricFlow;
+    }
+
     // Check for Cube.js format markers
     if content.contains("cubes:") {
         return ConfigFormat::Cube;
@@ -772,6 +888,8 @@ fn substitute_env_vars(content: &str) -> String {
 
 /// Parse content and return extends map for inheritance resolution
 fn parse_content_with_extends(content: &str, format: ConfigFormat) -> Result<ParsedConfig> {
+    // SQL definitions are parsed verbatim, without env var substitution.
+    let raw_content = content;
     let content = substitute_env_vars(content);
 
     match format {
@@ -785,6 +903,8 @@ fn parse_content_with_extends(content: &str, format: ConfigFormat) -> Result<Par
                 .filter_map(|m| m.extends.as_ref().map(|e| (m.name.clone(), e.clone())))
                 .collect();
             let graph_metadata = config.metadata.clone();
+            let dialect = config.default_dialect()?;
+            let flatten = config.flatten;
             let (mut models, mut top_level_metrics, top_level_parameters) = config.into_parts()?;
             apply_embedded_sql_definitions(&content, &mut models, &mut top_level_metrics)?;
 
@@ -794,6 +914,8 @@ fn parse_content_with_extends(content: &str, format: ConfigFormat) -> Result<Par
                 top_level_metrics,
                 top_level_parameters,
                 graph_metadata,
+                dialect,
+                flatten,
                 ..Default::default()
             })
         }
@@ -804,6 +926,26 @@ fn parse_content_with_extends(content: &str, format: ConfigFormat) -> Result<Par
                 ..Default::default()
             })
         }
+        ConfigFormat::MetricFlow => {
+            let doc = MetricFlowAdapter::new().parse_document(&content)?;
+            Ok(ParsedConfig {
+                models: doc.models,
+                explicit_relationships: doc.explicit_relationships,
+                primary_entities: doc.primary_entities,
+                foreign_entities: doc.foreign_entities,
+                ..Default::default()
+            })
+        }
+        ConfigFormat::LookML => {
+            let doc = LookMLAdapter::new().parse_document(&content)?;
+            Ok(ParsedConfig {
+                models: doc.models,
+                explicit_relationships: doc.explicit_relationships,
+                relationships: doc.relationships,
+                ..Default::default()
+            })
+        }
+        ConfigFormat::SidemanticSql => parse_sql_content(raw_content),
         ConfigFormat::Osi => {
             let doc = OsiAdapter::new().parse_document(&content)?;
             Ok(ParsedConfig {
@@ -835,6 +977,64 @@ fn register_graph_metrics(graph: &mut SemanticGraph, metrics: &[Metric]) -> Resu
     Ok(())
 }
 
+/// Attach relationships declared apart from their source model, skipping
+/// targets the model already relates to. Relationships declared without a
+/// primary key take the target model's.
+fn attach_relationships(
+    models: &mut HashMap<String, Model>,
+    relationships: Vec<(String, Relationship)>,
+) -> Result<()> {
+    for (source, mut relationship) in relationships {
+        if relationship.foreign_key.is_some()
+            && relationship.primary_key.is_none()
+            && relationship.sql.is_none()
+        {
+            let target = models.get(&relationship.name).ok_or_else(|| {
+                SidemanticError::Validation(format!(
+                    "Relationship from '{source}' targets unknown model '{}'",
+                    relationship.name
+                ))
+            })?;
+            relationship.primary_key = Some(target.primary_key.clone());
+            relationship.primary_key_columns = Some(vec![target.primary_key.clone()]);
+        }
+        let model = models.get_mut(&source).ok_or_else(|| {
+            SidemanticError::Validation(format!(
+                "Relationship to '{}' is declared on unknown model '{source}'",
+                relationship.name
+            ))
+        })?;
+        if model.get_relationship(&relationship.name).is_none() {
+            model.relationships.push(relationship);
+        }
+    }
+    Ok(())
+}
+
+/// `many_to_one` relationships for foreign entities whose primary entity is
+/// declared on another model. Entities no model declares as primary are
+/// skipped, as they are within a single MetricFlow file.
+fn foreign_entity_relationships(
+    models: &HashMap<String, Model>,
+    primary_entity_models: &HashMap<String, String>,
+    foreign_entities: Vec<ForeignEntity>,
+) -> Vec<(String, Relationship)> {
+    foreign_entities
+        .into_iter()
+        .filter_map(|foreign| {
+            let target = primary_entity_models.get(&foreign.entity)?;
+            let target_model = models.get(target)?;
+            (*target != foreign.model).then(|| {
+                (
+                    foreign.model,
+                    Relationship::many_to_one(target.clone())
+                        .with_keys(foreign.column, target_model.primary_key.clone()),
+                )
+            })
+        })
+        .collect()
+}
+
 fn collect_unique_models(models: Vec<Model>) -> Result<HashMap<String, Model>> {
     let mut map = HashMap::new();
     for model in models {
@@ -851,22 +1051,83 @@ fn collect_unique_models(models: Vec<Model>) -> Result<HashMap<String, Model>> {
 
 fn add_resolved_models_in_order(
     graph: &mut SemanticGraph,
-    mut resolved_models: HashMap<String, Model>,
+    resolved_models: HashMap<String, Model>,
     model_order: &[String],
 ) -> Result<()> {
-    for model_name in model_order {
-        if let Some(model) = resolved_models.remove(model_name) {
-            graph.add_model(model)?;
-        }
+    for model in models_in_order(resolved_models, model_order) {
+        graph.add_model(model)?;
     }
+    Ok(())
+}
 
+/// Models in load order, followed by any not in `model_order` sorted by name.
+fn models_in_order(
+    mut resolved_models: HashMap<String, Model>,
+    model_order: &[String],
+) -> Vec<Model> {
+    let mut ordered: Vec<Model> = model_order
+        .iter()
+        .filter_map(|model_name| resolved_models.remove(model_name))
+        .collect();
     let mut remaining_models: Vec<(String, Model)> = resolved_models.into_iter().collect();
     remaining_models.sort_by(|left, right| left.0.cmp(&right.0));
-    for (_, model) in remaining_models {
-        graph.add_model(model)?;
+    ordered.extend(remaining_models.into_iter().map(|(_, model)| model));
+    ordered
+}
+
+/// Models whose `extends` chain reaches a missing model or loops, with the
+/// error `resolve_model_inheritance` would report for them.
+fn unresolvable_models(
+    models: &HashMap<String, Model>,
+    extends_map: &HashMap<String, String>,
+) -> Vec<(String, SidemanticError)> {
+    let mut broken = Vec::new();
+    let mut names: Vec<&String> = models.keys().collect();
+    names.sort();
+    for name in names {
+        let mut seen = HashSet::from([name.as_str()]);
+        let mut current = name.as_str();
+        while let Some(parent) = extends_map.get(current) {
+            let error = if !models.contains_key(parent) {
+                format!("Model '{parent}' not found")
+            } else if !seen.insert(parent.as_str()) {
+                format!("Circular inheritance detected for model '{parent}'")
+            } else {
+                current = parent;
+                continue;
+            };
+            broken.push((name.clone(), SidemanticError::Validation(error)));
+            break;
+        }
     }
+    broken
+}
 
-    Ok(())
+/// Keep the per-file groups that `check` accepts together with the groups
+/// accepted before them, skipping the rest when loading leniently. Strict
+/// loads keep every group and let the caller surface the error.
+fn accepted_groups<T: Clone>(
+    groups: Vec<(PathBuf, Vec<T>)>,
+    errors: &mut Option<&mut Vec<LoadError>>,
+    check: impl Fn(&[T]) -> Result<()>,
+) -> Result<Vec<(PathBuf, Vec<T>)>> {
+    if errors.is_none() {
+        return Ok(groups);
+    }
+    let mut accepted: Vec<(PathBuf, Vec<T>)> = Vec::new();
+    let mut items: Vec<T> = Vec::new();
+    for (path, group) in groups {
+        let len = items.len();
+        items.extend(group.iter().cloned());
+        match check(&items) {
+            Ok(()) => accepted.push((path, group)),
+            Err(error) => {
+                items.truncate(len);
+                skip_file(errors, &path, error)?;
+            }
+        }
+    }
+    Ok(accepted)
 }
 
 fn owners_from_dotted_reference(
@@ -1189,6 +1450,7 @@ fn infer_relationships(models: &mut HashMap<String, Model>, skip: &HashSet<Strin
                             related_foreign_key: None,
                             related_foreign_key_columns: None,
                             sql: None,
+                            join_type: None,
                             metadata: None,
                         },
                     ));
@@ -1209,6 +1471,7 @@ fn infer_relationships(models: &mut HashMap<String, Model>, skip: &HashSet<Strin
                             related_foreign_key: None,
                             related_foreign_key_columns: None,
                             sql: None,
+                            join_type: None,
                             metadata: None,
                         },
                     ));
@@ -1276,6 +1539,19 @@ mod tests {
         assert_eq!(detect_format(content), ConfigFormat::Cube);
     }
 
+    #[test]
+    fn test_detect_file_format_uses_extension_then_content() {
+        assert_eq!(
+            detect_file_format(Path::new("models/orders.SQL"), "cubes:"),
+            Some(ConfigFormat::SidemanticSql)
+        );
+        assert_eq!(
+            detect_file_format(Path::new("orders.yaml"), "cubes:\n  - name: orders"),
+            Some(ConfigFormat::Cube)
+        );
+        assert_eq!(detect_file_format(Path::new("README.md"), "models:"), None);
+    }
+
     #[test]
     fn test_detect_format_osi() {
         assert_eq!(
@@ -1289,42 +1565,267 @@ mod tests {
     }
 
     #[test]
-    fn test_load_from_string_auto_detects_osi() {
+    fn test_detect_format_metricflow() {
+        let content = "semantic_models:\n  - name: orders\n";
+        assert_eq!(detect_format(content), ConfigFormat::MetricFlow);
+        // A nested key is not the MetricFlow top-level marker.
+        let content = "models:\n  - name: orders\n    meta:\n      semantic_models: []\n";
+        assert_eq!(detect_format(content), ConfigFormat::Sidemantic);
+    }
+
+    #[test]
+    fn test_load_from_string_auto_detects_metricflow() {
+        use crate::core::{Aggregation, MetricType, TimeGrain};
+
         let yaml = r#"
-semantic_model:
-  - name: shop
-    datasets:
-      - name: orders
-        source: public.orders
-        primary_key: [order_id]
-        fields:
-          - name: customer_id
-            expression:
-              dialects:
-                - dialect: ANSI_SQL
-                  expression: customer_id
-      - name: customers
-        source: public.customers
-        primary_key: [customer_id]
-        fields:
-          - name: customer_id
-            expression:
-              dialects:
-                - dialect: ANSI_SQL
-                  expression: customer_id
-    relationships:
-      - name: orders_to_customers
-        from: orders
-        to: customers
-        from_columns: [customer_id]
-        to_columns: [customer_id]
-    metrics:
-      - name: total_revenue
-        expression:
-          dialects:
-            - dialect: ANSI_SQL
-              expression: SUM(orders.amount)
-"#;
+semantic_models:
+  - name: orders
+    model: ref('orders')
+    description: "Order fact table"
+    defaults:
+      agg_time_dimension: order_date
+    entities:
+      - name: order
+        type: primary
+        expr: order_id
+      - name: customer
+        type: foreign
+        expr: customer_id
+    dimensions:
+      - name: order_date
+        type: time
+        type_params:
+          time_granularity: day
+        expr: created_at
+      - name: status
+        type: categorical
+    measures:
+      - name: order_count
+        agg: count
+        expr: 1
+      - name: revenue
+        agg: sum
+        expr: order_amount
+      - name: avg_order_value
+        agg: average
+        expr: order_amount
+
+  - name: customers
+    model: source('raw', 'customers')
+    entities:
+      - name: customer
+        type: primary
+        expr: customer_id
+    dimensions:
+      - name: region
+        type: categorical
+
+metrics:
+  - name: revenue_growth
+    type: derived
+    type_params:
+      expr: completed_revenue - revenue
+      metrics:
+        - completed_revenue
+        - revenue
+  - name: completed_revenue
+    type: simple
+    filter: "{{ Dimension('order__status') }} = 'completed'"
+    type_params:
+      measure: revenue
+  - name: average_order_value
+    type: ratio
+    type_params:
+      numerator: revenue
+      denominator: order_count
+  - name: revenue_mtd
+    type: cumulative
+    type_params:
+      measure: revenue
+      grain_to_date: month
+"#;
+
+        let loaded = load_from_string_with_metadata(yaml).unwrap();
+        let graph = &loaded.graph;
+        assert_eq!(
+            loaded.model_sources["orders"].source_format,
+            "MetricFlow".to_string()
+        );
+
+        let orders = graph.get_model("orders").unwrap();
+        assert_eq!(orders.table.as_deref(), Some("orders"));
+        assert_eq!(orders.primary_key, "order_id");
+        assert_eq!(orders.default_time_dimension.as_deref(), Some("order_date"));
+        assert_eq!(
+            orders.get_dimension("order_date").unwrap().sql.as_deref(),
+            Some("created_at")
+        );
+
+        // The foreign `customer` entity joins to the model whose primary entity it is.
+        let customers = graph.get_model("customers").unwrap();
+        assert_eq!(customers.table.as_deref(), Some("raw.customers"));
+        assert_eq!(orders.relationships.len(), 1);
+        let rel = orders.get_relationship("customers").unwrap();
+        assert_eq!(rel.r#type, RelationshipType::ManyToOne);
+        assert_eq!(rel.foreign_key.as_deref(), Some("customer_id"));
+        assert_eq!(rel.primary_key.as_deref(), Some("customer_id"));
+        assert!(customers.relationships.is_empty());
+
+        // Measures become simple metrics; top-level metrics land on the model
+        // owning their measure, with references qualified.
+        let revenue = orders.get_metric("revenue").unwrap();
+        assert_eq!(revenue.agg, Some(Aggregation::Sum));
+        assert_eq!(revenue.sql.as_deref(), Some("order_amount"));
+        assert_eq!(
+            orders.get_metric("avg_order_value").unwrap().agg,
+            Some(Aggregation::Avg)
+        );
+
+        let completed = orders.get_metric("completed_revenue").unwrap();
+        assert_eq!(completed.sql.as_deref(), Some("order_amount"));
+        assert_eq!(completed.filters, vec!["status = 'completed'".to_string()]);
+
+        let aov = orders.get_metric("average_order_value").unwrap();
+        assert_eq!(aov.r#type, MetricType::Ratio);
+        assert_eq!(aov.numerator.as_deref(), Some("orders.revenue"));
+        assert_eq!(aov.denominator.as_deref(), Some("orders.order_count"));
+
+        let growth = orders.get_metric("revenue_growth").unwrap();
+        assert_eq!(growth.r#type, MetricType::Derived);
+        assert_eq!(
+            growth.sql.as_deref(),
+            Some("orders.completed_revenue - orders.revenue")
+        );
+
+        let mtd = orders.get_metric("revenue_mtd").unwrap();
+        assert_eq!(mtd.r#type, MetricType::Cumulative);
+        assert_eq!(mtd.sql.as_deref(), Some("orders.revenue"));
+        assert_eq!(mtd.grain_to_date, Some(TimeGrain::Month));
+    }
+
+    #[test]
+    fn test_detect_format_lookml() {
+        let content = "# orders\nview: orders {\n  sql_table_name: orders ;;\n}\n";
+        assert_eq!(detect_format(content), ConfigFormat::LookML);
+        assert_eq!(
+            detect_file_format(Path::new("orders.explore.lkml"), ""),
+            Some(ConfigFormat::LookML)
+        );
+    }
+
+    #[test]
+    fn test_load_lookml_fixture() {
+        use crate::core::{Aggregation, DimensionType, MetricType};
+
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/lookml/ecommerce.lkml");
+        let loaded = load_from_file_with_metadata(&path).unwrap();
+        let graph = &loaded.graph;
+        assert_eq!(loaded.model_order, vec!["orders", "customers"]);
+        assert_eq!(loaded.model_sources["orders"].source_format, "LookML");
+
+        let orders = graph.get_model("orders").unwrap();
+        assert_eq!(orders.table.as_deref(), Some("public.orders"));
+        assert_eq!(orders.primary_key, "id");
+
+        // `${TABLE}.col` and `${dimension}` references become plain SQL.
+        let status = orders.get_dimension("status").unwrap();
+        assert_eq!(status.sql.as_deref(), Some("status"));
+        let is_large = orders.get_dimension("is_large").unwrap();
+        assert_eq!(is_large.r#type, DimensionType::Boolean);
+        assert_eq!(is_large.sql.as_deref(), Some("order_amount >= 500"));
+        assert!(!orders.get_dimension("customer_id").unwrap().public);
+
+        let created = orders.get_dimension("created").unwrap();
+        assert_eq!(created.r#type, DimensionType::Time);
+        assert_eq!(created.sql.as_deref(), Some("created_at"));
+        assert_eq!(created.granularity.as_deref(), Some("day"));
+        assert_eq!(
+            created.supported_granularities,
+            Some(vec![
+                "day".to_string(),
+                "week".to_string(),
+                "month".to_string(),
+                "year".to_string()
+            ])
+        );
+
+        let count = orders.get_metric("count").unwrap();
+        assert_eq!(count.agg, Some(Aggregation::Count));
+        assert_eq!(count.sql, None);
+        let revenue = orders.get_metric("revenue").unwrap();
+        assert_eq!(revenue.agg, Some(Aggregation::Sum));
+        assert_eq!(revenue.sql.as_deref(), Some("order_amount"));
+        assert_eq!(revenue.value_format_name.as_deref(), Some("usd"));
+        assert_eq!(
+            orders.get_metric("completed_revenue").unwrap().filters,
+            vec!["status = 'completed'".to_string()]
+        );
+        assert_eq!(
+            orders.get_metric("p90_amount").unwrap().agg,
+            Some(Aggregation::Percentile(0.9))
+        );
+        let aov = orders.get_metric("average_order_value").unwrap();
+        assert_eq!(aov.r#type, MetricType::Derived);
+        assert_eq!(aov.sql.as_deref(), Some("revenue / NULLIF(count, 0)"));
+
+        let customers = graph.get_model("customers").unwrap();
+        assert_eq!(customers.primary_key, "id");
+        assert_eq!(
+            customers.get_dimension("region").unwrap().label.as_deref(),
+            Some("Sales Region")
+        );
+        assert_eq!(
+            customers.get_metric("customer_count").unwrap().agg,
+            Some(Aggregation::CountDistinct)
+        );
+
+        // The explore join becomes a relationship; no FK inference on top.
+        assert_eq!(orders.relationships.len(), 1);
+        let rel = orders.get_relationship("customers").unwrap();
+        assert_eq!(rel.r#type, RelationshipType::ManyToOne);
+        assert_eq!(rel.foreign_key.as_deref(), Some("customer_id"));
+        assert_eq!(rel.primary_key.as_deref(), Some("id"));
+        assert!(customers.relationships.is_empty());
+    }
+
+    #[test]
+    fn test_load_from_string_auto_detects_osi() {
+        let yaml = r#"
+semantic_model:
+  - name: shop
+    datasets:
+      - name: orders
+        source: public.orders
+        primary_key: [order_id]
+        fields:
+          - name: customer_id
+            expression:
+              dialects:
+                - dialect: ANSI_SQL
+                  expression: customer_id
+      - name: customers
+        source: public.customers
+        primary_key: [customer_id]
+        fields:
+          - name: customer_id
+            expression:
+              dialects:
+                - dialect: ANSI_SQL
+                  expression: customer_id
+    relationships:
+      - name: orders_to_customers
+        from: orders
+        to: customers
+        from_columns: [customer_id]
+        to_columns: [customer_id]
+    metrics:
+      - name: total_revenue
+        expression:
+          dialects:
+            - dialect: ANSI_SQL
+              expression: SUM(orders.amount)
+"#;
 
         let loaded = load_from_string_with_metadata(yaml).unwrap();
         let graph = &loaded.graph;
@@ -1479,6 +1980,53 @@ models:
         assert!(graph.get_model("orders").is_some());
     }
 
+    #[test]
+    fn test_load_from_string_applies_default_dialect() {
+        use crate::sql::{SemanticQuery, SqlGenerator};
+
+        let yaml = r#"
+dialect: bigquery
+models:
+  - name: events
+    table: events
+    primary_key: event_id
+    dimensions:
+      - name: plan
+        type: categorical
+        sql: "properties->>'plan'"
+    metrics:
+      - name: event_count
+        agg: count
+"#;
+
+        let graph = load_from_string(yaml).unwrap();
+        assert_eq!(graph.dialect(), Some(DialectType::BigQuery));
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["events.event_count".into()])
+            .with_dimensions(vec!["events.plan".into()]);
+        let sql = SqlGenerator::new(&graph).generate(&query).unwrap();
+        assert!(
+            sql.contains("JSON_VALUE(events_cte.properties, '$.plan') AS plan"),
+            "{sql}"
+        );
+
+        let duckdb_sql = SqlGenerator::new(&graph)
+            .with_dialect(DialectType::DuckDB)
+            .generate(&query)
+            .unwrap();
+        assert!(
+            duckdb_sql.contains("events_cte.properties->>'plan' AS plan"),
+            "{duckdb_sql}"
+        );
+
+        let err = load_from_string("dialect: nosuchdb\nmodels: []").unwrap_err();
+        assert!(
+            err.to_string().contains("Unsupported dialect 'nosuchdb'"),
+            "{err}"
+        );
+    }
+
     #[test]
     fn test_load_from_string_accepts_missing_native_version_as_version_one() {
         let yaml = r#"
@@ -1870,6 +2418,114 @@ metrics:
         assert!(orders.get_metric("multi_platform_users").is_none());
     }
 
+    #[test]
+    fn test_load_from_directory_lenient_reports_broken_files() {
+        let dir = std::env::temp_dir().join(format!(
+            "sidemantic-rs-loader-lenient-{}-{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("orders.yml"),
+            r#"
+models:
+  - name: orders
+    table: orders
+    primary_key: order_id
+    metrics:
+      - name: revenue
+        agg: sum
+        sql: amount
+"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("broken.yml"),
+            "models:\n  - name: customers\n    table: [unclosed\n",
+        )
+        .unwrap();
+
+        assert!(load_from_directory(&dir).is_err());
+        let (graph, errors) = load_from_directory_lenient(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(graph.get_model("orders").is_some());
+        assert!(graph.get_model("customers").is_none());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, dir.join("broken.yml"));
+    }
+
+    #[test]
+    fn test_load_from_directory_lenient_skips_cross_file_errors() {
+        let dir = std::env::temp_dir().join(format!(
+            "sidemantic-rs-loader-lenient-cross-{}-{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("orders.yml"),
+            r#"
+models:
+  - name: orders
+    table: orders
+    primary_key: order_id
+    metrics:
+      - name: revenue
+        agg: sum
+        sql: amount
+"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("child.yml"),
+            r#"
+models:
+  - name: child_orders
+    extends: missing_parent
+    table: child_orders
+    primary_key: order_id
+"#,
+        )
+        .unwrap();
+        for file in ["metrics.yml", "metrics_copy.yml"] {
+            fs::write(
+                dir.join(file),
+                r#"
+metrics:
+  - name: total_amount
+    agg: sum
+    sql: orders.amount
+"#,
+            )
+            .unwrap();
+        }
+
+        assert!(load_from_directory(&dir).is_err());
+        let (graph, errors) = load_from_directory_lenient(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(graph.get_model("orders").is_some());
+        assert!(graph.get_model("child_orders").is_none());
+        assert!(graph
+            .get_model("orders")
+            .and_then(|orders| orders.get_metric("total_amount"))
+            .is_some());
+        let mut paths: Vec<_> = errors.iter().map(|error| error.path.clone()).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![dir.join("child.yml"), dir.join("metrics_copy.yml")]
+        );
+    }
+
     #[test]
     fn test_load_from_directory_resolves_cross_file_inheritance() {
         let dir = std::env::temp_dir().join(format!(
@@ -1922,6 +2578,199 @@ models:
         assert!(orders.get_metric("net_revenue").is_some());
     }
 
+    #[test]
+    fn test_load_from_directory_mixes_native_and_cube_files() {
+        let dir = std::env::temp_dir().join(format!(
+            "sidemantic-rs-loader-mixed-{}-{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        fs::create_dir_all(dir.join("cube")).unwrap();
+        fs::write(
+            dir.join("customers.yml"),
+            r#"
+models:
+  - name: customers
+    table: customers
+    primary_key: id
+    dimensions:
+      - name: region
+        type: categorical
+"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("cube").join("orders.yaml"),
+            r#"
+cubes:
+  - name: orders
+    sql_table: orders
+    dimensions:
+      - name: status
+        sql: "${CUBE}.status"
+        type: string
+    measures:
+      - name: revenue
+        sql: "${CUBE}.amount"
+        type: sum
+"#,
+        )
+        .unwrap();
+        fs::write(dir.join("README.md"), "not a model file").unwrap();
+
+        let loaded = load_from_directory_with_metadata(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(loaded.graph.get_model("customers").is_some());
+        let orders = loaded.graph.get_model("orders").unwrap();
+        assert!(orders.get_metric("revenue").is_some());
+        assert_eq!(
+            loaded.model_sources["customers"].source_format,
+            "Sidemantic"
+        );
+        assert_eq!(loaded.model_sources["orders"].source_format, "Cube");
+        assert_eq!(
+            loaded.model_sources["orders"].source_file.as_deref(),
+            Some(
+                std::path::Path::new("cube")
+                    .join("orders.yaml")
+                    .to_str()
+                    .unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn test_load_from_directory_resolves_metricflow_entities_across_files() {
+        let dir = std::env::temp_dir().join(format!(
+            "sidemantic-rs-loader-metricflow-{}-{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("orders.yml"),
+            r#"
+semantic_models:
+  - name: orders
+    model: ref('orders')
+    entities:
+      - name: order
+        type: primary
+        expr: order_id
+      - name: customer
+        type: foreign
+        expr: customer_id
+    measures:
+      - name: revenue
+        agg: sum
+        expr: amount
+"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("customers.yml"),
+            r#"
+semantic_models:
+  - name: customers
+    model: ref('customers')
+    entities:
+      - name: customer
+        type: primary
+        expr: customer_key
+    dimensions:
+      - name: region
+        type: categorical
+"#,
+        )
+        .unwrap();
+
+        let graph = load_from_directory(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let orders = graph.get_model("orders").unwrap();
+        assert_eq!(orders.relationships.len(), 1);
+        let relationship = &orders.relationships[0];
+        assert_eq!(relationship.name, "customers");
+        assert_eq!(relationship.foreign_key.as_deref(), Some("customer_id"));
+        assert_eq!(relationship.primary_key.as_deref(), Some("customer_key"));
+    }
+
+    #[test]
+    fn test_load_from_directory_resolves_lookml_join_keys_across_files() {
+        let dir = std::env::temp_dir().join(format!(
+            "sidemantic-rs-loader-lookml-{}-{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("views.lkml"),
+            r#"
+view: orders {
+  sql_table_name: orders ;;
+  dimension: id {
+    primary_key: yes
+  }
+  dimension: user_id {}
+}
+view: users {
+  sql_table_name: users ;;
+  dimension: user_key {
+    primary_key: yes
+  }
+}
+"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("orders.explore.lkml"),
+            r#"
+explore: orders {
+  join: users {
+    relationship: many_to_one
+    foreign_key: user_id
+  }
+}
+"#,
+        )
+        .unwrap();
+
+        let graph = load_from_directory(&dir).unwrap();
+
+        let relationship = graph
+            .get_model("orders")
+            .unwrap()
+            .get_relationship("users")
+            .unwrap();
+        assert_eq!(relationship.foreign_key.as_deref(), Some("user_id"));
+        assert_eq!(relationship.primary_key.as_deref(), Some("user_key"));
+
+        // A join to a view no file declares has no key to join on.
+        fs::remove_file(dir.join("views.lkml")).unwrap();
+        fs::write(
+            dir.join("views.lkml"),
+            "view: orders {\n  dimension: id {\n    primary_key: yes\n  }\n}\n",
+        )
+        .unwrap();
+        let err = load_from_directory(&dir).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(
+            err.to_string()
+                .contains("Relationship from 'orders' targets unknown model 'users'"),
+            "{err}"
+        );
+    }
+
     #[test]
     fn test_load_from_directory_merges_non_osi_root_metadata() {
         let dir = std::env::temp_dir().join(format!(
@@ -2210,6 +3059,65 @@ models:
         assert!(us_orders.get_metric("order_count").is_some()); // own
     }
 
+    #[test]
+    fn test_loaded_graph_serializes_byte_stable_in_definition_order() {
+        let yaml = r#"
+models:
+  - name: zeta_orders
+    table: orders
+    primary_key: order_id
+    dimensions:
+      - name: status
+        type: categorical
+      - name: region
+        type: categorical
+      - name: channel
+        type: categorical
+    metrics:
+      - name: revenue
+        agg: sum
+        sql: amount
+      - name: order_count
+        agg: count
+
+  - name: alpha_orders
+    extends: zeta_orders
+    dimensions:
+      - name: region
+        type: categorical
+        sql: region_code
+      - name: category
+        type: categorical
+    metrics:
+      - name: avg_amount
+        agg: avg
+        sql: amount
+"#;
+
+        let serialize = || {
+            let graph = load_from_string(yaml).unwrap();
+            let models: Vec<&Model> = graph.models().collect();
+            serde_json::to_string(&models).unwrap()
+        };
+
+        let first = serialize();
+        for _ in 0..5 {
+            assert_eq!(serialize(), first);
+        }
+
+        let graph = load_from_string(yaml).unwrap();
+        let model_names: Vec<&str> = graph.models().map(|m| m.name.as_str()).collect();
+        assert_eq!(model_names, vec!["zeta_orders", "alpha_orders"]);
+        let alpha = graph.get_model("alpha_orders").unwrap();
+        let dimension_names: Vec<&str> = alpha.dimensions.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(
+            dimension_names,
+            vec!["status", "region", "channel", "category"]
+        );
+        let metric_names: Vec<&str> = alpha.metrics.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(metric_names, vec!["revenue", "order_count", "avg_amount"]);
+    }
+
     #[test]
     fn test_load_from_string_parses_top_level_parameters() {
         let yaml = r#"
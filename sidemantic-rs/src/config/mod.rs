@@ -1,7 +1,7 @@
 //! Configuration loading for semantic layer definitions
 //!
-//! Supports loading from YAML files in native Sidemantic, Cube.js, and OSI
-//! formats, as well as SQL-based definitions.
+//! Supports loading native Sidemantic, Cube.js, dbt MetricFlow, and OSI YAML,
+//! LookML `.lkml` files, and SQL-based definitions.
 
 mod loader;
 pub(crate) mod schema;
@@ -9,9 +9,10 @@ mod sql_parser;
 
 pub use crate::adapters::cube::CubeConfig;
 pub use loader::{
-    load_from_directory, load_from_directory_with_metadata, load_from_file,
-    load_from_file_with_metadata, load_from_sql_string_with_metadata, load_from_string,
-    load_from_string_with_metadata, ConfigFormat, LoadedGraphMetadata, LoadedModelSource,
+    load_from_directory, load_from_directory_lenient, load_from_directory_with_metadata,
+    load_from_file, load_from_file_with_metadata, load_from_sql_string_with_metadata,
+    load_from_string, load_from_string_with_metadata, ConfigFormat, LoadError, LoadedGraphMetadata,
+    LoadedModelSource,
 };
 pub use schema::{ModelConfig, SidemanticConfig};
 pub use sql_parser::{
@@ -2,12 +2,16 @@
 //!
 //! Supports both native Sidemantic format and Cube.js format.
 
+use std::collections::BTreeMap;
+
+use polyglot_sql::DialectType;
 use serde::{Deserialize, Serialize};
 
 use crate::core::{
     Aggregation, CohortInnerMetric, ComparisonCalculation, ComparisonType, Dimension,
-    DimensionType, Metric, MetricType, Model, Parameter, ParameterType, PreAggregation,
-    PreAggregationType, RefreshKey, Relationship, RelationshipType, Segment, TimeGrain,
+    DimensionType, JoinType, Metric, MetricType, Model, NonAdditiveWindow, Parameter,
+    ParameterType, PreAggregation, PreAggregationType, RefreshKey, Relationship, RelationshipType,
+    Segment, TimeGrain,
 };
 
 pub const NATIVE_FORMAT_VERSION: u32 = 1;
@@ -37,6 +41,12 @@ pub struct SidemanticConfig {
     /// and Snowflake Cortex top-level sections from the Python native export).
     #[serde(default)]
     pub metadata: Option<serde_json::Value>,
+    /// Default SQL dialect for generators built from the loaded graph
+    #[serde(default)]
+    pub dialect: Option<String>,
+    /// Expose one-hop related dimensions on each model as `relationship__dimension`
+    #[serde(default)]
+    pub flatten: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,6 +57,8 @@ pub struct ModelConfig {
     pub extends: Option<String>,
     pub table: Option<String>,
     pub sql: Option<String>,
+    #[serde(default)]
+    pub columns: Option<Vec<String>>,
     pub source_uri: Option<String>,
     #[serde(default = "default_primary_key_config")]
     pub primary_key: KeyConfig,
@@ -54,6 +66,9 @@ pub struct ModelConfig {
     pub primary_key_columns: Option<Vec<String>>,
     #[serde(default)]
     pub unique_keys: Option<Vec<Vec<String>>>,
+    /// Columns that together identify one row (defaults to the primary key)
+    #[serde(default)]
+    pub grain: Vec<String>,
     pub description: Option<String>,
     pub label: Option<String>,
     #[serde(default)]
@@ -61,6 +76,8 @@ pub struct ModelConfig {
     #[serde(default)]
     pub meta: Option<serde_json::Value>,
     #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
     pub auto_dimensions: bool,
     #[serde(default)]
     pub dimensions: Vec<DimensionConfig>,
@@ -70,6 +87,9 @@ pub struct ModelConfig {
     pub relationships: Vec<RelationshipConfig>,
     #[serde(default)]
     pub segments: Vec<SegmentConfig>,
+    /// Filters applied to every query of the model, inside its source subquery
+    #[serde(default)]
+    pub default_filters: Vec<String>,
     #[serde(default)]
     pub pre_aggregations: Vec<PreAggregationConfig>,
     pub default_time_dimension: Option<String>,
@@ -98,9 +118,15 @@ pub enum KeyConfig {
 }
 
 impl KeyConfig {
+    /// A single string may list composite key columns separated by commas.
     fn into_columns(self) -> Vec<String> {
         match self {
-            Self::Single(value) => vec![value],
+            Self::Single(value) => value
+                .split(',')
+                .map(str::trim)
+                .filter(|column| !column.is_empty())
+                .map(str::to_string)
+                .collect(),
             Self::Multiple(values) => values,
         }
     }
@@ -144,8 +170,22 @@ pub struct DimensionConfig {
     pub cortex_search_service_name: Option<String>,
     pub format: Option<String>,
     pub value_format_name: Option<String>,
+    /// Display labels keyed by raw value.
+    #[serde(default)]
+    pub value_labels: Option<BTreeMap<String, String>>,
+    /// Label for NULL values when grouping and displaying.
+    #[serde(default)]
+    pub null_label: Option<String>,
     pub parent: Option<String>,
     pub window: Option<String>,
+    /// Pass-through reference to another model's dimension (`model.dimension`).
+    #[serde(default, rename = "ref")]
+    pub reference: Option<String>,
+    /// Approximate number of distinct values.
+    #[serde(default)]
+    pub cardinality_hint: Option<u64>,
+    #[serde(default)]
+    pub tags: Vec<String>,
     #[serde(default = "default_public")]
     pub public: bool,
 }
@@ -158,6 +198,8 @@ pub struct MetricConfig {
     #[serde(default, rename = "type")]
     pub metric_type: Option<String>,
     pub agg: Option<String>,
+    /// Fraction in [0, 1] for `percentile` / `approx_percentile` aggregations
+    pub percentile: Option<f64>,
     #[serde(default, alias = "expr", alias = "measure")]
     pub sql: Option<String>,
     #[serde(default, rename = "metrics", skip_serializing_if = "Option::is_none")]
@@ -170,7 +212,12 @@ pub struct MetricConfig {
     pub window_expression: Option<String>,
     pub window_frame: Option<String>,
     pub window_order: Option<String>,
+    pub window_periods: Option<usize>,
+    pub window_grain: Option<String>,
     pub base_metric: Option<String>,
+    /// Target metric that `attainment` and `variance_to_target` metrics
+    /// compare `base_metric` against.
+    pub target: Option<String>,
     pub comparison_type: Option<String>,
     pub time_offset: Option<String>,
     pub calculation: Option<String>,
@@ -192,6 +239,9 @@ pub struct MetricConfig {
     pub value_format_name: Option<String>,
     pub drill_fields: Option<Vec<String>>,
     pub non_additive_dimension: Option<String>,
+    pub non_additive_window: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
     #[serde(default)]
     pub filters: Vec<String>,
     pub description: Option<String>,
@@ -235,8 +285,11 @@ pub struct RelationshipConfig {
     #[serde(default)]
     pub related_foreign_key_columns: Option<Vec<String>>,
     /// Custom SQL join condition using {from} and {to} placeholders
+    #[serde(alias = "sql_on")]
     pub sql: Option<String>,
     #[serde(default)]
+    pub join_type: Option<JoinType>,
+    #[serde(default)]
     pub metadata: Option<serde_json::Value>,
 }
 
@@ -244,7 +297,8 @@ pub struct RelationshipConfig {
 #[serde(deny_unknown_fields)]
 pub struct SegmentConfig {
     pub name: String,
-    pub sql: String,
+    /// A single predicate, or a list of conditions combined with AND
+    pub sql: SegmentSqlConfig,
     pub description: Option<String>,
     #[serde(default = "default_public")]
     pub public: bool,
@@ -487,14 +541,45 @@ impl SidemanticConfig {
             validate_metric_config(metric, &format!("metrics.{}", metric.name))?;
         }
 
+        self.default_dialect()?;
+
         Ok(())
     }
 
+    /// Parse the top-level `dialect`, if set.
+    pub fn default_dialect(&self) -> crate::error::Result<Option<DialectType>> {
+        let Some(dialect) = self.dialect.as_deref() else {
+            return Ok(None);
+        };
+        dialect
+            .trim()
+            .to_ascii_lowercase()
+            .parse::<DialectType>()
+            .map(Some)
+            .map_err(|_| {
+                crate::error::SidemanticError::validation_issue(
+                    "unsupported_dialect",
+                    None,
+                    "dialect",
+                    Some(dialect),
+                    format!("Unsupported dialect '{dialect}'"),
+                )
+            })
+    }
+
     /// Convert to core models, top-level metrics, and top-level parameters.
     pub fn into_parts(self) -> crate::error::Result<(Vec<Model>, Vec<Metric>, Vec<Parameter>)> {
         self.validate_contract()?;
-        let models = self.models.into_iter().map(|m| m.into_model()).collect();
-        let metrics = self.metrics.into_iter().map(|m| m.into_metric()).collect();
+        let models = self
+            .models
+            .into_iter()
+            .map(|m| m.into_model())
+            .collect::<crate::error::Result<_>>()?;
+        let metrics = self
+            .metrics
+            .into_iter()
+            .map(|m| m.into_metric())
+            .collect::<crate::error::Result<_>>()?;
         let parameters = self
             .parameters
             .into_iter()
@@ -511,7 +596,7 @@ impl SidemanticConfig {
 
 impl ModelConfig {
     /// Convert to core Model type
-    pub fn into_model(self) -> Model {
+    pub fn into_model(self) -> crate::error::Result<Model> {
         let primary_key_columns = self
             .primary_key_columns
             .filter(|columns| !columns.is_empty())
@@ -521,21 +606,27 @@ impl ModelConfig {
             .cloned()
             .unwrap_or_else(default_primary_key);
 
-        Model {
+        Ok(Model {
             name: self.name,
             table: self.table,
             sql: self.sql,
+            columns: self.columns,
             source_uri: self.source_uri,
             extends: self.extends,
             primary_key,
             primary_key_columns,
             unique_keys: self.unique_keys,
+            grain: self.grain,
             dimensions: self
                 .dimensions
                 .into_iter()
                 .map(|d| d.into_dimension())
                 .collect(),
-            metrics: self.metrics.into_iter().map(|m| m.into_metric()).collect(),
+            metrics: self
+                .metrics
+                .into_iter()
+                .map(|m| m.into_metric())
+                .collect::<crate::error::Result<_>>()?,
             relationships: self
                 .relationships
                 .into_iter()
@@ -546,6 +637,7 @@ impl ModelConfig {
                 .into_iter()
                 .map(|s| s.into_segment())
                 .collect(),
+            default_filters: self.default_filters,
             pre_aggregations: self
                 .pre_aggregations
                 .into_iter()
@@ -557,7 +649,8 @@ impl ModelConfig {
             description: self.description,
             metadata: self.metadata,
             meta: self.meta,
-        }
+            tags: self.tags,
+        })
     }
 }
 
@@ -627,15 +720,20 @@ impl DimensionConfig {
             meta: self.meta,
             format: self.format,
             value_format_name: self.value_format_name,
+            value_labels: self.value_labels,
+            null_label: self.null_label,
             parent: self.parent,
             window: self.window,
+            reference: self.reference,
+            cardinality_hint: self.cardinality_hint,
+            tags: self.tags,
             public: self.public,
         }
     }
 }
 
 impl MetricConfig {
-    fn into_metric(self) -> Metric {
+    fn into_metric(self) -> crate::error::Result<Metric> {
         let explicit_metric_type = self.metric_type.as_deref().map(str::to_ascii_lowercase);
         let can_normalize_inline_aggregation =
             matches!(explicit_metric_type.as_deref(), None | Some("simple"));
@@ -647,6 +745,22 @@ impl MetricConfig {
             None
         };
 
+        // Target metrics compare `base_metric` (the actual) with `target`,
+        // expanding to the ratio or derived metric their constructors build.
+        let target_metric = match (
+            explicit_metric_type.as_deref(),
+            &self.base_metric,
+            &self.target,
+        ) {
+            (Some("attainment"), Some(actual), Some(target)) => {
+                Some(Metric::attainment(&self.name, actual, target))
+            }
+            (Some("variance_to_target"), Some(actual), Some(target)) => {
+                Some(Metric::variance_to_target(&self.name, actual, target))
+            }
+            _ => None,
+        };
+
         let metric_type = match explicit_metric_type.as_deref() {
             Some("simple") => MetricType::Simple,
             Some("derived") => MetricType::Derived,
@@ -656,6 +770,7 @@ impl MetricConfig {
             Some("conversion") => MetricType::Conversion,
             Some("retention") => MetricType::Retention,
             Some("cohort") => MetricType::Cohort,
+            Some("pct_of_total" | "percent_of_total") => MetricType::PctOfTotal,
             _ => {
                 if inline_aggregation.is_none() && self.agg.is_none() && self.sql.is_some() {
                     MetricType::Derived
@@ -665,16 +780,35 @@ impl MetricConfig {
             }
         };
 
-        let agg = self
-            .agg
-            .as_deref()
-            .map(parse_aggregation)
-            .or_else(|| inline_aggregation.as_ref().map(|(agg, _)| agg.clone()));
+        let agg = match self.agg.as_deref() {
+            Some(agg) => Some(parse_aggregation(agg, self.percentile, &self.name)?),
+            None => inline_aggregation.as_ref().map(|(agg, _)| agg.clone()),
+        };
         let sql = inline_aggregation
             .as_ref()
             .and_then(|(_, inner_sql)| inner_sql.clone())
             .or(self.sql);
+        let (metric_type, sql, numerator, denominator, base_metric, value_format_name) =
+            match target_metric {
+                Some(target_metric) => (
+                    target_metric.r#type,
+                    target_metric.sql,
+                    target_metric.numerator,
+                    target_metric.denominator,
+                    None,
+                    self.value_format_name.or(target_metric.value_format_name),
+                ),
+                None => (
+                    metric_type,
+                    sql,
+                    self.numerator,
+                    self.denominator,
+                    self.base_metric,
+                    self.value_format_name,
+                ),
+            };
         let grain_to_date = self.grain_to_date.as_deref().and_then(parse_time_grain);
+        let window_grain = self.window_grain.as_deref().and_then(parse_time_grain);
         let comparison_type = self
             .comparison_type
             .as_deref()
@@ -683,16 +817,30 @@ impl MetricConfig {
             .calculation
             .as_deref()
             .and_then(parse_comparison_calculation);
-        let inner_metrics = self.inner_metrics.map(|items| {
-            items
-                .into_iter()
-                .map(|item| CohortInnerMetric {
-                    name: item.name,
-                    agg: item.agg.as_deref().map(parse_aggregation),
-                    sql: item.sql,
-                })
-                .collect()
-        });
+        let non_additive_window = self
+            .non_additive_window
+            .as_deref()
+            .and_then(parse_non_additive_window)
+            .unwrap_or_default();
+        let inner_metrics = self
+            .inner_metrics
+            .map(|items| {
+                items
+                    .into_iter()
+                    .map(|item| {
+                        Ok(CohortInnerMetric {
+                            agg: item
+                                .agg
+                                .as_deref()
+                                .map(|agg| parse_aggregation(agg, None, &item.name))
+                                .transpose()?,
+                            name: item.name,
+                            sql: item.sql,
+                        })
+                    })
+                    .collect::<crate::error::Result<Vec<_>>>()
+            })
+            .transpose()?;
 
         let metadata = merge_metadata_extras(
             self.metadata,
@@ -702,14 +850,14 @@ impl MetricConfig {
                 .collect(),
         );
 
-        Metric {
+        Ok(Metric {
             name: self.name,
             extends: self.extends,
             r#type: metric_type,
             agg,
             sql,
-            numerator: self.numerator,
-            denominator: self.denominator,
+            numerator,
+            denominator,
             offset_window: self.offset_window,
             filters: self.filters,
             label: self.label,
@@ -721,7 +869,9 @@ impl MetricConfig {
             window_expression: self.window_expression,
             window_frame: self.window_frame,
             window_order: self.window_order,
-            base_metric: self.base_metric,
+            window_periods: self.window_periods,
+            window_grain,
+            base_metric,
             comparison_type,
             time_offset: self.time_offset,
             calculation,
@@ -739,11 +889,13 @@ impl MetricConfig {
             having: self.having,
             fill_nulls_with: self.fill_nulls_with,
             format: self.format,
-            value_format_name: self.value_format_name,
+            value_format_name,
             drill_fields: self.drill_fields,
             non_additive_dimension: self.non_additive_dimension,
+            non_additive_window,
+            tags: self.tags,
             public: self.public,
-        }
+        })
     }
 }
 
@@ -791,18 +943,29 @@ impl RelationshipConfig {
                 .related_foreign_key_columns
                 .filter(|columns| !columns.is_empty()),
             sql: self.sql,
+            join_type: self.join_type,
             metadata: self.metadata,
         }
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SegmentSqlConfig {
+    Single(String),
+    All(Vec<String>),
+}
+
 impl SegmentConfig {
     fn into_segment(self) -> Segment {
-        Segment {
-            name: self.name,
-            sql: self.sql,
+        let segment = Segment {
             description: self.description,
             public: self.public,
+            ..Segment::new(self.name)
+        };
+        match self.sql {
+            SegmentSqlConfig::Single(sql) => segment.with_sql(sql),
+            SegmentSqlConfig::All(conditions) => conditions.into_iter().fold(segment, Segment::and),
         }
     }
 }
@@ -878,7 +1041,7 @@ pub(crate) fn metric_from_sql_expression(
     sql: Option<String>,
     description: Option<String>,
     meta: Option<serde_json::Value>,
-) -> Metric {
+) -> crate::error::Result<Metric> {
     MetricConfig {
         name,
         sql,
@@ -897,7 +1060,7 @@ pub(crate) fn metrics_from_config_yaml(yaml: &str) -> crate::error::Result<Vec<M
     let configs: Vec<MetricConfig> = serde_yaml::from_str(yaml).map_err(|e| {
         crate::error::SidemanticError::Validation(format!("failed to parse metrics: {e}"))
     })?;
-    Ok(configs.into_iter().map(MetricConfig::into_metric).collect())
+    configs.into_iter().map(MetricConfig::into_metric).collect()
 }
 
 // =============================================================================
@@ -966,8 +1129,38 @@ fn validate_metric_config(metric: &MetricConfig, field_path: &str) -> crate::err
             "conversion",
             "retention",
             "cohort",
+            "pct_of_total",
+            "percent_of_total",
+            "attainment",
+            "variance_to_target",
         ],
     )?;
+    let target_type = metric
+        .metric_type
+        .as_deref()
+        .map(str::to_ascii_lowercase)
+        .filter(|metric_type| matches!(metric_type.as_str(), "attainment" | "variance_to_target"));
+    match (&target_type, &metric.target) {
+        (Some(metric_type), _) if metric.base_metric.is_none() || metric.target.is_none() => {
+            return Err(crate::error::SidemanticError::validation_issue(
+                "missing_target_metric",
+                None,
+                &format!("{field_path}.target"),
+                None,
+                format!("Metrics of type '{metric_type}' require 'base_metric' and 'target'"),
+            ));
+        }
+        (None, Some(target)) => {
+            return Err(crate::error::SidemanticError::validation_issue(
+                "unexpected_target_metric",
+                None,
+                &format!("{field_path}.target"),
+                Some(target),
+                "Metric 'target' only applies to attainment and variance_to_target metrics",
+            ));
+        }
+        _ => {}
+    }
     validate_optional_enum(
         metric.agg.as_deref(),
         &format!("{field_path}.agg"),
@@ -985,14 +1178,47 @@ fn validate_metric_config(metric: &MetricConfig, field_path: &str) -> crate::err
             "stddev_pop",
             "variance",
             "variance_pop",
+            "percentile",
+            "approx_percentile",
+            "approx_count_distinct",
             "expression",
         ],
     )?;
+    let percentile_agg = metric
+        .agg
+        .as_deref()
+        .is_some_and(|agg| matches!(agg, "percentile" | "approx_percentile"));
+    match metric.percentile {
+        Some(fraction) if !(0.0..=1.0).contains(&fraction) => {
+            return Err(crate::error::SidemanticError::validation_issue(
+                "invalid_percentile",
+                None,
+                &format!("{field_path}.percentile"),
+                Some(&fraction.to_string()),
+                "Metric percentile must be between 0 and 1",
+            ));
+        }
+        None if percentile_agg => {
+            return Err(crate::error::SidemanticError::validation_issue(
+                "missing_percentile",
+                None,
+                &format!("{field_path}.percentile"),
+                None,
+                "Percentile aggregations require a 'percentile' fraction (e.g. 0.95)",
+            ));
+        }
+        _ => {}
+    }
     validate_optional_enum(
         metric.grain_to_date.as_deref(),
         &format!("{field_path}.grain_to_date"),
         PERIOD_GRAINS,
     )?;
+    validate_optional_enum(
+        metric.window_grain.as_deref(),
+        &format!("{field_path}.window_grain"),
+        PERIOD_GRAINS,
+    )?;
     validate_optional_enum(
         metric.retention_granularity.as_deref(),
         &format!("{field_path}.retention_granularity"),
@@ -1011,7 +1237,12 @@ fn validate_metric_config(metric: &MetricConfig, field_path: &str) -> crate::err
     validate_optional_enum(
         metric.calculation.as_deref(),
         &format!("{field_path}.calculation"),
-        &["difference", "percent_change", "ratio"],
+        &["difference", "percent_change", "ratio", "previous_value"],
+    )?;
+    validate_optional_enum(
+        metric.non_additive_window.as_deref(),
+        &format!("{field_path}.non_additive_window"),
+        &["last", "first", "max", "min"],
     )?;
 
     if let Some(inner_metrics) = metric.inner_metrics.as_ref() {
@@ -1042,8 +1273,21 @@ fn validate_metric_config(metric: &MetricConfig, field_path: &str) -> crate::err
     Ok(())
 }
 
-fn parse_aggregation(s: &str) -> Aggregation {
-    match s.to_lowercase().as_str() {
+/// Parse an `agg` name. Percentile aggregations take their fraction from the
+/// metric's `percentile` field and fail without one.
+fn parse_aggregation(
+    s: &str,
+    percentile: Option<f64>,
+    metric_name: &str,
+) -> crate::error::Result<Aggregation> {
+    let percentile = || {
+        percentile.ok_or_else(|| {
+            crate::error::SidemanticError::Validation(format!(
+                "Metric '{metric_name}' uses '{s}' without a 'percentile' fraction"
+            ))
+        })
+    };
+    Ok(match s.to_lowercase().as_str() {
         "count" => Aggregation::Count,
         "count_distinct" | "countdistinct" => Aggregation::CountDistinct,
         "sum" => Aggregation::Sum,
@@ -1055,9 +1299,12 @@ fn parse_aggregation(s: &str) -> Aggregation {
         "stddev_pop" => Aggregation::StddevPop,
         "variance" => Aggregation::Variance,
         "variance_pop" | "var_pop" => Aggregation::VariancePop,
+        "percentile" => Aggregation::Percentile(percentile()?),
+        "approx_percentile" => Aggregation::ApproxPercentile(percentile()?),
+        "approx_count_distinct" => Aggregation::ApproxCountDistinct,
         "expression" => Aggregation::Expression,
         _ => Aggregation::Sum,
-    }
+    })
 }
 
 fn parse_inline_metric_aggregation(sql_expr: &str) -> Option<(Aggregation, Option<String>)> {
@@ -1116,7 +1363,10 @@ fn parse_inline_metric_aggregation(sql_expr: &str) -> Option<(Aggregation, Optio
             if inner.is_empty() {
                 None
             } else {
-                Some((parse_aggregation(&func), Some(inner.to_string())))
+                Some((
+                    parse_aggregation(&func, None, "").ok()?,
+                    Some(inner.to_string()),
+                ))
             }
         }
         "count" => {
@@ -1171,6 +1421,15 @@ fn parse_comparison_calculation(s: &str) -> Option<ComparisonCalculation> {
         "difference" => Some(ComparisonCalculation::Difference),
         "percent_change" => Some(ComparisonCalculation::PercentChange),
         "ratio" => Some(ComparisonCalculation::Ratio),
+        "previous_value" => Some(ComparisonCalculation::PreviousValue),
+        _ => None,
+    }
+}
+
+fn parse_non_additive_window(s: &str) -> Option<NonAdditiveWindow> {
+    match s.to_lowercase().as_str() {
+        "last" | "max" => Some(NonAdditiveWindow::Last),
+        "first" | "min" => Some(NonAdditiveWindow::First),
         _ => None,
     }
 }
@@ -1630,6 +1889,92 @@ models:
         assert_eq!(revenue_meta["synonyms"][0], "total revenue");
     }
 
+    #[test]
+    fn test_dimension_value_labels_flow_to_core_dimension() {
+        let yaml = r#"
+models:
+  - name: orders
+    table: orders
+    dimensions:
+      - name: status
+        type: categorical
+        value_labels:
+          P: Pending
+          S: Shipped
+"#;
+
+        let config: SidemanticConfig = serde_yaml::from_str(yaml).unwrap();
+        let (models, _, _) = config.into_parts().unwrap();
+        let status = models[0].get_dimension("status").unwrap();
+
+        let labels = status.value_labels.as_ref().expect("value labels kept");
+        assert_eq!(labels.get("P").map(String::as_str), Some("Pending"));
+        assert_eq!(labels.get("S").map(String::as_str), Some("Shipped"));
+
+        let json = serde_json::to_value(status).unwrap();
+        assert_eq!(json["value_labels"]["P"], "Pending");
+        assert_eq!(json["value_labels"]["S"], "Shipped");
+    }
+
+    #[test]
+    fn test_segment_sql_list_is_and_combined() {
+        let yaml = r#"
+models:
+  - name: orders
+    table: orders
+    primary_key: order_id
+    dimensions:
+      - name: status
+        type: categorical
+    metrics:
+      - name: revenue
+        agg: sum
+        sql: amount
+    segments:
+      - name: big_completed
+        sql:
+          - "{model}.status = 'completed'"
+          - "{model}.amount > 100"
+"#;
+
+        let config: SidemanticConfig = serde_yaml::from_str(yaml).unwrap();
+        let (models, _, _) = config.into_parts().unwrap();
+        assert_eq!(
+            models[0].segments[0].sql,
+            "({model}.status = 'completed') AND ({model}.amount > 100)"
+        );
+
+        let mut graph = crate::core::SemanticGraph::new();
+        graph.add_model(models[0].clone()).unwrap();
+        let sql = crate::sql::SqlGenerator::new(&graph)
+            .generate(
+                &crate::sql::SemanticQuery::new()
+                    .with_metrics(vec!["orders.revenue".into()])
+                    .with_segments(vec!["orders.big_completed".into()]),
+            )
+            .unwrap();
+        assert!(sql.contains("status = 'completed')"), "{sql}");
+        assert!(sql.contains("amount > 100)"), "{sql}");
+    }
+
+    #[test]
+    fn test_dimension_cardinality_hint_flows_to_core_dimension() {
+        let yaml = r#"
+models:
+  - name: orders
+    table: orders
+    dimensions:
+      - name: status
+        type: categorical
+        cardinality_hint: 5
+"#;
+
+        let config: SidemanticConfig = serde_yaml::from_str(yaml).unwrap();
+        let (models, _, _) = config.into_parts().unwrap();
+        let status = models[0].get_dimension("status").unwrap();
+        assert_eq!(status.cardinality_hint, Some(5));
+    }
+
     #[test]
     fn test_parse_many_to_many_relationship_fields() {
         let yaml = r#"
@@ -1705,6 +2050,80 @@ models:
         );
     }
 
+    #[test]
+    fn test_parse_comma_separated_keys_and_sql_on() {
+        let yaml = r#"
+models:
+  - name: orders
+    table: orders
+    relationships:
+      - name: customers
+        type: many_to_one
+        foreign_key: "tenant_id, customer_id"
+        primary_key: "tenant_id, id"
+      - name: invoices
+        type: one_to_one
+        sql_on: "{from}.order_id = {to}.order_id AND {from}.tenant_id = {to}.tenant_id"
+  - name: customers
+    table: customers
+  - name: invoices
+    table: invoices
+"#;
+
+        let config: SidemanticConfig = serde_yaml::from_str(yaml).unwrap();
+        let (models, _, _) = config.into_parts().unwrap();
+
+        let orders = models.iter().find(|m| m.name == "orders").unwrap();
+        let customers = orders.get_relationship("customers").unwrap();
+        assert_eq!(
+            customers.foreign_key_columns(),
+            vec!["tenant_id".to_string(), "customer_id".to_string()]
+        );
+        assert_eq!(
+            customers.primary_key_columns(),
+            vec!["tenant_id".to_string(), "id".to_string()]
+        );
+        let invoices = orders.get_relationship("invoices").unwrap();
+        assert_eq!(
+            invoices.custom_condition(),
+            Some("{from}.order_id = {to}.order_id AND {from}.tenant_id = {to}.tenant_id")
+        );
+    }
+
+    #[test]
+    fn test_parse_non_additive_window() {
+        let yaml = r#"
+models:
+  - name: balances
+    table: balances
+    dimensions:
+      - name: snapshot_date
+        type: time
+    metrics:
+      - name: ending_balance
+        agg: sum
+        sql: balance
+        non_additive_dimension: snapshot_date
+      - name: opening_balance
+        agg: sum
+        sql: balance
+        non_additive_dimension: snapshot_date
+        non_additive_window: first
+"#;
+
+        let config: SidemanticConfig = serde_yaml::from_str(yaml).unwrap();
+        let (models, _, _) = config.into_parts().unwrap();
+
+        let ending = models[0].get_metric("ending_balance").unwrap();
+        assert_eq!(
+            ending.non_additive_dimension.as_deref(),
+            Some("snapshot_date")
+        );
+        assert_eq!(ending.non_additive_window, NonAdditiveWindow::Last);
+        let opening = models[0].get_metric("opening_balance").unwrap();
+        assert_eq!(opening.non_additive_window, NonAdditiveWindow::First);
+    }
+
     #[test]
     fn test_parse_native_yaml_composite_keys() {
         let yaml = r#"
@@ -1747,6 +2166,68 @@ models:
         );
     }
 
+    #[test]
+    fn test_parse_native_yaml_target_metrics() {
+        let yaml = r#"
+models:
+  - name: sales
+    table: sales
+    metrics:
+      - name: revenue
+        agg: sum
+        sql: amount
+      - name: target_revenue
+        agg: sum
+        sql: target_amount
+      - name: revenue_attainment
+        type: attainment
+        base_metric: revenue
+        target: target_revenue
+      - name: revenue_variance
+        type: variance_to_target
+        base_metric: revenue
+        target: target_revenue
+"#;
+
+        let config: SidemanticConfig = serde_yaml::from_str(yaml).unwrap();
+        let (models, _, _) = config.into_parts().unwrap();
+        let sales = &models[0];
+
+        let attainment = sales.get_metric("revenue_attainment").unwrap();
+        assert_eq!(attainment.r#type, MetricType::Ratio);
+        assert_eq!(attainment.numerator.as_deref(), Some("revenue"));
+        assert_eq!(attainment.denominator.as_deref(), Some("target_revenue"));
+        assert_eq!(attainment.value_format_name.as_deref(), Some("percent"));
+        assert!(attainment.base_metric.is_none());
+
+        let variance = sales.get_metric("revenue_variance").unwrap();
+        assert_eq!(variance.r#type, MetricType::Derived);
+        assert_eq!(variance.sql_expr(), "revenue - target_revenue");
+
+        let missing_target = yaml.replace("        target: target_revenue\n", "");
+        let config: SidemanticConfig = serde_yaml::from_str(&missing_target).unwrap();
+        let err = config.into_parts().unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("require 'base_metric' and 'target'"),
+            "{err}"
+        );
+
+        let stray_target = r#"
+models:
+  - name: sales
+    table: sales
+    metrics:
+      - name: revenue
+        agg: sum
+        sql: amount
+        target: target_revenue
+"#;
+        let config: SidemanticConfig = serde_yaml::from_str(stray_target).unwrap();
+        let err = config.into_parts().unwrap_err();
+        assert!(err.to_string().contains("only applies to"), "{err}");
+    }
+
     #[test]
     fn test_parse_native_yaml_normalizes_inline_aggregate_metric() {
         let yaml = r#"
@@ -1830,4 +2311,44 @@ models:
             Some("SUM(orders.amount)")
         );
     }
+
+    #[test]
+    fn test_parse_percentile_metrics() {
+        let yaml = r#"
+models:
+  - name: requests
+    table: requests
+    primary_key: request_id
+    metrics:
+      - name: p95_latency
+        agg: percentile
+        percentile: 0.95
+        sql: latency_ms
+      - name: approx_p99_latency
+        agg: approx_percentile
+        percentile: 0.99
+        sql: latency_ms
+"#;
+        let config: SidemanticConfig = serde_yaml::from_str(yaml).unwrap();
+        let (models, _, _) = config.into_parts().unwrap();
+        let requests = &models[0];
+        assert_eq!(
+            requests.get_metric("p95_latency").unwrap().agg,
+            Some(Aggregation::Percentile(0.95))
+        );
+        assert_eq!(
+            requests.get_metric("approx_p99_latency").unwrap().agg,
+            Some(Aggregation::ApproxPercentile(0.99))
+        );
+
+        let missing_fraction = yaml.replace("        percentile: 0.95\n", "");
+        let config: SidemanticConfig = serde_yaml::from_str(&missing_fraction).unwrap();
+        let err = config.into_parts().unwrap_err();
+        assert!(err.to_string().contains("require a 'percentile'"), "{err}");
+
+        // Loaders that skip contract validation must not invent a fraction.
+        let err = metrics_from_config_yaml("- name: p50\n  agg: percentile\n  sql: latency_ms\n")
+            .unwrap_err();
+        assert!(err.to_string().contains("without a 'percentile'"), "{err}");
+    }
 }
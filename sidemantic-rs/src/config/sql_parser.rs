@@ -34,8 +34,9 @@ use regex::Regex;
 
 use crate::core::{
     Aggregation, CohortInnerMetric, ComparisonCalculation, ComparisonType, Dimension,
-    DimensionType, Index, Metric, MetricType, Model, Parameter, ParameterType, PreAggregation,
-    PreAggregationType, RefreshKey, Relationship, RelationshipType, Segment, TimeGrain,
+    DimensionType, Index, JoinType, Metric, MetricType, Model, NonAdditiveWindow, Parameter,
+    ParameterType, PreAggregation, PreAggregationType, RefreshKey, Relationship, RelationshipType,
+    Segment, TimeGrain,
 };
 use crate::error::{Result, SidemanticError};
 
@@ -310,6 +311,9 @@ fn parse_metric_type(value: Option<&String>) -> MetricType {
         Some(metric_type) if metric_type == "conversion" => MetricType::Conversion,
         Some(metric_type) if metric_type == "retention" => MetricType::Retention,
         Some(metric_type) if metric_type == "cohort" => MetricType::Cohort,
+        Some(metric_type) if metric_type == "pct_of_total" || metric_type == "percent_of_total" => {
+            MetricType::PctOfTotal
+        }
         _ => MetricType::Simple,
     }
 }
@@ -360,6 +364,15 @@ fn parse_comparison_calculation(value: Option<&String>) -> Option<ComparisonCalc
         "difference" => Some(ComparisonCalculation::Difference),
         "percent_change" => Some(ComparisonCalculation::PercentChange),
         "ratio" => Some(ComparisonCalculation::Ratio),
+        "previous_value" => Some(ComparisonCalculation::PreviousValue),
+        _ => None,
+    })
+}
+
+fn parse_non_additive_window(value: Option<&String>) -> Option<NonAdditiveWindow> {
+    value.and_then(|window| match window.to_lowercase().as_str() {
+        "last" | "max" => Some(NonAdditiveWindow::Last),
+        "first" | "min" => Some(NonAdditiveWindow::First),
         _ => None,
     })
 }
@@ -935,8 +948,13 @@ fn statement(input: &str) -> IResult<&str, Statement> {
     ))(input)
 }
 
-/// Skip comment line
+/// Skip a `--` comment line or a `/* ... */` block comment
 fn comment(input: &str) -> IResult<&str, ()> {
+    if let Ok((input, _)) = tag::<_, _, NomError<&str>>("/*")(input) {
+        let (input, _) = take_until("*/")(input)?;
+        let (input, _) = tag("*/")(input)?;
+        return Ok((input, ()));
+    }
     let (input, _) = tag("--")(input)?;
     let (input, _) = take_while(|c| c != '\n')(input)?;
     let (input, _) = opt(char('\n'))(input)?;
@@ -1365,7 +1383,7 @@ fn build_compact_model(
             }
             model
                 .segments
-                .push(Segment::new(segment_name, segment_sql.to_string()));
+                .push(Segment::new(segment_name).with_sql(segment_sql));
             continue;
         }
 
@@ -1850,6 +1868,10 @@ fn build_metric(props: &HashMap<String, String>) -> Option<Metric> {
     metric.window_expression = props.get("window_expression").cloned();
     metric.window_frame = props.get("window_frame").cloned();
     metric.window_order = props.get("window_order").cloned();
+    metric.window_periods = props
+        .get("window_periods")
+        .and_then(|value| value.parse().ok());
+    metric.window_grain = parse_time_grain(props.get("window_grain"));
     metric.base_metric = props.get("base_metric").cloned();
     metric.comparison_type = parse_comparison_type(props.get("comparison_type"));
     metric.time_offset = props.get("time_offset").cloned();
@@ -1921,6 +1943,8 @@ fn build_metric(props: &HashMap<String, String>) -> Option<Metric> {
     metric.format = props.get("format").cloned();
     metric.value_format_name = props.get("value_format_name").cloned();
     metric.non_additive_dimension = props.get("non_additive_dimension").cloned();
+    metric.non_additive_window =
+        parse_non_additive_window(props.get("non_additive_window")).unwrap_or_default();
 
     if let Some(fill_nulls_with) = props.get("fill_nulls_with") {
         let parsed = parse_literal(fill_nulls_with);
@@ -2030,6 +2054,9 @@ fn build_relationship(props: &HashMap<String, String>) -> Option<Relationship> {
         related_foreign_key_columns,
         sql: props.get("sql").cloned(),
         metadata: props.get("metadata").map(|value| parse_literal(value)),
+        join_type: props
+            .get("join_type")
+            .and_then(|value| JoinType::from_name(value)),
     })
 }
 
@@ -2287,6 +2314,19 @@ mod tests {
         let rel = model.get_relationship("customers").unwrap();
         assert_eq!(rel.r#type, RelationshipType::ManyToOne);
         assert_eq!(rel.foreign_key, Some("customer_id".to_string()));
+        assert_eq!(rel.join_type, None);
+    }
+
+    #[test]
+    fn test_parse_relationship_join_type() {
+        let sql = r#"
+            MODEL (name orders, table orders);
+            RELATIONSHIP (name customers, type many_to_one, foreign_key customer_id, join_type inner);
+        "#;
+
+        let model = parse_sql_model(sql).unwrap();
+        let rel = model.get_relationship("customers").unwrap();
+        assert_eq!(rel.join_type, Some(JoinType::Inner));
     }
 
     #[test]
@@ -2320,6 +2360,24 @@ mod tests {
         assert_eq!(model.metrics.len(), 1);
     }
 
+    #[test]
+    fn test_parse_skips_block_comments() {
+        let sql = r#"
+            MODEL (name orders, table orders);
+            /*
+            MODEL (name commented, table commented);
+            METRIC (name hidden, agg sum, sql amount);
+            */
+            METRIC (name revenue, agg sum, sql amount);
+        "#;
+
+        let models = parse_sql_models(sql).unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].name, "orders");
+        assert_eq!(models[0].metrics.len(), 1);
+        assert!(models[0].get_metric("hidden").is_none());
+    }
+
     #[test]
     fn test_simple_metric_syntax() {
         let sql = r#"
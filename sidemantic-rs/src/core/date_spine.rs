@@ -0,0 +1,322 @@
+//! Date spine: a generated calendar model with one row per day
+
+use polyglot_sql::DialectType;
+
+use super::model::{date_add_sql, date_trunc_sql, Dimension, DimensionType, Model};
+use crate::error::{Result, SidemanticError};
+
+/// Calendar attribute column projected by a [`DateSpine`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalendarAttribute {
+    Week,
+    Month,
+    Quarter,
+    Year,
+    /// Day of week, 0 (Sunday) through 6 (Saturday)
+    DayOfWeek,
+    IsWeekend,
+    /// Fiscal year starting in `start_month` (1-12), labelled by the calendar
+    /// year it ends in
+    FiscalYear {
+        start_month: u32,
+    },
+    /// Fiscal quarter (1-4) of a fiscal year starting in `start_month`
+    FiscalQuarter {
+        start_month: u32,
+    },
+}
+
+impl CalendarAttribute {
+    pub fn column_name(&self) -> &'static str {
+        match self {
+            CalendarAttribute::Week => "week",
+            CalendarAttribute::Month => "month",
+            CalendarAttribute::Quarter => "quarter",
+            CalendarAttribute::Year => "year",
+            CalendarAttribute::DayOfWeek => "day_of_week",
+            CalendarAttribute::IsWeekend => "is_weekend",
+            CalendarAttribute::FiscalYear { .. } => "fiscal_year",
+            CalendarAttribute::FiscalQuarter { .. } => "fiscal_quarter",
+        }
+    }
+
+    fn dimension_type(&self) -> DimensionType {
+        match self {
+            CalendarAttribute::Week
+            | CalendarAttribute::Month
+            | CalendarAttribute::Quarter
+            | CalendarAttribute::Year => DimensionType::Time,
+            CalendarAttribute::IsWeekend => DimensionType::Boolean,
+            CalendarAttribute::DayOfWeek
+            | CalendarAttribute::FiscalYear { .. }
+            | CalendarAttribute::FiscalQuarter { .. } => DimensionType::Numeric,
+        }
+    }
+
+    /// SQL expression computing this attribute from the `day` column
+    fn sql(&self, day: &str, dialect: DialectType) -> String {
+        match self {
+            CalendarAttribute::Week => day_trunc_sql("week", day, dialect),
+            CalendarAttribute::Month => day_trunc_sql("month", day, dialect),
+            CalendarAttribute::Quarter => day_trunc_sql("quarter", day, dialect),
+            CalendarAttribute::Year => day_trunc_sql("year", day, dialect),
+            CalendarAttribute::DayOfWeek => day_of_week_sql(day, dialect),
+            CalendarAttribute::IsWeekend => {
+                format!("{} IN (0, 6)", day_of_week_sql(day, dialect))
+            }
+            CalendarAttribute::FiscalYear { start_month } => format!(
+                "EXTRACT(YEAR FROM {})",
+                fiscal_shifted(day, *start_month, dialect)
+            ),
+            CalendarAttribute::FiscalQuarter { start_month } => format!(
+                "EXTRACT(QUARTER FROM {})",
+                fiscal_shifted(day, *start_month, dialect)
+            ),
+        }
+    }
+}
+
+/// Truncate `day` to `granularity`, keeping it a DATE so the calendar columns
+/// compare equal to `date_day`. BigQuery's generic truncation uses
+/// `TIMESTAMP_TRUNC`, which would turn them into TIMESTAMPs.
+fn day_trunc_sql(granularity: &str, day: &str, dialect: DialectType) -> String {
+    match dialect {
+        DialectType::BigQuery => format!("DATE_TRUNC({day}, {})", granularity.to_ascii_uppercase()),
+        _ => date_trunc_sql(granularity, day, dialect),
+    }
+}
+
+/// Day of week of `day`, 0 (Sunday) through 6 (Saturday).
+fn day_of_week_sql(day: &str, dialect: DialectType) -> String {
+    match dialect {
+        // BigQuery numbers days 1 (Sunday) through 7.
+        DialectType::BigQuery => format!("(EXTRACT(DAYOFWEEK FROM {day}) - 1)"),
+        _ => format!("EXTRACT(DOW FROM {day})"),
+    }
+}
+
+/// Shift `day` so the fiscal year starting in `start_month` lines up with the
+/// calendar year it ends in.
+fn fiscal_shifted(day: &str, start_month: u32, dialect: DialectType) -> String {
+    let shift = (13 - start_month) % 12;
+    if shift == 0 {
+        day.to_string()
+    } else {
+        date_add_sql(day, i64::from(shift), "month", dialect)
+    }
+}
+
+/// A calendar model generating one row per day between `start` and `end`
+/// (inclusive), plus computed calendar attributes. Rows come from
+/// `generate_series` on DuckDB and Postgres and `GENERATE_DATE_ARRAY` on
+/// BigQuery; other dialects are rejected.
+///
+/// The resulting model is keyed by [`DateSpine::DAY_COLUMN`], so facts can
+/// join to it with a `many_to_one` relationship on their date column.
+#[derive(Debug, Clone)]
+pub struct DateSpine {
+    pub name: String,
+    pub start: String,
+    pub end: String,
+    pub attributes: Vec<CalendarAttribute>,
+}
+
+impl DateSpine {
+    pub const DAY_COLUMN: &'static str = "date_day";
+
+    pub fn new(name: impl Into<String>, start: impl Into<String>, end: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            start: start.into(),
+            end: end.into(),
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn with_attribute(mut self, attribute: CalendarAttribute) -> Self {
+        if !self.attributes.contains(&attribute) {
+            self.attributes.push(attribute);
+        }
+        self
+    }
+
+    /// SQL producing the spine rows in `dialect`. Fails for fiscal attributes
+    /// whose start month is not 1-12.
+    pub fn sql(&self, dialect: DialectType) -> Result<String> {
+        for attribute in &self.attributes {
+            if let CalendarAttribute::FiscalYear { start_month }
+            | CalendarAttribute::FiscalQuarter { start_month } = attribute
+            {
+                if !(1..=12).contains(start_month) {
+                    return Err(SidemanticError::Validation(format!(
+                        "Date spine '{}' {} start month must be between 1 and 12, got {start_month}",
+                        self.name,
+                        attribute.column_name()
+                    )));
+                }
+            }
+        }
+        let start = self.start.replace('\'', "''");
+        let end = self.end.replace('\'', "''");
+        let (day, from) = match dialect {
+            DialectType::DuckDB => (
+                "series.day",
+                format!(
+                    "generate_series(DATE '{start}', DATE '{end}', INTERVAL 1 DAY) AS series(day)"
+                ),
+            ),
+            DialectType::PostgreSQL => (
+                "series.day",
+                format!(
+                    "generate_series(DATE '{start}', DATE '{end}', INTERVAL '1 day') AS series(day)"
+                ),
+            ),
+            DialectType::BigQuery => (
+                "day",
+                format!("UNNEST(GENERATE_DATE_ARRAY(DATE '{start}', DATE '{end}')) AS day"),
+            ),
+            other => {
+                return Err(SidemanticError::Validation(format!(
+                    "Date spine '{}' cannot be generated for dialect {other:?}",
+                    self.name
+                )))
+            }
+        };
+
+        let columns: Vec<String> =
+            std::iter::once(format!("CAST({day} AS DATE) AS {}", Self::DAY_COLUMN))
+                .chain(self.attributes.iter().map(|attribute| {
+                    format!(
+                        "{} AS {}",
+                        attribute.sql(day, dialect),
+                        attribute.column_name()
+                    )
+                }))
+                .collect();
+
+        Ok(format!("SELECT {} FROM {from}", columns.join(", ")))
+    }
+
+    /// Build the spine model for `dialect` with a dimension per generated column
+    pub fn into_model(self, dialect: DialectType) -> Result<Model> {
+        let model = Model::new(&self.name, Self::DAY_COLUMN)
+            .with_sql(self.sql(dialect)?)
+            .with_dimension(Dimension::time(Self::DAY_COLUMN));
+
+        Ok(self.attributes.iter().fold(model, |model, attribute| {
+            model.with_dimension(Dimension {
+                r#type: attribute.dimension_type(),
+                ..Dimension::new(attribute.column_name())
+            })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Metric, Relationship, SemanticGraph};
+    use crate::sql::{SemanticQuery, SqlGenerator};
+
+    #[test]
+    fn test_date_spine_sql_with_calendar_attributes() {
+        let spine = DateSpine::new("calendar", "2024-01-01", "2024-12-31")
+            .with_attribute(CalendarAttribute::Month)
+            .with_attribute(CalendarAttribute::IsWeekend)
+            .with_attribute(CalendarAttribute::FiscalQuarter { start_month: 7 });
+
+        assert_eq!(
+            spine.sql(DialectType::DuckDB).unwrap(),
+            "SELECT CAST(series.day AS DATE) AS date_day, \
+             DATE_TRUNC('month', series.day) AS month, \
+             EXTRACT(DOW FROM series.day) IN (0, 6) AS is_weekend, \
+             EXTRACT(QUARTER FROM series.day + INTERVAL '6 months') AS fiscal_quarter \
+             FROM generate_series(DATE '2024-01-01', DATE '2024-12-31', INTERVAL 1 DAY) AS series(day)"
+        );
+
+        let model = spine.into_model(DialectType::DuckDB).unwrap();
+        assert_eq!(model.primary_key, "date_day");
+        assert_eq!(
+            model.get_dimension("is_weekend").unwrap().r#type,
+            DimensionType::Boolean
+        );
+        assert_eq!(
+            model.get_dimension("month").unwrap().r#type,
+            DimensionType::Time
+        );
+    }
+
+    #[test]
+    fn test_date_spine_as_join_target() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                DateSpine::new("calendar", "2024-01-01", "2024-12-31")
+                    .with_attribute(CalendarAttribute::IsWeekend)
+                    .into_model(DialectType::DuckDB)
+                    .unwrap(),
+            )
+            .unwrap();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_metric(Metric::sum("revenue", "amount"))
+                    .with_relationship(
+                        Relationship::many_to_one("calendar").with_keys("order_day", "date_day"),
+                    ),
+            )
+            .unwrap();
+
+        let sql = SqlGenerator::new(&graph)
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec!["orders.revenue".into()])
+                    .with_dimensions(vec!["calendar.is_weekend".into()]),
+            )
+            .unwrap();
+
+        assert!(sql.contains("generate_series(DATE '2024-01-01'"), "{sql}");
+        assert!(
+            sql.contains("calendar_cte.date_day = orders_cte.order_day"),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_date_spine_sql_per_dialect() {
+        let spine = DateSpine::new("calendar", "2024-01-01", "2024-12-31")
+            .with_attribute(CalendarAttribute::Month)
+            .with_attribute(CalendarAttribute::IsWeekend)
+            .with_attribute(CalendarAttribute::FiscalYear { start_month: 7 });
+
+        assert_eq!(
+            spine.sql(DialectType::BigQuery).unwrap(),
+            "SELECT CAST(day AS DATE) AS date_day, \
+             DATE_TRUNC(day, MONTH) AS month, \
+             (EXTRACT(DAYOFWEEK FROM day) - 1) IN (0, 6) AS is_weekend, \
+             EXTRACT(YEAR FROM DATE_ADD(day, INTERVAL 6 MONTH)) AS fiscal_year \
+             FROM UNNEST(GENERATE_DATE_ARRAY(DATE '2024-01-01', DATE '2024-12-31')) AS day"
+        );
+        assert!(spine
+            .sql(DialectType::PostgreSQL)
+            .unwrap()
+            .contains("generate_series(DATE '2024-01-01', DATE '2024-12-31', INTERVAL '1 day')"));
+
+        let err = spine.into_model(DialectType::Snowflake).unwrap_err();
+        assert!(err.to_string().contains("Snowflake"), "{err}");
+    }
+
+    #[test]
+    fn test_date_spine_rejects_invalid_fiscal_start_month() {
+        for start_month in [0, 13] {
+            let spine = DateSpine::new("calendar", "2024-01-01", "2024-12-31")
+                .with_attribute(CalendarAttribute::FiscalQuarter { start_month });
+            let err = spine.sql(DialectType::DuckDB).unwrap_err();
+            assert!(
+                matches!(err, SidemanticError::Validation(ref msg) if msg.contains("start month")),
+                "{err}"
+            );
+        }
+    }
+}
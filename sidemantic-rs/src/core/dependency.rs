@@ -28,12 +28,20 @@ pub fn extract_dependencies_with_context(
 
     match metric.r#type {
         MetricType::Ratio => {
-            // Ratio metrics depend on numerator and denominator
-            if let Some(ref num) = metric.numerator {
-                deps.insert(num.clone());
-            }
-            if let Some(ref denom) = metric.denominator {
-                deps.insert(denom.clone());
+            // Ratio metrics depend on numerator and denominator. A side may be an
+            // inline filtered aggregate (e.g. COUNT(CASE WHEN ... END)), which
+            // only depends on the metrics it references.
+            for side in [&metric.numerator, &metric.denominator]
+                .into_iter()
+                .flatten()
+            {
+                if !has_inline_aggregation(side) {
+                    deps.insert(side.clone());
+                } else if let Some(g) = graph {
+                    deps.extend(extract_column_references(side).into_iter().filter_map(
+                        |ref_name| resolve_metric_reference(&ref_name, g, model_context),
+                    ));
+                }
             }
         }
         MetricType::Derived => {
@@ -77,8 +85,8 @@ pub fn extract_dependencies_with_context(
                 deps.insert(base_metric.clone());
             }
         }
-        MetricType::TimeComparison => {
-            // Time comparison metrics depend on the base_metric
+        MetricType::TimeComparison | MetricType::PctOfTotal => {
+            // Time comparison and percent-of-total metrics depend on the base_metric
             if let Some(ref base) = metric.base_metric {
                 deps.insert(base.clone());
             }
@@ -253,7 +261,7 @@ fn sanitize_reference(raw: &str) -> Option<String> {
     if is_keyword(candidate) || is_number(candidate) || is_cast_type(candidate) {
         return None;
     }
-    if candidate.eq_ignore_ascii_case("cube") {
+    if candidate.eq_ignore_ascii_case("cube") || candidate == Metric::ROW_COUNT {
         return None;
     }
     Some(candidate.to_string())
@@ -409,9 +417,7 @@ pub fn check_circular_dependencies(
 
     for (name, _) in metrics {
         if !visited.contains(*name) && has_cycle(name, &adj, &mut visited, &mut rec_stack) {
-            return Err(format!(
-                "Circular dependency detected involving metric '{name}'"
-            ));
+            return Err(format!("metric '{name}' depends on itself"));
         }
     }
 
@@ -1,11 +1,16 @@
 //! SemanticGraph: stores models and finds join paths
 
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::{Deref, DerefMut};
 
-use crate::core::extract_dependencies;
-use crate::core::model::{DimensionType, Metric, MetricType, Model, RelationshipType};
+use polyglot_sql::DialectType;
+
+use crate::core::model::{
+    Dimension, DimensionType, JoinType, Metric, MetricType, Model, Relationship, RelationshipType,
+};
 use crate::core::Parameter;
 use crate::core::TableCalculation;
+use crate::core::{check_circular_dependencies, extract_dependencies_with_context};
 use crate::error::{Result, SidemanticError};
 
 /// A step in a join path
@@ -20,6 +25,8 @@ pub struct JoinStep {
     pub relationship_type: RelationshipType,
     /// Custom SQL join condition (overrides FK/PK join)
     pub custom_condition: Option<String>,
+    /// Explicit join type; `None` lets the generator choose.
+    pub join_type: Option<JoinType>,
 }
 
 impl JoinStep {
@@ -68,27 +75,170 @@ impl JoinPath {
     }
 }
 
-/// Edge in the adjacency list: (target_model, from_keys, to_keys, relationship_type, custom_sql)
+/// Edge in the adjacency list:
+/// (target_model, from_keys, to_keys, relationship_type, custom_sql, join_type)
 type AdjacencyEdge = (
     String,
     Vec<String>,
     Vec<String>,
     RelationshipType,
     Option<String>,
+    Option<JoinType>,
 );
 
+/// A resolved join path, or the competing routes when the shortest path is
+/// ambiguous.
+type CachedJoinPath = std::result::Result<JoinPath, Vec<Vec<String>>>;
+
+/// All-pairs shortest join paths, valid for the graph version they were
+/// computed at. Pairs with no path are absent; ambiguous pairs map to the
+/// competing routes.
+#[derive(Debug, Clone)]
+struct JoinPathCache {
+    version: u64,
+    paths: HashMap<(String, String), CachedJoinPath>,
+}
+
+/// Breadth-first layering from a start model: each reachable model's hop
+/// count and the distinct predecessor models (with the edge index used) that
+/// reach it along a shortest path, in discovery order.
+type ShortestPredecessors = HashMap<String, (usize, Vec<(String, usize)>)>;
+
+/// Upper bound on routes enumerated when reporting an ambiguous join path.
+const MAX_REPORTED_ROUTES: usize = 8;
+
+/// Name-keyed map that iterates in insertion order.
+///
+/// Replacing an existing entry keeps its original position, so iteration
+/// always follows definition order regardless of hashing.
+#[derive(Debug, Clone)]
+struct NamedMap<V> {
+    entries: HashMap<String, V>,
+    order: Vec<String>,
+}
+
+impl<V> Default for NamedMap<V> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: Vec::new(),
+        }
+    }
+}
+
+impl<V> NamedMap<V> {
+    fn get(&self, name: &str) -> Option<&V> {
+        self.entries.get(name)
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut V> {
+        self.entries.get_mut(name)
+    }
+
+    fn get_key_value(&self, name: &str) -> Option<(&String, &V)> {
+        self.entries.get_key_value(name)
+    }
+
+    fn contains_key(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    fn insert(&mut self, name: String, value: V) {
+        if !self.entries.contains_key(&name) {
+            self.order.push(name.clone());
+        }
+        self.entries.insert(name, value);
+    }
+
+    fn remove(&mut self, name: &str) -> Option<V> {
+        let value = self.entries.remove(name)?;
+        self.order.retain(|entry| entry != name);
+        Some(value)
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &String> {
+        self.order.iter()
+    }
+
+    fn values(&self) -> impl Iterator<Item = &V> {
+        self.order.iter().filter_map(|name| self.entries.get(name))
+    }
+
+    fn into_values(mut self) -> Vec<V> {
+        let order = std::mem::take(&mut self.order);
+        order
+            .iter()
+            .filter_map(|name| self.entries.remove(name))
+            .collect()
+    }
+}
+
 /// The semantic graph holds all models and their relationships
+///
+/// Models, metrics, table calculations, and parameters iterate in the order
+/// they were added.
 #[derive(Debug, Default, Clone)]
 pub struct SemanticGraph {
-    models: HashMap<String, Model>,
-    metrics: HashMap<String, Metric>,
-    model_metrics: HashMap<String, Metric>,
-    table_calculations: HashMap<String, TableCalculation>,
-    parameters: HashMap<String, Parameter>,
+    models: NamedMap<Model>,
+    metrics: NamedMap<Metric>,
+    model_metrics: NamedMap<Metric>,
+    table_calculations: NamedMap<TableCalculation>,
+    parameters: NamedMap<Parameter>,
     /// Adjacency list: model -> edges
     adjacency: HashMap<String, Vec<AdjacencyEdge>>,
     /// Graph-level metadata payload (e.g. format-specific import/export state).
     metadata: Option<serde_json::Value>,
+    /// Change counter, bumped whenever models or metrics change.
+    version: u64,
+    /// Resolve model and field names ignoring ASCII case.
+    case_insensitive: bool,
+    /// Default dialect for SQL generators built from this graph.
+    dialect: Option<DialectType>,
+    /// Longest join path, in hops, that `find_join_path` will return.
+    max_join_hops: Option<usize>,
+    /// Join paths precomputed by `precompute_paths`.
+    path_cache: Option<JoinPathCache>,
+}
+
+/// Mutable handle to a graph's metadata payload, returned by
+/// [`SemanticGraph::metadata_mut`]. Dropping it bumps the graph version if
+/// the payload was mutably dereferenced.
+pub struct MetadataMut<'a> {
+    metadata: &'a mut Option<serde_json::Value>,
+    version: &'a mut u64,
+    written: bool,
+}
+
+impl Deref for MetadataMut<'_> {
+    type Target = Option<serde_json::Value>;
+
+    fn deref(&self) -> &Self::Target {
+        self.metadata
+    }
+}
+
+impl DerefMut for MetadataMut<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.written = true;
+        self.metadata
+    }
+}
+
+impl Drop for MetadataMut<'_> {
+    fn drop(&mut self) {
+        if self.written {
+            *self.version += 1;
+        }
+    }
 }
 
 impl SemanticGraph {
@@ -230,8 +380,17 @@ impl SemanticGraph {
 
         self.index_model_metrics(&model);
 
-        self.models.insert(name, model);
+        let check_cycles = Self::has_dependent_metrics(&model);
+        self.models.insert(name.clone(), model);
+        if check_cycles {
+            if let Err(error) = self.check_metric_cycles() {
+                self.models.remove(&name);
+                self.rebuild_model_metric_index();
+                return Err(error);
+            }
+        }
         self.rebuild_adjacency();
+        self.bump_version();
         Ok(())
     }
 
@@ -240,12 +399,309 @@ impl SemanticGraph {
         let name = model.name.clone();
 
         Self::validate_model(&model)?;
-        self.models.insert(name, model);
+        let check_cycles = Self::has_dependent_metrics(&model);
+        let previous = if check_cycles {
+            self.models.get(&name).cloned()
+        } else {
+            None
+        };
+        self.models.insert(name.clone(), model);
+        self.rebuild_model_metric_index();
+        if check_cycles {
+            if let Err(error) = self.check_metric_cycles() {
+                match previous {
+                    Some(previous) => self.models.insert(name, previous),
+                    None => {
+                        self.models.remove(&name);
+                    }
+                }
+                self.rebuild_model_metric_index();
+                return Err(error);
+            }
+        }
+        self.rebuild_adjacency();
+        self.bump_version();
+        Ok(())
+    }
+
+    /// Remove a model from the graph, returning it.
+    ///
+    /// Fails without changing anything while relationships or metrics of
+    /// other models, or graph-level metrics, still reference the model.
+    pub fn remove_model(&mut self, name: &str) -> Result<Model> {
+        if !self.models.contains_key(name) {
+            let available: Vec<&str> = self.models.keys().map(|s| s.as_str()).collect();
+            return Err(SidemanticError::model_not_found(name, &available));
+        }
+        let references = self.model_references(name);
+        if !references.is_empty() {
+            return Err(SidemanticError::Validation(format!(
+                "Cannot remove model '{name}': still referenced by {}",
+                references.join(", ")
+            )));
+        }
+        let model = self
+            .models
+            .remove(name)
+            .expect("model presence checked above");
+        self.rebuild_model_metric_index();
+        self.rebuild_adjacency();
+        self.bump_version();
+        Ok(model)
+    }
+
+    /// Relationships and metrics outside model `name` that depend on it.
+    fn model_references(&self, name: &str) -> Vec<String> {
+        let mut references = Vec::new();
+        for model in self.models.values().filter(|model| model.name != name) {
+            for relationship in &model.relationships {
+                if relationship.name == name || relationship.through.as_deref() == Some(name) {
+                    references.push(format!(
+                        "relationship '{}.{}'",
+                        model.name, relationship.name
+                    ));
+                }
+            }
+            for metric in &model.metrics {
+                if self.metric_references_model(metric, Some(&model.name), name) {
+                    references.push(format!("metric '{}.{}'", model.name, metric.name));
+                }
+            }
+        }
+        for metric in self.metrics.values() {
+            if self.metric_references_model(metric, None, name) {
+                references.push(format!("metric '{}'", metric.name));
+            }
+        }
+        references
+    }
+
+    /// Whether `metric` has a dependency that only model `name` provides.
+    fn metric_references_model(
+        &self,
+        metric: &Metric,
+        model_context: Option<&str>,
+        name: &str,
+    ) -> bool {
+        extract_dependencies_with_context(metric, Some(self), model_context)
+            .iter()
+            .any(|dependency| match dependency.rsplit_once('.') {
+                Some((model_name, _)) => model_name == name,
+                None => {
+                    !self.metrics.contains_key(dependency)
+                        && self
+                            .models
+                            .values()
+                            .filter(|model| model.get_metric(dependency).is_some())
+                            .map(|model| model.name.as_str())
+                            .eq([name])
+                }
+            })
+    }
+
+    /// Monotonic change counter for cache invalidation and hot reload.
+    ///
+    /// Bumped by every mutation (adding, replacing, removing, or merging
+    /// models, metrics, table calculations, parameters, or metadata) and left
+    /// unchanged by lookups.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Merge every model, metric, table calculation, and parameter of `other`
+    /// into this graph. Fails without changing anything if a name is already
+    /// defined here or the merged metrics depend on each other in a cycle.
+    /// Graph metadata is taken from `other` only when this graph has none.
+    pub fn merge(&mut self, other: SemanticGraph) -> Result<()> {
+        let duplicate = |kind: &str, name: &str| {
+            Err(SidemanticError::Validation(format!(
+                "Cannot merge graphs: {kind} '{name}' already exists"
+            )))
+        };
+        if let Some(name) = other.models.keys().find(|n| self.models.contains_key(n)) {
+            return duplicate("model", name);
+        }
+        if let Some(name) = other
+            .metrics
+            .keys()
+            .find(|n| self.metrics.contains_key(n) || self.model_metrics.contains_key(n))
+        {
+            return duplicate("metric", name);
+        }
+        if let Some(name) = other
+            .table_calculations
+            .keys()
+            .find(|n| self.table_calculations.contains_key(n))
+        {
+            return duplicate("table calculation", name);
+        }
+        if let Some(name) = other
+            .parameters
+            .keys()
+            .find(|n| self.parameters.contains_key(n))
+        {
+            return duplicate("parameter", name);
+        }
+
+        let model_names: Vec<String> = other.models.keys().cloned().collect();
+        let metric_names: Vec<String> = other.metrics.keys().cloned().collect();
+        let calc_names: Vec<String> = other.table_calculations.keys().cloned().collect();
+        let parameter_names: Vec<String> = other.parameters.keys().cloned().collect();
+        let takes_metadata = self.metadata.is_none();
+        let takes_dialect = self.dialect.is_none();
+
+        for model in other.models.into_values() {
+            self.models.insert(model.name.clone(), model);
+        }
+        for metric in other.metrics.into_values() {
+            self.metrics.insert(metric.name.clone(), metric);
+        }
+        for calc in other.table_calculations.into_values() {
+            self.table_calculations.insert(calc.name.clone(), calc);
+        }
+        for parameter in other.parameters.into_values() {
+            self.parameters.insert(parameter.name.clone(), parameter);
+        }
+        if takes_metadata {
+            self.metadata = other.metadata;
+        }
+        if takes_dialect {
+            self.dialect = other.dialect;
+        }
         self.rebuild_model_metric_index();
+
+        if let Err(error) = self.check_metric_cycles() {
+            for name in &model_names {
+                self.models.remove(name);
+            }
+            for name in &metric_names {
+                self.metrics.remove(name);
+            }
+            for name in &calc_names {
+                self.table_calculations.remove(name);
+            }
+            for name in &parameter_names {
+                self.parameters.remove(name);
+            }
+            if takes_metadata {
+                self.metadata = None;
+            }
+            if takes_dialect {
+                self.dialect = None;
+            }
+            self.rebuild_model_metric_index();
+            return Err(error);
+        }
         self.rebuild_adjacency();
+        self.bump_version();
         Ok(())
     }
 
+    fn bump_version(&mut self) {
+        self.version += 1;
+    }
+
+    /// Whether model and field names resolve ignoring case.
+    pub fn case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+
+    /// Resolve model and field names ignoring ASCII case, so `Orders.Revenue`
+    /// finds `orders.revenue`. Exact matches always win; names that only
+    /// collide under case folding are rejected as ambiguous.
+    pub fn set_case_insensitive(&mut self, case_insensitive: bool) {
+        self.case_insensitive = case_insensitive;
+        self.bump_version();
+    }
+
+    /// Default dialect for SQL generated from this graph, if configured.
+    pub fn dialect(&self) -> Option<DialectType> {
+        self.dialect
+    }
+
+    /// Set the default dialect used by generators created from this graph.
+    /// [`SqlGenerator::with_dialect`](crate::sql::SqlGenerator::with_dialect)
+    /// still overrides it per generator.
+    pub fn set_dialect(&mut self, dialect: DialectType) {
+        self.dialect = Some(dialect);
+        self.bump_version();
+    }
+
+    /// Maximum number of joins allowed in a single join path, if limited.
+    pub fn max_join_hops(&self) -> Option<usize> {
+        self.max_join_hops
+    }
+
+    /// Limit join paths to `max_hops` joins. Longer shortest paths are rejected
+    /// with [`SidemanticError::NoJoinPath`] instead of silently chaining joins.
+    pub fn set_max_join_hops(&mut self, max_hops: Option<usize>) {
+        self.max_join_hops = max_hops;
+        self.bump_version();
+    }
+
+    /// Expose each model's one-hop related dimensions on the model itself as
+    /// pass-through dimensions named `relationship__dimension`, so
+    /// `orders.customer__country` joins `customer` when selected. Only
+    /// many-to-one and one-to-one relationships are followed, so child rows
+    /// are never presented as attributes of the parent. Existing dimensions of
+    /// the same name win, and dimensions that are themselves pass-throughs are
+    /// skipped.
+    pub fn flatten_foreign_dimensions(&mut self) {
+        let mut additions: Vec<(String, Vec<Dimension>)> = Vec::new();
+        for model in self.models.values() {
+            let mut flattened = Vec::new();
+            for rel in &model.relationships {
+                let single_valued = matches!(
+                    rel.r#type,
+                    RelationshipType::ManyToOne | RelationshipType::OneToOne
+                );
+                if !single_valued || rel.through.is_some() || rel.name == model.name {
+                    continue;
+                }
+                let Some(target) = self.models.get(&rel.name) else {
+                    continue;
+                };
+                for dimension in &target.dimensions {
+                    if dimension.reference.is_some() || !dimension.public {
+                        continue;
+                    }
+                    let name = format!("{}__{}", rel.name, dimension.name);
+                    if model.get_dimension(&name).is_some() {
+                        continue;
+                    }
+                    let mut exposed = Dimension::new(name)
+                        .with_reference(format!("{}.{}", target.name, dimension.name));
+                    exposed.r#type = dimension.r#type.clone();
+                    exposed.granularity = dimension.granularity.clone();
+                    exposed.description = dimension.description.clone();
+                    exposed.null_label = dimension.null_label.clone();
+                    flattened.push(exposed);
+                }
+            }
+            if !flattened.is_empty() {
+                additions.push((model.name.clone(), flattened));
+            }
+        }
+
+        for (name, dimensions) in additions {
+            if let Some(model) = self.models.get_mut(&name) {
+                model.dimensions.extend(dimensions);
+            }
+        }
+        self.bump_version();
+    }
+
+    /// Resolve a model name to its canonical spelling.
+    pub fn resolve_model_name(&self, name: &str) -> Result<Option<&str>> {
+        if let Some((canonical, _)) = self.models.get_key_value(name) {
+            return Ok(Some(canonical));
+        }
+        if !self.case_insensitive {
+            return Ok(None);
+        }
+        fold_case_match(name, self.models.keys().map(String::as_str))
+    }
+
     fn is_indexed_model_metric(metric: &Metric) -> bool {
         matches!(
             metric.r#type,
@@ -265,8 +721,7 @@ impl SemanticGraph {
     fn rebuild_model_metric_index(&mut self) {
         self.model_metrics.clear();
 
-        let mut model_names: Vec<String> = self.models.keys().cloned().collect();
-        model_names.sort();
+        let model_names: Vec<String> = self.models.keys().cloned().collect();
         for model_name in model_names {
             if let Some(model) = self.models.get(&model_name).cloned() {
                 self.index_model_metrics(&model);
@@ -276,7 +731,10 @@ impl SemanticGraph {
 
     /// Get a model by name
     pub fn get_model(&self, name: &str) -> Option<&Model> {
-        self.models.get(name)
+        self.models.get(name).or_else(|| {
+            let canonical = self.resolve_model_name(name).ok().flatten()?;
+            self.models.get(canonical)
+        })
     }
 
     /// Get all models
@@ -297,6 +755,7 @@ impl SemanticGraph {
             )));
         }
         self.metrics.insert(metric.name.clone(), metric);
+        self.bump_version();
         Ok(())
     }
 
@@ -309,7 +768,13 @@ impl SemanticGraph {
             )));
         }
         self.validate_metric_dependencies(&metric)?;
-        self.metrics.insert(metric.name.clone(), metric);
+        let name = metric.name.clone();
+        self.metrics.insert(name.clone(), metric);
+        if let Err(error) = self.check_metric_cycles() {
+            self.metrics.remove(&name);
+            return Err(error);
+        }
+        self.bump_version();
         Ok(())
     }
 
@@ -319,7 +784,15 @@ impl SemanticGraph {
     /// dependency order (e.g. OSI import) can insert them all first, then
     /// validate once everything is present.
     pub fn validate_metric_dependencies(&self, metric: &Metric) -> Result<()> {
-        for dependency in extract_dependencies(metric, Some(self)) {
+        self.validate_metric_dependencies_in(metric, None)
+    }
+
+    fn validate_metric_dependencies_in(
+        &self,
+        metric: &Metric,
+        model_context: Option<&str>,
+    ) -> Result<()> {
+        for dependency in extract_dependencies_with_context(metric, Some(self), model_context) {
             let dependency_name = dependency
                 .rsplit_once('.')
                 .map(|(_, name)| name)
@@ -349,6 +822,122 @@ impl SemanticGraph {
         Ok(())
     }
 
+    /// Validate the whole graph, reporting every problem found rather than
+    /// stopping at the first: relationship targets and junction models exist,
+    /// metric dependencies resolve without cycles, and dimension and metric
+    /// SQL parses.
+    pub fn validate(&self) -> std::result::Result<(), Vec<SidemanticError>> {
+        let mut errors = Vec::new();
+
+        for model in self.models.values() {
+            for relationship in &model.relationships {
+                let targets = std::iter::once(&relationship.name).chain(&relationship.through);
+                for target in targets {
+                    if !self.models.contains_key(target) {
+                        errors.push(SidemanticError::Validation(format!(
+                            "Model '{}' has a relationship to unknown model '{target}'",
+                            model.name
+                        )));
+                    }
+                }
+            }
+
+            for dimension in &model.dimensions {
+                if let Some(sql) = &dimension.sql {
+                    if let Err(error) = Self::parse_field_sql(model, sql) {
+                        errors.push(SidemanticError::Validation(format!(
+                            "Dimension '{}.{}' has invalid SQL: {error}",
+                            model.name, dimension.name
+                        )));
+                    }
+                }
+            }
+
+            for metric in &model.metrics {
+                if let Err(error) = self.validate_metric_dependencies_in(metric, Some(&model.name))
+                {
+                    errors.push(SidemanticError::Validation(format!(
+                        "Metric '{}.{}': {error}",
+                        model.name, metric.name
+                    )));
+                }
+                let parses_sql = matches!(metric.r#type, MetricType::Simple | MetricType::Derived);
+                if let Some(sql) = metric.sql.as_ref().filter(|_| parses_sql) {
+                    if let Err(error) = Self::parse_field_sql(model, sql) {
+                        errors.push(SidemanticError::Validation(format!(
+                            "Metric '{}.{}' has invalid SQL: {error}",
+                            model.name, metric.name
+                        )));
+                    }
+                }
+            }
+        }
+
+        for metric in self.metrics.values() {
+            if let Err(error) = self.validate_metric_dependencies(metric) {
+                errors.push(error);
+            }
+        }
+
+        if let Err(error) = self.check_metric_cycles() {
+            errors.push(error);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Whether any of the model's metrics can depend on other metrics, and so
+    /// could close a dependency cycle.
+    fn has_dependent_metrics(model: &Model) -> bool {
+        model.metrics.iter().any(|metric| {
+            !matches!(
+                metric.r#type,
+                MetricType::Simple
+                    | MetricType::Conversion
+                    | MetricType::Retention
+                    | MetricType::Cohort
+            )
+        })
+    }
+
+    /// Fail with `CircularDependency` if model and graph-level metrics depend
+    /// on each other in a cycle.
+    fn check_metric_cycles(&self) -> Result<()> {
+        let metric_names: Vec<(String, &Metric)> = self
+            .models
+            .values()
+            .flat_map(|model| {
+                model
+                    .metrics
+                    .iter()
+                    .map(|metric| (format!("{}.{}", model.name, metric.name), metric))
+            })
+            .chain(
+                self.metrics
+                    .values()
+                    .map(|metric| (metric.name.clone(), metric)),
+            )
+            .collect();
+        let named: Vec<(&str, &Metric)> = metric_names
+            .iter()
+            .map(|(name, metric)| (name.as_str(), *metric))
+            .collect();
+        check_circular_dependencies(&named, self).map_err(SidemanticError::CircularDependency)
+    }
+
+    fn parse_field_sql(model: &Model, sql: &str) -> std::result::Result<(), String> {
+        let sql = sql
+            .replace("${CUBE}", &model.name)
+            .replace("{model}", &model.name);
+        polyglot_sql::parse(&format!("SELECT {sql}"), DialectType::Generic)
+            .map(|_| ())
+            .map_err(|error| error.to_string())
+    }
+
     fn metric_dependency_exists(&self, dependency: &str) -> Result<bool> {
         if let Some((model_name, metric_name)) = dependency.rsplit_once('.') {
             let Some(model) = self.models.get(model_name) else {
@@ -427,9 +1016,20 @@ impl SemanticGraph {
 
     /// Get a graph-level metric by name.
     pub fn get_metric(&self, name: &str) -> Option<&Metric> {
-        self.metrics
+        let exact = self
+            .metrics
             .get(name)
-            .or_else(|| self.model_metrics.get(name))
+            .or_else(|| self.model_metrics.get(name));
+        if exact.is_some() || !self.case_insensitive {
+            return exact;
+        }
+        let names = self.metrics.keys().chain(self.model_metrics.keys());
+        let canonical = fold_case_match(name, names.map(String::as_str))
+            .ok()
+            .flatten()?;
+        self.metrics
+            .get(canonical)
+            .or_else(|| self.model_metrics.get(canonical))
     }
 
     /// Get all graph-level metrics.
@@ -437,6 +1037,52 @@ impl SemanticGraph {
         self.metrics.values().chain(self.model_metrics.values())
     }
 
+    /// References (`model.field`, or the bare name for graph-level metrics)
+    /// of every dimension and metric tagged `tag`, directly or through its
+    /// model's tags.
+    pub fn fields_with_tag(&self, tag: &str) -> Vec<String> {
+        let has_tag = |tags: &[String]| tags.iter().any(|t| t == tag);
+        let mut fields = Vec::new();
+        for model in self.models.values() {
+            let model_tagged = has_tag(&model.tags);
+            let dimensions = model
+                .dimensions
+                .iter()
+                .filter(|d| model_tagged || has_tag(&d.tags))
+                .map(|d| &d.name);
+            let metrics = model
+                .metrics
+                .iter()
+                .filter(|m| model_tagged || has_tag(&m.tags))
+                .map(|m| &m.name);
+            fields.extend(
+                dimensions
+                    .chain(metrics)
+                    .map(|f| format!("{}.{f}", model.name)),
+            );
+        }
+        fields.extend(
+            self.metrics
+                .values()
+                .filter(|m| has_tag(&m.tags))
+                .map(|m| m.name.clone()),
+        );
+        fields
+    }
+
+    /// Serialize the whole graph as JSON: every model (with its dimensions,
+    /// metrics, relationships, and segments) plus graph-level metrics, table
+    /// calculations, and parameters, in definition order.
+    pub fn to_json(&self) -> String {
+        serde_json::json!({
+            "models": self.models.values().collect::<Vec<_>>(),
+            "metrics": self.metrics.values().collect::<Vec<_>>(),
+            "table_calculations": self.table_calculations.values().collect::<Vec<_>>(),
+            "parameters": self.parameters.values().collect::<Vec<_>>(),
+        })
+        .to_string()
+    }
+
     /// Add a graph-level table calculation.
     pub fn add_table_calculation(&mut self, calc: TableCalculation) -> Result<()> {
         if self.table_calculations.contains_key(&calc.name) {
@@ -446,6 +1092,7 @@ impl SemanticGraph {
             )));
         }
         self.table_calculations.insert(calc.name.clone(), calc);
+        self.bump_version();
         Ok(())
     }
 
@@ -468,6 +1115,7 @@ impl SemanticGraph {
             )));
         }
         self.parameters.insert(parameter.name.clone(), parameter);
+        self.bump_version();
         Ok(())
     }
 
@@ -489,11 +1137,17 @@ impl SemanticGraph {
     /// Replace the graph-level metadata payload.
     pub fn set_metadata(&mut self, metadata: serde_json::Value) {
         self.metadata = Some(metadata);
+        self.bump_version();
     }
 
-    /// Mutable access to the graph-level metadata payload.
-    pub fn metadata_mut(&mut self) -> &mut Option<serde_json::Value> {
-        &mut self.metadata
+    /// Mutable access to the graph-level metadata payload. The version is
+    /// bumped when the returned handle is written through, not on access.
+    pub fn metadata_mut(&mut self) -> MetadataMut<'_> {
+        MetadataMut {
+            metadata: &mut self.metadata,
+            version: &mut self.version,
+            written: false,
+        }
     }
 
     /// Rebuild the adjacency list from model relationships
@@ -547,6 +1201,7 @@ impl SemanticGraph {
                             source_fks.clone(),
                             RelationshipType::OneToMany,
                             None,
+                            rel.join_type,
                         ));
                         // through -> source (many_to_one)
                         self.adjacency
@@ -558,6 +1213,7 @@ impl SemanticGraph {
                                 source_pk,
                                 RelationshipType::ManyToOne,
                                 None,
+                                rel.join_type.map(JoinType::reversed),
                             ));
 
                         // through -> target (many_to_one)
@@ -570,6 +1226,7 @@ impl SemanticGraph {
                                 target_pk.clone(),
                                 RelationshipType::ManyToOne,
                                 None,
+                                rel.join_type,
                             ));
                         // target -> through (one_to_many)
                         self.adjacency.entry(rel.name.clone()).or_default().push((
@@ -578,36 +1235,21 @@ impl SemanticGraph {
                             target_fks,
                             RelationshipType::OneToMany,
                             None,
+                            rel.join_type.map(JoinType::reversed),
                         ));
                         continue;
                     }
                 }
 
-                let fk_keys = rel.foreign_key_columns();
-                let pk_keys = if rel.primary_key.is_some() || rel.primary_key_columns.is_some() {
-                    rel.primary_key_columns()
-                } else {
-                    self.models
-                        .get(&rel.name)
-                        .map(|target_model| target_model.primary_keys())
-                        .unwrap_or_else(|| vec!["id".to_string()])
-                };
-
-                let (from_keys, to_keys) = match rel.r#type {
-                    RelationshipType::ManyToOne | RelationshipType::OneToOne => {
-                        (fk_keys.clone(), pk_keys.clone())
-                    }
-                    RelationshipType::OneToMany | RelationshipType::ManyToMany => {
-                        (pk_keys.clone(), fk_keys.clone())
-                    }
-                };
+                let (from_keys, to_keys) = self.relationship_keys(rel);
 
                 self.adjacency.entry(model.name.clone()).or_default().push((
                     rel.name.clone(),
-                    from_keys.clone(),
-                    to_keys.clone(),
+                    from_keys,
+                    to_keys,
                     rel.r#type.clone(),
                     rel.sql.clone(),
+                    rel.join_type,
                 ));
             }
 
@@ -617,18 +1259,6 @@ impl SemanticGraph {
                     continue;
                 }
 
-                // If the target model already declares an explicit reverse relationship,
-                // don't synthesize another reverse edge. This avoids conflicting
-                // FK/PK directions when both sides are configured.
-                if self
-                    .models
-                    .get(&rel.name)
-                    .and_then(|target| target.get_relationship(&model.name))
-                    .is_some()
-                {
-                    continue;
-                }
-
                 let reverse_type = match rel.r#type {
                     RelationshipType::ManyToOne => RelationshipType::OneToMany,
                     RelationshipType::OneToMany => RelationshipType::ManyToOne,
@@ -643,93 +1273,349 @@ impl SemanticGraph {
                         .replace("__TEMP__", "{to}")
                 });
 
-                let fk_keys = rel.foreign_key_columns();
-                let pk_keys = if rel.primary_key.is_some() || rel.primary_key_columns.is_some() {
-                    rel.primary_key_columns()
-                } else {
-                    self.models
-                        .get(&rel.name)
-                        .map(|target_model| target_model.primary_keys())
-                        .unwrap_or_else(|| vec!["id".to_string()])
-                };
-
-                let (reverse_from_keys, reverse_to_keys) = match rel.r#type {
-                    RelationshipType::ManyToOne | RelationshipType::OneToOne => {
-                        (pk_keys.clone(), fk_keys.clone())
-                    }
-                    RelationshipType::OneToMany | RelationshipType::ManyToMany => {
-                        (fk_keys.clone(), pk_keys.clone())
-                    }
-                };
+                let (to_keys, from_keys) = self.relationship_keys(rel);
+
+                // If the target model already declares the reverse relationship,
+                // don't synthesize another edge. A declaration that relies on
+                // default keys counts as the same join, which avoids conflicting
+                // FK/PK directions; one with explicit, different keys is a
+                // parallel relationship and keeps both edges.
+                let declared_reverse = self.models.get(&rel.name).is_some_and(|target| {
+                    target
+                        .relationships
+                        .iter()
+                        .filter(|back| back.name == model.name)
+                        .any(|back| {
+                            let explicit = back.foreign_key.is_some()
+                                || back.foreign_key_columns.is_some()
+                                || back.sql.is_some();
+                            !explicit
+                                || (back.sql == reverse_sql
+                                    && self.relationship_keys(back)
+                                        == (from_keys.clone(), to_keys.clone()))
+                        })
+                });
+                if declared_reverse {
+                    continue;
+                }
 
                 self.adjacency.entry(rel.name.clone()).or_default().push((
                     model.name.clone(),
-                    reverse_from_keys,
-                    reverse_to_keys,
+                    from_keys,
+                    to_keys,
                     reverse_type,
                     reverse_sql,
+                    rel.join_type.map(JoinType::reversed),
                 ));
             }
         }
     }
 
-    /// Find the shortest join path between two models using BFS
-    pub fn find_join_path(&self, from: &str, to: &str) -> Result<JoinPath> {
-        if from == to {
-            return Ok(JoinPath { steps: Vec::new() });
-        }
+    /// Join keys of a direct relationship edge, as `(from_keys, to_keys)`.
+    fn relationship_keys(&self, rel: &Relationship) -> (Vec<String>, Vec<String>) {
+        let fk_keys = rel.foreign_key_columns();
+        let pk_keys = if rel.primary_key.is_some() || rel.primary_key_columns.is_some() {
+            rel.primary_key_columns()
+        } else {
+            self.models
+                .get(&rel.name)
+                .map(|target_model| target_model.primary_keys())
+                .unwrap_or_else(|| vec!["id".to_string()])
+        };
 
-        if !self.models.contains_key(from) {
-            let available: Vec<&str> = self.models.keys().map(|s| s.as_str()).collect();
-            return Err(SidemanticError::model_not_found(from, &available));
+        match rel.r#type {
+            RelationshipType::ManyToOne | RelationshipType::OneToOne => (fk_keys, pk_keys),
+            RelationshipType::OneToMany | RelationshipType::ManyToMany => (pk_keys, fk_keys),
         }
-        if !self.models.contains_key(to) {
-            let available: Vec<&str> = self.models.keys().map(|s| s.as_str()).collect();
-            return Err(SidemanticError::model_not_found(to, &available));
+    }
+
+    /// Precompute the shortest join path between every pair of models, so
+    /// `find_join_path` becomes a lookup instead of a BFS per call.
+    ///
+    /// The cache is tied to [`version`](Self::version): any later mutation
+    /// makes lookups fall back to BFS until this is called again.
+    pub fn precompute_paths(&mut self) {
+        let mut paths = HashMap::new();
+        for from in self.models.keys() {
+            let predecessors = self.shortest_predecessors(from);
+            for to in predecessors.keys().filter(|to| *to != from) {
+                let routes = self.shortest_routes(&predecessors, to);
+                let path = if routes.len() > 1 {
+                    Err(routes)
+                } else {
+                    Ok(JoinPath {
+                        steps: self.first_shortest_steps(&predecessors, to),
+                    })
+                };
+                paths.insert((from.clone(), to.clone()), path);
+            }
         }
+        self.path_cache = Some(JoinPathCache {
+            version: self.version,
+            paths,
+        });
+    }
 
-        // BFS to find shortest path
-        let mut visited: HashSet<String> = HashSet::new();
-        let mut queue: VecDeque<(String, Vec<JoinStep>)> = VecDeque::new();
-
-        visited.insert(from.to_string());
-        queue.push_back((from.to_string(), Vec::new()));
-
-        while let Some((current, path)) = queue.pop_front() {
-            if let Some(edges) = self.adjacency.get(&current) {
-                for (target, from_keys, to_keys, rel_type, custom_sql) in edges {
-                    if !visited.contains(target) {
-                        let mut new_path = path.clone();
-                        let from_key = from_keys.first().cloned().unwrap_or_default();
-                        let to_key = to_keys.first().cloned().unwrap_or_default();
-                        new_path.push(JoinStep {
-                            from_model: current.clone(),
-                            to_model: target.clone(),
-                            from_key,
-                            to_key,
-                            from_keys: from_keys.clone(),
-                            to_keys: to_keys.clone(),
-                            relationship_type: rel_type.clone(),
-                            custom_condition: custom_sql.clone(),
-                        });
-
-                        if target == to {
-                            return Ok(JoinPath { steps: new_path });
-                        }
+    /// Whether precomputed join paths are current for this graph version.
+    pub fn has_precomputed_paths(&self) -> bool {
+        self.path_cache
+            .as_ref()
+            .is_some_and(|cache| cache.version == self.version)
+    }
+
+    fn shortest_predecessors(&self, from: &str) -> ShortestPredecessors {
+        let mut predecessors: ShortestPredecessors = HashMap::new();
+        let mut queue: VecDeque<String> = VecDeque::new();
 
-                        visited.insert(target.clone());
-                        queue.push_back((target.clone(), new_path));
+        predecessors.insert(from.to_string(), (0, Vec::new()));
+        queue.push_back(from.to_string());
+
+        while let Some(current) = queue.pop_front() {
+            let depth = predecessors[&current].0;
+            let Some(edges) = self.adjacency.get(&current) else {
+                continue;
+            };
+            for (index, (target, ..)) in edges.iter().enumerate() {
+                match predecessors.get_mut(target) {
+                    None => {
+                        predecessors
+                            .insert(target.clone(), (depth + 1, vec![(current.clone(), index)]));
+                        queue.push_back(target.clone());
+                    }
+                    // Another edge of the same length is a competing route unless it
+                    // joins the same model on the same condition (e.g. the reverse of
+                    // a relationship declared on both sides).
+                    Some((target_depth, entries))
+                        if *target_depth == depth + 1
+                            && !entries.iter().any(|(model, existing)| {
+                                *model == current
+                                    && Self::same_join(&edges[*existing], &edges[index])
+                            }) =>
+                    {
+                        entries.push((current.clone(), index));
                     }
+                    Some(_) => {}
                 }
             }
         }
 
-        Err(SidemanticError::NoJoinPath {
-            from: from.to_string(),
-            to: to.to_string(),
+        predecessors
+    }
+
+    /// The shortest path to `to` that BFS discovers first.
+    fn first_shortest_steps(&self, predecessors: &ShortestPredecessors, to: &str) -> Vec<JoinStep> {
+        let mut steps = Vec::new();
+        let mut current = to;
+        while let Some((previous, index)) = predecessors
+            .get(current)
+            .and_then(|(_, entries)| entries.first())
+        {
+            let (target, from_keys, to_keys, rel_type, custom_sql, join_type) =
+                &self.adjacency[previous.as_str()][*index];
+            steps.push(JoinStep {
+                from_model: previous.clone(),
+                to_model: target.clone(),
+                from_key: from_keys.first().cloned().unwrap_or_default(),
+                to_key: to_keys.first().cloned().unwrap_or_default(),
+                from_keys: from_keys.clone(),
+                to_keys: to_keys.clone(),
+                relationship_type: rel_type.clone(),
+                custom_condition: custom_sql.clone(),
+                join_type: *join_type,
+            });
+            current = previous;
+        }
+        steps.reverse();
+        steps
+    }
+
+    fn same_join(left: &AdjacencyEdge, right: &AdjacencyEdge) -> bool {
+        left.0 == right.0 && left.1 == right.1 && left.2 == right.2 && left.4 == right.4
+    }
+
+    /// Distinct shortest routes to `to`, as model-name sequences, capped at
+    /// [`MAX_REPORTED_ROUTES`]. Parallel relationships between the same two
+    /// models are labelled with their join keys.
+    fn shortest_routes(&self, predecessors: &ShortestPredecessors, to: &str) -> Vec<Vec<String>> {
+        let Some((_, entries)) = predecessors.get(to) else {
+            return Vec::new();
+        };
+        if entries.is_empty() {
+            return vec![vec![to.to_string()]];
+        }
+        let mut routes = Vec::new();
+        for (previous, index) in entries {
+            let parallel = entries
+                .iter()
+                .filter(|(model, _)| model == previous)
+                .count()
+                > 1;
+            let step = if parallel {
+                let (_, from_keys, to_keys, ..) = &self.adjacency[previous.as_str()][*index];
+                format!("{to} ({} = {})", from_keys.join(", "), to_keys.join(", "))
+            } else {
+                to.to_string()
+            };
+            for mut route in self.shortest_routes(predecessors, previous) {
+                if routes.len() == MAX_REPORTED_ROUTES {
+                    return routes;
+                }
+                route.push(step.clone());
+                routes.push(route);
+            }
+        }
+        routes
+    }
+
+    /// Whether a join step multiplies rows. A one-to-many join whose keys cover
+    /// the target model's grain matches at most one row per source row.
+    pub(crate) fn step_fans_out(&self, step: &JoinStep) -> bool {
+        match step.relationship_type {
+            RelationshipType::OneToMany => !self.keys_cover_grain(&step.to_model, &step.to_keys),
+            _ => step.causes_fan_out(),
+        }
+    }
+
+    /// Whether `keys` include every grain column of `model_name`, so each key
+    /// value identifies at most one row.
+    pub(crate) fn keys_cover_grain(&self, model_name: &str, keys: &[String]) -> bool {
+        self.get_model(model_name).is_some_and(|model| {
+            model
+                .grain_columns()
+                .iter()
+                .all(|column| keys.contains(column))
         })
     }
 
+    /// Find the shortest join path between two models using BFS
+    pub fn find_join_path(&self, from: &str, to: &str) -> Result<JoinPath> {
+        self.find_join_path_within(from, to, self.max_join_hops)
+    }
+
+    /// Find the shortest join path, rejecting paths longer than `max_hops` joins.
+    ///
+    /// Fails with [`SidemanticError::AmbiguousJoinPath`] when several routes
+    /// of the same minimal length connect the two models; use
+    /// [`find_join_path_via`](Self::find_join_path_via) to pick one.
+    pub fn find_join_path_within(
+        &self,
+        from: &str,
+        to: &str,
+        max_hops: Option<usize>,
+    ) -> Result<JoinPath> {
+        if from == to {
+            return Ok(JoinPath { steps: Vec::new() });
+        }
+
+        if !self.models.contains_key(from) {
+            let available: Vec<&str> = self.models.keys().map(|s| s.as_str()).collect();
+            return Err(SidemanticError::model_not_found(from, &available));
+        }
+        if !self.models.contains_key(to) {
+            let available: Vec<&str> = self.models.keys().map(|s| s.as_str()).collect();
+            return Err(SidemanticError::model_not_found(to, &available));
+        }
+
+        let path = match self
+            .path_cache
+            .as_ref()
+            .filter(|cache| cache.version == self.version)
+        {
+            Some(cache) => match cache.paths.get(&(from.to_string(), to.to_string())) {
+                Some(Ok(path)) => path.clone(),
+                Some(Err(routes)) => {
+                    return Err(SidemanticError::ambiguous_join_path(from, to, routes))
+                }
+                None => return Err(self.no_join_path_error(from, to)),
+            },
+            None => {
+                let predecessors = self.shortest_predecessors(from);
+                if !predecessors.contains_key(to) {
+                    return Err(self.no_join_path_error(from, to));
+                }
+                let routes = self.shortest_routes(&predecessors, to);
+                if routes.len() > 1 {
+                    return Err(SidemanticError::ambiguous_join_path(from, to, &routes));
+                }
+                JoinPath {
+                    steps: self.first_shortest_steps(&predecessors, to),
+                }
+            }
+        };
+
+        match max_hops {
+            Some(max_hops) if path.steps.len() > max_hops => {
+                Err(self.exceeds_max_hops_error(from, to, path.steps.len(), max_hops))
+            }
+            _ => Ok(path),
+        }
+    }
+
+    /// Find a join path from `from` to `to` that passes through `via` in
+    /// order, joining each consecutive pair along its shortest path.
+    pub fn find_join_path_via(&self, from: &str, to: &str, via: &[&str]) -> Result<JoinPath> {
+        let mut steps = Vec::new();
+        let mut current = from;
+        for next in via.iter().copied().chain(std::iter::once(to)) {
+            steps.extend(self.find_join_path_within(current, next, None)?.steps);
+            current = next;
+        }
+        Ok(JoinPath { steps })
+    }
+
+    fn no_join_path_error(&self, from: &str, to: &str) -> SidemanticError {
+        let mut reachable: Vec<String> = self.reachable_from(from).into_iter().collect();
+        reachable.sort();
+        let reachable: Vec<&str> = reachable.iter().map(String::as_str).collect();
+        SidemanticError::no_join_path(from, to, &reachable)
+    }
+
+    fn exceeds_max_hops_error(
+        &self,
+        from: &str,
+        to: &str,
+        hops: usize,
+        max_hops: usize,
+    ) -> SidemanticError {
+        let mut reachable: Vec<String> = self
+            .reachable_within(from, Some(max_hops))
+            .into_iter()
+            .collect();
+        reachable.sort();
+        let reachable: Vec<&str> = reachable.iter().map(String::as_str).collect();
+        SidemanticError::join_path_exceeds_max_hops(from, to, &reachable, hops, max_hops)
+    }
+
+    /// Models reachable from `model` through relationship chains, excluding itself.
+    pub fn reachable_from(&self, model: &str) -> HashSet<String> {
+        self.reachable_within(model, None)
+    }
+
+    /// Models reachable from `model` in at most `max_hops` joins, excluding itself.
+    fn reachable_within(&self, model: &str, max_hops: Option<usize>) -> HashSet<String> {
+        let mut visited: HashSet<String> = HashSet::new();
+        let mut queue: VecDeque<(&str, usize)> = VecDeque::new();
+        visited.insert(model.to_string());
+        queue.push_back((model, 0));
+
+        while let Some((current, hops)) = queue.pop_front() {
+            if max_hops.is_some_and(|max_hops| hops >= max_hops) {
+                continue;
+            }
+            let Some(edges) = self.adjacency.get(current) else {
+                continue;
+            };
+            for (target, ..) in edges {
+                if visited.insert(target.clone()) {
+                    queue.push_back((target, hops + 1));
+                }
+            }
+        }
+
+        visited.remove(model);
+        visited
+    }
+
     /// Parse a qualified reference (model.field) and return (model_name, field_name, granularity)
     pub fn parse_reference(&self, reference: &str) -> Result<(String, String, Option<String>)> {
         let parts: Vec<&str> = reference.split('.').collect();
@@ -756,12 +1642,113 @@ impl SemanticGraph {
             };
 
         // Verify model exists
-        if !self.models.contains_key(model_name) {
+        let Some(canonical_model) = self.resolve_model_name(model_name)? else {
             let available: Vec<&str> = self.models.keys().map(|s| s.as_str()).collect();
             return Err(SidemanticError::model_not_found(model_name, &available));
+        };
+
+        let model = self.models.get(canonical_model).expect("resolved model");
+        let field_names = model
+            .dimensions
+            .iter()
+            .map(|d| d.name.as_str())
+            .chain(model.metrics.iter().map(|m| m.name.as_str()))
+            .chain(model.segments.iter().map(|s| s.name.as_str()));
+
+        // A field whose own name contains `__` (e.g. a flattened
+        // `customer__country`) is not a granularity suffix.
+        let (field_name, granularity) = if granularity.is_some()
+            && field_names
+                .clone()
+                .any(|name| name == field_with_granularity)
+        {
+            (field_with_granularity.to_string(), None)
+        } else {
+            (field_name, granularity)
+        };
+
+        if !self.case_insensitive {
+            let field_name = if field_names.clone().any(|name| name == field_name) {
+                field_name
+            } else {
+                resolve_label(model, &field_name, false)?.unwrap_or(field_name)
+            };
+            return Ok((canonical_model.to_string(), field_name, granularity));
         }
 
-        Ok((model_name.to_string(), field_name, granularity))
+        let field_name = match fold_case_match(&field_name, field_names)? {
+            Some(name) => name.to_string(),
+            None => resolve_label(model, &field_name, true)?.unwrap_or(field_name),
+        };
+
+        Ok((
+            canonical_model.to_string(),
+            field_name,
+            granularity.map(|g| g.to_ascii_lowercase()),
+        ))
+    }
+}
+
+/// Resolve a display label (e.g. "Order Status") to the name of the single
+/// dimension or metric on `model` carrying it. Used when a reference matches
+/// no field name, since BI tools often send labels instead of names.
+fn resolve_label(model: &Model, label: &str, ignore_case: bool) -> Result<Option<String>> {
+    let matches_label = |candidate: Option<&String>| {
+        candidate.is_some_and(|candidate| {
+            let candidate = candidate.trim();
+            if ignore_case {
+                candidate.eq_ignore_ascii_case(label)
+            } else {
+                candidate == label
+            }
+        })
+    };
+    let matches: Vec<&str> = model
+        .dimensions
+        .iter()
+        .filter(|d| matches_label(d.label.as_ref()))
+        .map(|d| d.name.as_str())
+        .chain(
+            model
+                .metrics
+                .iter()
+                .filter(|m| matches_label(m.label.as_ref()))
+                .map(|m| m.name.as_str()),
+        )
+        .collect();
+    match matches.as_slice() {
+        [] => Ok(None),
+        [only] => Ok(Some(only.to_string())),
+        _ => Err(SidemanticError::Validation(format!(
+            "Ambiguous label '{label}' on model '{}' matches fields: {}",
+            model.name,
+            matches.join(", ")
+        ))),
+    }
+}
+
+/// Find the single candidate equal to `name`, falling back to an ASCII
+/// case-insensitive match. Distinct names that fold together are ambiguous.
+fn fold_case_match<'a>(
+    name: &str,
+    candidates: impl Iterator<Item = &'a str>,
+) -> Result<Option<&'a str>> {
+    let mut matches: Vec<&str> = Vec::new();
+    for candidate in candidates {
+        if candidate == name {
+            return Ok(Some(candidate));
+        }
+        if candidate.eq_ignore_ascii_case(name) && !matches.contains(&candidate) {
+            matches.push(candidate);
+        }
+    }
+    match matches.as_slice() {
+        [] => Ok(None),
+        [only] => Ok(Some(*only)),
+        _ => Err(SidemanticError::AmbiguousName {
+            name: name.to_string(),
+            matches: matches.join(", "),
+        }),
     }
 }
 
@@ -958,6 +1945,498 @@ mod tests {
         assert_eq!(path.steps.len(), 1);
     }
 
+    fn create_disconnected_graph() -> SemanticGraph {
+        let mut graph = create_test_graph();
+        graph
+            .add_model(
+                Model::new("products", "product_id")
+                    .with_table("products")
+                    .with_relationship(Relationship::one_to_many("orders")),
+            )
+            .unwrap();
+        graph
+            .add_model(Model::new("regions", "region_id").with_table("regions"))
+            .unwrap();
+        graph
+    }
+
+    #[test]
+    fn test_reachable_from() {
+        let graph = create_disconnected_graph();
+
+        let reachable = graph.reachable_from("orders");
+        assert_eq!(
+            reachable,
+            HashSet::from(["customers".to_string(), "products".to_string()])
+        );
+        assert!(graph.reachable_from("regions").is_empty());
+        assert!(graph.reachable_from("missing").is_empty());
+    }
+
+    #[test]
+    fn test_no_join_path_error_lists_reachable_models() {
+        let graph = create_disconnected_graph();
+
+        let err = graph.find_join_path("orders", "regions").unwrap_err();
+        match &err {
+            SidemanticError::NoJoinPath {
+                from,
+                to,
+                reachable,
+                ..
+            } => {
+                assert_eq!(from, "orders");
+                assert_eq!(to, "regions");
+                assert_eq!(reachable, "customers, products");
+            }
+            other => panic!("expected NoJoinPath, got {other:?}"),
+        }
+        assert!(err
+            .to_string()
+            .contains("'orders' can reach customers, products but not 'regions'"));
+
+        let err = graph.find_join_path("regions", "orders").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("'regions' can reach no other models"));
+    }
+
+    #[test]
+    fn test_to_json_describes_models_metrics_and_relationships() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("public.orders")
+                    .with_dimension(Dimension::categorical("status"))
+                    .with_metric(Metric::sum("revenue", "amount"))
+                    .with_relationship(Relationship::many_to_one("customers")),
+            )
+            .unwrap();
+        graph
+            .add_model(Model::new("customers", "id").with_table("customers"))
+            .unwrap();
+        graph
+            .add_metric(Metric::derived("doubled", "orders.revenue * 2"))
+            .unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&graph.to_json()).unwrap();
+        let orders = &json["models"][0];
+        assert_eq!(orders["name"], "orders");
+        assert_eq!(json["models"][1]["name"], "customers");
+        assert_eq!(orders["dimensions"][0]["name"], "status");
+        assert_eq!(orders["metrics"][0]["type"], "simple");
+        assert_eq!(orders["relationships"][0]["name"], "customers");
+        assert_eq!(orders["relationships"][0]["type"], "many_to_one");
+        assert_eq!(json["metrics"][0]["name"], "doubled");
+        assert_eq!(json["metrics"][0]["type"], "derived");
+    }
+
+    #[test]
+    fn test_fields_with_tag() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_dimension(Dimension {
+                        tags: vec!["finance".into()],
+                        ..Dimension::categorical("status")
+                    })
+                    .with_dimension(Dimension::categorical("channel"))
+                    .with_metric(Metric {
+                        tags: vec!["finance".into(), "kpi".into()],
+                        ..Metric::sum("revenue", "amount")
+                    })
+                    .with_metric(Metric::count("order_count")),
+            )
+            .unwrap();
+        graph
+            .add_model(Model {
+                tags: vec!["finance".into()],
+                ..Model::new("invoices", "id")
+                    .with_table("invoices")
+                    .with_dimension(Dimension::categorical("currency"))
+                    .with_metric(Metric::sum("invoiced", "total"))
+            })
+            .unwrap();
+        graph
+            .add_metric(Metric {
+                tags: vec!["kpi".into()],
+                ..Metric::derived("aov", "orders.revenue / orders.order_count")
+            })
+            .unwrap();
+
+        assert_eq!(
+            graph.fields_with_tag("finance"),
+            vec![
+                "orders.status",
+                "orders.revenue",
+                "invoices.currency",
+                "invoices.invoiced"
+            ]
+        );
+        assert_eq!(graph.fields_with_tag("kpi"), vec!["orders.revenue", "aov"]);
+        assert!(graph.fields_with_tag("unknown").is_empty());
+    }
+
+    #[test]
+    fn test_validate_reports_every_error() {
+        assert!(create_test_graph().validate().is_ok());
+
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_metric(Metric::sum("revenue", "amount"))
+                    .with_metric(Metric::derived("margin", "revenue - refunds"))
+                    .with_relationship(Relationship::many_to_one("customers")),
+            )
+            .unwrap();
+
+        let messages: Vec<String> = graph
+            .validate()
+            .unwrap_err()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(messages.len(), 2, "{messages:?}");
+        assert!(
+            messages[0].contains("relationship to unknown model 'customers'"),
+            "{messages:?}"
+        );
+        assert!(
+            messages[1].contains("Metric 'orders.margin'") && messages[1].contains("refunds"),
+            "{messages:?}"
+        );
+    }
+
+    #[test]
+    fn test_metric_closing_a_cycle_is_rejected() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_metric(Metric::derived("a", "b + 1")),
+            )
+            .unwrap();
+
+        let err = graph
+            .add_metric(Metric::derived("b", "orders.a + 1"))
+            .unwrap_err();
+        assert!(
+            matches!(err, SidemanticError::CircularDependency(_)),
+            "{err:?}"
+        );
+        assert!(graph.get_metric("b").is_none());
+
+        let err = graph
+            .add_model(
+                Model::new("items", "item_id")
+                    .with_table("items")
+                    .with_metric(Metric::derived("x", "items.y + 1"))
+                    .with_metric(Metric::derived("y", "items.x + 1")),
+            )
+            .unwrap_err();
+        assert!(matches!(err, SidemanticError::CircularDependency(_)));
+        assert!(graph.get_model("items").is_none());
+    }
+
+    #[test]
+    fn test_ambiguous_join_path_lists_routes() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_relationship(Relationship::many_to_one("billing_addresses"))
+                    .with_relationship(Relationship::many_to_one("shipping_addresses")),
+            )
+            .unwrap();
+        for address in ["billing_addresses", "shipping_addresses"] {
+            graph
+                .add_model(
+                    Model::new(address, "id")
+                        .with_table(address)
+                        .with_relationship(Relationship::many_to_one("customers")),
+                )
+                .unwrap();
+        }
+        graph
+            .add_model(Model::new("customers", "id").with_table("customers"))
+            .unwrap();
+
+        let expected = vec![
+            "orders -> billing_addresses -> customers".to_string(),
+            "orders -> shipping_addresses -> customers".to_string(),
+        ];
+        match graph.find_join_path("orders", "customers").unwrap_err() {
+            SidemanticError::AmbiguousJoinPath { from, to, paths } => {
+                assert_eq!(from, "orders");
+                assert_eq!(to, "customers");
+                assert_eq!(paths, expected);
+            }
+            other => panic!("expected AmbiguousJoinPath, got {other:?}"),
+        }
+
+        let path = graph
+            .find_join_path_via("orders", "customers", &["shipping_addresses"])
+            .unwrap();
+        let models: Vec<&str> = path
+            .steps
+            .iter()
+            .map(|step| step.to_model.as_str())
+            .collect();
+        assert_eq!(models, vec!["shipping_addresses", "customers"]);
+
+        // Precomputed paths report the same ambiguity.
+        graph.precompute_paths();
+        assert!(matches!(
+            graph.find_join_path("customers", "orders"),
+            Err(SidemanticError::AmbiguousJoinPath { .. })
+        ));
+        assert_eq!(
+            graph
+                .find_join_path("orders", "billing_addresses")
+                .unwrap()
+                .steps
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_parallel_relationships_are_ambiguous_and_routes_are_capped() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_relationship(
+                        Relationship::many_to_one("customers").with_keys("customer_id", "id"),
+                    ),
+            )
+            .unwrap();
+        graph
+            .add_model(
+                Model::new("customers", "id")
+                    .with_table("customers")
+                    .with_relationship(
+                        Relationship::one_to_many("orders").with_keys("referrer_id", "id"),
+                    ),
+            )
+            .unwrap();
+
+        match graph.find_join_path("orders", "customers").unwrap_err() {
+            SidemanticError::AmbiguousJoinPath { paths, .. } => assert_eq!(
+                paths,
+                vec![
+                    "orders -> customers (customer_id = id)",
+                    "orders -> customers (referrer_id = id)",
+                ]
+            ),
+            other => panic!("expected AmbiguousJoinPath, got {other:?}"),
+        }
+
+        // The same relationship declared from both sides is not ambiguous.
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_relationship(
+                        Relationship::many_to_one("customers").with_keys("customer_id", "id"),
+                    ),
+            )
+            .unwrap();
+        graph
+            .add_model(
+                Model::new("customers", "id")
+                    .with_table("customers")
+                    .with_relationship(
+                        Relationship::one_to_many("orders").with_keys("customer_id", "id"),
+                    ),
+            )
+            .unwrap();
+        assert_eq!(
+            graph
+                .find_join_path("orders", "customers")
+                .unwrap()
+                .steps
+                .len(),
+            1
+        );
+
+        // A wide diamond reports at most MAX_REPORTED_ROUTES routes.
+        let mut graph = SemanticGraph::new();
+        let mut source = Model::new("source", "id").with_table("source");
+        for index in 0..20 {
+            let middle = format!("middle_{index}");
+            source = source.with_relationship(Relationship::many_to_one(&middle));
+            graph
+                .add_model(
+                    Model::new(&middle, "id")
+                        .with_table(&middle)
+                        .with_relationship(Relationship::many_to_one("sink")),
+                )
+                .unwrap();
+        }
+        graph.add_model(source).unwrap();
+        graph
+            .add_model(Model::new("sink", "id").with_table("sink"))
+            .unwrap();
+        match graph.find_join_path("source", "sink").unwrap_err() {
+            SidemanticError::AmbiguousJoinPath { paths, .. } => {
+                assert_eq!(paths.len(), MAX_REPORTED_ROUTES)
+            }
+            other => panic!("expected AmbiguousJoinPath, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_precomputed_paths_match_bfs_and_invalidate_on_mutation() {
+        let mut graph = create_test_graph();
+        graph
+            .replace_model(
+                graph
+                    .get_model("customers")
+                    .unwrap()
+                    .clone()
+                    .with_relationship(Relationship::many_to_one("regions")),
+            )
+            .unwrap();
+        graph
+            .add_model(Model::new("regions", "id").with_table("regions"))
+            .unwrap();
+        graph
+            .add_model(Model::new("islands", "id").with_table("islands"))
+            .unwrap();
+
+        let describe = |result: Result<JoinPath>| -> std::result::Result<Vec<String>, String> {
+            result
+                .map(|path| {
+                    path.steps
+                        .iter()
+                        .map(|step| {
+                            format!(
+                                "{}({}) -> {}({})",
+                                step.from_model,
+                                step.from_keys.join(","),
+                                step.to_model,
+                                step.to_keys.join(",")
+                            )
+                        })
+                        .collect()
+                })
+                .map_err(|err| err.to_string())
+        };
+
+        let uncached = graph.clone();
+        graph.precompute_paths();
+        assert!(graph.has_precomputed_paths());
+        let names: Vec<String> = graph.models().map(|m| m.name.clone()).collect();
+        for from in &names {
+            for to in &names {
+                assert_eq!(
+                    describe(graph.find_join_path(from, to)),
+                    describe(uncached.find_join_path(from, to)),
+                    "{from} -> {to}"
+                );
+            }
+        }
+        assert!(graph
+            .find_join_path_within("orders", "regions", Some(1))
+            .is_err());
+
+        // A shortcut relationship must be visible immediately after mutation.
+        graph
+            .replace_model(
+                graph
+                    .get_model("orders")
+                    .unwrap()
+                    .clone()
+                    .with_relationship(Relationship::many_to_one("regions")),
+            )
+            .unwrap();
+        assert!(!graph.has_precomputed_paths());
+        assert_eq!(
+            graph
+                .find_join_path("orders", "regions")
+                .unwrap()
+                .steps
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_max_join_hops_rejects_longer_paths() {
+        let mut graph = create_test_graph();
+        graph
+            .replace_model(
+                graph
+                    .get_model("customers")
+                    .unwrap()
+                    .clone()
+                    .with_relationship(Relationship::many_to_one("regions")),
+            )
+            .unwrap();
+        graph
+            .add_model(
+                Model::new("regions", "id")
+                    .with_table("regions")
+                    .with_relationship(Relationship::many_to_one("countries")),
+            )
+            .unwrap();
+        graph
+            .add_model(
+                Model::new("countries", "id")
+                    .with_table("countries")
+                    .with_dimension(Dimension::categorical("name")),
+            )
+            .unwrap();
+
+        assert_eq!(
+            graph
+                .find_join_path("orders", "countries")
+                .unwrap()
+                .steps
+                .len(),
+            3
+        );
+
+        let query = crate::sql::SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["countries.name".into()]);
+        let err = crate::sql::SqlGenerator::new(&graph)
+            .with_max_join_hops(2)
+            .generate(&query)
+            .unwrap_err();
+        assert!(matches!(err, SidemanticError::NoJoinPath { .. }), "{err}");
+
+        graph.set_max_join_hops(Some(2));
+        assert_eq!(
+            graph
+                .find_join_path("orders", "regions")
+                .unwrap()
+                .steps
+                .len(),
+            2
+        );
+        let err = graph.find_join_path("orders", "countries").unwrap_err();
+        let message = err.to_string();
+        assert!(
+            message.contains("'orders' can reach customers, regions but not 'countries'"),
+            "{message}"
+        );
+        assert!(
+            message.contains("shortest path of 3 hops exceeds max hops of 2"),
+            "{message}"
+        );
+    }
+
     #[test]
     fn test_one_to_many_omitted_key_defaults_to_id() {
         let mut graph = SemanticGraph::new();
@@ -1030,6 +2509,108 @@ mod tests {
         assert_eq!(gran.unwrap(), "month");
     }
 
+    #[test]
+    fn test_case_insensitive_reference_resolution() {
+        let mut graph = create_test_graph();
+        assert!(graph.parse_reference("Orders.Revenue").is_err());
+
+        graph.set_case_insensitive(true);
+        let (model, field, gran) = graph.parse_reference("Orders.Revenue").unwrap();
+        assert_eq!((model.as_str(), field.as_str()), ("orders", "revenue"));
+        assert!(gran.is_none());
+
+        let (model, field, gran) = graph.parse_reference("ORDERS.Order_Date__Month").unwrap();
+        assert_eq!((model.as_str(), field.as_str()), ("orders", "order_date"));
+        assert_eq!(gran.as_deref(), Some("month"));
+
+        assert_eq!(graph.get_model("Customers").unwrap().name, "customers");
+    }
+
+    #[test]
+    fn test_case_insensitive_folding_collision_is_ambiguous() {
+        let mut graph = create_test_graph();
+        graph
+            .add_model(Model::new("Orders", "id").with_table("legacy_orders"))
+            .unwrap();
+        graph.set_case_insensitive(true);
+
+        let err = graph.parse_reference("ORDERS.status").unwrap_err();
+        assert!(
+            matches!(err, SidemanticError::AmbiguousName { ref matches, .. } if matches == "orders, Orders"),
+            "{err}"
+        );
+        assert!(graph.get_model("ORDERS").is_none());
+
+        // Exact spellings still resolve.
+        assert_eq!(graph.parse_reference("orders.status").unwrap().0, "orders");
+        assert_eq!(
+            graph.get_model("Orders").unwrap().table.as_deref(),
+            Some("legacy_orders")
+        );
+    }
+
+    #[test]
+    fn test_reference_by_label_resolves_canonical_field() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_dimension(Dimension {
+                        label: Some("Order Status".into()),
+                        ..Dimension::categorical("status")
+                    })
+                    .with_dimension(Dimension {
+                        label: Some("Ordered At".into()),
+                        ..Dimension::time("order_date")
+                    })
+                    .with_metric(Metric {
+                        label: Some("Revenue".into()),
+                        ..Metric::sum("revenue", "amount")
+                    })
+                    .with_metric(Metric {
+                        label: Some("Amount".into()),
+                        ..Metric::sum("gross_amount", "amount")
+                    })
+                    .with_metric(Metric {
+                        label: Some("Amount".into()),
+                        ..Metric::avg("avg_amount", "amount")
+                    }),
+            )
+            .unwrap();
+
+        let (model, field, gran) = graph.parse_reference("orders.Order Status").unwrap();
+        assert_eq!((model.as_str(), field.as_str()), ("orders", "status"));
+        assert!(gran.is_none());
+
+        let (_, field, gran) = graph.parse_reference("orders.Ordered At__month").unwrap();
+        assert_eq!(field, "order_date");
+        assert_eq!(gran.as_deref(), Some("month"));
+
+        // Names win over labels; unknown labels pass through untouched.
+        assert_eq!(
+            graph.parse_reference("orders.revenue").unwrap().1,
+            "revenue"
+        );
+        assert_eq!(
+            graph.parse_reference("orders.Missing").unwrap().1,
+            "Missing"
+        );
+
+        let err = graph.parse_reference("orders.Amount").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Ambiguous label 'Amount' on model 'orders' matches fields: gross_amount, avg_amount"),
+            "{err}"
+        );
+
+        graph.set_case_insensitive(true);
+        assert_eq!(
+            graph.parse_reference("orders.order status").unwrap().1,
+            "status"
+        );
+    }
+
     #[test]
     fn test_fan_out_detection() {
         let graph = create_test_graph();
@@ -1094,6 +2675,7 @@ mod tests {
                 related_foreign_key: None,
                 related_foreign_key_columns: None,
                 sql: None,
+                join_type: None,
                 metadata: None,
             });
 
@@ -1129,6 +2711,7 @@ mod tests {
                 related_foreign_key: Some("product_id".to_string()),
                 related_foreign_key_columns: None,
                 sql: None,
+                join_type: None,
                 metadata: None,
             });
         let order_items = Model::new("order_items", "id").with_table("order_items");
@@ -1186,6 +2769,7 @@ mod tests {
                     "product_id".to_string(),
                 ]),
                 sql: None,
+                join_type: None,
                 metadata: None,
             });
         let order_items = Model::new("order_items", "id").with_table("order_items");
@@ -1291,4 +2875,167 @@ mod tests {
         let err = graph.add_parameter(parameter).unwrap_err();
         assert!(err.to_string().contains("already exists"));
     }
+
+    #[test]
+    fn test_version_bumps_on_mutation_only() {
+        let mut graph = create_test_graph();
+        let start = graph.version();
+
+        // Read-only calls leave the version alone.
+        graph.get_model("orders").unwrap();
+        graph.find_join_path("orders", "customers").unwrap();
+        graph.parse_reference("orders.status").unwrap();
+        let _ = graph.models().count();
+        let _ = graph.metadata();
+        assert_eq!(graph.version(), start);
+
+        let mut other = SemanticGraph::new();
+        other
+            .add_model(Model::new("products", "id").with_table("products"))
+            .unwrap();
+        graph.merge(other).unwrap();
+        assert_eq!(graph.version(), start + 1);
+        assert!(graph.get_model("products").is_some());
+
+        graph.remove_model("products").unwrap();
+        assert_eq!(graph.version(), start + 2);
+
+        graph
+            .add_parameter(Parameter {
+                name: "region".to_string(),
+                parameter_type: ParameterType::String,
+                description: None,
+                label: None,
+                default_value: None,
+                allowed_values: None,
+                default_to_today: false,
+            })
+            .unwrap();
+        graph.set_metadata(serde_json::json!({"source": "test"}));
+        assert_eq!(graph.version(), start + 4);
+
+        // Reading through the mutable handle is not a change; writing is.
+        assert!(graph.metadata_mut().is_some());
+        assert_eq!(graph.version(), start + 4);
+        *graph.metadata_mut() = None;
+        assert_eq!(graph.version(), start + 5);
+    }
+
+    #[test]
+    fn test_merge_rejects_duplicates_without_changes() {
+        let mut graph = create_test_graph();
+        let start = graph.version();
+
+        let mut other = SemanticGraph::new();
+        other
+            .add_model(Model::new("products", "id").with_table("products"))
+            .unwrap();
+        other
+            .add_model(Model::new("orders", "id").with_table("orders_copy"))
+            .unwrap();
+
+        let err = graph.merge(other).unwrap_err();
+        assert!(
+            err.to_string().contains("model 'orders' already exists"),
+            "{err}"
+        );
+        assert!(graph.get_model("products").is_none());
+        assert_eq!(graph.version(), start);
+    }
+
+    #[test]
+    fn test_merge_rejects_metric_cycles_without_changes() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("m1", "id")
+                    .with_table("m1")
+                    .with_metric(Metric::derived("x", "m2.y + 1")),
+            )
+            .unwrap();
+        let start = graph.version();
+
+        let mut other = SemanticGraph::new();
+        other
+            .add_model(
+                Model::new("m2", "id")
+                    .with_table("m2")
+                    .with_metric(Metric::derived("y", "m1.x + 1")),
+            )
+            .unwrap();
+        other.set_dialect(DialectType::BigQuery);
+
+        let err = graph.merge(other).unwrap_err();
+        assert!(
+            matches!(err, SidemanticError::CircularDependency(_)),
+            "{err}"
+        );
+        assert!(graph.get_model("m2").is_none());
+        assert!(graph.dialect().is_none());
+        assert_eq!(graph.version(), start);
+    }
+
+    #[test]
+    fn test_remove_model_rejects_referenced_model() {
+        let mut graph = create_test_graph();
+        graph
+            .add_metric(Metric::derived("revenue_plus_one", "orders.revenue + 1"))
+            .unwrap();
+        let start = graph.version();
+
+        let err = graph.remove_model("customers").unwrap_err();
+        assert!(
+            err.to_string().contains("relationship 'orders.customers'"),
+            "{err}"
+        );
+        let err = graph.remove_model("orders").unwrap_err();
+        assert!(
+            err.to_string().contains("metric 'revenue_plus_one'"),
+            "{err}"
+        );
+        assert!(graph.get_model("customers").is_some());
+        assert!(graph.get_model("orders").is_some());
+        assert_eq!(graph.version(), start);
+
+        graph.metrics.remove("revenue_plus_one");
+        graph.remove_model("orders").unwrap();
+        graph.remove_model("customers").unwrap();
+        assert!(graph.get_model("customers").is_none());
+    }
+
+    #[test]
+    fn test_flatten_skips_one_to_many_and_many_to_many_targets() {
+        let mut graph = SemanticGraph::new();
+        let mut tags = Relationship::many_to_one("tags");
+        tags.r#type = RelationshipType::ManyToMany;
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_relationship(Relationship::many_to_one("customers"))
+                    .with_relationship(Relationship::one_to_many("line_items"))
+                    .with_relationship(tags),
+            )
+            .unwrap();
+        for (name, dimension) in [
+            ("customers", "country"),
+            ("line_items", "sku"),
+            ("tags", "label"),
+        ] {
+            graph
+                .add_model(
+                    Model::new(name, "id")
+                        .with_table(name)
+                        .with_dimension(Dimension::categorical(dimension)),
+                )
+                .unwrap();
+        }
+
+        graph.flatten_foreign_dimensions();
+
+        let orders = graph.get_model("orders").unwrap();
+        assert!(orders.get_dimension("customers__country").is_some());
+        assert!(orders.get_dimension("line_items__sku").is_none());
+        assert!(orders.get_dimension("tags__label").is_none());
+    }
 }
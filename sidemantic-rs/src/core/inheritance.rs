@@ -17,6 +17,7 @@ pub fn merge_model(child: &Model, parent: &Model) -> Model {
     // Start with parent's table/sql, override with child if set
     let table = child.table.clone().or_else(|| parent.table.clone());
     let sql = child.sql.clone().or_else(|| parent.sql.clone());
+    let columns = child.columns.clone().or_else(|| parent.columns.clone());
     let source_uri = child
         .source_uri
         .clone()
@@ -50,6 +51,11 @@ pub fn merge_model(child: &Model, parent: &Model) -> Model {
         .unique_keys
         .clone()
         .or_else(|| parent.unique_keys.clone());
+    let grain = if child.grain.is_empty() {
+        parent.grain.clone()
+    } else {
+        child.grain.clone()
+    };
     let description = child
         .description
         .clone()
@@ -57,6 +63,12 @@ pub fn merge_model(child: &Model, parent: &Model) -> Model {
     let label = child.label.clone().or_else(|| parent.label.clone());
     let metadata = child.metadata.clone().or_else(|| parent.metadata.clone());
     let meta = child.meta.clone().or_else(|| parent.meta.clone());
+    let mut tags = parent.tags.clone();
+    for tag in &child.tags {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
     let default_time_dimension = child
         .default_time_dimension
         .clone()
@@ -66,74 +78,37 @@ pub fn merge_model(child: &Model, parent: &Model) -> Model {
         .clone()
         .or_else(|| parent.default_grain.clone());
 
-    // Merge dimensions by name (child overrides parent)
-    let mut dimensions_map: HashMap<String, _> = parent
-        .dimensions
-        .iter()
-        .map(|d| (d.name.clone(), d.clone()))
-        .collect();
-    for dim in &child.dimensions {
-        dimensions_map.insert(dim.name.clone(), dim.clone());
-    }
-    let dimensions: Vec<_> = dimensions_map.into_values().collect();
-
-    // Merge metrics by name
-    let mut metrics_map: HashMap<String, _> = parent
-        .metrics
-        .iter()
-        .map(|m| (m.name.clone(), m.clone()))
-        .collect();
-    for metric in &child.metrics {
-        metrics_map.insert(metric.name.clone(), metric.clone());
-    }
-    let metrics: Vec<_> = metrics_map.into_values().collect();
-
-    // Merge relationships by name
-    let mut relationships_map: HashMap<String, _> = parent
-        .relationships
-        .iter()
-        .map(|r| (r.name.clone(), r.clone()))
-        .collect();
-    for rel in &child.relationships {
-        relationships_map.insert(rel.name.clone(), rel.clone());
-    }
-    let relationships: Vec<_> = relationships_map.into_values().collect();
-
-    // Merge segments by name
-    let mut segments_map: HashMap<String, _> = parent
-        .segments
-        .iter()
-        .map(|s| (s.name.clone(), s.clone()))
-        .collect();
-    for seg in &child.segments {
-        segments_map.insert(seg.name.clone(), seg.clone());
-    }
-    let segments: Vec<_> = segments_map.into_values().collect();
-
-    // Merge pre-aggregations by name
-    let mut pre_aggs_map: HashMap<String, _> = parent
-        .pre_aggregations
-        .iter()
-        .map(|p| (p.name.clone(), p.clone()))
-        .collect();
-    for pre_agg in &child.pre_aggregations {
-        pre_aggs_map.insert(pre_agg.name.clone(), pre_agg.clone());
+    // Merge list fields by name (child overrides parent, definition order kept)
+    let dimensions = merge_by_name(&parent.dimensions, &child.dimensions, |d| &d.name);
+    let metrics = merge_by_name(&parent.metrics, &child.metrics, |m| &m.name);
+    let relationships = merge_by_name(&parent.relationships, &child.relationships, |r| &r.name);
+    let segments = merge_by_name(&parent.segments, &child.segments, |s| &s.name);
+    let mut default_filters = parent.default_filters.clone();
+    for filter in &child.default_filters {
+        if !default_filters.contains(filter) {
+            default_filters.push(filter.clone());
+        }
     }
-    let pre_aggregations: Vec<_> = pre_aggs_map.into_values().collect();
+    let pre_aggregations = merge_by_name(&parent.pre_aggregations, &child.pre_aggregations, |p| {
+        &p.name
+    });
 
     Model {
         name: child.name.clone(),
         table,
         sql,
+        columns,
         source_uri,
         extends,
         primary_key,
         primary_key_columns,
         unique_keys,
+        grain,
         dimensions,
         metrics,
         relationships,
         segments,
+        default_filters,
         pre_aggregations,
         default_time_dimension,
         default_grain,
@@ -141,9 +116,28 @@ pub fn merge_model(child: &Model, parent: &Model) -> Model {
         description,
         metadata,
         meta,
+        tags,
     }
 }
 
+/// Merge two named lists, keeping parent order.
+///
+/// Child items replace parent items of the same name in place; new child
+/// items are appended in their own order.
+fn merge_by_name<T: Clone>(parent: &[T], child: &[T], name: impl Fn(&T) -> &String) -> Vec<T> {
+    let mut merged: Vec<T> = parent.to_vec();
+    for item in child {
+        match merged
+            .iter()
+            .position(|existing| name(existing) == name(item))
+        {
+            Some(index) => merged[index] = item.clone(),
+            None => merged.push(item.clone()),
+        }
+    }
+    merged
+}
+
 /// Resolve inheritance for all models.
 ///
 /// Models with `extends` field are merged with their parent models.
@@ -251,6 +245,25 @@ mod tests {
         assert_eq!(revenue.sql, Some("total_amount".to_string()));
     }
 
+    #[test]
+    fn test_merge_model_preserves_definition_order() {
+        let parent = Model::new("base", "id")
+            .with_table("base_table")
+            .with_dimension(Dimension::categorical("status"))
+            .with_dimension(Dimension::categorical("region"))
+            .with_dimension(Dimension::categorical("channel"));
+
+        let child = Model::new("child", "id")
+            .with_dimension(Dimension::categorical("region").with_sql("region_code"))
+            .with_dimension(Dimension::categorical("category"));
+
+        let merged = merge_model(&child, &parent);
+
+        let names: Vec<&str> = merged.dimensions.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["status", "region", "channel", "category"]);
+        assert_eq!(merged.dimensions[1].sql, Some("region_code".to_string()));
+    }
+
     #[test]
     fn test_resolve_inheritance() {
         let mut models = HashMap::new();
@@ -1,5 +1,6 @@
 //! Core semantic layer types and graph
 
+mod date_spine;
 mod dependency;
 mod graph;
 mod inheritance;
@@ -10,19 +11,21 @@ mod segment;
 pub mod symmetric_agg;
 mod table_calc;
 
+pub use date_spine::{CalendarAttribute, DateSpine};
 pub use dependency::{
     check_circular_dependencies, extract_column_references_from_expr, extract_dependencies,
     extract_dependencies_with_context,
 };
-pub use graph::{JoinPath, JoinStep, SemanticGraph};
+pub use graph::{JoinPath, JoinStep, MetadataMut, SemanticGraph};
 pub use inheritance::{merge_model, resolve_model_inheritance};
 pub use model::{
-    Aggregation, CohortInnerMetric, ComparisonCalculation, ComparisonType, Dimension,
-    DimensionType, Index, Metric, MetricType, Model, PreAggregation, PreAggregationType,
+    date_add_sql, date_trunc_sql, string_type_sql, supports_aggregate_filter, Aggregation,
+    CohortInnerMetric, ComparisonCalculation, ComparisonType, Dimension, DimensionType, Index,
+    JoinType, Metric, MetricType, Model, NonAdditiveWindow, PreAggregation, PreAggregationType,
     RefreshKey, Relationship, RelationshipType, TimeGrain,
 };
 pub use parameter::{Parameter, ParameterType};
-pub use relative_date::RelativeDate;
+pub use relative_date::{DateRange, DateUnit, RelativeDate};
 pub use segment::Segment;
 pub use symmetric_agg::{
     build_symmetric_aggregate_sql, build_symmetric_aggregate_sql_with_key_expr, SqlDialect,
@@ -1,5 +1,9 @@
 //! Core semantic layer types: Model, Dimension, Metric, Relationship
 
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+use polyglot_sql::DialectType;
 use serde::{Deserialize, Serialize};
 
 use super::segment::Segment;
@@ -44,12 +48,28 @@ pub struct Dimension {
     /// Named display format
     #[serde(default)]
     pub value_format_name: Option<String>,
+    /// Display labels keyed by raw value (e.g. `"P"` -> `"Pending"`)
+    #[serde(default)]
+    pub value_labels: Option<BTreeMap<String, String>>,
+    /// Label that NULL values group and display under (e.g. `"Unknown"`)
+    #[serde(default)]
+    pub null_label: Option<String>,
     /// Parent dimension name for hierarchies
     #[serde(default)]
     pub parent: Option<String>,
     /// Window expression projected in model CTEs.
     #[serde(default)]
     pub window: Option<String>,
+    /// Pass-through reference to another model's dimension (`model.dimension`),
+    /// joined in automatically when selected.
+    #[serde(default, rename = "ref")]
+    pub reference: Option<String>,
+    /// Approximate number of distinct values, for query planning and chart choice
+    #[serde(default)]
+    pub cardinality_hint: Option<u64>,
+    /// Free-form tags for organizing the catalog
+    #[serde(default)]
+    pub tags: Vec<String>,
     /// Whether dimension is visible in API/UI.
     #[serde(default = "default_true")]
     pub public: bool,
@@ -69,8 +89,13 @@ impl Dimension {
             meta: None,
             format: None,
             value_format_name: None,
+            value_labels: None,
+            null_label: None,
             parent: None,
             window: None,
+            reference: None,
+            cardinality_hint: None,
+            tags: Vec::new(),
             public: true,
         }
     }
@@ -96,6 +121,89 @@ impl Dimension {
         self
     }
 
+    pub fn with_null_label(mut self, null_label: impl Into<String>) -> Self {
+        self.null_label = Some(null_label.into());
+        self
+    }
+
+    pub fn with_cardinality_hint(mut self, cardinality_hint: u64) -> Self {
+        self.cardinality_hint = Some(cardinality_hint);
+        self
+    }
+
+    /// Compute this dimension with a window expression, projected in the
+    /// model CTE so outer queries can group and filter on its result.
+    pub fn with_window(mut self, window: impl Into<String>) -> Self {
+        self.window = Some(window.into());
+        self
+    }
+
+    /// Cohort flag classifying each row as `'new'` when it is the first
+    /// occurrence of `entity` by `time` and `'returning'` otherwise, e.g.
+    /// first versus repeat orders per customer.
+    pub fn new_vs_returning(
+        name: impl Into<String>,
+        entity: impl AsRef<str>,
+        time: impl AsRef<str>,
+    ) -> Self {
+        let (entity, time) = (entity.as_ref(), time.as_ref());
+        Self::categorical(name).with_window(format!(
+            "CASE WHEN {time} = MIN({time}) OVER (PARTITION BY {entity}) \
+             THEN 'new' ELSE 'returning' END"
+        ))
+    }
+
+    /// Expose another model's dimension (`model.dimension`) under this name.
+    pub fn with_reference(mut self, reference: impl Into<String>) -> Self {
+        self.reference = Some(reference.into());
+        self
+    }
+
+    pub fn with_value_labels<K, V>(mut self, labels: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.value_labels = Some(
+            labels
+                .into_iter()
+                .map(|(value, label)| (value.into(), label.into()))
+                .collect(),
+        );
+        self
+    }
+
+    /// Returns a CASE expression mapping raw values of `expr` to their display
+    /// labels, falling back to the raw value. `None` when no labels are defined.
+    pub fn value_label_case_sql(&self, expr: &str, dialect: DialectType) -> Option<String> {
+        let labels = self
+            .value_labels
+            .as_ref()
+            .filter(|labels| !labels.is_empty())?;
+        let quote = |value: &str| format!("'{}'", value.replace('\'', "''"));
+        let branches: Vec<String> = labels
+            .iter()
+            .map(|(value, label)| format!("WHEN {} THEN {}", quote(value), quote(label)))
+            .collect();
+        let string_type = string_type_sql(dialect);
+        Some(format!(
+            "CASE CAST({expr} AS {string_type}) {} ELSE CAST({expr} AS {string_type}) END",
+            branches.join(" ")
+        ))
+    }
+
+    /// Returns `expr` wrapped in a COALESCE to the null label, so NULL values
+    /// group and display under it. `None` when no null label is set or the
+    /// dimension isn't categorical, since a string label can't stand in for a
+    /// time, numeric or boolean value.
+    pub fn null_label_sql(&self, expr: &str) -> Option<String> {
+        if self.r#type != DimensionType::Categorical {
+            return None;
+        }
+        let label = self.null_label.as_ref()?;
+        Some(format!("COALESCE({expr}, '{}')", label.replace('\'', "''")))
+    }
+
     /// Returns the SQL expression for this dimension
     pub fn sql_expr(&self) -> &str {
         self.sql.as_deref().unwrap_or(&self.name)
@@ -106,18 +214,98 @@ impl Dimension {
         self.window.as_deref().unwrap_or_else(|| self.sql_expr())
     }
 
-    /// Returns SQL with time granularity applied (DATE_TRUNC)
-    pub fn sql_with_granularity(&self, granularity: Option<&str>) -> String {
+    /// Returns SQL with time granularity applied, truncated the way `dialect`
+    /// spells it (see [`date_trunc_sql`])
+    pub fn sql_with_granularity(&self, granularity: Option<&str>, dialect: DialectType) -> String {
         let base_sql = self.sql_expr();
         match granularity.or(self.granularity.as_deref()) {
-            Some(g) => format!("DATE_TRUNC('{g}', {base_sql})"),
+            Some(g) => date_trunc_sql(g, base_sql, dialect),
             None => base_sql.to_string(),
         }
     }
 }
 
+/// The type name to CAST to for a variable-length string in the given dialect:
+/// `VARCHAR` by default, `STRING` on BigQuery and Spark, `String` on ClickHouse.
+pub fn string_type_sql(dialect: DialectType) -> &'static str {
+    match dialect {
+        DialectType::BigQuery | DialectType::Databricks | DialectType::Spark => "STRING",
+        DialectType::ClickHouse => "String",
+        DialectType::MySQL => "CHAR",
+        DialectType::TSQL => "NVARCHAR(MAX)",
+        _ => "VARCHAR",
+    }
+}
+
+/// Truncate `expr` to `granularity` in the given dialect: `DATE_TRUNC('month', x)`
+/// by default, `TIMESTAMP_TRUNC(x, MONTH)` on BigQuery, `DATE_TRUNC(month, x)`
+/// on Snowflake and `DATETRUNC(month, x)` on SQL Server.
+pub fn date_trunc_sql(granularity: &str, expr: &str, dialect: DialectType) -> String {
+    match dialect {
+        DialectType::BigQuery => format!(
+            "TIMESTAMP_TRUNC({expr}, {})",
+            granularity.to_ascii_uppercase()
+        ),
+        DialectType::Snowflake => format!("DATE_TRUNC({granularity}, {expr})"),
+        DialectType::TSQL => format!("DATETRUNC({granularity}, {expr})"),
+        _ => format!("DATE_TRUNC('{granularity}', {expr})"),
+    }
+}
+
+/// Move `expr` by `count` calendar `unit`s (negative counts go back) in the
+/// given dialect: `x - INTERVAL '3 months'` by default, `DATE_SUB(x, INTERVAL
+/// 3 MONTH)` on BigQuery and `DATEADD(month, -3, x)` on Snowflake-like engines.
+pub fn date_add_sql(expr: &str, count: i64, unit: &str, dialect: DialectType) -> String {
+    let sign = if count < 0 { "-" } else { "+" };
+    let amount = count.unsigned_abs();
+    match dialect {
+        DialectType::BigQuery | DialectType::MySQL => {
+            let function = if count < 0 { "DATE_SUB" } else { "DATE_ADD" };
+            format!(
+                "{function}({expr}, INTERVAL {amount} {})",
+                unit.to_ascii_uppercase()
+            )
+        }
+        DialectType::Snowflake
+        | DialectType::Redshift
+        | DialectType::Databricks
+        | DialectType::Spark
+        | DialectType::TSQL => format!("DATEADD({unit}, {count}, {expr})"),
+        DialectType::ClickHouse => {
+            format!(
+                "{expr} {sign} INTERVAL {amount} {}",
+                unit.to_ascii_uppercase()
+            )
+        }
+        _ => {
+            // Postgres interval literals have no quarter unit.
+            let (amount, unit) = match unit {
+                "quarter" => (amount * 3, "month"),
+                unit => (amount, unit),
+            };
+            let plural = if amount == 1 { "" } else { "s" };
+            format!("{expr} {sign} INTERVAL '{amount} {unit}{plural}'")
+        }
+    }
+}
+
+/// Whether `dialect` accepts `AGG(...) FILTER (WHERE ...)`.
+pub fn supports_aggregate_filter(dialect: DialectType) -> bool {
+    matches!(
+        dialect,
+        DialectType::Generic
+            | DialectType::DuckDB
+            | DialectType::PostgreSQL
+            | DialectType::CockroachDB
+            | DialectType::Materialize
+            | DialectType::RisingWave
+            | DialectType::Spark
+            | DialectType::Databricks
+    )
+}
+
 /// Aggregation function type
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum Aggregation {
     #[default]
@@ -132,16 +320,23 @@ pub enum Aggregation {
     StddevPop,
     Variance,
     VariancePop,
+    /// Exact continuous percentile, e.g. `Percentile(0.95)` for p95
+    Percentile(f64),
+    /// Approximate percentile (cheaper on large inputs)
+    ApproxPercentile(f64),
+    /// Approximate distinct count
+    ApproxCountDistinct,
     /// Raw expression that already contains aggregation (e.g., SUM(amount) * 2)
     Expression,
 }
 
 impl Aggregation {
+    /// Aggregate function name. Use `call_sql` to render a call, which adds
+    /// `DISTINCT` for `CountDistinct` and the ordering clauses percentiles need.
     pub fn as_sql(&self) -> &'static str {
         match self {
             Aggregation::Sum => "SUM",
-            Aggregation::Count => "COUNT",
-            Aggregation::CountDistinct => "COUNT(DISTINCT",
+            Aggregation::Count | Aggregation::CountDistinct => "COUNT",
             Aggregation::Avg => "AVG",
             Aggregation::Min => "MIN",
             Aggregation::Max => "MAX",
@@ -150,9 +345,47 @@ impl Aggregation {
             Aggregation::StddevPop => "STDDEV_POP",
             Aggregation::Variance => "VARIANCE",
             Aggregation::VariancePop => "VAR_POP",
+            Aggregation::Percentile(_) => "PERCENTILE_CONT",
+            Aggregation::ApproxPercentile(_) => "APPROX_QUANTILE",
+            Aggregation::ApproxCountDistinct => "APPROX_COUNT_DISTINCT",
             Aggregation::Expression => "", // Not used - expression stored in sql field
         }
     }
+
+    /// Render this aggregation applied to `expr`.
+    pub fn call_sql(&self, expr: &str) -> String {
+        match self {
+            Aggregation::CountDistinct => format!("COUNT(DISTINCT {expr})"),
+            Aggregation::Percentile(fraction) => {
+                format!("PERCENTILE_CONT({fraction}) WITHIN GROUP (ORDER BY {expr})")
+            }
+            Aggregation::ApproxPercentile(fraction) => {
+                format!("APPROX_QUANTILE({expr}, {fraction})")
+            }
+            _ => format!("{}({expr})", self.as_sql()),
+        }
+    }
+}
+
+impl PartialEq for Aggregation {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Percentile(a), Self::Percentile(b))
+            | (Self::ApproxPercentile(a), Self::ApproxPercentile(b)) => a.to_bits() == b.to_bits(),
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl Eq for Aggregation {}
+
+impl Hash for Aggregation {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        if let Self::Percentile(fraction) | Self::ApproxPercentile(fraction) = self {
+            fraction.to_bits().hash(state);
+        }
+    }
 }
 
 /// Metric type
@@ -169,6 +402,9 @@ pub enum MetricType {
     Conversion,
     Retention,
     Cohort,
+    /// Share of the grand total of `base_metric` across all result rows
+    #[serde(alias = "percent_of_total")]
+    PctOfTotal,
 }
 
 /// Time comparison type
@@ -191,6 +427,32 @@ pub enum ComparisonCalculation {
     #[default]
     PercentChange,
     Ratio,
+    /// The base metric's value in the comparison period itself
+    PreviousValue,
+}
+
+/// Which snapshot of a semi-additive metric's `non_additive_dimension` is
+/// kept per group: `max`/`min` of the dimension, as in MetricFlow's window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NonAdditiveWindow {
+    /// Latest snapshot (e.g. closing balance)
+    #[default]
+    #[serde(alias = "max")]
+    Last,
+    /// Earliest snapshot (e.g. opening balance)
+    #[serde(alias = "min")]
+    First,
+}
+
+impl NonAdditiveWindow {
+    /// Aggregate picking this snapshot's value of the dimension
+    pub fn aggregate(self) -> &'static str {
+        match self {
+            NonAdditiveWindow::Last => "MAX",
+            NonAdditiveWindow::First => "MIN",
+        }
+    }
 }
 
 /// Time grain for period-to-date calculations
@@ -204,6 +466,18 @@ pub enum TimeGrain {
     Year,
 }
 
+impl TimeGrain {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimeGrain::Day => "day",
+            TimeGrain::Week => "week",
+            TimeGrain::Month => "month",
+            TimeGrain::Quarter => "quarter",
+            TimeGrain::Year => "year",
+        }
+    }
+}
+
 /// Inner per-entity aggregate for cohort metrics.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CohortInnerMetric {
@@ -264,6 +538,12 @@ pub struct Metric {
     /// ORDER BY column override for window metrics
     #[serde(default)]
     pub window_order: Option<String>,
+    /// Trailing window size, counted in periods of `window_grain`
+    #[serde(default)]
+    pub window_periods: Option<usize>,
+    /// Grain each trailing period is pre-aggregated at (e.g., day)
+    #[serde(default)]
+    pub window_grain: Option<TimeGrain>,
 
     // Time comparison fields
     /// Base metric for time comparison
@@ -337,12 +617,22 @@ pub struct Metric {
     /// Dimension across which this metric is non-additive
     #[serde(default)]
     pub non_additive_dimension: Option<String>,
+    /// Snapshot of `non_additive_dimension` aggregated per group
+    #[serde(default)]
+    pub non_additive_window: NonAdditiveWindow,
+    /// Free-form tags for organizing the catalog
+    #[serde(default)]
+    pub tags: Vec<String>,
     /// Whether metric is visible in API/UI.
     #[serde(default = "default_true")]
     pub public: bool,
 }
 
 impl Metric {
+    /// Implicit reference usable in derived and ratio metrics for the number
+    /// of rows in the current group (`COUNT(*)` over the joined rows).
+    pub const ROW_COUNT: &'static str = "__row_count";
+
     pub fn new(name: impl Into<String>) -> Self {
         Self {
             name: name.into(),
@@ -363,6 +653,8 @@ impl Metric {
             window_expression: None,
             window_frame: None,
             window_order: None,
+            window_periods: None,
+            window_grain: None,
             base_metric: None,
             comparison_type: None,
             time_offset: None,
@@ -384,6 +676,8 @@ impl Metric {
             value_format_name: None,
             drill_fields: None,
             non_additive_dimension: None,
+            non_additive_window: NonAdditiveWindow::Last,
+            tags: Vec::new(),
             public: true,
         }
     }
@@ -412,6 +706,15 @@ impl Metric {
         }
     }
 
+    /// Create an exact percentile metric; `fraction` is in [0, 1] (0.95 for p95).
+    pub fn percentile(name: impl Into<String>, sql: impl Into<String>, fraction: f64) -> Self {
+        Self {
+            agg: Some(Aggregation::Percentile(fraction)),
+            sql: Some(sql.into()),
+            ..Self::new(name)
+        }
+    }
+
     pub fn avg(name: impl Into<String>, sql: impl Into<String>) -> Self {
         Self {
             agg: Some(Aggregation::Avg),
@@ -443,6 +746,52 @@ impl Metric {
         }
     }
 
+    /// Create a target attainment metric (`actual / target`), formatted as a percent
+    pub fn attainment(
+        name: impl Into<String>,
+        actual: impl Into<String>,
+        target: impl Into<String>,
+    ) -> Self {
+        Self {
+            value_format_name: Some("percent".into()),
+            ..Self::ratio(name, actual, target)
+        }
+    }
+
+    /// Create a variance-to-target metric (`actual - target`)
+    ///
+    /// Operands other than plain metric references are parenthesized, so a
+    /// compound target like `target_a + target_b` is subtracted as a whole.
+    pub fn variance_to_target(
+        name: impl Into<String>,
+        actual: impl Into<String>,
+        target: impl Into<String>,
+    ) -> Self {
+        let sql = format!(
+            "{} - {}",
+            variance_operand(&actual.into()),
+            variance_operand(&target.into())
+        );
+        Self::derived(name, sql)
+    }
+
+    /// Create a weighted average metric (`SUM(value * weight) / NULLIF(SUM(weight), 0)`)
+    ///
+    /// Bare column names are qualified with `{model}`; other expressions are
+    /// used as written.
+    pub fn weighted_avg(
+        name: impl Into<String>,
+        value: impl Into<String>,
+        weight: impl Into<String>,
+    ) -> Self {
+        let value = weighted_avg_operand(&value.into());
+        let weight = weighted_avg_operand(&weight.into());
+        Self::derived(
+            name,
+            format!("SUM({value} * {weight}) / NULLIF(SUM({weight}), 0)"),
+        )
+    }
+
     /// Create a cumulative (running total) metric
     pub fn cumulative(name: impl Into<String>, base_metric: impl Into<String>) -> Self {
         Self {
@@ -453,6 +802,21 @@ impl Metric {
         }
     }
 
+    /// Create a trailing-window metric over the last `periods` periods of `grain`
+    /// (e.g., trailing 30-day revenue). Early periods sum whatever rows exist.
+    pub fn trailing(
+        name: impl Into<String>,
+        base_metric: impl Into<String>,
+        periods: usize,
+        grain: TimeGrain,
+    ) -> Self {
+        Self {
+            window_periods: Some(periods),
+            window_grain: Some(grain),
+            ..Self::cumulative(name, base_metric)
+        }
+    }
+
     /// Create a period-to-date metric (MTD, YTD, etc.)
     pub fn period_to_date(
         name: impl Into<String>,
@@ -468,6 +832,16 @@ impl Metric {
         }
     }
 
+    /// Create a percent-of-total metric: `base / SUM(base) OVER ()`
+    pub fn pct_of_total(name: impl Into<String>, base_metric: impl Into<String>) -> Self {
+        Self {
+            r#type: MetricType::PctOfTotal,
+            agg: None,
+            base_metric: Some(base_metric.into()),
+            ..Self::new(name)
+        }
+    }
+
     /// Create a time comparison metric (YoY, MoM, etc.)
     pub fn time_comparison(
         name: impl Into<String>,
@@ -503,6 +877,51 @@ impl Metric {
         self
     }
 
+    /// Compare against a custom offset such as `"3 months"` instead of the
+    /// comparison type's default period.
+    pub fn with_time_offset(mut self, time_offset: impl Into<String>) -> Self {
+        self.time_offset = Some(time_offset.into());
+        self
+    }
+
+    /// Whether this metric can be summed across rollups of any dimension.
+    ///
+    /// Only plain `SUM`/`COUNT` aggregations without a non-additive dimension
+    /// qualify; averages, distinct counts, ratios, and derived expressions
+    /// (including weighted averages) must be recomputed from raw data.
+    pub fn is_additive(&self) -> bool {
+        self.r#type == MetricType::Simple
+            && self.non_additive_dimension.is_none()
+            && matches!(
+                self.agg.as_ref().unwrap_or(&Aggregation::Sum),
+                Aggregation::Sum | Aggregation::Count
+            )
+    }
+
+    /// AND of the metric's `filters`, with `{model}` resolved to `alias`.
+    fn filter_condition(&self, alias: Option<&str>) -> Option<String> {
+        if self.filters.is_empty() {
+            return None;
+        }
+        let conditions: Vec<String> = self
+            .filters
+            .iter()
+            .map(|filter| match alias {
+                Some(alias) => filter.replace("{model}", alias),
+                None => filter.replace("{model}.", ""),
+            })
+            .collect();
+        Some(if conditions.len() == 1 {
+            conditions.into_iter().next().unwrap()
+        } else {
+            conditions
+                .iter()
+                .map(|c| format!("({c})"))
+                .collect::<Vec<_>>()
+                .join(" AND ")
+        })
+    }
+
     /// Returns the SQL expression for this metric
     pub fn sql_expr(&self) -> &str {
         self.sql.as_deref().unwrap_or(&self.name)
@@ -510,26 +929,50 @@ impl Metric {
 
     /// Converts metric to SQL aggregation expression
     pub fn to_sql(&self, alias: Option<&str>) -> String {
+        self.to_sql_for_dialect(alias, DialectType::Generic)
+    }
+
+    /// Converts metric to SQL aggregation expression for `dialect`.
+    ///
+    /// Metric-level `filters` on simple metrics scope the aggregate with
+    /// `FILTER (WHERE ...)` where the dialect supports it, and fall back to
+    /// `AGG(CASE WHEN ... THEN expr END)` elsewhere.
+    pub fn to_sql_for_dialect(&self, alias: Option<&str>, dialect: DialectType) -> String {
+        self.to_sql_with_count_argument(alias, dialect, "*")
+    }
+
+    /// Like [`to_sql_for_dialect`](Self::to_sql_for_dialect), with a count
+    /// of rows rendered as `COUNT({count_argument})`.
+    pub fn to_sql_with_count_argument(
+        &self,
+        alias: Option<&str>,
+        dialect: DialectType,
+        count_argument: &str,
+    ) -> String {
         let prefix = alias.map(|a| format!("{a}.")).unwrap_or_default();
 
         match self.r#type {
             MetricType::Simple => {
                 let agg = self.agg.as_ref().unwrap_or(&Aggregation::Sum);
-                // COUNT without explicit sql defaults to COUNT(*)
-                let full_expr = if *agg == Aggregation::Count && self.sql.is_none() {
-                    "*".to_string()
+                // COUNT without explicit sql counts rows
+                let counts_rows =
+                    (self.sql.is_none() && *agg == Aggregation::Count) || self.sql_expr() == "*";
+                let full_expr = if counts_rows {
+                    count_argument.to_string()
                 } else {
-                    let sql_expr = self.sql_expr();
-                    if sql_expr == "*" {
-                        "*".to_string()
-                    } else {
-                        format!("{prefix}{sql_expr}")
-                    }
+                    format!("{prefix}{}", self.sql_expr())
                 };
 
-                match agg {
-                    Aggregation::CountDistinct => format!("COUNT(DISTINCT {full_expr})"),
-                    _ => format!("{}({})", agg.as_sql(), full_expr),
+                match self.filter_condition(alias) {
+                    Some(condition) if *agg != Aggregation::Expression => {
+                        if supports_aggregate_filter(dialect) {
+                            format!("{} FILTER (WHERE {condition})", agg.call_sql(&full_expr))
+                        } else {
+                            let value = if counts_rows { "1" } else { &full_expr };
+                            agg.call_sql(&format!("CASE WHEN {condition} THEN {value} END"))
+                        }
+                    }
+                    _ => agg.call_sql(&full_expr),
                 }
             }
             MetricType::Derived => self.sql_expr().to_string(),
@@ -581,14 +1024,34 @@ impl Metric {
                     ComparisonCalculation::Ratio => {
                         format!("({base} / NULLIF(LAG({base}) OVER (), 0)) /* {comparison} */")
                     }
+                    ComparisonCalculation::PreviousValue => {
+                        format!("LAG({base}) OVER () /* {comparison} */")
+                    }
                 }
             }
+            MetricType::PctOfTotal => {
+                let base = self.base_metric.as_deref().unwrap_or(&self.name);
+                format!("{base} / NULLIF(SUM({base}) OVER (), 0)")
+            }
             MetricType::Conversion | MetricType::Retention | MetricType::Cohort => {
                 "NULL /* complex metric */".to_string()
             }
         }
     }
 
+    /// Whether queries of this metric must include a time dimension to order
+    /// its window by: running, rolling and grain-to-date cumulative metrics
+    /// (unless `window_order` names another column), time comparisons, and
+    /// ratios with an `offset_window`.
+    pub fn requires_time_dimension(&self) -> bool {
+        match self.r#type {
+            MetricType::Cumulative => self.window_order.is_none(),
+            MetricType::TimeComparison => true,
+            MetricType::Ratio => self.offset_window.is_some(),
+            _ => false,
+        }
+    }
+
     /// Check if this is a simple aggregation (not a complex metric)
     pub fn is_simple_aggregation(&self) -> bool {
         self.r#type == MetricType::Simple && self.agg.is_some()
@@ -606,6 +1069,47 @@ pub enum RelationshipType {
     ManyToMany,
 }
 
+/// SQL join used when traversing a relationship
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JoinType {
+    Left,
+    Inner,
+    Right,
+    Full,
+}
+
+impl JoinType {
+    /// Parse a join type name such as `inner` or `full_outer`, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "left" | "left_outer" => Some(JoinType::Left),
+            "inner" => Some(JoinType::Inner),
+            "right" | "right_outer" => Some(JoinType::Right),
+            "full" | "full_outer" => Some(JoinType::Full),
+            _ => None,
+        }
+    }
+
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            JoinType::Left => "LEFT JOIN",
+            JoinType::Inner => "INNER JOIN",
+            JoinType::Right => "RIGHT JOIN",
+            JoinType::Full => "FULL OUTER JOIN",
+        }
+    }
+
+    /// The equivalent join when the relationship is traversed from its target.
+    pub fn reversed(self) -> Self {
+        match self {
+            JoinType::Left => JoinType::Right,
+            JoinType::Right => JoinType::Left,
+            other => other,
+        }
+    }
+}
+
 /// A relationship defines how models join together
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Relationship {
@@ -641,13 +1145,27 @@ pub struct Relationship {
     pub related_foreign_key_columns: Option<Vec<String>>,
     /// Custom SQL join condition (overrides FK/PK)
     /// Use {from} and {to} placeholders for table aliases
-    #[serde(default)]
+    #[serde(default, alias = "sql_on")]
     pub sql: Option<String>,
+    /// Join type to emit; defaults to `LEFT JOIN` (or `INNER JOIN` when the
+    /// query filters the joined model)
+    #[serde(default)]
+    pub join_type: Option<JoinType>,
     /// Adapter-specific metadata payload.
     #[serde(default)]
     pub metadata: Option<serde_json::Value>,
 }
 
+/// Split comma-separated composite key entries into individual columns.
+fn split_key_columns(keys: &[String]) -> Vec<String> {
+    keys.iter()
+        .flat_map(|key| key.split(','))
+        .map(str::trim)
+        .filter(|column| !column.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
 impl Relationship {
     pub fn new(target: impl Into<String>) -> Self {
         Self {
@@ -663,6 +1181,7 @@ impl Relationship {
             related_foreign_key: None,
             related_foreign_key_columns: None,
             sql: None,
+            join_type: None,
             metadata: None,
         }
     }
@@ -712,6 +1231,12 @@ impl Relationship {
         self
     }
 
+    /// Override the SQL join emitted for this relationship
+    pub fn with_join_type(mut self, join_type: JoinType) -> Self {
+        self.join_type = Some(join_type);
+        self
+    }
+
     /// Returns the foreign key column name
     pub fn fk(&self) -> String {
         self.foreign_key_columns()
@@ -728,11 +1253,17 @@ impl Relationship {
             .unwrap_or_else(|| "id".to_string())
     }
 
+    /// Foreign key columns; entries may list composite keys separated by commas.
     pub fn foreign_key_columns(&self) -> Vec<String> {
         self.foreign_key_columns
-            .clone()
+            .as_deref()
             .filter(|columns| !columns.is_empty())
-            .or_else(|| self.foreign_key.clone().map(|key| vec![key]))
+            .map(split_key_columns)
+            .or_else(|| {
+                self.foreign_key
+                    .as_ref()
+                    .map(|key| split_key_columns(std::slice::from_ref(key)))
+            })
             .unwrap_or_else(|| {
                 if self.r#type == RelationshipType::ManyToOne {
                     vec![format!("{}_id", self.name)]
@@ -742,11 +1273,17 @@ impl Relationship {
             })
     }
 
+    /// Primary key columns; entries may list composite keys separated by commas.
     pub fn primary_key_columns(&self) -> Vec<String> {
         self.primary_key_columns
-            .clone()
+            .as_deref()
             .filter(|columns| !columns.is_empty())
-            .or_else(|| self.primary_key.clone().map(|key| vec![key]))
+            .map(split_key_columns)
+            .or_else(|| {
+                self.primary_key
+                    .as_ref()
+                    .map(|key| split_key_columns(std::slice::from_ref(key)))
+            })
             .unwrap_or_else(|| vec!["id".to_string()])
     }
 
@@ -807,6 +1344,36 @@ pub enum PreAggregationType {
     Lambda,
 }
 
+/// Qualify a bare column name with `{model}`; wrap other expressions in parens.
+fn weighted_avg_operand(expr: &str) -> String {
+    let expr = expr.trim();
+    let is_identifier = expr
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && expr.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_identifier {
+        format!("{{model}}.{expr}")
+    } else {
+        format!("({expr})")
+    }
+}
+
+fn variance_operand(expr: &str) -> String {
+    let expr = expr.trim();
+    let is_reference = expr.split('.').all(|part| {
+        part.chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    });
+    if is_reference {
+        expr.to_string()
+    } else {
+        format!("({expr})")
+    }
+}
+
 fn default_true() -> bool {
     true
 }
@@ -901,6 +1468,10 @@ pub struct Model {
     pub table: Option<String>,
     /// SQL expression for derived tables
     pub sql: Option<String>,
+    /// Explicit column set exposed by the model source.
+    /// When set, generated CTEs project these columns instead of `SELECT *`.
+    #[serde(default)]
+    pub columns: Option<Vec<String>>,
     /// Remote source URI
     #[serde(default)]
     pub source_uri: Option<String>,
@@ -915,6 +1486,9 @@ pub struct Model {
     /// Unique key constraints
     #[serde(default)]
     pub unique_keys: Option<Vec<Vec<String>>>,
+    /// Columns that together identify one row; empty means the primary key.
+    #[serde(default)]
+    pub grain: Vec<String>,
     /// Dimensions (grouping attributes)
     #[serde(default)]
     pub dimensions: Vec<Dimension>,
@@ -927,6 +1501,10 @@ pub struct Model {
     /// Segments (reusable filters)
     #[serde(default)]
     pub segments: Vec<Segment>,
+    /// Filters always applied to the model's rows, inside its own source
+    /// subquery, so they don't turn outer joins into inner joins.
+    #[serde(default)]
+    pub default_filters: Vec<String>,
     /// Pre-aggregations for query routing
     #[serde(default)]
     pub pre_aggregations: Vec<PreAggregation>,
@@ -946,6 +1524,9 @@ pub struct Model {
     /// Arbitrary extension metadata.
     #[serde(default)]
     pub meta: Option<serde_json::Value>,
+    /// Free-form tags for organizing the catalog; apply to every field of the model
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 impl Model {
@@ -955,15 +1536,18 @@ impl Model {
             name: name.into(),
             table: None,
             sql: None,
+            columns: None,
             source_uri: None,
             extends: None,
             primary_key: primary_key.clone(),
             primary_key_columns: vec![primary_key],
             unique_keys: None,
+            grain: Vec::new(),
             dimensions: Vec::new(),
             metrics: Vec::new(),
             relationships: Vec::new(),
             segments: Vec::new(),
+            default_filters: Vec::new(),
             pre_aggregations: Vec::new(),
             default_time_dimension: None,
             default_grain: None,
@@ -971,6 +1555,7 @@ impl Model {
             description: None,
             metadata: None,
             meta: None,
+            tags: Vec::new(),
         }
     }
 
@@ -982,6 +1567,19 @@ impl Model {
         self
     }
 
+    /// Add a filter applied to every query of this model, e.g.
+    /// `"{model}.deleted_at IS NULL"`.
+    pub fn with_default_filter(mut self, filter: impl Into<String>) -> Self {
+        self.default_filters.push(filter.into());
+        self
+    }
+
+    /// Declare the columns that together identify one row of the model.
+    pub fn with_grain(mut self, grain: Vec<String>) -> Self {
+        self.grain = grain;
+        self
+    }
+
     pub fn with_table(mut self, table: impl Into<String>) -> Self {
         self.table = Some(table.into());
         self
@@ -992,6 +1590,11 @@ impl Model {
         self
     }
 
+    pub fn with_columns(mut self, columns: Vec<String>) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+
     pub fn with_dimension(mut self, dimension: Dimension) -> Self {
         self.dimensions.push(dimension);
         self
@@ -1030,6 +1633,15 @@ impl Model {
         }
     }
 
+    /// Returns the declared grain, falling back to the primary key columns.
+    pub fn grain_columns(&self) -> Vec<String> {
+        if self.grain.is_empty() {
+            self.primary_keys()
+        } else {
+            self.grain.clone()
+        }
+    }
+
     /// Returns the table source (table name or SQL subquery)
     pub fn table_source(&self) -> String {
         if let Some(sql) = &self.sql {
@@ -1039,6 +1651,13 @@ impl Model {
         }
     }
 
+    /// Returns the declared column set, if any
+    pub fn declared_columns(&self) -> Option<&[String]> {
+        self.columns
+            .as_deref()
+            .filter(|columns| !columns.is_empty())
+    }
+
     /// Find a dimension by name
     pub fn get_dimension(&self, name: &str) -> Option<&Dimension> {
         self.dimensions.iter().find(|d| d.name == name)
@@ -1078,6 +1697,86 @@ mod tests {
         assert_eq!(dim.sql_expr(), "status");
     }
 
+    #[test]
+    fn test_target_metric_constructors() {
+        let attainment = Metric::attainment("attainment", "revenue", "target_revenue");
+        assert_eq!(attainment.r#type, MetricType::Ratio);
+        assert_eq!(attainment.numerator.as_deref(), Some("revenue"));
+        assert_eq!(attainment.denominator.as_deref(), Some("target_revenue"));
+        assert_eq!(attainment.value_format_name.as_deref(), Some("percent"));
+        assert_eq!(
+            attainment.to_sql(None),
+            "(revenue) / NULLIF(target_revenue, 0)"
+        );
+
+        let variance = Metric::variance_to_target("revenue_variance", "revenue", "target_revenue");
+        assert_eq!(variance.r#type, MetricType::Derived);
+        assert_eq!(variance.sql_expr(), "revenue - target_revenue");
+
+        let variance = Metric::variance_to_target("v", "orders.revenue", "target_a + target_b");
+        assert_eq!(
+            variance.sql_expr(),
+            "orders.revenue - (target_a + target_b)"
+        );
+    }
+
+    #[test]
+    fn test_weighted_avg_constructor() {
+        let metric = Metric::weighted_avg("avg_price", "unit_price", "quantity");
+        assert_eq!(metric.r#type, MetricType::Derived);
+        assert_eq!(
+            metric.sql_expr(),
+            "SUM({model}.unit_price * {model}.quantity) / NULLIF(SUM({model}.quantity), 0)"
+        );
+
+        let metric = Metric::weighted_avg("avg_net", "{model}.price - {model}.discount", "qty");
+        assert_eq!(
+            metric.sql_expr(),
+            "SUM(({model}.price - {model}.discount) * {model}.qty) / NULLIF(SUM({model}.qty), 0)"
+        );
+    }
+
+    #[test]
+    fn test_metric_additivity() {
+        assert!(Metric::sum("revenue", "amount").is_additive());
+        assert!(Metric::count("order_count").is_additive());
+        assert!(!Metric::avg("avg_amount", "amount").is_additive());
+        assert!(!Metric::count_distinct("customers", "customer_id").is_additive());
+        assert!(!Metric::ratio("aov", "revenue", "order_count").is_additive());
+        assert!(!Metric::weighted_avg("avg_price", "unit_price", "quantity").is_additive());
+
+        let mut balance = Metric::sum("balance", "amount");
+        balance.non_additive_dimension = Some("snapshot_date".into());
+        assert!(!balance.is_additive());
+    }
+
+    #[test]
+    fn test_model_grain_defaults_to_primary_key() {
+        let model = Model::new("orders", "order_id");
+        assert_eq!(model.grain_columns(), vec!["order_id".to_string()]);
+
+        let model = model.with_grain(vec!["tenant_id".into(), "order_id".into()]);
+        assert_eq!(
+            model.grain_columns(),
+            vec!["tenant_id".to_string(), "order_id".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_metric_requires_time_dimension() {
+        assert!(Metric::cumulative("running_revenue", "revenue").requires_time_dimension());
+        assert!(!Metric::sum("revenue", "amount").requires_time_dimension());
+        assert!(!Metric::ratio("aov", "revenue", "order_count").requires_time_dimension());
+
+        let mut by_sequence = Metric::cumulative("running_revenue", "revenue");
+        by_sequence.window_order = Some("sequence_number".into());
+        assert!(!by_sequence.requires_time_dimension());
+
+        let mut mom = Metric::ratio("revenue_mom", "revenue", "revenue");
+        mom.offset_window = Some("1 month".into());
+        assert!(mom.requires_time_dimension());
+    }
+
     #[test]
     fn test_metric_to_sql() {
         let metric = Metric::sum("revenue", "amount");
@@ -1117,6 +1816,77 @@ mod tests {
             ..Metric::new("revenue_variance_pop")
         };
         assert_eq!(metric.to_sql(None), "VAR_POP(amount)");
+
+        let metric = Metric::percentile("p95_latency", "latency_ms", 0.95);
+        assert_eq!(
+            metric.to_sql(Some("r")),
+            "PERCENTILE_CONT(0.95) WITHIN GROUP (ORDER BY r.latency_ms)"
+        );
+
+        let metric = Metric {
+            agg: Some(Aggregation::ApproxPercentile(0.95)),
+            ..Metric::percentile("p95_latency", "latency_ms", 0.95)
+        };
+        assert_eq!(metric.to_sql(None), "APPROX_QUANTILE(latency_ms, 0.95)");
+    }
+
+    #[test]
+    fn test_aggregation_eq_and_hash_compare_fractions() {
+        let aggregations: std::collections::HashSet<Aggregation> = [
+            Aggregation::Percentile(0.95),
+            Aggregation::Percentile(0.95),
+            Aggregation::ApproxPercentile(0.95),
+            Aggregation::Percentile(0.5),
+            Aggregation::Sum,
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(aggregations.len(), 4);
+        assert_ne!(Aggregation::Sum, Aggregation::Count);
+    }
+
+    #[test]
+    fn test_filtered_metric_uses_aggregate_filter_clause() {
+        let metric =
+            Metric::sum("completed_revenue", "amount").with_filter("{model}.status = 'completed'");
+        assert_eq!(
+            metric.to_sql(Some("o")),
+            "SUM(o.amount) FILTER (WHERE o.status = 'completed')"
+        );
+        assert_eq!(
+            metric.to_sql(None),
+            "SUM(amount) FILTER (WHERE status = 'completed')"
+        );
+    }
+
+    #[test]
+    fn test_filtered_metric_falls_back_to_case_when() {
+        let metric =
+            Metric::sum("completed_revenue", "amount").with_filter("{model}.status = 'completed'");
+        assert_eq!(
+            metric.to_sql_for_dialect(Some("o"), DialectType::BigQuery),
+            "SUM(CASE WHEN o.status = 'completed' THEN o.amount END)"
+        );
+
+        let metric = Metric::count("completed_orders")
+            .with_filter("{model}.status = 'completed'")
+            .with_filter("{model}.amount > 0");
+        assert_eq!(
+            metric.to_sql_for_dialect(Some("o"), DialectType::Snowflake),
+            "COUNT(CASE WHEN (o.status = 'completed') AND (o.amount > 0) THEN 1 END)"
+        );
+    }
+
+    #[test]
+    fn test_relationship_with_join_type() {
+        let rel = Relationship::many_to_one("customers");
+        assert_eq!(rel.join_type, None);
+
+        let rel = rel.with_join_type(JoinType::Inner);
+        assert_eq!(rel.join_type, Some(JoinType::Inner));
+        assert_eq!(JoinType::Inner.reversed(), JoinType::Inner);
+        assert_eq!(JoinType::Left.reversed(), JoinType::Right);
+        assert_eq!(JoinType::Full.as_sql(), "FULL OUTER JOIN");
     }
 
     #[test]
@@ -3,20 +3,154 @@
 //! Parses expressions like "last 7 days", "this month", "yesterday" into SQL.
 
 use lazy_static::lazy_static;
+use polyglot_sql::DialectType;
 use regex::Regex;
 
+use super::model::{date_add_sql, date_trunc_sql};
+
 /// Relative date expression parser
 pub struct RelativeDate;
 
 lazy_static! {
-    static ref LAST_N_DAYS: Regex = Regex::new(r"^last (\d+) days?$").unwrap();
-    static ref LAST_N_WEEKS: Regex = Regex::new(r"^last (\d+) weeks?$").unwrap();
-    static ref LAST_N_MONTHS: Regex = Regex::new(r"^last (\d+) months?$").unwrap();
-    static ref LAST_N_YEARS: Regex = Regex::new(r"^last (\d+) years?$").unwrap();
+    static ref LAST_N: Regex =
+        Regex::new(r"^last\s+(\d+)\s+(day|week|month|quarter|year)s?$").unwrap();
+    static ref PERIOD: Regex =
+        Regex::new(r"^(this|last|next)\s+(week|month|quarter|year)$").unwrap();
+    static ref RANGE_TO_DATE: Regex =
+        Regex::new(r"^(week|month|quarter|year)\s+to\s+date$").unwrap();
+    static ref RANGE_SPAN: Regex = Regex::new(r"^(\S+)\s+to\s+(\S+)$").unwrap();
+    static ref ISO_YEAR: Regex = Regex::new(r"^(\d{4})$").unwrap();
+    static ref ISO_QUARTER: Regex = Regex::new(r"^(\d{4})-q([1-4])$").unwrap();
+    static ref ISO_MONTH: Regex = Regex::new(r"^(\d{4})-(\d{2})$").unwrap();
+    static ref ISO_DAY: Regex = Regex::new(r"^(\d{4})-(\d{2})-(\d{2})$").unwrap();
+}
+
+/// A date relative to today: the start of the current `period` (or today
+/// itself) moved by `offset` `unit`s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RelativePoint {
+    period: Option<DateUnit>,
+    offset: i64,
+    unit: DateUnit,
+}
+
+impl RelativePoint {
+    fn today(offset: i64) -> Self {
+        Self {
+            period: None,
+            offset,
+            unit: DateUnit::Day,
+        }
+    }
+
+    fn period_start(unit: DateUnit, offset: i64) -> Self {
+        Self {
+            period: Some(unit),
+            offset,
+            unit,
+        }
+    }
+
+    /// Parse the anchor point of a relative date expression (lowercased and
+    /// trimmed), along with the number of periods a range from it spans.
+    fn parse(expr: &str) -> Option<(Self, i64)> {
+        match expr {
+            "today" => return Some((Self::today(0), 1)),
+            "yesterday" => return Some((Self::today(-1), 1)),
+            "tomorrow" => return Some((Self::today(1), 1)),
+            _ => {}
+        }
+        if let Some(caps) = PERIOD.captures(expr) {
+            let offset = match &caps[1] {
+                "last" => -1,
+                "next" => 1,
+                _ => 0,
+            };
+            return Some((Self::period_start(DateUnit::parse(&caps[2])?, offset), 1));
+        }
+        if let Some(caps) = LAST_N.captures(expr) {
+            let count: i64 = caps[1].parse().ok()?;
+            return Some((Self::last(count, DateUnit::parse(&caps[2])?), count));
+        }
+        None
+    }
+
+    /// Start of `last <count> <unit>s`. Trailing days and weeks count back
+    /// from today; longer units cover whole calendar periods, counting back
+    /// from the start of the current one.
+    fn last(count: i64, unit: DateUnit) -> Self {
+        match unit {
+            DateUnit::Day => Self::today(-count),
+            DateUnit::Week => Self::today(-count * 7),
+            unit => Self::period_start(unit, -count),
+        }
+    }
+
+    /// The same point moved by `periods` further units.
+    fn shifted(self, periods: i64) -> Self {
+        Self {
+            offset: self.offset + periods,
+            ..self
+        }
+    }
+
+    fn to_sql(self, dialect: DialectType) -> String {
+        self.render(dialect, true)
+    }
+
+    /// Render the point, with day offsets as integer arithmetic where the
+    /// dialect allows it when `integer_days` is set, otherwise as intervals.
+    fn render(self, dialect: DialectType, integer_days: bool) -> String {
+        let base = match self.period {
+            Some(unit) => date_trunc_sql(unit.as_str(), "CURRENT_DATE", dialect),
+            None => "CURRENT_DATE".to_string(),
+        };
+        match (self.offset, self.period, self.unit) {
+            (0, _, _) => base,
+            // Dates take whole-day integer arithmetic on DuckDB and Postgres.
+            (offset, None, DateUnit::Day) if integer_days && integer_day_arithmetic(dialect) => {
+                let sign = if offset < 0 { "-" } else { "+" };
+                format!("{base} {sign} {}", offset.unsigned_abs())
+            }
+            (offset, _, unit) => date_add_sql(&base, offset, unit.as_str(), dialect),
+        }
+    }
+
+    /// Filter on `column` from this point: half-open over `periods` whole
+    /// calendar periods, or open-ended from a day.
+    fn range_sql(
+        self,
+        periods: i64,
+        column: &str,
+        dialect: DialectType,
+        integer_days: bool,
+    ) -> String {
+        let start = self.render(dialect, integer_days);
+        match self.period {
+            Some(_) => format!(
+                "{column} >= {start} AND {column} < {}",
+                self.shifted(periods).render(dialect, integer_days)
+            ),
+            None => format!("{column} >= {start}"),
+        }
+    }
+}
+
+fn integer_day_arithmetic(dialect: DialectType) -> bool {
+    matches!(
+        dialect,
+        DialectType::Generic
+            | DialectType::DuckDB
+            | DialectType::PostgreSQL
+            | DialectType::Redshift
+            | DialectType::CockroachDB
+            | DialectType::Materialize
+            | DialectType::RisingWave
+    )
 }
 
 impl RelativeDate {
-    /// Parse a relative date expression to SQL.
+    /// Parse a relative date expression to DuckDB SQL.
     ///
     /// Returns None if the expression is not recognized.
     ///
@@ -27,148 +161,300 @@ impl RelativeDate {
     /// assert_eq!(RelativeDate::parse("last 7 days"), Some("CURRENT_DATE - 7".to_string()));
     /// ```
     pub fn parse(expr: &str) -> Option<String> {
-        let expr = expr.to_lowercase();
-        let expr = expr.trim();
+        Self::parse_for_dialect(expr, DialectType::DuckDB)
+    }
 
-        // Simple keywords
-        match expr {
-            "today" => return Some("CURRENT_DATE".to_string()),
-            "yesterday" => return Some("CURRENT_DATE - 1".to_string()),
-            "tomorrow" => return Some("CURRENT_DATE + 1".to_string()),
-            "this week" => return Some("DATE_TRUNC('week', CURRENT_DATE)".to_string()),
-            "last week" => {
-                return Some("DATE_TRUNC('week', CURRENT_DATE) - INTERVAL '1 week'".to_string())
-            }
-            "next week" => {
-                return Some("DATE_TRUNC('week', CURRENT_DATE) + INTERVAL '1 week'".to_string())
-            }
-            "this month" => return Some("DATE_TRUNC('month', CURRENT_DATE)".to_string()),
-            "last month" => {
-                return Some("DATE_TRUNC('month', CURRENT_DATE) - INTERVAL '1 month'".to_string())
-            }
-            "next month" => {
-                return Some("DATE_TRUNC('month', CURRENT_DATE) + INTERVAL '1 month'".to_string())
-            }
-            "this quarter" => return Some("DATE_TRUNC('quarter', CURRENT_DATE)".to_string()),
-            "last quarter" => {
-                return Some(
-                    "DATE_TRUNC('quarter', CURRENT_DATE) - INTERVAL '3 months'".to_string(),
-                )
-            }
-            "next quarter" => {
-                return Some(
-                    "DATE_TRUNC('quarter', CURRENT_DATE) + INTERVAL '3 months'".to_string(),
-                )
-            }
-            "this year" => return Some("DATE_TRUNC('year', CURRENT_DATE)".to_string()),
-            "last year" => {
-                return Some("DATE_TRUNC('year', CURRENT_DATE) - INTERVAL '1 year'".to_string())
-            }
-            "next year" => {
-                return Some("DATE_TRUNC('year', CURRENT_DATE) + INTERVAL '1 year'".to_string())
-            }
-            _ => {}
-        }
+    /// Parse a relative date expression to SQL for `dialect`.
+    ///
+    /// # Examples
+    /// ```
+    /// use polyglot_sql::DialectType;
+    /// use sidemantic::RelativeDate;
+    /// assert_eq!(
+    ///     RelativeDate::parse_for_dialect("last month", DialectType::Snowflake),
+    ///     Some("DATEADD(month, -1, DATE_TRUNC(month, CURRENT_DATE))".to_string())
+    /// );
+    /// ```
+    pub fn parse_for_dialect(expr: &str, dialect: DialectType) -> Option<String> {
+        let (point, _) = RelativePoint::parse(expr.trim().to_lowercase().as_str())?;
+        Some(point.to_sql(dialect))
+    }
 
-        // Last N days/weeks/months/years
-        if let Some(caps) = LAST_N_DAYS.captures(expr) {
-            let n: i32 = caps[1].parse().ok()?;
-            return Some(format!("CURRENT_DATE - {n}"));
+    /// Convert a relative date expression to a DuckDB SQL range filter.
+    ///
+    /// # Examples
+    /// ```
+    /// use sidemantic::RelativeDate;
+    /// assert_eq!(
+    ///     RelativeDate::to_range("last 7 days", "created_at"),
+    ///     Some("created_at >= CURRENT_DATE - 7".to_string())
+    /// );
+    /// ```
+    pub fn to_range(expr: &str, column: &str) -> Option<String> {
+        Self::to_range_for_dialect(expr, column, DialectType::DuckDB)
+    }
+
+    /// Convert a relative date expression to a SQL range filter for `dialect`.
+    ///
+    /// Single days compare for equality, trailing days and weeks are
+    /// open-ended, and calendar periods are half-open ranges spanning every
+    /// period named (so `last 3 months` covers three whole months).
+    pub fn to_range_for_dialect(expr: &str, column: &str, dialect: DialectType) -> Option<String> {
+        let expr = expr.trim().to_lowercase();
+        let (point, periods) = RelativePoint::parse(&expr)?;
+        if matches!(expr.as_str(), "today" | "yesterday" | "tomorrow") {
+            return Some(format!("{column} = {}", point.to_sql(dialect)));
         }
+        Some(point.range_sql(periods, column, dialect, true))
+    }
 
-        if let Some(caps) = LAST_N_WEEKS.captures(expr) {
-            let n: i32 = caps[1].parse().ok()?;
-            return Some(format!("CURRENT_DATE - {}", n * 7));
+    /// Check if an expression is a recognized relative date.
+    pub fn is_relative_date(expr: &str) -> bool {
+        RelativePoint::parse(expr.trim().to_lowercase().as_str()).is_some()
+    }
+}
+
+/// Calendar unit used by [`DateRange`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateUnit {
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl DateUnit {
+    fn parse(unit: &str) -> Option<Self> {
+        match unit {
+            "day" => Some(Self::Day),
+            "week" => Some(Self::Week),
+            "month" => Some(Self::Month),
+            "quarter" => Some(Self::Quarter),
+            "year" => Some(Self::Year),
+            _ => None,
         }
+    }
 
-        if let Some(caps) = LAST_N_MONTHS.captures(expr) {
-            let n: i32 = caps[1].parse().ok()?;
-            return Some(format!(
-                "DATE_TRUNC('month', CURRENT_DATE) - INTERVAL '{n} months'"
-            ));
+    /// Granularity name accepted by `DATE_TRUNC`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Day => "day",
+            Self::Week => "week",
+            Self::Month => "month",
+            Self::Quarter => "quarter",
+            Self::Year => "year",
         }
+    }
+}
+
+/// A date range filter value such as `last 7 days`, `this month`,
+/// `year to date`, `2024-Q1` or `2024-01-01 to 2024-03-31`.
+///
+/// Calendar periods and fixed dates render as half-open comparisons
+/// (`>= start AND < end`) so that timestamp columns include the whole of the
+/// final day; trailing ranges are open-ended from their start.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DateRange {
+    /// `last <count> <unit>s`, with the same meaning as in [`RelativeDate`]:
+    /// trailing days and weeks back from today, or the `count` whole calendar
+    /// periods before the current one.
+    Last { count: u32, unit: DateUnit },
+    /// The calendar period containing today.
+    This(DateUnit),
+    /// The calendar period before the current one (`last week`).
+    Previous(DateUnit),
+    /// The calendar period after the current one (`next month`).
+    Next(DateUnit),
+    /// From the start of the current period onwards.
+    ToDate(DateUnit),
+    /// Fixed calendar dates as `YYYY-MM-DD`, `start` inclusive and `end` exclusive.
+    Fixed { start: String, end: String },
+}
 
-        if let Some(caps) = LAST_N_YEARS.captures(expr) {
-            let n: i32 = caps[1].parse().ok()?;
-            return Some(format!(
-                "DATE_TRUNC('year', CURRENT_DATE) - INTERVAL '{n} years'"
-            ));
+impl DateRange {
+    /// Parse a date range expression.
+    ///
+    /// Fixed ranges accept ISO years (`2024`), quarters (`2024-Q1`), months
+    /// (`2024-03`) and days (`2024-03-15`), either alone or as `<from> to <to>`,
+    /// which spans from the start of `from` to the end of `to`.
+    ///
+    /// # Examples
+    /// ```
+    /// use sidemantic::DateRange;
+    /// assert_eq!(
+    ///     DateRange::parse("2024-Q1"),
+    ///     Some(DateRange::Fixed {
+    ///         start: "2024-01-01".into(),
+    ///         end: "2024-04-01".into(),
+    ///     })
+    /// );
+    /// assert!(DateRange::parse("sometime soon").is_none());
+    /// ```
+    pub fn parse(expr: &str) -> Option<Self> {
+        let expr = expr.trim().to_lowercase();
+
+        if let Some(caps) = LAST_N.captures(&expr) {
+            let count: u32 = caps[1].parse().ok()?;
+            return Some(Self::Last {
+                count,
+                unit: DateUnit::parse(&caps[2])?,
+            });
+        }
+        if let Some(caps) = PERIOD.captures(&expr) {
+            let unit = DateUnit::parse(&caps[2])?;
+            return Some(match &caps[1] {
+                "last" => Self::Previous(unit),
+                "next" => Self::Next(unit),
+                _ => Self::This(unit),
+            });
+        }
+        if let Some(caps) = RANGE_TO_DATE.captures(&expr) {
+            return Some(Self::ToDate(DateUnit::parse(&caps[1])?));
+        }
+        if let Some(caps) = RANGE_SPAN.captures(&expr) {
+            let (start, _) = iso_period(&caps[1])?;
+            let (_, end) = iso_period(&caps[2])?;
+            if start >= end {
+                return None;
+            }
+            return Some(Self::Fixed {
+                start: start.to_string(),
+                end: end.to_string(),
+            });
         }
 
-        None
+        let (start, end) = iso_period(&expr)?;
+        Some(Self::Fixed {
+            start: start.to_string(),
+            end: end.to_string(),
+        })
     }
 
-    /// Convert a relative date expression to a SQL range filter.
+    /// Render the range as a filter on `column` for the given dialect.
     ///
     /// # Examples
     /// ```
-    /// use sidemantic::RelativeDate;
+    /// use polyglot_sql::DialectType;
+    /// use sidemantic::DateRange;
+    /// let range = DateRange::parse("last 7 days").unwrap();
     /// assert_eq!(
-    ///     RelativeDate::to_range("last 7 days", "created_at"),
-    ///     Some("created_at >= CURRENT_DATE - 7".to_string())
+    ///     range.to_sql("created_at", DialectType::DuckDB),
+    ///     "created_at >= CURRENT_DATE - INTERVAL '7 days'"
     /// );
     /// ```
-    pub fn to_range(expr: &str, column: &str) -> Option<String> {
-        let expr_lower = expr.to_lowercase();
-        let expr_lower = expr_lower.trim();
-
-        // For single day expressions - exact match
-        match expr_lower {
-            "today" | "yesterday" | "tomorrow" => {
-                let sql = Self::parse(expr)?;
-                return Some(format!("{column} = {sql}"));
+    pub fn to_sql(&self, column: &str, dialect: DialectType) -> String {
+        match self {
+            Self::Last { count, unit } => {
+                let count = i64::from(*count);
+                RelativePoint::last(count, *unit).range_sql(count, column, dialect, false)
             }
-            _ => {}
+            Self::This(unit) => {
+                RelativePoint::period_start(*unit, 0).range_sql(1, column, dialect, false)
+            }
+            Self::Previous(unit) => {
+                RelativePoint::period_start(*unit, -1).range_sql(1, column, dialect, false)
+            }
+            Self::Next(unit) => {
+                RelativePoint::period_start(*unit, 1).range_sql(1, column, dialect, false)
+            }
+            Self::ToDate(unit) => format!(
+                "{column} >= {}",
+                RelativePoint::period_start(*unit, 0).to_sql(dialect)
+            ),
+            Self::Fixed { start, end } => format!(
+                "{column} >= {} AND {column} < {}",
+                date_literal_sql(start, dialect),
+                date_literal_sql(end, dialect)
+            ),
         }
+    }
+}
 
-        // For "last N days/weeks" - use >= comparison
-        if expr_lower.starts_with("last ")
-            && (expr_lower.contains("day") || expr_lower.contains("week"))
-        {
-            let sql = Self::parse(expr)?;
-            return Some(format!("{column} >= {sql}"));
-        }
-
-        // For "this/last/next month/quarter/year" - use range
-        if (expr_lower.contains("month")
-            || expr_lower.contains("quarter")
-            || expr_lower.contains("year"))
-            && (expr_lower.starts_with("this ")
-                || expr_lower.starts_with("last ")
-                || expr_lower.starts_with("next "))
-        {
-            let start_sql = Self::parse(expr)?;
-            let interval = if expr_lower.contains("month") {
-                "1 month"
-            } else if expr_lower.contains("quarter") {
-                "3 months"
-            } else {
-                "1 year"
-            };
-            return Some(format!(
-                "{column} >= {start_sql} AND {column} < {start_sql} + INTERVAL '{interval}'"
-            ));
+/// A `YYYY-MM-DD` date literal for `dialect`.
+fn date_literal_sql(date: &str, dialect: DialectType) -> String {
+    match dialect {
+        DialectType::TSQL | DialectType::ClickHouse => format!("CAST('{date}' AS DATE)"),
+        _ => format!("DATE '{date}'"),
+    }
+}
+
+/// A validated calendar date, ordered chronologically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct CalendarDate {
+    year: u32,
+    month: u32,
+    day: u32,
+}
+
+impl CalendarDate {
+    fn new(year: u32, month: u32, day: u32) -> Option<Self> {
+        ((1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day))
+            .then_some(Self { year, month, day })
+    }
+
+    fn add_months(self, months: u32) -> Self {
+        let index = self.month - 1 + months;
+        Self {
+            year: self.year + index / 12,
+            month: index % 12 + 1,
+            day: 1,
         }
+    }
 
-        // For "this/last/next week" - use range
-        if expr_lower.contains("week")
-            && (expr_lower.starts_with("this ")
-                || expr_lower.starts_with("last ")
-                || expr_lower.starts_with("next "))
-        {
-            let start_sql = Self::parse(expr)?;
-            return Some(format!(
-                "{column} >= {start_sql} AND {column} < {start_sql} + INTERVAL '1 week'"
-            ));
+    fn next_day(self) -> Self {
+        if self.day < days_in_month(self.year, self.month) {
+            Self {
+                day: self.day + 1,
+                ..self
+            }
+        } else {
+            self.add_months(1)
         }
+    }
+}
 
-        None
+impl std::fmt::Display for CalendarDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
     }
+}
 
-    /// Check if an expression is a recognized relative date.
-    pub fn is_relative_date(expr: &str) -> bool {
-        Self::parse(expr).is_some()
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Start (inclusive) and end (exclusive) of an ISO year, quarter, month or day.
+fn iso_period(expr: &str) -> Option<(CalendarDate, CalendarDate)> {
+    if let Some(caps) = ISO_YEAR.captures(expr) {
+        let start = CalendarDate::new(caps[1].parse().ok()?, 1, 1)?;
+        return Some((start, start.add_months(12)));
+    }
+    if let Some(caps) = ISO_QUARTER.captures(expr) {
+        let quarter: u32 = caps[2].parse().ok()?;
+        let start = CalendarDate::new(caps[1].parse().ok()?, (quarter - 1) * 3 + 1, 1)?;
+        return Some((start, start.add_months(3)));
     }
+    if let Some(caps) = ISO_MONTH.captures(expr) {
+        let start = CalendarDate::new(caps[1].parse().ok()?, caps[2].parse().ok()?, 1)?;
+        return Some((start, start.add_months(1)));
+    }
+    if let Some(caps) = ISO_DAY.captures(expr) {
+        let start = CalendarDate::new(
+            caps[1].parse().ok()?,
+            caps[2].parse().ok()?,
+            caps[3].parse().ok()?,
+        )?;
+        return Some((start, start.next_day()));
+    }
+    None
 }
 
 #[cfg(test)]
@@ -215,10 +501,177 @@ mod tests {
             .contains("INTERVAL '1 month'"));
     }
 
+    #[test]
+    fn test_relative_date_for_dialect() {
+        assert_eq!(
+            RelativeDate::parse_for_dialect("yesterday", DialectType::Snowflake),
+            Some("DATEADD(day, -1, CURRENT_DATE)".into())
+        );
+        assert_eq!(
+            RelativeDate::to_range_for_dialect("last 3 months", "d", DialectType::DuckDB),
+            Some(
+                "d >= DATE_TRUNC('month', CURRENT_DATE) - INTERVAL '3 months' \
+                 AND d < DATE_TRUNC('month', CURRENT_DATE)"
+                    .into()
+            )
+        );
+        assert_eq!(
+            RelativeDate::to_range_for_dialect("next quarter", "d", DialectType::DuckDB),
+            Some(
+                "d >= DATE_TRUNC('quarter', CURRENT_DATE) + INTERVAL '3 months' \
+                 AND d < DATE_TRUNC('quarter', CURRENT_DATE) + INTERVAL '6 months'"
+                    .into()
+            )
+        );
+    }
+
     #[test]
     fn test_is_relative_date() {
         assert!(RelativeDate::is_relative_date("today"));
         assert!(RelativeDate::is_relative_date("last 7 days"));
         assert!(!RelativeDate::is_relative_date("invalid"));
     }
+
+    #[test]
+    fn test_date_range_parse() {
+        assert_eq!(
+            DateRange::parse("Last 7 Days"),
+            Some(DateRange::Last {
+                count: 7,
+                unit: DateUnit::Day
+            })
+        );
+        assert_eq!(
+            DateRange::parse("this month"),
+            Some(DateRange::This(DateUnit::Month))
+        );
+        assert_eq!(
+            DateRange::parse("year to date"),
+            Some(DateRange::ToDate(DateUnit::Year))
+        );
+        assert_eq!(
+            DateRange::parse("2024"),
+            Some(DateRange::Fixed {
+                start: "2024-01-01".into(),
+                end: "2025-01-01".into()
+            })
+        );
+        assert_eq!(
+            DateRange::parse("2024-q4"),
+            Some(DateRange::Fixed {
+                start: "2024-10-01".into(),
+                end: "2025-01-01".into()
+            })
+        );
+        assert_eq!(
+            DateRange::parse("2024-02-29"),
+            Some(DateRange::Fixed {
+                start: "2024-02-29".into(),
+                end: "2024-03-01".into()
+            })
+        );
+        assert_eq!(
+            DateRange::parse("2024-01-15 to 2024-03"),
+            Some(DateRange::Fixed {
+                start: "2024-01-15".into(),
+                end: "2024-04-01".into()
+            })
+        );
+        assert!(DateRange::parse("2023-02-29").is_none());
+        assert!(DateRange::parse("2024-13").is_none());
+        assert!(DateRange::parse("2024-03 to 2024-01").is_none());
+        assert_eq!(
+            DateRange::parse("last week"),
+            Some(DateRange::Previous(DateUnit::Week))
+        );
+        assert_eq!(
+            DateRange::parse("Next Quarter"),
+            Some(DateRange::Next(DateUnit::Quarter))
+        );
+    }
+
+    #[test]
+    fn test_date_range_to_sql() {
+        let last_7_days = DateRange::parse("last 7 days").unwrap();
+        assert_eq!(
+            last_7_days.to_sql("created_at", DialectType::DuckDB),
+            "created_at >= CURRENT_DATE - INTERVAL '7 days'"
+        );
+        assert_eq!(
+            DateRange::parse("last 2 quarters")
+                .unwrap()
+                .to_sql("d", DialectType::DuckDB),
+            "d >= DATE_TRUNC('quarter', CURRENT_DATE) - INTERVAL '6 months' \
+             AND d < DATE_TRUNC('quarter', CURRENT_DATE)"
+        );
+        assert_eq!(
+            DateRange::parse("year to date")
+                .unwrap()
+                .to_sql("d", DialectType::DuckDB),
+            "d >= DATE_TRUNC('year', CURRENT_DATE)"
+        );
+        assert_eq!(
+            DateRange::parse("this month")
+                .unwrap()
+                .to_sql("d", DialectType::Snowflake),
+            "d >= DATE_TRUNC(month, CURRENT_DATE) \
+             AND d < DATEADD(month, 1, DATE_TRUNC(month, CURRENT_DATE))"
+        );
+        assert_eq!(
+            last_7_days.to_sql("d", DialectType::BigQuery),
+            "d >= DATE_SUB(CURRENT_DATE, INTERVAL 7 DAY)"
+        );
+        assert_eq!(
+            DateRange::parse("2024")
+                .unwrap()
+                .to_sql("d", DialectType::TSQL),
+            "d >= CAST('2024-01-01' AS DATE) AND d < CAST('2025-01-01' AS DATE)"
+        );
+        assert_eq!(
+            DateRange::parse("2024-Q1")
+                .unwrap()
+                .to_sql("d", DialectType::DuckDB),
+            "d >= DATE '2024-01-01' AND d < DATE '2024-04-01'"
+        );
+    }
+
+    #[test]
+    fn test_last_n_means_the_same_range_in_both_parsers() {
+        for (expr, unit) in [
+            ("last 3 months", DateUnit::Month),
+            ("last 2 years", DateUnit::Year),
+        ] {
+            let date_range = DateRange::parse(expr).unwrap();
+            assert!(matches!(date_range, DateRange::Last { unit: u, .. } if u == unit));
+            assert_eq!(
+                date_range.to_sql("d", DialectType::Snowflake),
+                RelativeDate::to_range_for_dialect(expr, "d", DialectType::Snowflake).unwrap()
+            );
+        }
+        for expr in ["last week", "next month"] {
+            assert_eq!(
+                DateRange::parse(expr)
+                    .unwrap()
+                    .to_sql("d", DialectType::Snowflake),
+                RelativeDate::to_range_for_dialect(expr, "d", DialectType::Snowflake).unwrap()
+            );
+        }
+        // Trailing days: the same bound, spelled as an interval by DateRange.
+        assert_eq!(
+            RelativeDate::to_range("last 7 days", "d").unwrap(),
+            "d >= CURRENT_DATE - 7"
+        );
+        assert_eq!(
+            DateRange::parse("last 7 days")
+                .unwrap()
+                .to_sql("d", DialectType::DuckDB),
+            "d >= CURRENT_DATE - INTERVAL '7 days'"
+        );
+        assert_eq!(
+            DateRange::parse("last 7 days")
+                .unwrap()
+                .to_sql("d", DialectType::Snowflake),
+            RelativeDate::to_range_for_dialect("last 7 days", "d", DialectType::Snowflake).unwrap()
+        );
+    }
 }
@@ -20,15 +20,31 @@ fn default_public() -> bool {
 }
 
 impl Segment {
-    pub fn new(name: impl Into<String>, sql: impl Into<String>) -> Self {
+    pub fn new(name: impl Into<String>) -> Self {
         Self {
             name: name.into(),
-            sql: sql.into(),
+            sql: String::new(),
             description: None,
             public: true,
         }
     }
 
+    pub fn with_sql(mut self, sql: impl Into<String>) -> Self {
+        self.sql = sql.into();
+        self
+    }
+
+    /// AND another condition onto the segment predicate
+    pub fn and(mut self, condition: impl Into<String>) -> Self {
+        let condition = condition.into();
+        self.sql = if self.sql.trim().is_empty() {
+            condition
+        } else {
+            format!("({}) AND ({condition})", self.sql)
+        };
+        self
+    }
+
     pub fn with_description(mut self, description: impl Into<String>) -> Self {
         self.description = Some(description.into());
         self
@@ -50,13 +66,24 @@ mod tests {
 
     #[test]
     fn test_segment_get_sql() {
-        let segment = Segment::new("completed", "{model}.status = 'completed'");
+        let segment = Segment::new("completed").with_sql("{model}.status = 'completed'");
         assert_eq!(segment.get_sql("o"), "o.status = 'completed'");
     }
 
     #[test]
     fn test_segment_cube_placeholder() {
-        let segment = Segment::new("completed", "${CUBE}.status = 'completed'");
+        let segment = Segment::new("completed").with_sql("${CUBE}.status = 'completed'");
         assert_eq!(segment.get_sql("orders"), "orders.status = 'completed'");
     }
+
+    #[test]
+    fn test_segment_and_combines_conditions() {
+        let segment = Segment::new("big_completed")
+            .and("{model}.status = 'completed'")
+            .and("{model}.amount > 100 OR {model}.is_vip");
+        assert_eq!(
+            segment.get_sql("o"),
+            "(o.status = 'completed') AND (o.amount > 100 OR o.is_vip)"
+        );
+    }
 }
@@ -11,6 +11,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use super::model::Aggregation;
+
 /// SQL dialect for symmetric aggregate generation
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -169,7 +171,7 @@ pub fn build_symmetric_aggregate_sql_with_key_expr(
         }
         SymmetricAggType::CountDistinct => {
             // Count distinct on the measure itself - no symmetric aggregate needed
-            format!("COUNT(DISTINCT {measure_col})")
+            Aggregation::CountDistinct.call_sql(&measure_col)
         }
         SymmetricAggType::Min => format!("MIN({measure_col})"),
         SymmetricAggType::Max => format!("MAX({measure_col})"),
@@ -234,6 +236,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_symmetric_count_distinct_ignores_primary_key() {
+        let sql = build_symmetric_aggregate_sql(
+            "customer_id",
+            "order_id",
+            SymmetricAggType::CountDistinct,
+            Some("o"),
+            SqlDialect::DuckDB,
+        );
+        assert_eq!(sql, "COUNT(DISTINCT o.customer_id)");
+    }
+
     #[test]
     fn test_symmetric_count_star_is_unfiltered() {
         let sql = build_symmetric_aggregate_sql(
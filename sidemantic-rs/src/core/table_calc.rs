@@ -6,7 +6,7 @@
 use serde::{Deserialize, Serialize};
 
 /// Type of table calculation
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum TableCalcType {
     /// Custom formula referencing result columns
@@ -34,7 +34,7 @@ pub enum TableCalcType {
 }
 
 /// Table calculation definition
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct TableCalculation {
     /// Unique name for this calculation
     pub name: String,
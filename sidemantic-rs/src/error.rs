@@ -33,15 +33,28 @@ pub enum SidemanticError {
 
     // Join/relationship errors
     #[error(
-        "No join path found between models '{from}' and '{to}'. Check that a relationship is defined."
+        "No join path found between models '{from}' and '{to}'. '{from}' can reach {reachable} but not '{to}' {note}"
     )]
-    NoJoinPath { from: String, to: String },
+    NoJoinPath {
+        from: String,
+        to: String,
+        reachable: String,
+        note: String,
+    },
 
     #[error("Relationship not found: '{from}' -> '{to}'")]
     RelationshipNotFound { from: String, to: String },
 
-    #[error("Ambiguous join path between '{from}' and '{to}': multiple paths exist")]
-    AmbiguousJoinPath { from: String, to: String },
+    #[error(
+        "Ambiguous join path between '{from}' and '{to}': multiple paths exist ({}). Join through an intermediate model explicitly.",
+        .paths.join("; ")
+    )]
+    /// Several equally short join routes connect two models.
+    AmbiguousJoinPath {
+        from: String,
+        to: String,
+        paths: Vec<String>,
+    },
 
     // SQL errors
     #[error("SQL parse error: {0}")]
@@ -64,6 +77,9 @@ pub enum SidemanticError {
     #[error("Ambiguous reference: '{field}' exists in multiple models: {models}. Use model.field syntax.")]
     AmbiguousReference { field: String, models: String },
 
+    #[error("Ambiguous name: '{name}' matches {matches} when ignoring case")]
+    AmbiguousName { name: String, matches: String },
+
     // Configuration errors
     #[error("YAML parse error: {0}")]
     YamlParse(String),
@@ -130,6 +146,43 @@ impl SidemanticError {
         }
     }
 
+    /// Create a NoJoinPath error listing the models reachable from `from`
+    pub fn no_join_path(from: &str, to: &str, reachable: &[&str]) -> Self {
+        SidemanticError::NoJoinPath {
+            from: from.to_string(),
+            to: to.to_string(),
+            reachable: reachable_list(reachable),
+            note: "(no relationship chain). Check that a relationship is defined.".to_string(),
+        }
+    }
+
+    /// Create a NoJoinPath error for a path longer than the configured hop limit
+    pub fn join_path_exceeds_max_hops(
+        from: &str,
+        to: &str,
+        reachable: &[&str],
+        hops: usize,
+        max_hops: usize,
+    ) -> Self {
+        SidemanticError::NoJoinPath {
+            from: from.to_string(),
+            to: to.to_string(),
+            reachable: reachable_list(reachable),
+            note: format!(
+                "(shortest path of {hops} hops exceeds max hops of {max_hops}). Join through an intermediate model explicitly or raise the limit."
+            ),
+        }
+    }
+
+    /// Create an AmbiguousJoinPath error from competing model routes
+    pub fn ambiguous_join_path(from: &str, to: &str, routes: &[Vec<String>]) -> Self {
+        SidemanticError::AmbiguousJoinPath {
+            from: from.to_string(),
+            to: to.to_string(),
+            paths: routes.iter().map(|route| route.join(" -> ")).collect(),
+        }
+    }
+
     /// Create a SegmentNotFound error with available segments
     pub fn segment_not_found(model: &str, segment: &str, available: &[&str]) -> Self {
         SidemanticError::SegmentNotFound {
@@ -156,6 +209,14 @@ impl SidemanticError {
     }
 }
 
+fn reachable_list(reachable: &[&str]) -> String {
+    if reachable.is_empty() {
+        "no other models".to_string()
+    } else {
+        reachable.join(", ")
+    }
+}
+
 impl From<std::io::Error> for SidemanticError {
     fn from(err: std::io::Error) -> Self {
         SidemanticError::Io(err.to_string())
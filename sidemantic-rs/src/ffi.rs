@@ -20,13 +20,16 @@ use std::sync::{
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use once_cell::sync::Lazy;
+use serde::Deserialize;
 
 use crate::config::{
     load_from_directory_with_metadata, load_from_file_with_metadata,
     load_from_sql_string_with_metadata, load_from_string_with_metadata, parse_sql_model,
+    LoadedGraphMetadata,
 };
-use crate::core::SemanticGraph;
-use crate::sql::QueryRewriter;
+use crate::core::{Model, SemanticGraph};
+use crate::error::SidemanticError;
+use crate::sql::{QueryRewriter, SemanticQuery, SqlGenerator};
 
 const DEFAULT_CONTEXT_KEY: &str = "__sidemantic_default_context__";
 const DEFINITIONS_LOCK_TIMEOUT: Duration = Duration::from_secs(10);
@@ -41,7 +44,11 @@ struct FfiState {
 /// Semantic graph state keyed by DuckDB database/session context.
 static FFI_STATES: Lazy<Mutex<HashMap<String, FfiState>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
+/// Semantic graph state of `sidemantic_session_*` sessions, kept apart from
+/// the context-keyed states so no context string can reach a session.
+static FFI_SESSIONS: Lazy<Mutex<HashMap<u64, FfiState>>> = Lazy::new(|| Mutex::new(HashMap::new()));
 static DEFINITIONS_LOCK_TOKEN_COUNTER: AtomicU64 = AtomicU64::new(1);
+static SESSION_COUNTER: AtomicU64 = AtomicU64::new(1);
 
 /// Result from rewrite operation
 #[repr(C)]
@@ -54,6 +61,33 @@ pub struct SidemanticRewriteResult {
     pub was_rewritten: bool,
 }
 
+/// JSON shape accepted by `sidemantic_compile_query`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CompileQueryPayload {
+    metrics: Vec<String>,
+    dimensions: Vec<String>,
+    filters: Vec<String>,
+    segments: Vec<String>,
+    order_by: Vec<String>,
+    limit: Option<usize>,
+}
+
+impl CompileQueryPayload {
+    fn into_query(self) -> SemanticQuery {
+        let mut query = SemanticQuery::new()
+            .with_metrics(self.metrics)
+            .with_dimensions(self.dimensions)
+            .with_filters(self.filters)
+            .with_segments(self.segments)
+            .with_order_by(self.order_by);
+        if let Some(limit) = self.limit {
+            query = query.with_limit(limit);
+        }
+        query
+    }
+}
+
 fn c_string_arg(ptr: *const c_char, name: &str) -> std::result::Result<String, *mut c_char> {
     if ptr.is_null() {
         return Err(to_c_string(&format!("Error: null {name} pointer")));
@@ -68,6 +102,10 @@ fn c_string_arg(ptr: *const c_char, name: &str) -> std::result::Result<String, *
 }
 
 fn context_key(context: *const c_char) -> std::result::Result<String, *mut c_char> {
+    parse_context_key(context).map_err(|error| to_c_string(&error))
+}
+
+fn parse_context_key(context: *const c_char) -> std::result::Result<String, String> {
     if context.is_null() {
         return Ok(DEFAULT_CONTEXT_KEY.to_string());
     }
@@ -75,7 +113,7 @@ fn context_key(context: *const c_char) -> std::result::Result<String, *mut c_cha
     let raw = unsafe {
         CStr::from_ptr(context)
             .to_str()
-            .map_err(|e| to_c_string(&format!("Error: invalid UTF-8: {e}")))?
+            .map_err(|e| format!("Error: invalid UTF-8: {e}"))?
     };
     let trimmed = raw.trim();
     if trimmed.is_empty() {
@@ -93,6 +131,103 @@ fn active_model_for_loaded_models(model_order: &[String]) -> Option<String> {
     }
 }
 
+/// Run `f` on a registered session's state. The lookup and `f` share one
+/// lock, so a concurrent `sidemantic_session_free` cannot slip in between.
+fn with_session<T>(
+    session: u64,
+    f: impl FnOnce(&mut FfiState) -> T,
+) -> std::result::Result<T, String> {
+    let mut sessions = FFI_SESSIONS.lock().unwrap();
+    let state = sessions
+        .get_mut(&session)
+        .ok_or_else(|| format!("Error: unknown session {session}"))?;
+    Ok(f(state))
+}
+
+/// Create an isolated semantic session
+///
+/// Returns an opaque, never-reused session id for the `sidemantic_session_*`
+/// functions. Release it with `sidemantic_session_free`.
+#[no_mangle]
+pub extern "C" fn sidemantic_session_new() -> u64 {
+    let session = SESSION_COUNTER.fetch_add(1, Ordering::Relaxed);
+    FFI_SESSIONS
+        .lock()
+        .unwrap()
+        .insert(session, FfiState::default());
+    session
+}
+
+/// Drop a session and its models. Unknown ids are ignored.
+#[no_mangle]
+pub extern "C" fn sidemantic_session_free(session: u64) {
+    FFI_SESSIONS.lock().unwrap().remove(&session);
+}
+
+/// Load semantic models from a YAML string into a session.
+#[no_mangle]
+pub extern "C" fn sidemantic_session_load_yaml(session: u64, yaml: *const c_char) -> *mut c_char {
+    let yaml_str = match c_string_arg(yaml, "yaml") {
+        Ok(value) => value,
+        Err(error) => return error,
+    };
+    match load_from_string_with_metadata(&yaml_str) {
+        Ok(metadata) => {
+            match with_session(session, |state| merge_loaded_models(state, &metadata)) {
+                Ok(result) => result,
+                Err(error) => to_c_string(&error),
+            }
+        }
+        Err(e) => to_c_string(&format!("Error: {e}")),
+    }
+}
+
+/// Define a semantic model in a session. See `sidemantic_define`.
+#[no_mangle]
+pub extern "C" fn sidemantic_session_define(
+    session: u64,
+    definition_sql: *const c_char,
+    db_path: *const c_char,
+    replace: bool,
+) -> *mut c_char {
+    let sql_str = match c_string_arg(definition_sql, "definition_sql") {
+        Ok(value) => value,
+        Err(error) => return error,
+    };
+    let model = match parse_sql_model(&sql_str) {
+        Ok(m) => m,
+        Err(e) => return to_c_string(&format!("Error parsing definition: {e}")),
+    };
+    let definitions_path = get_definitions_path(db_path);
+    match with_session(session, |state| {
+        define_in_state(state, model, &sql_str, definitions_path.as_deref(), replace)
+    }) {
+        Ok(result) => result,
+        Err(error) => to_c_string(&error),
+    }
+}
+
+/// Rewrite a SQL query using a session's definitions.
+#[no_mangle]
+pub extern "C" fn sidemantic_session_rewrite(
+    session: u64,
+    sql: *const c_char,
+) -> SidemanticRewriteResult {
+    let sql_str = match c_string_arg(sql, "sql") {
+        Ok(value) => value,
+        Err(error) => {
+            return SidemanticRewriteResult {
+                sql: ptr::null_mut(),
+                error,
+                was_rewritten: false,
+            }
+        }
+    };
+    let result = with_session(session, |state| rewrite_with_state(Some(state), &sql_str))
+        .and_then(|result| result);
+    rewrite_result(result)
+}
+
 /// Load semantic models from YAML string
 ///
 /// Returns null on success, error message on failure.
@@ -119,22 +254,26 @@ pub extern "C" fn sidemantic_load_yaml_for_context(
 
     match load_from_string_with_metadata(&yaml_str) {
         Ok(metadata) => {
-            let active_model = active_model_for_loaded_models(&metadata.model_order);
             let mut states = FFI_STATES.lock().unwrap();
-            let state = states.entry(key).or_default();
-            // Merge new models into existing graph, replacing same-name definitions.
-            for model in metadata.graph.models() {
-                if let Err(e) = state.graph.replace_model(model.clone()) {
-                    return to_c_string(&format!("Error adding model: {e}"));
-                }
-            }
-            state.active_model = active_model;
-            ptr::null_mut() // Success
+            merge_loaded_models(states.entry(key).or_default(), &metadata)
         }
         Err(e) => to_c_string(&format!("Error: {e}")),
     }
 }
 
+/// Merge loaded models into `state`, replacing same-name definitions.
+///
+/// Returns null on success, error message on failure.
+fn merge_loaded_models(state: &mut FfiState, metadata: &LoadedGraphMetadata) -> *mut c_char {
+    for model in metadata.graph.models() {
+        if let Err(e) = state.graph.replace_model(model.clone()) {
+            return to_c_string(&format!("Error adding model: {e}"));
+        }
+    }
+    state.active_model = active_model_for_loaded_models(&metadata.model_order);
+    ptr::null_mut() // Success
+}
+
 /// Load semantic models from a file or directory path
 ///
 /// Returns null on success, error message on failure.
@@ -174,17 +313,8 @@ pub extern "C" fn sidemantic_load_file_for_context(
 
     match result {
         Ok(metadata) => {
-            let active_model = active_model_for_loaded_models(&metadata.model_order);
             let mut states = FFI_STATES.lock().unwrap();
-            let state = states.entry(key).or_default();
-            // Merge new models into existing graph, replacing same-name definitions.
-            for model in metadata.graph.models() {
-                if let Err(e) = state.graph.replace_model(model.clone()) {
-                    return to_c_string(&format!("Error adding model: {e}"));
-                }
-            }
-            state.active_model = active_model;
-            ptr::null_mut() // Success
+            merge_loaded_models(states.entry(key).or_default(), &metadata)
         }
         Err(e) => to_c_string(&format!("Error: {e}")),
     }
@@ -245,13 +375,32 @@ pub extern "C" fn sidemantic_define_for_context(
         Err(e) => return to_c_string(&format!("Error parsing definition: {e}")),
     };
 
-    let model_name = model.name.clone();
-
     let definitions_path = get_definitions_path(db_path);
 
-    // Stage all in-memory work first so duplicate/invalid definitions never touch disk.
     let mut states = FFI_STATES.lock().unwrap();
-    let state = states.entry(key).or_default();
+    define_in_state(
+        states.entry(key).or_default(),
+        model,
+        &sql_str,
+        definitions_path.as_deref(),
+        replace,
+    )
+}
+
+/// Add (or replace) `model` in `state` and append its definition to the
+/// definitions file, if any.
+///
+/// Returns null on success, error message on failure.
+fn define_in_state(
+    state: &mut FfiState,
+    model: Model,
+    sql_str: &str,
+    definitions_path: Option<&Path>,
+    replace: bool,
+) -> *mut c_char {
+    let model_name = model.name.clone();
+
+    // Stage all in-memory work first so duplicate/invalid definitions never touch disk.
     let mut candidate_state = state.clone();
     let result = if replace {
         candidate_state.graph.replace_model(model)
@@ -263,7 +412,7 @@ pub extern "C" fn sidemantic_define_for_context(
     }
     candidate_state.active_model = Some(model_name.clone());
 
-    if let Some(definitions_path) = definitions_path.as_ref() {
+    if let Some(definitions_path) = definitions_path {
         let _definitions_lock = match lock_definitions_file(definitions_path) {
             Ok(lock) => lock,
             Err(e) => return to_c_string(&format!("Error locking definitions file: {e}")),
@@ -277,7 +426,7 @@ pub extern "C" fn sidemantic_define_for_context(
         } else {
             content
         };
-        let candidate_content = append_definition_to_content(&content, &sql_str);
+        let candidate_content = append_definition_to_content(&content, sql_str);
         if let Err(e) = validate_definitions_content(&candidate_content) {
             return to_c_string(&format!("Error validating definitions file: {e}"));
         }
@@ -291,6 +440,138 @@ pub extern "C" fn sidemantic_define_for_context(
     ptr::null_mut() // Success
 }
 
+/// Dry-run a semantic model definition
+///
+/// Parses the definition and validates it against a copy of the current
+/// graph, following the same add-vs-replace rule as `sidemantic_define`.
+/// Only errors the definition introduces are reported; problems already
+/// present in the loaded graph are ignored. Nothing is stored and no file is
+/// written.
+///
+/// Returns null if valid, error message otherwise.
+/// Caller must free the returned string with `sidemantic_free`.
+#[no_mangle]
+pub extern "C" fn sidemantic_validate(definition_sql: *const c_char, replace: bool) -> *mut c_char {
+    sidemantic_validate_for_context(ptr::null(), definition_sql, replace)
+}
+
+/// Dry-run a semantic model definition against one context's graph.
+#[no_mangle]
+pub extern "C" fn sidemantic_validate_for_context(
+    context: *const c_char,
+    definition_sql: *const c_char,
+    replace: bool,
+) -> *mut c_char {
+    let key = match context_key(context) {
+        Ok(key) => key,
+        Err(error) => return error,
+    };
+    let sql_str = match c_string_arg(definition_sql, "definition_sql") {
+        Ok(value) => value,
+        Err(error) => return error,
+    };
+
+    let model = match parse_sql_model(&sql_str) {
+        Ok(m) => m,
+        Err(e) => return to_c_string(&format!("Error parsing definition: {e}")),
+    };
+
+    let base = FFI_STATES
+        .lock()
+        .unwrap()
+        .get(&key)
+        .map(|state| state.graph.clone())
+        .unwrap_or_default();
+    let mut graph = base.clone();
+    let result = if replace {
+        graph.replace_model(model)
+    } else {
+        graph.add_model(model)
+    };
+    if let Err(e) = result {
+        return to_c_string(&format!("Error validating definition: {e}"));
+    }
+
+    let existing: HashSet<String> = match base.validate() {
+        Ok(()) => HashSet::new(),
+        Err(errors) => errors.iter().map(ToString::to_string).collect(),
+    };
+    let messages: Vec<String> = match graph.validate() {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors
+            .iter()
+            .map(ToString::to_string)
+            .filter(|message| !existing.contains(message))
+            .collect(),
+    };
+    if messages.is_empty() {
+        ptr::null_mut()
+    } else {
+        to_c_string(&format!(
+            "Error validating definition: {}",
+            messages.join("; ")
+        ))
+    }
+}
+
+/// Drop a single semantic model
+///
+/// Removes the model from the current session and, when `db_path` is given,
+/// from its definitions file. Errors if the model is not loaded or other
+/// models or metrics still reference it.
+///
+/// Returns null on success, error message on failure.
+/// Caller must free the returned string with `sidemantic_free`.
+#[no_mangle]
+pub extern "C" fn sidemantic_drop_model(
+    model_name: *const c_char,
+    db_path: *const c_char,
+) -> *mut c_char {
+    sidemantic_drop_model_for_context(ptr::null(), model_name, db_path)
+}
+
+/// Drop a single semantic model from a context-keyed graph.
+#[no_mangle]
+pub extern "C" fn sidemantic_drop_model_for_context(
+    context: *const c_char,
+    model_name: *const c_char,
+    db_path: *const c_char,
+) -> *mut c_char {
+    let key = match context_key(context) {
+        Ok(key) => key,
+        Err(error) => return error,
+    };
+    let name = match c_string_arg(model_name, "model_name") {
+        Ok(value) => value,
+        Err(error) => return error,
+    };
+
+    let mut states = FFI_STATES.lock().unwrap();
+    let Some(state) = states.get_mut(&key) else {
+        let error = SidemanticError::model_not_found(&name, &[]);
+        return to_c_string(&format!("Error dropping model: {error}"));
+    };
+    let mut candidate_state = state.clone();
+    if let Err(e) = candidate_state.graph.remove_model(&name) {
+        return to_c_string(&format!("Error dropping model: {e}"));
+    }
+    if candidate_state.active_model.as_deref() == Some(name.as_str()) {
+        candidate_state.active_model = None;
+    }
+
+    if let Some(definitions_path) = get_definitions_path(db_path) {
+        if definitions_path.exists() {
+            if let Err(e) = remove_model_from_file(&definitions_path, &name) {
+                return to_c_string(&format!("Error writing to definitions file: {e}"));
+            }
+        }
+    }
+
+    *state = candidate_state;
+
+    ptr::null_mut() // Success
+}
+
 /// Get the definitions file path based on database path
 fn get_definitions_path(db_path: *const c_char) -> Option<PathBuf> {
     if db_path.is_null() {
@@ -316,7 +597,6 @@ fn get_definitions_path(db_path: *const c_char) -> Option<PathBuf> {
 }
 
 /// Remove a model definition from the file by name
-#[cfg(test)]
 fn remove_model_from_file(path: &Path, model_name: &str) -> std::io::Result<()> {
     let _definitions_lock = lock_definitions_file(path)?;
     let content = read_definitions_file(path)?;
@@ -1360,6 +1640,94 @@ pub extern "C" fn sidemantic_list_models_for_context(context: *const c_char) ->
     to_c_string(&names.join(","))
 }
 
+/// Describe registered models as JSON
+///
+/// Returns `{"models": [...]}` with each model's name, table, primary key,
+/// dimensions, and metrics, in definition order.
+/// Caller must free the returned string with `sidemantic_free`.
+#[no_mangle]
+pub extern "C" fn sidemantic_describe() -> *mut c_char {
+    sidemantic_describe_for_context(ptr::null())
+}
+
+/// Describe registered models for one context as JSON.
+#[no_mangle]
+pub extern "C" fn sidemantic_describe_for_context(context: *const c_char) -> *mut c_char {
+    let key = match context_key(context) {
+        Ok(key) => key,
+        Err(error) => return error,
+    };
+    let states = FFI_STATES.lock().unwrap();
+    let models: Vec<serde_json::Value> = states
+        .get(&key)
+        .map(|state| state.graph.models().map(describe_model).collect())
+        .unwrap_or_default();
+    to_c_string(&serde_json::json!({ "models": models }).to_string())
+}
+
+/// Serialize the full semantic graph as JSON
+///
+/// Unlike `sidemantic_describe`, includes every model field (relationships,
+/// segments, metric definitions) plus graph-level metrics, table
+/// calculations, and parameters.
+/// Caller must free the returned string with `sidemantic_free`.
+#[no_mangle]
+pub extern "C" fn sidemantic_describe_json() -> *mut c_char {
+    sidemantic_describe_json_for_context(ptr::null())
+}
+
+/// Serialize one context's semantic graph as JSON.
+#[no_mangle]
+pub extern "C" fn sidemantic_describe_json_for_context(context: *const c_char) -> *mut c_char {
+    let key = match context_key(context) {
+        Ok(key) => key,
+        Err(error) => return error,
+    };
+    let states = FFI_STATES.lock().unwrap();
+    let json = states
+        .get(&key)
+        .map(|state| state.graph.to_json())
+        .unwrap_or_else(|| SemanticGraph::new().to_json());
+    to_c_string(&json)
+}
+
+fn describe_model(model: &Model) -> serde_json::Value {
+    let dimensions: Vec<serde_json::Value> = model
+        .dimensions
+        .iter()
+        .map(|dimension| {
+            serde_json::json!({
+                "name": dimension.name,
+                "type": dimension.r#type,
+                "label": dimension.label,
+                "description": dimension.description,
+            })
+        })
+        .collect();
+    let metrics: Vec<serde_json::Value> = model
+        .metrics
+        .iter()
+        .map(|metric| {
+            serde_json::json!({
+                "name": metric.name,
+                "type": metric.r#type,
+                "agg": metric.agg,
+                "label": metric.label,
+                "description": metric.description,
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "name": model.name,
+        "table": model.table_name(),
+        "primary_key": model.primary_keys(),
+        "description": model.description,
+        "dimensions": dimensions,
+        "metrics": metrics,
+    })
+}
+
 /// Rewrite a SQL query using semantic definitions
 ///
 /// Returns a SidemanticRewriteResult struct. Caller must free with `sidemantic_free_result`.
@@ -1397,37 +1765,161 @@ pub extern "C" fn sidemantic_rewrite_for_context(
     };
 
     let states = FFI_STATES.lock().unwrap();
-    let Some(state) = states.get(&key) else {
-        return SidemanticRewriteResult {
-            sql: to_c_string(&sql_str),
+    rewrite_result(rewrite_with_state(states.get(&key), &sql_str))
+}
+
+fn rewrite_result(result: std::result::Result<(String, bool), String>) -> SidemanticRewriteResult {
+    match result {
+        Ok((sql, was_rewritten)) => SidemanticRewriteResult {
+            sql: to_c_string(&sql),
             error: ptr::null_mut(),
+            was_rewritten,
+        },
+        Err(error) => SidemanticRewriteResult {
+            sql: ptr::null_mut(),
+            error: to_c_string(&error),
             was_rewritten: false,
-        };
+        },
+    }
+}
+
+/// Rewrite one query, passing it through unchanged when no models are loaded
+/// or it references none. Returns the SQL and whether it was rewritten.
+fn rewrite_with_state(
+    state: Option<&FfiState>,
+    sql: &str,
+) -> std::result::Result<(String, bool), String> {
+    let Some(state) = state else {
+        return Ok((sql.to_string(), false));
     };
 
     // Check if query references any semantic models
-    if !query_references_models(&sql_str, &state.graph) {
+    if !query_references_models(sql, &state.graph) {
         // Passthrough - not a semantic query
-        return SidemanticRewriteResult {
-            sql: to_c_string(&sql_str),
-            error: ptr::null_mut(),
-            was_rewritten: false,
-        };
+        return Ok((sql.to_string(), false));
     }
 
-    // Rewrite the query
-    let rewriter = QueryRewriter::new(&state.graph);
-    match rewriter.rewrite(&sql_str) {
-        Ok(rewritten) => SidemanticRewriteResult {
-            sql: to_c_string(&rewritten),
+    QueryRewriter::new(&state.graph)
+        .rewrite(sql)
+        .map(|rewritten| (rewritten, true))
+        .map_err(|e| format!("Error: {e}"))
+}
+
+/// Rewrite several queries while holding the graph lock once
+///
+/// `queries` points to `len` bytes of UTF-8 holding NUL-separated queries;
+/// a buffer without any NUL is split on newlines instead, and one trailing
+/// separator is ignored. Returns a JSON array with one `{"sql", "error",
+/// "was_rewritten"}` object per entry, in input order; empty or non-UTF-8
+/// entries get an error object. A null buffer or invalid context yields a
+/// single error object.
+/// Caller must free the returned string with `sidemantic_free`.
+#[no_mangle]
+pub extern "C" fn sidemantic_rewrite_batch(queries: *const c_char, len: usize) -> *mut c_char {
+    sidemantic_rewrite_batch_for_context(ptr::null(), queries, len)
+}
+
+/// Rewrite several queries using one context's definitions.
+#[no_mangle]
+pub extern "C" fn sidemantic_rewrite_batch_for_context(
+    context: *const c_char,
+    queries: *const c_char,
+    len: usize,
+) -> *mut c_char {
+    let batch_error = |error: String| vec![Err(error)];
+    let results = match parse_context_key(context) {
+        Err(error) => batch_error(error),
+        Ok(_) if queries.is_null() => batch_error("Error: null queries pointer".to_string()),
+        Ok(key) => {
+            let bytes = unsafe { std::slice::from_raw_parts(queries.cast::<u8>(), len) };
+            let delimiter = if bytes.contains(&0) { 0 } else { b'\n' };
+            let bytes = bytes.strip_suffix(&[delimiter]).unwrap_or(bytes);
+
+            if bytes.is_empty() {
+                return to_c_string("[]");
+            }
+
+            let states = FFI_STATES.lock().unwrap();
+            let state = states.get(&key);
+            bytes
+                .split(|byte| *byte == delimiter)
+                .map(|entry| {
+                    let query = std::str::from_utf8(entry)
+                        .map_err(|e| format!("Error: invalid UTF-8: {e}"))?;
+                    if query.trim().is_empty() {
+                        return Err("Error: empty query".to_string());
+                    }
+                    rewrite_with_state(state, query)
+                })
+                .collect()
+        }
+    };
+
+    let results: Vec<serde_json::Value> = results
+        .into_iter()
+        .map(|result| match result {
+            Ok((sql, was_rewritten)) => serde_json::json!({
+                "sql": sql,
+                "error": null,
+                "was_rewritten": was_rewritten,
+            }),
+            Err(error) => serde_json::json!({
+                "sql": null,
+                "error": error,
+                "was_rewritten": false,
+            }),
+        })
+        .collect();
+    to_c_string(&serde_json::Value::Array(results).to_string())
+}
+
+/// Compile a JSON-encoded semantic query into SQL
+///
+/// Accepts `{"metrics", "dimensions", "filters", "segments", "order_by",
+/// "limit"}`; every key is optional and unknown keys are rejected. Returns a SidemanticRewriteResult with
+/// `was_rewritten` set on success. Caller must free with `sidemantic_free_result`.
+#[no_mangle]
+pub extern "C" fn sidemantic_compile_query(query_json: *const c_char) -> SidemanticRewriteResult {
+    sidemantic_compile_query_for_context(ptr::null(), query_json)
+}
+
+/// Compile a JSON-encoded semantic query against one context's definitions.
+#[no_mangle]
+pub extern "C" fn sidemantic_compile_query_for_context(
+    context: *const c_char,
+    query_json: *const c_char,
+) -> SidemanticRewriteResult {
+    let failure = |error| SidemanticRewriteResult {
+        sql: ptr::null_mut(),
+        error,
+        was_rewritten: false,
+    };
+
+    let key = match context_key(context) {
+        Ok(key) => key,
+        Err(error) => return failure(error),
+    };
+    let json = match c_string_arg(query_json, "query_json") {
+        Ok(value) => value,
+        Err(error) => return failure(error),
+    };
+    let payload: CompileQueryPayload = match serde_json::from_str(&json) {
+        Ok(payload) => payload,
+        Err(e) => return failure(to_c_string(&format!("Error: invalid query JSON: {e}"))),
+    };
+
+    let states = FFI_STATES.lock().unwrap();
+    let Some(state) = states.get(&key) else {
+        return failure(to_c_string("Error: no semantic models loaded"));
+    };
+
+    match SqlGenerator::new(&state.graph).generate(&payload.into_query()) {
+        Ok(sql) => SidemanticRewriteResult {
+            sql: to_c_string(&sql),
             error: ptr::null_mut(),
             was_rewritten: true,
         },
-        Err(e) => SidemanticRewriteResult {
-            sql: ptr::null_mut(),
-            error: to_c_string(&format!("Error: {e}")),
-            was_rewritten: false,
-        },
+        Err(e) => failure(to_c_string(&format!("Error: {e}"))),
     }
 }
 
@@ -1581,11 +2073,226 @@ models:
     }
 
     #[test]
-    fn test_passthrough() {
+    fn test_describe_returns_model_metadata_as_json() {
         let _guard = test_lock();
         sidemantic_clear();
 
-        // Query without semantic models should pass through
+        let yaml = CString::new(
+            r#"
+models:
+  - name: orders
+    table: analytics.orders
+    primary_key: order_id
+    description: All orders
+    dimensions:
+      - name: status
+        type: categorical
+        label: Order status
+    metrics:
+      - name: revenue
+        agg: sum
+        sql: amount
+  - name: "customers,archived"
+    table: customers
+    primary_key: id
+"#,
+        )
+        .unwrap();
+        assert_success(sidemantic_load_yaml(yaml.as_ptr()));
+
+        let result = sidemantic_describe();
+        assert!(!result.is_null());
+        let json = unsafe { CStr::from_ptr(result).to_string_lossy().into_owned() };
+        sidemantic_free(result);
+
+        let described: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let models = described["models"].as_array().unwrap();
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0]["name"], "orders");
+        assert_eq!(models[0]["table"], "analytics.orders");
+        assert_eq!(models[0]["primary_key"], serde_json::json!(["order_id"]));
+        assert_eq!(models[0]["description"], "All orders");
+        assert_eq!(models[0]["dimensions"][0]["name"], "status");
+        assert_eq!(models[0]["dimensions"][0]["type"], "categorical");
+        assert_eq!(models[0]["dimensions"][0]["label"], "Order status");
+        assert_eq!(models[0]["metrics"][0]["name"], "revenue");
+        assert_eq!(models[0]["metrics"][0]["agg"], "sum");
+        assert_eq!(models[1]["name"], "customers,archived");
+        sidemantic_clear();
+    }
+
+    #[test]
+    fn test_describe_json_serializes_full_graph() {
+        let _guard = test_lock();
+        sidemantic_clear();
+
+        let yaml = CString::new(
+            r#"
+models:
+  - name: orders
+    table: orders
+    primary_key: order_id
+    metrics:
+      - name: revenue
+        agg: sum
+        sql: amount
+    relationships:
+      - name: customers
+        type: many_to_one
+  - name: customers
+    table: customers
+    primary_key: id
+"#,
+        )
+        .unwrap();
+        assert_success(sidemantic_load_yaml(yaml.as_ptr()));
+
+        let result = sidemantic_describe_json();
+        let json = unsafe { CStr::from_ptr(result).to_string_lossy().into_owned() };
+        sidemantic_free(result);
+
+        let described: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(described["models"][0]["name"], "orders");
+        assert_eq!(described["models"][0]["metrics"][0]["type"], "simple");
+        assert_eq!(
+            described["models"][0]["relationships"][0]["name"],
+            "customers"
+        );
+        sidemantic_clear();
+    }
+
+    #[test]
+    fn test_compile_query_from_json() {
+        let _guard = test_lock();
+        sidemantic_clear();
+
+        let yaml = CString::new(
+            r#"
+models:
+  - name: orders
+    table: orders
+    primary_key: order_id
+    dimensions:
+      - name: status
+        type: categorical
+    metrics:
+      - name: revenue
+        agg: sum
+        sql: amount
+"#,
+        )
+        .unwrap();
+        assert_success(sidemantic_load_yaml(yaml.as_ptr()));
+
+        let query = CString::new(
+            r#"{"metrics": ["orders.revenue"], "dimensions": ["orders.status"],
+                "filters": ["orders.status = 'shipped'"], "order_by": ["orders.revenue DESC"],
+                "limit": 5}"#,
+        )
+        .unwrap();
+        let sql = take_rewrite_sql(sidemantic_compile_query(query.as_ptr()));
+        assert!(sql.contains("SUM("), "{sql}");
+        assert!(sql.contains("'shipped'"), "{sql}");
+        assert!(sql.contains("LIMIT 5"), "{sql}");
+
+        let invalid = CString::new("{not json").unwrap();
+        let error = take_rewrite_error(sidemantic_compile_query(invalid.as_ptr()));
+        assert!(error.contains("invalid query JSON"), "{error}");
+
+        let misspelled = CString::new(r#"{"metrics": ["orders.revenue"], "limt": 5}"#).unwrap();
+        let error = take_rewrite_error(sidemantic_compile_query(misspelled.as_ptr()));
+        assert!(error.contains("unknown field `limt`"), "{error}");
+        sidemantic_clear();
+    }
+
+    #[test]
+    fn test_rewrite_batch_rewrites_each_query() {
+        let _guard = test_lock();
+        sidemantic_clear();
+
+        let yaml = CString::new(
+            r#"
+models:
+  - name: orders
+    table: orders
+    primary_key: order_id
+    dimensions:
+      - name: status
+        type: categorical
+    metrics:
+      - name: revenue
+        agg: sum
+        sql: amount
+"#,
+        )
+        .unwrap();
+        assert_success(sidemantic_load_yaml(yaml.as_ptr()));
+
+        let batch = "SELECT orders.revenue FROM orders\0\
+                     SELECT orders.status,\n  orders.revenue\nFROM orders\0\
+                     SELECT 1\0\
+                     SELECT orders.missing FROM orders\0";
+        let result = sidemantic_rewrite_batch(batch.as_ptr().cast(), batch.len());
+        let json = unsafe { CStr::from_ptr(result).to_string_lossy().into_owned() };
+        sidemantic_free(result);
+
+        let results: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(results.len(), 4, "{json}");
+        assert_eq!(results[0]["was_rewritten"], true);
+        assert!(
+            results[0]["sql"].as_str().unwrap().contains("SUM("),
+            "{json}"
+        );
+        assert_eq!(results[1]["was_rewritten"], true);
+        assert!(
+            results[1]["sql"].as_str().unwrap().contains("status"),
+            "{json}"
+        );
+        assert_eq!(results[2]["was_rewritten"], false);
+        assert_eq!(results[2]["sql"], "SELECT 1");
+        assert!(results[3]["error"].is_string(), "{json}");
+
+        let lines = "SELECT orders.revenue FROM orders\nSELECT 1\n";
+        let result = sidemantic_rewrite_batch(lines.as_ptr().cast(), lines.len());
+        let json = unsafe { CStr::from_ptr(result).to_string_lossy().into_owned() };
+        sidemantic_free(result);
+        let results: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(results.len(), 2, "{json}");
+        assert_eq!(results[0]["was_rewritten"], true);
+
+        // Every entry gets a result, so positions line up with the input.
+        let batch = b"SELECT 1\0\0SELECT \xff\0SELECT 2";
+        let result = sidemantic_rewrite_batch(batch.as_ptr().cast(), batch.len());
+        let json = unsafe { CStr::from_ptr(result).to_string_lossy().into_owned() };
+        sidemantic_free(result);
+        let results: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(results.len(), 4, "{json}");
+        assert_eq!(results[0]["sql"], "SELECT 1");
+        assert_eq!(results[1]["error"], "Error: empty query");
+        assert!(
+            results[2]["error"]
+                .as_str()
+                .unwrap()
+                .contains("invalid UTF-8"),
+            "{json}"
+        );
+        assert_eq!(results[3]["sql"], "SELECT 2");
+
+        let result = sidemantic_rewrite_batch(ptr::null(), 0);
+        let json = unsafe { CStr::from_ptr(result).to_string_lossy().into_owned() };
+        sidemantic_free(result);
+        let results: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(results.len(), 1, "{json}");
+        assert_eq!(results[0]["error"], "Error: null queries pointer");
+        sidemantic_clear();
+    }
+
+    #[test]
+    fn test_passthrough() {
+        let _guard = test_lock();
+        sidemantic_clear();
+
+        // Query without semantic models should pass through
         let sql = CString::new("SELECT * FROM some_table").unwrap();
         let result = sidemantic_rewrite(sql.as_ptr());
 
@@ -1595,6 +2302,191 @@ models:
         sidemantic_free_result(result);
     }
 
+    #[test]
+    fn test_validate_reports_bad_relationship_without_loading() {
+        let _guard = test_lock();
+        sidemantic_clear();
+
+        let definition = CString::new(
+            "MODEL (name orders, table orders, primary_key order_id);\nRELATIONSHIP (name missing, type many_to_one, foreign_key missing_id);",
+        )
+        .unwrap();
+        let error = sidemantic_validate(definition.as_ptr(), false);
+        assert!(!error.is_null());
+        let message = unsafe { CStr::from_ptr(error).to_string_lossy().into_owned() };
+        sidemantic_free(error);
+        assert!(
+            message.contains("relationship to unknown model 'missing'"),
+            "{message}"
+        );
+
+        let orders = CString::new("orders").unwrap();
+        assert!(!sidemantic_is_model(orders.as_ptr()));
+
+        let valid =
+            CString::new("MODEL (name orders, table orders, primary_key order_id);").unwrap();
+        assert!(sidemantic_validate(valid.as_ptr(), false).is_null());
+        assert!(!sidemantic_is_model(orders.as_ptr()));
+        sidemantic_clear();
+    }
+
+    #[test]
+    fn test_validate_follows_replace_flag_and_reports_only_new_errors() {
+        let _guard = test_lock();
+        sidemantic_clear();
+
+        let broken = CString::new(
+            "MODEL (name customers, table customers, primary_key id);\nRELATIONSHIP (name missing, type many_to_one, foreign_key missing_id);",
+        )
+        .unwrap();
+        assert_success(sidemantic_define(broken.as_ptr(), ptr::null(), false));
+        let orders =
+            CString::new("MODEL (name orders, table orders, primary_key order_id);").unwrap();
+        assert_success(sidemantic_define(orders.as_ptr(), ptr::null(), false));
+
+        let revised = CString::new(
+            "MODEL (name orders, table orders_v2, primary_key order_id);\nRELATIONSHIP (name customers, type many_to_one, foreign_key customer_id);",
+        )
+        .unwrap();
+        let error = sidemantic_validate(revised.as_ptr(), false);
+        assert!(!error.is_null());
+        let message = unsafe { CStr::from_ptr(error).to_string_lossy().into_owned() };
+        sidemantic_free(error);
+        assert!(message.contains("already exists"), "{message}");
+
+        // The broken `customers` model is already loaded; its error is not
+        // attributed to the new definition.
+        assert!(sidemantic_validate(revised.as_ptr(), true).is_null());
+
+        let bad = CString::new(
+            "MODEL (name orders, table orders, primary_key order_id);\nRELATIONSHIP (name products, type many_to_one, foreign_key product_id);",
+        )
+        .unwrap();
+        let error = sidemantic_validate(bad.as_ptr(), true);
+        assert!(!error.is_null());
+        let message = unsafe { CStr::from_ptr(error).to_string_lossy().into_owned() };
+        sidemantic_free(error);
+        assert!(message.contains("'products'"), "{message}");
+        assert!(!message.contains("'missing'"), "{message}");
+        sidemantic_clear();
+    }
+
+    #[test]
+    fn test_drop_model_removes_model_and_definition() {
+        let _guard = test_lock();
+        sidemantic_clear();
+
+        let db_path = unique_db_path("drop_model");
+        let db_path = CString::new(db_path.to_string_lossy().to_string()).unwrap();
+        remove_definitions_file(&db_path);
+        let definitions_path = get_definitions_path(db_path.as_ptr()).unwrap();
+
+        for definition in [
+            "MODEL (name orders, table orders, primary_key order_id);",
+            "MODEL (name customers, table customers, primary_key id);",
+        ] {
+            let definition = CString::new(definition).unwrap();
+            assert_success(sidemantic_define(
+                definition.as_ptr(),
+                db_path.as_ptr(),
+                false,
+            ));
+        }
+
+        let orders = CString::new("orders").unwrap();
+        assert_success(sidemantic_drop_model(orders.as_ptr(), db_path.as_ptr()));
+
+        assert!(!sidemantic_is_model(orders.as_ptr()));
+        let customers = CString::new("customers").unwrap();
+        assert!(sidemantic_is_model(customers.as_ptr()));
+        let content = fs::read_to_string(&definitions_path).unwrap();
+        assert!(!content.contains("name orders"), "{content}");
+        assert!(content.contains("name customers"), "{content}");
+
+        remove_definitions_file(&db_path);
+        sidemantic_clear();
+    }
+
+    #[test]
+    fn test_drop_missing_model_errors() {
+        let _guard = test_lock();
+        sidemantic_clear();
+
+        let model =
+            CString::new("MODEL (name orders, table orders, primary_key order_id);").unwrap();
+        assert_success(sidemantic_define(model.as_ptr(), ptr::null(), false));
+
+        let missing = CString::new("missing").unwrap();
+        let error = sidemantic_drop_model(missing.as_ptr(), ptr::null());
+        assert!(!error.is_null());
+        let message = unsafe { CStr::from_ptr(error).to_string_lossy().into_owned() };
+        sidemantic_free(error);
+        assert!(message.contains("missing"), "{message}");
+
+        let orders = CString::new("orders").unwrap();
+        assert!(sidemantic_is_model(orders.as_ptr()));
+        sidemantic_clear();
+    }
+
+    #[test]
+    fn test_drop_model_in_unknown_context_errors_without_creating_state() {
+        let _guard = test_lock();
+        sidemantic_clear();
+
+        let context = CString::new("duckdb:drop-unknown").unwrap();
+        let orders = CString::new("orders").unwrap();
+        let error =
+            sidemantic_drop_model_for_context(context.as_ptr(), orders.as_ptr(), ptr::null());
+        assert!(!error.is_null());
+        let message = unsafe { CStr::from_ptr(error).to_string_lossy().into_owned() };
+        sidemantic_free(error);
+        assert!(message.contains("orders"), "{message}");
+        assert!(!FFI_STATES
+            .lock()
+            .unwrap()
+            .contains_key("duckdb:drop-unknown"));
+        sidemantic_clear();
+    }
+
+    #[test]
+    fn test_drop_referenced_model_errors_and_keeps_definition() {
+        let _guard = test_lock();
+        sidemantic_clear();
+
+        let db_path = unique_db_path("drop_referenced_model");
+        let db_path = CString::new(db_path.to_string_lossy().to_string()).unwrap();
+        remove_definitions_file(&db_path);
+        let definitions_path = get_definitions_path(db_path.as_ptr()).unwrap();
+
+        for definition in [
+            "MODEL (name customers, table customers, primary_key id);",
+            "MODEL (name orders, table orders, primary_key order_id);\nRELATIONSHIP (name customers, type many_to_one, foreign_key customer_id);",
+        ] {
+            let definition = CString::new(definition).unwrap();
+            assert_success(sidemantic_define(
+                definition.as_ptr(),
+                db_path.as_ptr(),
+                false,
+            ));
+        }
+
+        let customers = CString::new("customers").unwrap();
+        let error = sidemantic_drop_model(customers.as_ptr(), db_path.as_ptr());
+        assert!(!error.is_null());
+        let message = unsafe { CStr::from_ptr(error).to_string_lossy().into_owned() };
+        sidemantic_free(error);
+        assert!(
+            message.contains("relationship 'orders.customers'"),
+            "{message}"
+        );
+        assert!(sidemantic_is_model(customers.as_ptr()));
+        let content = fs::read_to_string(&definitions_path).unwrap();
+        assert!(content.contains("name customers"), "{content}");
+
+        remove_definitions_file(&db_path);
+        sidemantic_clear();
+    }
+
     #[test]
     fn test_define_replace_updates_in_memory_graph() {
         let _guard = test_lock();
@@ -2165,6 +3057,80 @@ models:
         remove_definitions_file(&db_path);
     }
 
+    #[test]
+    fn test_sessions_isolate_models() {
+        let _guard = test_lock();
+        sidemantic_clear();
+
+        let session_a = sidemantic_session_new();
+        let session_b = sidemantic_session_new();
+        assert_ne!(session_a, session_b);
+
+        let orders =
+            CString::new("MODEL (name orders, table orders, primary_key order_id);\nMETRIC (name order_count, agg count);")
+                .unwrap();
+        assert_success(sidemantic_session_define(
+            session_a,
+            orders.as_ptr(),
+            ptr::null(),
+            false,
+        ));
+        let yaml = CString::new(
+            r#"
+models:
+  - name: customers
+    table: customers
+    primary_key: id
+    metrics:
+      - name: customer_count
+        agg: count
+"#,
+        )
+        .unwrap();
+        assert_success(sidemantic_session_load_yaml(session_b, yaml.as_ptr()));
+
+        let orders_sql = CString::new("SELECT orders.order_count FROM orders").unwrap();
+        let customers_sql = CString::new("SELECT customers.customer_count FROM customers").unwrap();
+
+        let rewritten =
+            take_rewrite_sql(sidemantic_session_rewrite(session_a, orders_sql.as_ptr()));
+        assert!(rewritten.contains("COUNT"), "{rewritten}");
+
+        let passthrough = sidemantic_session_rewrite(session_a, customers_sql.as_ptr());
+        assert!(!passthrough.was_rewritten);
+        sidemantic_free_result(passthrough);
+
+        let rewritten = take_rewrite_sql(sidemantic_session_rewrite(
+            session_b,
+            customers_sql.as_ptr(),
+        ));
+        assert!(rewritten.contains("COUNT"), "{rewritten}");
+
+        // The default session sees neither model.
+        let default_result = sidemantic_rewrite(orders_sql.as_ptr());
+        assert!(!default_result.was_rewritten);
+        sidemantic_free_result(default_result);
+
+        // Sessions are not reachable through context-keyed calls.
+        let context = CString::new(format!("__sidemantic_session_{session_b}__")).unwrap();
+        let result = sidemantic_rewrite_for_context(context.as_ptr(), customers_sql.as_ptr());
+        assert!(!result.was_rewritten);
+        sidemantic_free_result(result);
+        sidemantic_clear_for_context(context.as_ptr());
+
+        sidemantic_session_free(session_a);
+        let error = take_rewrite_error(sidemantic_session_rewrite(session_a, orders_sql.as_ptr()));
+        assert!(error.contains("unknown session"), "{error}");
+        // A freed session stays gone instead of being recreated on use.
+        let error = take_error(sidemantic_session_load_yaml(session_a, yaml.as_ptr()));
+        assert!(error.contains("unknown session"), "{error}");
+        let error = take_rewrite_error(sidemantic_session_rewrite(session_a, orders_sql.as_ptr()));
+        assert!(error.contains("unknown session"), "{error}");
+
+        sidemantic_session_free(session_b);
+        sidemantic_clear();
+    }
+
     #[test]
     fn test_context_keyed_state_isolates_models_and_active_model() {
         let _guard = test_lock();
@@ -2470,6 +3436,59 @@ models:
         let _ = fs::remove_file(definitions_path);
     }
 
+    #[test]
+    fn test_autoload_keeps_model_with_model_keyword_in_description() {
+        let _guard = test_lock();
+
+        let context = CString::new("duckdb:autoload-model-keyword").unwrap();
+        sidemantic_clear_for_context(context.as_ptr());
+
+        let db_path = unique_db_path("autoload_model_keyword");
+        let db_path = CString::new(db_path.to_string_lossy().to_string()).unwrap();
+        let definitions_path = get_definitions_path(db_path.as_ptr()).unwrap();
+        let content = r#"MODEL (
+    name orders,
+    table orders,
+    primary_key order_id,
+    description 'data MODEL notes:
+MODEL (name phantom, table phantom) is just text'
+);
+/*
+MODEL (name commented, table commented);
+*/
+METRIC revenue AS SUM(amount);
+
+MODEL (name customers, table customers, primary_key customer_id);
+"#;
+        fs::write(&definitions_path, content).unwrap();
+
+        let blocks = split_definitions(content);
+        assert_eq!(blocks.len(), 2, "{blocks:?}");
+        assert!(blocks[0].contains("is just text'"), "{}", blocks[0]);
+        assert!(blocks[0].contains("METRIC revenue"), "{}", blocks[0]);
+
+        assert_success(sidemantic_autoload_for_context(
+            context.as_ptr(),
+            db_path.as_ptr(),
+        ));
+        for (name, expected) in [
+            ("orders", true),
+            ("customers", true),
+            ("phantom", false),
+            ("commented", false),
+        ] {
+            let name_c = CString::new(name).unwrap();
+            assert_eq!(
+                sidemantic_is_model_for_context(context.as_ptr(), name_c.as_ptr()),
+                expected,
+                "{name}"
+            );
+        }
+
+        sidemantic_clear_for_context(context.as_ptr());
+        let _ = fs::remove_file(definitions_path);
+    }
+
     #[test]
     fn test_autoload_invalid_definition_clears_context_and_returns_error() {
         let _guard = test_lock();
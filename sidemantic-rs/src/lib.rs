@@ -35,6 +35,7 @@
 //! ```
 
 pub mod adapters;
+pub mod api;
 pub mod config;
 pub mod core;
 pub mod db;
@@ -48,14 +49,19 @@ pub mod sql;
 pub mod wasm;
 
 // Re-export commonly used types
-pub use adapters::{Adapter, CubeAdapter, OsiAdapter, ParsedDocument};
+pub use adapters::{
+    Adapter, CubeAdapter, LookMLAdapter, MetricFlowAdapter, OsiAdapter, ParsedDocument,
+};
+pub use api::Sidemantic;
 pub use config::{
-    load_from_directory, load_from_directory_with_metadata, load_from_file, load_from_string,
+    load_from_directory, load_from_directory_lenient, load_from_directory_with_metadata,
+    load_from_file, load_from_string, LoadError,
 };
 pub use core::{
     build_symmetric_aggregate_sql, merge_model, resolve_model_inheritance, Aggregation,
-    CohortInnerMetric, Dimension, DimensionType, JoinPath, JoinStep, Metric, MetricType, Model,
-    Parameter, ParameterType, Relationship, RelationshipType, RelativeDate, Segment, SemanticGraph,
+    CalendarAttribute, CohortInnerMetric, DateRange, DateSpine, DateUnit, Dimension, DimensionType,
+    JoinPath, JoinStep, JoinType, Metric, MetricType, Model, NonAdditiveWindow, Parameter,
+    ParameterType, Relationship, RelationshipType, RelativeDate, Segment, SemanticGraph,
     SqlDialect, SymmetricAggType, TableCalcType, TableCalculation,
 };
 pub use error::{Result, SidemanticError};
@@ -91,7 +97,10 @@ pub use runtime::{
     PreaggregationRefreshResultShape, QueryValidationContext, RelationshipPathError,
     RelationshipPathStep, SidemanticRuntime,
 };
-pub use sql::{QueryRewriter, SemanticQuery, SqlGenerator};
+pub use sql::{
+    CountStyle, Filter, FilterValidation, FilterValue, GenerationStrategy, GranularityPolicy,
+    QueryRewriter, ResultShape, SegmentFilter, SemanticQuery, SqlGenerator,
+};
 #[cfg(feature = "wasm")]
 pub use wasm::{
     wasm_analyze_migrator_query, wasm_build_preaggregation_refresh_statements,
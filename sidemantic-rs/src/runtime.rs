@@ -28,8 +28,8 @@ use crate::core::{
     build_symmetric_aggregate_sql as build_symmetric_aggregate_sql_core,
     extract_column_references_from_expr, extract_dependencies_with_context,
     resolve_model_inheritance as resolve_models_inheritance, Aggregation, Dimension, DimensionType,
-    JoinPath, Metric, MetricType, Model, Parameter, ParameterType, Relationship, RelationshipType,
-    SemanticGraph, SqlDialect, SymmetricAggType,
+    JoinPath, JoinType, Metric, MetricType, Model, Parameter, ParameterType, Relationship,
+    RelationshipType, SemanticGraph, SqlDialect, SymmetricAggType,
 };
 #[cfg(any(target_arch = "wasm32", test))]
 use crate::core::{TableCalcType, TableCalculation};
@@ -161,6 +161,8 @@ struct GraphPathRelationshipPayload {
     related_foreign_key: Option<String>,
     #[serde(default)]
     related_foreign_key_columns: Vec<String>,
+    #[serde(default)]
+    join_type: Option<JoinType>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -610,6 +612,7 @@ fn parse_metric_type_for_dependencies(payload: &MetricDependencyPayload) -> Metr
         Some("conversion") => MetricType::Conversion,
         Some("retention") => MetricType::Retention,
         Some("cohort") => MetricType::Cohort,
+        Some("pct_of_total" | "percent_of_total") => MetricType::PctOfTotal,
         _ => {
             if payload.agg.is_none() && payload.sql.is_some() {
                 MetricType::Derived
@@ -3460,6 +3463,13 @@ pub fn validate_metric_payload(metric_yaml: &str) -> Result<bool> {
                     ));
                 }
             }
+            "pct_of_total" | "percent_of_total" => {
+                if !payload_has_non_empty_string(mapping, "base_metric") {
+                    return Err(SidemanticError::Validation(
+                        "pct_of_total metric requires 'base_metric' field".to_string(),
+                    ));
+                }
+            }
             "conversion" => {
                 if !payload_has_non_empty_string(mapping, "entity") {
                     return Err(SidemanticError::Validation(
@@ -4809,6 +4819,7 @@ pub fn generate_time_comparison_sql(
         "ratio" => Ok(format!(
             "({current_metric_sql} / NULLIF({offset_metric_sql}, 0))"
         )),
+        "previous_value" => Ok(offset_metric_sql),
         _ => Err(SidemanticError::Validation(format!(
             "Unknown calculation type: {calculation}"
         ))),
@@ -4886,6 +4897,9 @@ fn catalog_aggregation_name(aggregation: Option<&Aggregation>) -> Option<&'stati
         Some(Aggregation::StddevPop) => Some("stddev_pop"),
         Some(Aggregation::Variance) => Some("variance"),
         Some(Aggregation::VariancePop) => Some("variance_pop"),
+        Some(Aggregation::Percentile(_)) => Some("percentile"),
+        Some(Aggregation::ApproxPercentile(_)) => Some("approx_percentile"),
+        Some(Aggregation::ApproxCountDistinct) => Some("approx_count_distinct"),
         Some(Aggregation::Expression) => Some("expression"),
         None => None,
     }
@@ -4893,7 +4907,7 @@ fn catalog_aggregation_name(aggregation: Option<&Aggregation>) -> Option<&'stati
 
 fn catalog_metric_data_type(aggregation: Option<&str>) -> &'static str {
     match aggregation {
-        Some("count" | "count_distinct") => "BIGINT",
+        Some("count" | "count_distinct" | "approx_count_distinct") => "BIGINT",
         Some(
             "sum" | "avg" | "min" | "max" | "median" | "stddev" | "stddev_pop" | "variance"
             | "variance_pop" | "percentile",
@@ -5180,8 +5194,10 @@ impl SidemanticRuntime {
                             select_exprs.push(format!("COUNT({sql_expr}) as {measure_name}_raw"));
                         }
                         Some(Aggregation::CountDistinct) => {
-                            select_exprs
-                                .push(format!("COUNT(DISTINCT {sql_expr}) as {measure_name}_raw"));
+                            select_exprs.push(format!(
+                                "{} as {measure_name}_raw",
+                                Aggregation::CountDistinct.call_sql(sql_expr)
+                            ));
                         }
                         Some(Aggregation::Sum) => {
                             select_exprs.push(format!("SUM({sql_expr}) as {measure_name}_raw"));
@@ -5212,6 +5228,14 @@ impl SidemanticRuntime {
                         Some(Aggregation::VariancePop) => {
                             select_exprs.push(format!("VAR_POP({sql_expr}) as {measure_name}_raw"));
                         }
+                        Some(
+                            agg @ (Aggregation::Percentile(_)
+                            | Aggregation::ApproxPercentile(_)
+                            | Aggregation::ApproxCountDistinct),
+                        ) => {
+                            select_exprs
+                                .push(format!("{} as {measure_name}_raw", agg.call_sql(sql_expr)));
+                        }
                         Some(Aggregation::Expression) | None => {
                             select_exprs.push(format!("SUM({sql_expr}) as {measure_name}_raw"));
                         }
@@ -5242,14 +5266,18 @@ impl SidemanticRuntime {
 
         for model in self.graph.models() {
             let model_primary_keys = model.primary_keys();
-            tables.push(json_object(serde_json::json!({
+            let mut table = json_object(serde_json::json!({
                 "table_catalog": "sidemantic",
                 "table_schema": schema,
                 "table_name": model.name,
                 "table_type": "BASE TABLE",
                 "is_insertable_into": "NO",
                 "is_typed": "NO",
-            })));
+            }));
+            if !model.tags.is_empty() {
+                table.insert("tags".to_string(), serde_json::json!(model.tags));
+            }
+            tables.push(table);
 
             let mut ordinal_position = 1_i64;
 
@@ -5333,6 +5361,9 @@ impl SidemanticRuntime {
                         serde_json::Value::String(label.clone()),
                     );
                 }
+                if !dimension.tags.is_empty() {
+                    column.insert("tags".to_string(), serde_json::json!(dimension.tags));
+                }
 
                 columns.push(column);
                 ordinal_position += 1;
@@ -5371,6 +5402,9 @@ impl SidemanticRuntime {
                         serde_json::Value::String(label.clone()),
                     );
                 }
+                if !metric.tags.is_empty() {
+                    column.insert("tags".to_string(), serde_json::json!(metric.tags));
+                }
 
                 columns.push(column);
                 ordinal_position += 1;
@@ -5592,7 +5626,7 @@ fn relationship_path_with_runtime(
             SidemanticError::ModelNotFound(model_name, _) => {
                 RelationshipPathError::ModelNotFound(model_name)
             }
-            SidemanticError::NoJoinPath { from, to } => RelationshipPathError::NoJoinPath {
+            SidemanticError::NoJoinPath { from, to, .. } => RelationshipPathError::NoJoinPath {
                 from_model: from,
                 to_model: to,
             },
@@ -5634,7 +5668,7 @@ fn relationship_path_with_graph(
             SidemanticError::ModelNotFound(model_name, _) => {
                 RelationshipPathError::ModelNotFound(model_name)
             }
-            SidemanticError::NoJoinPath { from, to } => RelationshipPathError::NoJoinPath {
+            SidemanticError::NoJoinPath { from, to, .. } => RelationshipPathError::NoJoinPath {
                 from_model: from,
                 to_model: to,
             },
@@ -5736,6 +5770,7 @@ fn semantic_graph_from_graph_path_payload(
                 },
                 sql: None,
                 metadata: None,
+                join_type: relationship_payload.join_type,
             });
         }
 
@@ -8485,6 +8520,49 @@ models:
             .any(|constraint| constraint["constraint_type"] == "FOREIGN KEY"));
     }
 
+    #[test]
+    fn test_runtime_generate_catalog_metadata_includes_tags() {
+        let yaml = r#"
+models:
+  - name: orders
+    table: orders
+    primary_key: order_id
+    tags: [sales]
+    dimensions:
+      - name: status
+        type: categorical
+        tags: [workflow]
+    metrics:
+      - name: revenue
+        agg: sum
+        sql: amount
+        tags: [finance, kpi]
+"#;
+
+        let runtime = SidemanticRuntime::from_yaml(yaml).unwrap();
+        let json = runtime.generate_catalog_metadata("analytics").unwrap();
+        let payload: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(payload["tables"][0]["tags"], serde_json::json!(["sales"]));
+        let columns = payload["columns"].as_array().unwrap();
+        let column = |name: &str| {
+            columns
+                .iter()
+                .find(|column| column["column_name"] == name)
+                .unwrap()
+        };
+        assert_eq!(column("status")["tags"], serde_json::json!(["workflow"]));
+        assert_eq!(
+            column("revenue")["tags"],
+            serde_json::json!(["finance", "kpi"])
+        );
+        assert!(column("order_id").get("tags").is_none());
+        assert_eq!(
+            runtime.graph().fields_with_tag("finance"),
+            vec!["orders.revenue"]
+        );
+    }
+
     #[test]
     fn test_runtime_generate_catalog_metadata_errors_on_missing_relationship_model() {
         let yaml = r#"
@@ -0,0 +1,697 @@
+//! DataFusion logical plan output (requires the `datafusion` feature)
+//!
+//! Translates a [`SemanticQuery`] into a DataFusion [`LogicalPlan`] so queries
+//! can run in-process without going through a SQL string. Covers simple
+//! aggregations, dimensions (with time granularity), join paths, metric
+//! filters, model default filters, query and typed filters, ordering, and
+//! limits. Query options the plan cannot honour are rejected rather than
+//! ignored.
+
+use std::collections::{HashMap, HashSet};
+
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::common::{Column, DFSchema};
+use datafusion::functions::expr_fn::date_trunc;
+use datafusion::functions_aggregate::expr_fn::{avg, count, count_distinct, max, median, min, sum};
+use datafusion::logical_expr::{
+    col, lit, table_scan, when, Expr, JoinType, LogicalPlan, LogicalPlanBuilder,
+};
+use datafusion::prelude::SessionContext;
+use polyglot_sql::{Dialect, DialectType, TokenType};
+
+use crate::core::{Aggregation, Metric, MetricType, Model, RelationshipType, SemanticGraph};
+use crate::error::{Result, SidemanticError};
+use crate::sql::{Filter, SemanticQuery};
+
+/// Builds DataFusion logical plans from semantic queries.
+///
+/// Every model referenced by a query needs a registered Arrow schema; the
+/// resulting plan scans a table named after the model. Models defined by SQL
+/// rather than a table are not supported.
+pub struct DataFusionPlanner<'a> {
+    graph: &'a SemanticGraph,
+    schemas: HashMap<String, SchemaRef>,
+    ctx: SessionContext,
+}
+
+impl<'a> DataFusionPlanner<'a> {
+    pub fn new(graph: &'a SemanticGraph) -> Self {
+        Self {
+            graph,
+            schemas: HashMap::new(),
+            ctx: SessionContext::new(),
+        }
+    }
+
+    /// Register the Arrow schema of a model's underlying table.
+    pub fn with_table_schema(mut self, model: impl Into<String>, schema: SchemaRef) -> Self {
+        self.schemas.insert(model.into(), schema);
+        self
+    }
+
+    /// Generate a DataFusion logical plan for a semantic query.
+    pub fn generate_datafusion(&self, query: &SemanticQuery) -> Result<LogicalPlan> {
+        reject_unsupported_options(query)?;
+
+        let mut dimensions = Vec::with_capacity(query.dimensions.len());
+        for reference in &query.dimensions {
+            dimensions.push(self.graph.parse_reference(reference)?);
+        }
+        let mut metrics = Vec::with_capacity(query.metrics.len());
+        for reference in &query.metrics {
+            let (model, name, _) = self.graph.parse_reference(reference)?;
+            metrics.push((model, name));
+        }
+
+        let base_model = dimensions
+            .first()
+            .map(|(model, _, _)| model.clone())
+            .or_else(|| metrics.first().map(|(model, _)| model.clone()))
+            .ok_or_else(|| {
+                SidemanticError::Validation(
+                    "Query must have at least one metric or dimension".into(),
+                )
+            })?;
+
+        let metric_models: HashSet<&str> =
+            metrics.iter().map(|(model, _)| model.as_str()).collect();
+        let mut builder = self.scan(&base_model)?;
+        let mut joined: HashSet<String> = HashSet::from([base_model.clone()]);
+        let required = dimensions
+            .iter()
+            .map(|(model, _, _)| model)
+            .chain(metrics.iter().map(|(model, _)| model));
+        for model_name in required {
+            if joined.contains(model_name) {
+                continue;
+            }
+            let path = self.graph.find_join_path(&base_model, model_name)?;
+            for (index, step) in path.steps.iter().enumerate() {
+                if !joined.insert(step.to_model.clone()) {
+                    continue;
+                }
+                if step.custom_condition.is_some() {
+                    return Err(unsupported(format!(
+                        "custom join condition between '{}' and '{}'",
+                        step.from_model, step.to_model
+                    )));
+                }
+                // No symmetric aggregates here, so refuse any join that would
+                // repeat the rows a queried metric aggregates over: a fan-out
+                // repeats the models before it, a many-to-one off a finer grain
+                // the models after it.
+                let downstream: HashSet<&str> = path.steps[index..]
+                    .iter()
+                    .map(|later| later.to_model.as_str())
+                    .collect();
+                let repeats_metric_rows = if self.graph.step_fans_out(step) {
+                    metric_models
+                        .iter()
+                        .any(|model| !downstream.contains(model))
+                } else {
+                    step.relationship_type == RelationshipType::ManyToOne
+                        && !self
+                            .graph
+                            .keys_cover_grain(&step.from_model, &step.from_keys)
+                        && metric_models.iter().any(|model| downstream.contains(model))
+                };
+                if repeats_metric_rows {
+                    return Err(unsupported(format!(
+                        "fan-out join from '{}' to '{}' with metrics",
+                        step.from_model, step.to_model
+                    )));
+                }
+                let left: Vec<Column> = step
+                    .from_keys
+                    .iter()
+                    .map(|key| Column::new(Some(step.from_model.as_str()), key))
+                    .collect();
+                let right: Vec<Column> = step
+                    .to_keys
+                    .iter()
+                    .map(|key| Column::new(Some(step.to_model.as_str()), key))
+                    .collect();
+                let right_plan = self.scan(&step.to_model)?.build().map_err(plan_error)?;
+                builder = builder
+                    .join(right_plan, join_type(step.join_type), (left, right), None)
+                    .map_err(plan_error)?;
+            }
+        }
+
+        let all_filters: Vec<String> = query
+            .filters
+            .iter()
+            .cloned()
+            .chain(
+                query
+                    .typed_filters
+                    .iter()
+                    .map(|filter| self.typed_filter_sql(filter))
+                    .collect::<Result<Vec<_>>>()?,
+            )
+            .chain(
+                query
+                    .segments
+                    .iter()
+                    .map(|segment| self.segment_sql(segment))
+                    .collect::<Result<Vec<_>>>()?,
+            )
+            .collect();
+        for filter in &all_filters {
+            let predicate = self.parse_expr(&self.qualify_references(filter), builder.schema())?;
+            builder = builder.filter(predicate).map_err(plan_error)?;
+        }
+
+        let mut group_exprs = Vec::with_capacity(dimensions.len());
+        for (model_name, dimension_name, granularity) in &dimensions {
+            let model = self.model(model_name)?;
+            let dimension = model.get_dimension(dimension_name).ok_or_else(|| {
+                let available: Vec<&str> =
+                    model.dimensions.iter().map(|d| d.name.as_str()).collect();
+                SidemanticError::dimension_not_found(model_name, dimension_name, &available)
+            })?;
+            let mut expr = self.parse_expr(
+                &qualify_expression(dimension.sql_expr(), model_name),
+                builder.schema(),
+            )?;
+            let alias = match granularity {
+                Some(granularity) => {
+                    expr = date_trunc(lit(granularity.as_str()), expr);
+                    format!("{dimension_name}__{granularity}")
+                }
+                None => dimension_name.clone(),
+            };
+            group_exprs.push(expr.alias(alias));
+        }
+
+        let mut aggr_exprs = Vec::with_capacity(metrics.len());
+        for (model_name, metric_name) in &metrics {
+            let model = self.model(model_name)?;
+            let metric = model.get_metric(metric_name).ok_or_else(|| {
+                let available: Vec<&str> = model.metrics.iter().map(|m| m.name.as_str()).collect();
+                SidemanticError::metric_not_found(model_name, metric_name, &available)
+            })?;
+            aggr_exprs.push(
+                self.aggregate_expr(model, metric, builder.schema())?
+                    .alias(metric_name.as_str()),
+            );
+        }
+
+        builder = builder
+            .aggregate(group_exprs, aggr_exprs)
+            .map_err(plan_error)?;
+
+        if !query.order_by.is_empty() {
+            let sort_exprs = query
+                .order_by
+                .iter()
+                .map(|item| {
+                    let trimmed = item.trim();
+                    let (field, descending) = match trimmed.rsplit_once(char::is_whitespace) {
+                        Some((field, direction)) if direction.eq_ignore_ascii_case("desc") => {
+                            (field.trim(), true)
+                        }
+                        Some((field, direction)) if direction.eq_ignore_ascii_case("asc") => {
+                            (field.trim(), false)
+                        }
+                        _ => (trimmed, false),
+                    };
+                    let alias = field.rsplit('.').next().unwrap_or(field);
+                    col(alias).sort(!descending, descending)
+                })
+                .collect::<Vec<_>>();
+            builder = builder.sort(sort_exprs).map_err(plan_error)?;
+        }
+
+        if query.limit.is_some() || query.offset.is_some() {
+            builder = builder
+                .limit(query.offset.unwrap_or(0), query.limit)
+                .map_err(plan_error)?;
+        }
+
+        builder.build().map_err(plan_error)
+    }
+
+    fn model(&self, name: &str) -> Result<&Model> {
+        self.graph.get_model(name).ok_or_else(|| {
+            let available: Vec<&str> = self.graph.models().map(|m| m.name.as_str()).collect();
+            SidemanticError::model_not_found(name, &available)
+        })
+    }
+
+    /// Scan a model's table, applying its default filters before any join so
+    /// they never drop rows from the other side of an outer join.
+    fn scan(&self, model_name: &str) -> Result<LogicalPlanBuilder> {
+        let model = self.model(model_name)?;
+        if model.sql.is_some() {
+            return Err(unsupported(format!("SQL-defined model '{model_name}'")));
+        }
+        let schema = self.schemas.get(model_name).ok_or_else(|| {
+            SidemanticError::Validation(format!(
+                "No table schema registered for model '{model_name}'"
+            ))
+        })?;
+        let mut builder =
+            table_scan(Some(model_name), schema.as_ref(), None).map_err(plan_error)?;
+        for filter in &model.default_filters {
+            let filter = qualify_expression(filter, model_name);
+            let predicate = self.parse_expr(&self.qualify_references(&filter), builder.schema())?;
+            builder = builder.filter(predicate).map_err(plan_error)?;
+        }
+        Ok(builder)
+    }
+
+    /// Render a typed filter against the dimension it names.
+    fn typed_filter_sql(&self, filter: &Filter) -> Result<String> {
+        let (model_name, field, granularity) = self.graph.parse_reference(filter.field())?;
+        if granularity.is_some() {
+            return Err(SidemanticError::Validation(format!(
+                "Filter field '{}' cannot specify a granularity",
+                filter.field()
+            )));
+        }
+        let model = self.model(&model_name)?;
+        if model.get_dimension(&field).is_none() {
+            if model.get_metric(&field).is_some() {
+                return Err(unsupported(format!(
+                    "typed filter on metric '{}'",
+                    filter.field()
+                )));
+            }
+            return Err(SidemanticError::Validation(format!(
+                "Filter field '{}' must be a dimension or metric",
+                filter.field()
+            )));
+        }
+        filter.to_sql(&format!("{model_name}.{field}"), DialectType::DataFusion)
+    }
+
+    fn segment_sql(&self, reference: &str) -> Result<String> {
+        let (model_name, segment_name, _) = self.graph.parse_reference(reference)?;
+        let model = self.model(&model_name)?;
+        let segment = model.get_segment(&segment_name).ok_or_else(|| {
+            let available: Vec<&str> = model.segments.iter().map(|s| s.name.as_str()).collect();
+            SidemanticError::segment_not_found(&model_name, &segment_name, &available)
+        })?;
+        Ok(segment.sql.replace("{model}", &model_name))
+    }
+
+    fn aggregate_expr(&self, model: &Model, metric: &Metric, schema: &DFSchema) -> Result<Expr> {
+        if metric.r#type != MetricType::Simple {
+            return Err(unsupported(format!(
+                "{:?} metric '{}.{}'",
+                metric.r#type, model.name, metric.name
+            )));
+        }
+
+        let mut value = match metric.sql.as_deref().filter(|sql| !sql.trim().is_empty()) {
+            Some("*") | None => lit(1),
+            Some(sql) => self.parse_expr(&qualify_expression(sql, &model.name), schema)?,
+        };
+        if !metric.filters.is_empty() {
+            let condition = metric
+                .filters
+                .iter()
+                .map(|filter| {
+                    self.parse_expr(
+                        &self.qualify_references(&filter.replace("{model}", &model.name)),
+                        schema,
+                    )
+                })
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .reduce(Expr::and)
+                .expect("non-empty metric filters");
+            value = when(condition, value).end().map_err(plan_error)?;
+        }
+
+        let expr = match metric.agg.as_ref() {
+            Some(Aggregation::Sum) => sum(value),
+            Some(Aggregation::Count) => count(value),
+            Some(Aggregation::CountDistinct) => count_distinct(value),
+            Some(Aggregation::Avg) => avg(value),
+            Some(Aggregation::Min) => min(value),
+            Some(Aggregation::Max) => max(value),
+            Some(Aggregation::Median) => median(value),
+            other => {
+                return Err(unsupported(format!(
+                    "aggregation {other:?} on metric '{}.{}'",
+                    model.name, metric.name
+                )))
+            }
+        };
+        Ok(expr)
+    }
+
+    /// Replace `model.dimension` references with the dimension's qualified SQL.
+    ///
+    /// Works on tokens, so references inside string literals and names that
+    /// merely share a prefix (`orders.status_code`) are left alone. SQL the
+    /// tokenizer rejects is returned unchanged for DataFusion to report.
+    fn qualify_references(&self, sql: &str) -> String {
+        let Ok(tokens) = Dialect::get(DialectType::DuckDB).tokenize(sql) else {
+            return sql.to_string();
+        };
+
+        let mut qualified = String::with_capacity(sql.len());
+        let mut last = 0;
+        for (index, window) in tokens.windows(3).enumerate() {
+            let [model_token, dot, field_token] = window else {
+                continue;
+            };
+            let follows_dot = index > 0 && tokens[index - 1].token_type == TokenType::Dot;
+            if dot.token_type != TokenType::Dot
+                || follows_dot
+                || model_token.span.start < last
+                || model_token.token_type == TokenType::String
+                || field_token.token_type == TokenType::String
+            {
+                continue;
+            }
+            let Some(dimension) = self
+                .graph
+                .get_model(&model_token.text)
+                .and_then(|model| model.get_dimension(&field_token.text))
+            else {
+                continue;
+            };
+            let expr = qualify_expression(dimension.sql_expr(), &model_token.text);
+            qualified.push_str(&sql[last..model_token.span.start]);
+            if expr == format!("{}.{}", model_token.text, field_token.text) {
+                qualified.push_str(&expr);
+            } else {
+                qualified.push_str(&format!("({expr})"));
+            }
+            last = field_token.span.end;
+        }
+        qualified.push_str(&sql[last..]);
+        qualified
+    }
+
+    fn parse_expr(&self, sql: &str, schema: &DFSchema) -> Result<Expr> {
+        self.ctx.parse_sql_expr(sql, schema).map_err(plan_error)
+    }
+}
+
+/// Fail on query options the plan would otherwise silently drop.
+fn reject_unsupported_options(query: &SemanticQuery) -> Result<()> {
+    let options = [
+        (!query.join_filters.is_empty(), "join filters"),
+        (query.segment_expr.is_some(), "segment expressions"),
+        (!query.table_calculations.is_empty(), "table calculations"),
+        (query.ungrouped, "ungrouped queries"),
+        (query.top_n_per.is_some(), "top-N-per queries"),
+        (query.grand_total.is_some(), "grand totals"),
+        (query.apply_value_labels, "value labels"),
+        (query.approximate, "approximate aggregation"),
+        (query.timezone.is_some(), "query time zones"),
+    ];
+    match options.into_iter().find(|(set, _)| *set) {
+        Some((_, option)) => Err(unsupported(option.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Qualify a model-level SQL expression with the model's table name.
+fn qualify_expression(sql: &str, model_name: &str) -> String {
+    if sql.contains("{model}") {
+        return sql.replace("{model}", model_name);
+    }
+    let is_identifier = sql
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && sql.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_identifier {
+        format!("{model_name}.{sql}")
+    } else {
+        sql.to_string()
+    }
+}
+
+/// DataFusion join for a relationship's declared join type, `LEFT` by default.
+fn join_type(join_type: Option<crate::core::JoinType>) -> JoinType {
+    match join_type {
+        None | Some(crate::core::JoinType::Left) => JoinType::Left,
+        Some(crate::core::JoinType::Inner) => JoinType::Inner,
+        Some(crate::core::JoinType::Right) => JoinType::Right,
+        Some(crate::core::JoinType::Full) => JoinType::Full,
+    }
+}
+
+fn unsupported(what: String) -> SidemanticError {
+    SidemanticError::Validation(format!("DataFusion plans do not support {what}"))
+}
+
+fn plan_error(err: datafusion::error::DataFusionError) -> SidemanticError {
+    SidemanticError::SqlGeneration(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+
+    use super::*;
+    use crate::core::{Dimension, JoinType as CoreJoinType, Relationship};
+
+    fn create_planner(graph: &SemanticGraph) -> DataFusionPlanner<'_> {
+        let orders = Schema::new(vec![
+            Field::new("order_id", DataType::Int64, false),
+            Field::new("customer_id", DataType::Int64, true),
+            Field::new("status", DataType::Utf8, true),
+            Field::new("amount", DataType::Float64, true),
+        ]);
+        let customers = Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("country", DataType::Utf8, true),
+        ]);
+        DataFusionPlanner::new(graph)
+            .with_table_schema("orders", Arc::new(orders))
+            .with_table_schema("customers", Arc::new(customers))
+    }
+
+    fn create_graph() -> SemanticGraph {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_dimension(Dimension::categorical("status"))
+                    .with_metric(Metric::sum("revenue", "amount"))
+                    .with_metric(Metric::count("order_count"))
+                    .with_relationship(Relationship::many_to_one("customers")),
+            )
+            .unwrap();
+        graph
+            .add_model(
+                Model::new("customers", "id")
+                    .with_table("customers")
+                    .with_dimension(Dimension::categorical("country")),
+            )
+            .unwrap();
+        graph
+    }
+
+    #[test]
+    fn test_simple_grouped_query_plan() {
+        let graph = create_graph();
+        let planner = create_planner(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into(), "orders.order_count".into()])
+            .with_dimensions(vec!["orders.status".into()]);
+        let plan = planner.generate_datafusion(&query).unwrap();
+
+        assert!(matches!(plan, LogicalPlan::Aggregate(_)), "{plan}");
+        let fields: Vec<&str> = plan
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect();
+        assert_eq!(fields, vec!["status", "revenue", "order_count"]);
+        let display = plan.display_indent().to_string();
+        assert!(display.contains("TableScan: orders"), "{display}");
+        assert!(display.contains("sum(orders.amount)"), "{display}");
+    }
+
+    #[test]
+    fn test_joined_dimension_filter_and_limit_plan() {
+        let graph = create_graph();
+        let planner = create_planner(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["customers.country".into()])
+            .with_filters(vec!["orders.status = 'completed'".into()])
+            .with_order_by(vec!["orders.revenue DESC".into()])
+            .with_limit(10);
+        let plan = planner.generate_datafusion(&query).unwrap();
+
+        let display = plan.display_indent().to_string();
+        assert!(display.contains("Limit: skip=0, fetch=10"), "{display}");
+        assert!(display.contains("Sort: revenue DESC"), "{display}");
+        assert!(display.contains("Left Join"), "{display}");
+        assert!(
+            display.contains("orders.status = Utf8(\"completed\")"),
+            "{display}"
+        );
+    }
+
+    #[test]
+    fn test_join_uses_relationship_join_type() {
+        let mut graph = create_graph();
+        let mut orders = graph.get_model("orders").unwrap().clone();
+        orders.relationships =
+            vec![Relationship::many_to_one("customers").with_join_type(CoreJoinType::Inner)];
+        graph.replace_model(orders).unwrap();
+        let planner = create_planner(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.status".into(), "customers.country".into()]);
+        let display = planner
+            .generate_datafusion(&query)
+            .unwrap()
+            .display_indent()
+            .to_string();
+        assert!(display.contains("Inner Join"), "{display}");
+    }
+
+    #[test]
+    fn test_fan_out_join_with_parent_metric_is_refused() {
+        let mut graph = create_graph();
+        let customers = graph
+            .get_model("customers")
+            .unwrap()
+            .clone()
+            .with_metric(Metric::count("customer_count"));
+        graph.replace_model(customers).unwrap();
+        let planner = create_planner(&graph);
+
+        // customers -> orders is one_to_many, and orders -> customers repeats
+        // each customer once per order: either way the count is inflated.
+        for dimensions in [
+            vec!["customers.country".into(), "orders.status".into()],
+            vec!["orders.status".into(), "customers.country".into()],
+        ] {
+            let query = SemanticQuery::new()
+                .with_metrics(vec!["customers.customer_count".into()])
+                .with_dimensions(dimensions);
+            let err = planner.generate_datafusion(&query).unwrap_err();
+            assert!(err.to_string().contains("fan-out join"), "{err}");
+        }
+
+        // Without metrics the join only widens the dimension domain.
+        let query = SemanticQuery::new()
+            .with_dimensions(vec!["customers.country".into(), "orders.status".into()]);
+        assert!(planner.generate_datafusion(&query).is_ok());
+    }
+
+    #[test]
+    fn test_filter_references_skip_string_literals() {
+        let mut graph = create_graph();
+        let orders = graph
+            .get_model("orders")
+            .unwrap()
+            .clone()
+            .with_dimension(Dimension::categorical("state").with_sql("UPPER(status)"));
+        graph.replace_model(orders).unwrap();
+        let planner = create_planner(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_filters(vec!["orders.state = 'orders.state'".into()]);
+        let plan = planner.generate_datafusion(&query).unwrap();
+
+        let display = plan.display_indent().to_string();
+        assert!(
+            display.contains("upper(orders.status) = Utf8(\"orders.state\")"),
+            "{display}"
+        );
+    }
+
+    #[test]
+    fn test_default_and_typed_filters_land_in_plan() {
+        let mut graph = create_graph();
+        let mut customers = graph.get_model("customers").unwrap().clone();
+        customers.default_filters = vec!["{model}.country <> 'XX'".into()];
+        graph.replace_model(customers).unwrap();
+        let planner = create_planner(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["customers.country".into()])
+            .with_typed_filters(vec![Filter::in_values(
+                "orders.status",
+                ["completed", "it's"],
+            )]);
+        let display = planner
+            .generate_datafusion(&query)
+            .unwrap()
+            .display_indent()
+            .to_string();
+        assert!(
+            display.contains("Filter: customers.country != Utf8(\"XX\")"),
+            "{display}"
+        );
+        assert!(display.contains("it's"), "{display}");
+        // The default filter sits under the join, on the customers scan.
+        let filter_line = display
+            .lines()
+            .position(|line| line.contains("customers.country !="))
+            .unwrap();
+        let join_line = display
+            .lines()
+            .position(|line| line.contains("Left Join"))
+            .unwrap();
+        assert!(filter_line > join_line, "{display}");
+    }
+
+    #[test]
+    fn test_unsupported_query_options_are_errors() {
+        let graph = create_graph();
+        let planner = create_planner(&graph);
+        let base = || SemanticQuery::new().with_metrics(vec!["orders.revenue".into()]);
+
+        let mut ungrouped = base();
+        ungrouped.ungrouped = true;
+        let mut grand_total = base();
+        grand_total.grand_total = Some("Total".into());
+        let mut join_filters = base();
+        join_filters.join_filters = vec!["customers.country = 'US'".into()];
+        for (query, option) in [
+            (ungrouped, "ungrouped queries"),
+            (grand_total, "grand totals"),
+            (join_filters, "join filters"),
+        ] {
+            let err = planner.generate_datafusion(&query).unwrap_err();
+            assert!(err.to_string().contains(option), "{err}");
+        }
+
+        let mut sql_graph = create_graph();
+        let mut orders = sql_graph.get_model("orders").unwrap().clone();
+        orders.table = None;
+        orders.sql = Some("SELECT * FROM raw_orders".into());
+        sql_graph.replace_model(orders).unwrap();
+        let err = create_planner(&sql_graph)
+            .generate_datafusion(&base())
+            .unwrap_err();
+        assert!(err.to_string().contains("SQL-defined model"), "{err}");
+    }
+
+    #[test]
+    fn test_missing_schema_is_an_error() {
+        let graph = create_graph();
+        let planner = DataFusionPlanner::new(&graph);
+
+        let query = SemanticQuery::new().with_metrics(vec!["orders.revenue".into()]);
+        let err = planner.generate_datafusion(&query).unwrap_err();
+        assert!(
+            err.to_string().contains("No table schema registered"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,378 @@
+//! Typed query filters
+//!
+//! A structured alternative to raw SQL filter strings: each [`Filter`] names a
+//! field (`model.dimension`) and carries typed values, which are rendered as
+//! properly quoted SQL literals. [`SegmentFilter`] composes named segments
+//! with `AND`, `OR` and `NOT`.
+
+use std::hash::{Hash, Hasher};
+use std::ops::Not;
+
+use polyglot_sql::DialectType;
+
+use crate::error::{Result, SidemanticError};
+
+/// A literal value compared against a field in a [`Filter`].
+#[derive(Debug, Clone)]
+pub enum FilterValue {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+}
+
+impl FilterValue {
+    /// Render the value as a SQL literal for `dialect`. Embedded single quotes
+    /// are doubled, and backslashes are doubled too on dialects that read them
+    /// as escapes. Booleans render as `1`/`0` on T-SQL, which has no boolean
+    /// literals.
+    pub fn to_sql(&self, dialect: DialectType) -> Result<String> {
+        match self {
+            Self::String(value) => {
+                let escaped = if escapes_backslash(dialect) {
+                    value.replace('\\', "\\\\").replace('\'', "''")
+                } else {
+                    value.replace('\'', "''")
+                };
+                Ok(format!("'{escaped}'"))
+            }
+            Self::Integer(value) => Ok(value.to_string()),
+            Self::Float(value) if value.is_finite() => Ok(value.to_string()),
+            Self::Float(value) => Err(SidemanticError::Validation(format!(
+                "Filter value {value} is not a finite number"
+            ))),
+            Self::Boolean(value) => Ok(match (dialect, value) {
+                (DialectType::TSQL | DialectType::Fabric, true) => "1",
+                (DialectType::TSQL | DialectType::Fabric, false) => "0",
+                (_, true) => "TRUE",
+                (_, false) => "FALSE",
+            }
+            .to_string()),
+        }
+    }
+}
+
+/// Whether string literals in `dialect` treat `\` as an escape character.
+fn escapes_backslash(dialect: DialectType) -> bool {
+    matches!(
+        dialect,
+        DialectType::MySQL
+            | DialectType::TiDB
+            | DialectType::SingleStore
+            | DialectType::Doris
+            | DialectType::StarRocks
+            | DialectType::BigQuery
+            | DialectType::Spark
+            | DialectType::Databricks
+            | DialectType::Hive
+            | DialectType::ClickHouse
+    )
+}
+
+impl PartialEq for FilterValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::String(a), Self::String(b)) => a == b,
+            (Self::Integer(a), Self::Integer(b)) => a == b,
+            (Self::Float(a), Self::Float(b)) => a.to_bits() == b.to_bits(),
+            (Self::Boolean(a), Self::Boolean(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for FilterValue {}
+
+impl Hash for FilterValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Self::String(value) => value.hash(state),
+            Self::Integer(value) => value.hash(state),
+            Self::Float(value) => value.to_bits().hash(state),
+            Self::Boolean(value) => value.hash(state),
+        }
+    }
+}
+
+impl From<&str> for FilterValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for FilterValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<i64> for FilterValue {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<i32> for FilterValue {
+    fn from(value: i32) -> Self {
+        Self::Integer(value.into())
+    }
+}
+
+impl From<f64> for FilterValue {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<bool> for FilterValue {
+    fn from(value: bool) -> Self {
+        Self::Boolean(value)
+    }
+}
+
+/// A structured filter on a field reference such as `orders.status`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Filter {
+    Equals {
+        field: String,
+        value: FilterValue,
+    },
+    In {
+        field: String,
+        values: Vec<FilterValue>,
+    },
+    NotIn {
+        field: String,
+        values: Vec<FilterValue>,
+    },
+    GreaterThan {
+        field: String,
+        value: FilterValue,
+    },
+    /// Inclusive on both ends
+    Between {
+        field: String,
+        low: FilterValue,
+        high: FilterValue,
+    },
+    IsNull {
+        field: String,
+    },
+    /// SQL `LIKE` pattern, using `%` and `_` wildcards
+    Like {
+        field: String,
+        pattern: String,
+    },
+}
+
+impl Filter {
+    pub fn equals(field: impl Into<String>, value: impl Into<FilterValue>) -> Self {
+        Self::Equals {
+            field: field.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn in_values<V: Into<FilterValue>>(
+        field: impl Into<String>,
+        values: impl IntoIterator<Item = V>,
+    ) -> Self {
+        Self::In {
+            field: field.into(),
+            values: values.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn not_in_values<V: Into<FilterValue>>(
+        field: impl Into<String>,
+        values: impl IntoIterator<Item = V>,
+    ) -> Self {
+        Self::NotIn {
+            field: field.into(),
+            values: values.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn greater_than(field: impl Into<String>, value: impl Into<FilterValue>) -> Self {
+        Self::GreaterThan {
+            field: field.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn between(
+        field: impl Into<String>,
+        low: impl Into<FilterValue>,
+        high: impl Into<FilterValue>,
+    ) -> Self {
+        Self::Between {
+            field: field.into(),
+            low: low.into(),
+            high: high.into(),
+        }
+    }
+
+    pub fn is_null(field: impl Into<String>) -> Self {
+        Self::IsNull {
+            field: field.into(),
+        }
+    }
+
+    pub fn like(field: impl Into<String>, pattern: impl Into<String>) -> Self {
+        Self::Like {
+            field: field.into(),
+            pattern: pattern.into(),
+        }
+    }
+
+    /// The field reference this filter applies to.
+    pub fn field(&self) -> &str {
+        match self {
+            Self::Equals { field, .. }
+            | Self::In { field, .. }
+            | Self::NotIn { field, .. }
+            | Self::GreaterThan { field, .. }
+            | Self::Between { field, .. }
+            | Self::IsNull { field }
+            | Self::Like { field, .. } => field,
+        }
+    }
+
+    /// Render the predicate against `column` with literals quoted for
+    /// `dialect`. An empty `In` list matches no rows and an empty `NotIn` list
+    /// matches every row.
+    pub fn to_sql(&self, column: &str, dialect: DialectType) -> Result<String> {
+        let list = |values: &[FilterValue]| -> Result<String> {
+            Ok(values
+                .iter()
+                .map(|value| value.to_sql(dialect))
+                .collect::<Result<Vec<_>>>()?
+                .join(", "))
+        };
+        Ok(match self {
+            Self::Equals { value, .. } => format!("{column} = {}", value.to_sql(dialect)?),
+            Self::In { values, .. } if values.is_empty() => "1 = 0".to_string(),
+            Self::In { values, .. } => format!("{column} IN ({})", list(values)?),
+            Self::NotIn { values, .. } if values.is_empty() => "1 = 1".to_string(),
+            Self::NotIn { values, .. } => format!("{column} NOT IN ({})", list(values)?),
+            Self::GreaterThan { value, .. } => format!("{column} > {}", value.to_sql(dialect)?),
+            Self::Between { low, high, .. } => {
+                format!(
+                    "{column} BETWEEN {} AND {}",
+                    low.to_sql(dialect)?,
+                    high.to_sql(dialect)?
+                )
+            }
+            Self::IsNull { .. } => format!("{column} IS NULL"),
+            Self::Like { pattern, .. } => format!(
+                "{column} LIKE {}",
+                FilterValue::String(pattern.clone()).to_sql(dialect)?
+            ),
+        })
+    }
+}
+
+/// Boolean composition of segment references such as `orders.completed`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SegmentFilter {
+    Segment(String),
+    And(Vec<SegmentFilter>),
+    Or(Vec<SegmentFilter>),
+    Not(Box<SegmentFilter>),
+}
+
+impl SegmentFilter {
+    pub fn segment(reference: impl Into<String>) -> Self {
+        Self::Segment(reference.into())
+    }
+
+    pub fn and(self, other: SegmentFilter) -> Self {
+        match self {
+            Self::And(mut operands) => {
+                operands.push(other);
+                Self::And(operands)
+            }
+            this => Self::And(vec![this, other]),
+        }
+    }
+
+    pub fn or(self, other: SegmentFilter) -> Self {
+        match self {
+            Self::Or(mut operands) => {
+                operands.push(other);
+                Self::Or(operands)
+            }
+            this => Self::Or(vec![this, other]),
+        }
+    }
+}
+
+impl Not for SegmentFilter {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self::Not(Box::new(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_to_sql_quotes_literals() {
+        assert_eq!(
+            Filter::in_values("o.status", ["a", "it's"])
+                .to_sql("o.status", DialectType::Generic)
+                .unwrap(),
+            "o.status IN ('a', 'it''s')"
+        );
+        assert_eq!(
+            Filter::between("o.amount", 10, 20.5)
+                .to_sql("o.amount", DialectType::Generic)
+                .unwrap(),
+            "o.amount BETWEEN 10 AND 20.5"
+        );
+        assert_eq!(
+            Filter::like("o.name", "O'%")
+                .to_sql("o.name", DialectType::Generic)
+                .unwrap(),
+            "o.name LIKE 'O''%'"
+        );
+        assert_eq!(
+            Filter::in_values("o.status", Vec::<&str>::new())
+                .to_sql("o.status", DialectType::Generic)
+                .unwrap(),
+            "1 = 0"
+        );
+        assert!(Filter::equals("o.amount", f64::NAN)
+            .to_sql("o.amount", DialectType::Generic)
+            .is_err());
+    }
+
+    #[test]
+    fn test_filter_value_to_sql_follows_dialect() {
+        let value = FilterValue::from("\\' OR 1=1 --");
+        assert_eq!(
+            value.to_sql(DialectType::BigQuery).unwrap(),
+            "'\\\\'' OR 1=1 --'"
+        );
+        assert_eq!(
+            value.to_sql(DialectType::PostgreSQL).unwrap(),
+            "'\\'' OR 1=1 --'"
+        );
+        assert_eq!(
+            Filter::equals("o.active", true)
+                .to_sql("o.active", DialectType::TSQL)
+                .unwrap(),
+            "o.active = 1"
+        );
+        assert_eq!(
+            FilterValue::from(false)
+                .to_sql(DialectType::DuckDB)
+                .unwrap(),
+            "FALSE"
+        );
+    }
+}
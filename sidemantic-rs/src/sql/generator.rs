@@ -1,28 +1,90 @@
 //! SQL generator: compiles semantic queries to SQL
 
+use std::borrow::Cow;
+use std::cell::OnceCell;
 use std::collections::{HashMap, HashSet};
 
-use polyglot_sql::expressions::{Expression, Literal, Raw};
-use polyglot_sql::DialectType;
+use polyglot_sql::expressions::{Expression, Identifier, Literal, Raw};
+use polyglot_sql::traversal::ExpressionWalk;
+use polyglot_sql::{Dialect, DialectType, TokenType};
 
 use crate::core::{
-    build_symmetric_aggregate_sql_with_key_expr, Aggregation, CohortInnerMetric, JoinPath, Metric,
-    MetricType, Model, RelationshipType, RelativeDate, SemanticGraph, SqlDialect, SymmetricAggType,
-    TableCalculation,
+    build_symmetric_aggregate_sql_with_key_expr, Aggregation, CohortInnerMetric, DateRange,
+    JoinPath, Metric, MetricType, Model, RelationshipType, RelativeDate, SemanticGraph, SqlDialect,
+    SymmetricAggType, TableCalcType, TableCalculation,
 };
 use crate::error::{Result, SidemanticError};
 
+use super::filter::{Filter, SegmentFilter};
+use super::rewriter::parse_all_with_large_stack;
+
 type CtePushdownClassification = (HashMap<String, Vec<String>>, Vec<String>);
+type OutputRefs = (Vec<DimensionRef>, Vec<MetricRef>, HashMap<String, usize>);
 const SOURCE_DIALECT: DialectType = DialectType::DuckDB;
 
+/// Matches `parent HAVING COUNT(child) <op> n` relationship count filters.
+fn relationship_count_filter_regex() -> regex::Regex {
+    regex::Regex::new(
+        r"(?i)^\s*([A-Za-z_][A-Za-z0-9_]*)\s+HAVING\s+COUNT\s*\(\s*([A-Za-z_][A-Za-z0-9_]*)\s*\)\s*(>=|<=|<>|!=|=|>|<)\s*(\d+)\s*$",
+    )
+    .expect("valid relationship count filter regex")
+}
+
+/// BigQuery approximate percentile: pick the quantile boundary for `fraction`
+/// from `APPROX_QUANTILES`, using the fewest buckets (100, 1000, or 10000)
+/// that represent it exactly.
+fn bigquery_approx_quantile_sql(expr: &str, fraction: f64) -> String {
+    let buckets = [100u32, 1000]
+        .into_iter()
+        .find(|buckets| {
+            let scaled = fraction * f64::from(*buckets);
+            (scaled - scaled.round()).abs() < 1e-9
+        })
+        .unwrap_or(10_000);
+    let offset = (fraction * f64::from(buckets)).round();
+    format!("APPROX_QUANTILES({expr}, {buckets})[OFFSET({offset})]")
+}
+
+/// Like `Regex::replace_all`, but leaves matches that start inside a
+/// single-quoted string literal untouched.
+fn replace_outside_string_literals(
+    re: &regex::Regex,
+    sql: &str,
+    mut replace: impl FnMut(&regex::Captures) -> String,
+) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut last = 0;
+    for caps in re.captures_iter(sql) {
+        let whole = caps.get(0).expect("capture group 0");
+        // An odd number of quotes before the match means it sits inside a
+        // literal; doubled `''` escapes keep the count even.
+        if sql[..whole.start()].matches('\'').count() % 2 == 1 {
+            continue;
+        }
+        out.push_str(&sql[last..whole.start()]);
+        out.push_str(&replace(&caps));
+        last = whole.end();
+    }
+    out.push_str(&sql[last..]);
+    out
+}
+
 /// A semantic query definition
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub struct SemanticQuery {
     pub metrics: Vec<String>,
     pub dimensions: Vec<String>,
     pub filters: Vec<String>,
+    /// Structured filters, rendered as quoted SQL and applied like `filters`
+    pub typed_filters: Vec<Filter>,
+    /// Predicates placed in the `ON` clause of the join to the one model they
+    /// reference, so rows without a match survive the LEFT JOIN with NULLs
+    /// instead of being dropped as they would be by a WHERE filter
+    pub join_filters: Vec<String>,
     /// Segment references (e.g., "orders.completed")
     pub segments: Vec<String>,
+    /// Boolean composition of segments, ANDed with `segments` and `filters`
+    pub segment_expr: Option<SegmentFilter>,
     /// Table calculations (window functions)
     pub table_calculations: Vec<TableCalculation>,
     pub order_by: Vec<String>,
@@ -33,6 +95,109 @@ pub struct SemanticQuery {
     pub preagg_database: Option<String>,
     pub preagg_schema: Option<String>,
     pub skip_default_time_dimensions: bool,
+    /// Keep only the top N rows within each value of a dimension
+    pub top_n_per: Option<TopNPer>,
+    /// Project dimensions with `value_labels` as their display labels
+    pub apply_value_labels: bool,
+    /// How strictly `filters` are checked before being spliced into SQL
+    pub filter_validation: FilterValidation,
+    /// Append a grand-total row via UNION ALL, labelling categorical
+    /// dimensions with this value (other dimensions are NULL)
+    pub grand_total: Option<String>,
+    /// Use the dialect's approximate distinct count for `count_distinct`
+    /// metrics, trading exactness for speed (e.g. previews)
+    pub approximate: bool,
+    /// Time zone (e.g. `America/New_York`) whose current date anchors
+    /// relative date filters such as `last 7 days`
+    pub timezone: Option<String>,
+}
+
+/// Validation applied to query filter strings, for filters that come from
+/// untrusted input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FilterValidation {
+    /// Filters are trusted and used as written.
+    #[default]
+    Trusted,
+    /// Each filter must be a single boolean predicate with no subqueries.
+    Predicate,
+    /// Like `Predicate`, but subqueries are allowed.
+    PredicateWithSubqueries,
+}
+
+/// How [`SqlGenerator`] combines metrics from several models in one query.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum GenerationStrategy {
+    /// Join all models and aggregate once, using symmetric aggregates against
+    /// fan-out; metrics from models joined many-to-one are pre-aggregated.
+    #[default]
+    Auto,
+    /// Aggregate each model's metrics in its own grouped CTE, then join the
+    /// CTEs on the query dimensions.
+    PreAggregate,
+}
+
+/// How [`SqlGenerator`] renders row counts: `COUNT(*)` or `COUNT(1)`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CountStyle {
+    #[default]
+    Star,
+    One,
+}
+
+impl CountStyle {
+    /// The argument passed to `COUNT`.
+    pub fn argument(self) -> &'static str {
+        match self {
+            Self::Star => "*",
+            Self::One => "1",
+        }
+    }
+}
+
+/// How [`SqlGenerator`] treats time granularities outside its known set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum GranularityPolicy {
+    /// Reject granularities other than `second` through `year`.
+    #[default]
+    StrictEnum,
+    /// Pass unknown granularities (e.g. `isoweek`) verbatim to the
+    /// warehouse's truncation function.
+    Passthrough,
+}
+
+/// How a query's flat result rows fold into nested JSON.
+///
+/// Rows are grouped by each key column in turn, outermost first, and the
+/// measure columns sit at the innermost level. A query without dimensions has
+/// no keys and folds into a single object of measures.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResultShape {
+    /// Grouping key columns, outermost first, in SELECT order
+    pub keys: Vec<String>,
+    /// Measure columns at the innermost level, in SELECT order
+    pub measures: Vec<String>,
+}
+
+/// Top-N-per-group option: rank rows within `dimension` by `order_by`
+/// and keep the first `n` of each group.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TopNPer {
+    /// Partitioning dimension reference (e.g., "products.category")
+    pub dimension: String,
+    pub n: usize,
+    /// Ranking field, optionally followed by ASC/DESC (defaults to DESC)
+    pub order_by: String,
+}
+
+impl TopNPer {
+    pub fn new(dimension: impl Into<String>, n: usize, order_by: impl Into<String>) -> Self {
+        Self {
+            dimension: dimension.into(),
+            n,
+            order_by: order_by.into(),
+        }
+    }
 }
 
 impl SemanticQuery {
@@ -55,11 +220,26 @@ impl SemanticQuery {
         self
     }
 
+    pub fn with_typed_filters(mut self, typed_filters: Vec<Filter>) -> Self {
+        self.typed_filters = typed_filters;
+        self
+    }
+
+    pub fn with_join_filters(mut self, join_filters: Vec<String>) -> Self {
+        self.join_filters = join_filters;
+        self
+    }
+
     pub fn with_segments(mut self, segments: Vec<String>) -> Self {
         self.segments = segments;
         self
     }
 
+    pub fn with_segment_expr(mut self, segment_expr: SegmentFilter) -> Self {
+        self.segment_expr = Some(segment_expr);
+        self
+    }
+
     pub fn with_table_calculations(mut self, calcs: Vec<TableCalculation>) -> Self {
         self.table_calculations = calcs;
         self
@@ -104,6 +284,36 @@ impl SemanticQuery {
         self.skip_default_time_dimensions = skip_default_time_dimensions;
         self
     }
+
+    pub fn with_top_n_per(mut self, top_n_per: TopNPer) -> Self {
+        self.top_n_per = Some(top_n_per);
+        self
+    }
+
+    pub fn with_value_labels(mut self, apply_value_labels: bool) -> Self {
+        self.apply_value_labels = apply_value_labels;
+        self
+    }
+
+    pub fn with_filter_validation(mut self, filter_validation: FilterValidation) -> Self {
+        self.filter_validation = filter_validation;
+        self
+    }
+
+    pub fn with_grand_total(mut self, label: impl Into<String>) -> Self {
+        self.grand_total = Some(label.into());
+        self
+    }
+
+    pub fn with_approximate(mut self, approximate: bool) -> Self {
+        self.approximate = approximate;
+        self
+    }
+
+    pub fn with_timezone(mut self, timezone: impl Into<String>) -> Self {
+        self.timezone = Some(timezone.into());
+        self
+    }
 }
 
 /// Parsed dimension reference with optional granularity
@@ -113,6 +323,15 @@ struct DimensionRef {
     name: String,
     granularity: Option<String>,
     alias: String,
+    /// Model whose pass-through dimension was selected, when it differs from `model`.
+    exposed_by: Option<String>,
+}
+
+impl DimensionRef {
+    /// Model the query asked for, before following pass-through references.
+    fn query_model(&self) -> &str {
+        self.exposed_by.as_deref().unwrap_or(&self.model)
+    }
 }
 
 /// Parsed metric reference
@@ -128,16 +347,36 @@ struct MetricRef {
 pub struct SqlGenerator<'a> {
     graph: &'a SemanticGraph,
     dialect: DialectType,
+    max_join_hops: Option<usize>,
+    debug_comments: bool,
+    strategy: GenerationStrategy,
+    window_functions: bool,
+    count_style: CountStyle,
+    granularity_policy: GranularityPolicy,
+    /// The graph's expression dimensions, parsed on first use.
+    expression_dimensions: OnceCell<HashMap<String, Expression>>,
 }
 
 impl<'a> SqlGenerator<'a> {
     pub fn new(graph: &'a SemanticGraph) -> Self {
         Self {
             graph,
-            dialect: SOURCE_DIALECT,
+            dialect: graph.dialect().unwrap_or(SOURCE_DIALECT),
+            max_join_hops: graph.max_join_hops(),
+            debug_comments: false,
+            strategy: GenerationStrategy::Auto,
+            window_functions: true,
+            count_style: CountStyle::Star,
+            granularity_policy: GranularityPolicy::StrictEnum,
+            expression_dimensions: OnceCell::new(),
         }
     }
 
+    /// Generator emitting SQL for `dialect` instead of the graph's default.
+    pub fn new_with_dialect(graph: &'a SemanticGraph, dialect: DialectType) -> Self {
+        Self::new(graph).with_dialect(dialect)
+    }
+
     pub fn with_dialect(mut self, dialect: DialectType) -> Self {
         self.dialect = dialect;
         self
@@ -147,8 +386,95 @@ impl<'a> SqlGenerator<'a> {
         self.dialect
     }
 
+    /// Reject queries whose joins need more than `max_hops` relationship hops,
+    /// overriding the graph's limit.
+    pub fn with_max_join_hops(mut self, max_hops: usize) -> Self {
+        self.max_join_hops = Some(max_hops);
+        self
+    }
+
+    /// Annotate projections and joins with `/* ... */` comments naming the
+    /// semantic field or relationship they come from.
+    pub fn with_debug_comments(mut self, debug_comments: bool) -> Self {
+        self.debug_comments = debug_comments;
+        self
+    }
+
+    /// Choose how metrics spanning several models are aggregated.
+    pub fn with_strategy(mut self, strategy: GenerationStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Declare whether the target engine supports window functions. Without
+    /// them, percent-of-total table calculations divide by a grand total
+    /// computed in a `CROSS JOIN`ed subquery instead of `SUM(...) OVER ()`.
+    pub fn with_window_functions(mut self, window_functions: bool) -> Self {
+        self.window_functions = window_functions;
+        self
+    }
+
+    /// Render implicit row counts (`__row_count`, relationship count filters,
+    /// and argument-less count metrics) as `COUNT(*)` or `COUNT(1)`.
+    pub fn with_count_style(mut self, count_style: CountStyle) -> Self {
+        self.count_style = count_style;
+        self
+    }
+
+    /// Choose whether unknown time granularities are rejected or passed
+    /// through to the warehouse.
+    pub fn with_granularity_policy(mut self, granularity_policy: GranularityPolicy) -> Self {
+        self.granularity_policy = granularity_policy;
+        self
+    }
+
+    /// SQL substituted for [`Metric::ROW_COUNT`], relationship count filters
+    /// and argument-less count metrics.
+    fn row_count_sql(&self) -> String {
+        format!("COUNT({})", self.count_style.argument())
+    }
+
+    /// Whether `metric` counts rows: an unfiltered `count` without an argument.
+    fn is_row_count_metric(metric: &Metric) -> bool {
+        metric.r#type == MetricType::Simple
+            && metric.agg == Some(Aggregation::Count)
+            && metric
+                .sql
+                .as_deref()
+                .is_none_or(|sql| sql.is_empty() || sql == "*")
+            && metric.filters.is_empty()
+            && metric.non_additive_dimension.is_none()
+    }
+
+    /// Trailing provenance comment, or an empty string when comments are off.
+    fn debug_comment(&self, text: &str) -> String {
+        if self.debug_comments {
+            format!(" /* {} */", text.replace("*/", "* /"))
+        } else {
+            String::new()
+        }
+    }
+
+    fn find_join_path(&self, from: &str, to: &str) -> Result<crate::core::JoinPath> {
+        self.graph
+            .find_join_path_within(from, to, self.max_join_hops)
+    }
+
     /// Generate SQL from a semantic query
     pub fn generate(&self, query: &SemanticQuery) -> Result<String> {
+        if let Some(top_n_per) = &query.top_n_per {
+            return self.generate_top_n_per(query, top_n_per);
+        }
+        let typed_expanded = self.expand_typed_filters(query)?;
+        let query = typed_expanded.as_ref();
+        self.validate_filters(query)?;
+        let localized = self.localize_relative_dates(query)?;
+        let query = localized.as_ref();
+        if !self.window_functions && !query.table_calculations.is_empty() {
+            return self.generate_with_cross_join_totals(query);
+        }
+        let query = self.expand_relationship_count_filters(query)?;
+        let query = query.as_ref();
         let effective_dimensions = if query.skip_default_time_dimensions {
             query.dimensions.clone()
         } else {
@@ -160,6 +486,7 @@ impl<'a> SqlGenerator<'a> {
         let metric_refs = self.parse_metric_refs(&query.metrics)?;
         let direct_required_models = self.find_required_models(&dimension_refs, &metric_refs)?;
         self.ensure_queryable_sources(&direct_required_models)?;
+        self.ensure_time_dimension_for_window_metrics(&dimension_refs, &metric_refs)?;
         if self.has_cumulative_metrics(&metric_refs)? {
             return self.generate_with_cumulative(
                 query,
@@ -171,7 +498,7 @@ impl<'a> SqlGenerator<'a> {
 
         // Find all required models
         let mut required_models = self.find_required_models(&dimension_refs, &metric_refs)?;
-        let segment_filters = self.resolve_segments(&query.segments)?;
+        let segment_filters = self.resolve_segments(query)?;
         let all_filters: Vec<String> = query
             .filters
             .iter()
@@ -181,6 +508,9 @@ impl<'a> SqlGenerator<'a> {
         for model_name in self.find_filter_models(&all_filters) {
             required_models.insert(model_name);
         }
+        for model_name in self.find_filter_models(&query.join_filters) {
+            required_models.insert(model_name);
+        }
         for metric_ref in &metric_refs {
             self.collect_metric_referenced_models(
                 metric_ref,
@@ -223,7 +553,7 @@ impl<'a> SqlGenerator<'a> {
         // including zero-count rows for related metric models.
         let base_model = dimension_refs
             .first()
-            .map(|d| d.model.clone())
+            .map(|d| d.query_model().to_string())
             .or_else(|| metric_refs.first().map(|m| m.model.clone()))
             .ok_or_else(|| {
                 SidemanticError::Validation(
@@ -231,12 +561,19 @@ impl<'a> SqlGenerator<'a> {
                 )
             })?;
 
-        // Build join paths from base model to all other required models
-        let join_paths = self.build_join_paths(&base_model, &required_models)?;
+        // Build join paths from base model to all other required models. A query
+        // that only touches the base model skips join planning entirely.
+        let join_paths = if required_models.len() == 1 && required_models.contains(&base_model) {
+            HashMap::new()
+        } else {
+            self.build_join_paths(&base_model, &required_models)?
+        };
 
         // Detect fan-out risk for symmetric aggregate handling
         let fan_out_at_risk = self.detect_fan_out_risk(&base_model, &join_paths);
         let cte_models = self.collect_models_in_join_plan(&base_model, &join_paths);
+        let join_filters =
+            self.classify_join_filters(&query.join_filters, &base_model, &cte_models)?;
         let mut alias_collisions: HashMap<String, usize> = HashMap::new();
         for dim_ref in &dimension_refs {
             *alias_collisions.entry(dim_ref.alias.clone()).or_insert(0) += 1;
@@ -246,8 +583,7 @@ impl<'a> SqlGenerator<'a> {
                 .entry(metric_ref.alias.clone())
                 .or_insert(0) += 1;
         }
-        let (where_filters, having_filters) =
-            self.split_filters(&all_filters, &alias_collisions)?;
+        let (where_filters, having_filters) = self.split_filters(&all_filters);
         let (cte_where_filters, where_filters) =
             self.classify_filters_for_cte_pushdown(&where_filters, &cte_models)?;
         let mut raw_metric_dependencies = HashSet::new();
@@ -314,14 +650,20 @@ impl<'a> SqlGenerator<'a> {
             let raw_alias = self.metric_raw_alias(model, &metric_name, metric);
             let mut raw_expr =
                 self.normalize_cte_source_expression(&self.metric_raw_expression(metric, model));
-            if !metric.filters.is_empty() {
+            let (own_filters, _) = self.partition_metric_filters(&metric.filters, &model_name);
+            if !own_filters.is_empty() {
                 let metric_filter = self.normalize_metric_filters(
-                    &metric.filters,
+                    &own_filters,
                     &model_name,
                     &self.model_alias(&model_name),
                 );
                 raw_expr = format!("CASE WHEN {metric_filter} THEN {raw_expr} END");
             }
+            if let Some(snapshot) =
+                self.non_additive_snapshot_condition(metric, model, &dimension_refs)?
+            {
+                raw_expr = format!("CASE WHEN {snapshot} THEN {raw_expr} END");
+            }
             raw_model_columns
                 .entry(model_name)
                 .or_default()
@@ -378,20 +720,30 @@ impl<'a> SqlGenerator<'a> {
                 } else {
                     model.table_name().to_string()
                 };
+                let projection = match model.declared_columns() {
+                    Some(columns) => columns
+                        .iter()
+                        .map(|column| self.quote_identifier(column))
+                        .collect::<Vec<_>>()
+                        .join(",\n    "),
+                    None => "*".to_string(),
+                };
                 let cte_select = if let Some(raw_cols) = raw_model_columns.get(model_name) {
-                    format!("SELECT *,\n    {}", raw_cols.join(",\n    "))
+                    format!("SELECT {projection},\n    {}", raw_cols.join(",\n    "))
                 } else {
-                    "SELECT *".to_string()
+                    format!("SELECT {projection}")
                 };
-                let cte_where = if let Some(filters) = cte_where_filters.get(model_name) {
-                    let filter_sql = self.expand_filters_for_cte(model_name, filters)?;
-                    if filter_sql.is_empty() {
-                        String::new()
-                    } else {
-                        format!("\n  WHERE {}", filter_sql.join(" AND "))
-                    }
-                } else {
+                // Default filters stay inside the model's CTE, so joining the
+                // model keeps its LEFT JOIN semantics.
+                let mut filter_sql =
+                    self.expand_filters_for_cte(model_name, &model.default_filters)?;
+                if let Some(filters) = cte_where_filters.get(model_name) {
+                    filter_sql.extend(self.expand_filters_for_cte(model_name, filters)?);
+                }
+                let cte_where = if filter_sql.is_empty() {
                     String::new()
+                } else {
+                    format!("\n  WHERE {}", filter_sql.join(" AND "))
                 };
                 cte_defs.push(format!(
                     "{model_name}_cte AS (\n  {cte_select}\n  FROM {cte_source}{cte_where}\n)"
@@ -405,6 +757,7 @@ impl<'a> SqlGenerator<'a> {
         // Note: fan_out_at_risk is used below to apply symmetric aggregates
 
         // SELECT clause
+        let select_keyword_end = sql.len() + "SELECT".len();
         sql.push_str("SELECT\n");
         let mut select_parts = Vec::new();
 
@@ -441,16 +794,28 @@ impl<'a> SqlGenerator<'a> {
                     &available,
                 ));
             };
+            let sql_expr = match model.get_dimension(&dim_ref.name) {
+                Some(dimension) if query.apply_value_labels => dimension
+                    .value_label_case_sql(&sql_expr, self.dialect)
+                    .unwrap_or(sql_expr),
+                _ => sql_expr,
+            };
+            let sql_expr = match model.get_dimension(&dim_ref.name) {
+                Some(dimension) => dimension.null_label_sql(&sql_expr).unwrap_or(sql_expr),
+                None => sql_expr,
+            };
             let output_alias = self.output_alias(&dim_ref.model, &dim_ref.alias, &alias_collisions);
 
             select_parts.push(format!(
-                "  {} AS {}",
+                "  {} AS {}{}",
                 sql_expr,
-                self.quote_identifier(&output_alias)
+                self.quote_identifier(&output_alias),
+                self.debug_comment(&format!("{}.{} (dimension)", dim_ref.model, dim_ref.name))
             ));
         }
 
         // Add metrics to SELECT
+        let mut metric_select_exprs: HashMap<(String, String), String> = HashMap::new();
         for metric_ref in &metric_refs {
             let model = self.graph.get_model(&metric_ref.model).ok_or_else(|| {
                 let available: Vec<&str> = self.graph.models().map(|m| m.name.as_str()).collect();
@@ -463,31 +828,42 @@ impl<'a> SqlGenerator<'a> {
             let output_alias =
                 self.output_alias(&metric_ref.model, &metric_ref.alias, &alias_collisions);
             let raw_alias = self.metric_raw_alias(model, &metric_ref.name, metric);
-            let raw_col = format!("{alias}.{}", self.quote_identifier(&raw_alias));
+            let raw_col = self.filtered_raw_column(
+                metric,
+                &metric_ref.model,
+                format!("{alias}.{}", self.quote_identifier(&raw_alias)),
+            )?;
 
             let sql_expr = match metric.r#type {
                 MetricType::Simple if query.ungrouped => raw_col.clone(),
+                // Distinct counts are not inflated by fan-out, so the approximate
+                // form needs no symmetric aggregate.
+                MetricType::Simple
+                    if query.approximate && metric.agg == Some(Aggregation::CountDistinct) =>
+                {
+                    self.approx_count_distinct_sql(&raw_col)
+                }
                 MetricType::Simple if use_symmetric => {
                     // Use symmetric aggregate to prevent fan-out inflation
-                    let primary_key_expr = self.model_primary_key_expr(model, Some(&alias));
+                    let grain_expr = self.model_grain_expr(model, Some(&alias));
                     match metric.agg {
                         Some(Aggregation::Sum) => build_symmetric_aggregate_sql_with_key_expr(
                             &raw_alias,
-                            &primary_key_expr,
+                            &grain_expr,
                             SymmetricAggType::Sum,
                             Some(&alias),
                             self.symmetric_agg_dialect(),
                         ),
                         Some(Aggregation::Avg) => build_symmetric_aggregate_sql_with_key_expr(
                             &raw_alias,
-                            &primary_key_expr,
+                            &grain_expr,
                             SymmetricAggType::Avg,
                             Some(&alias),
                             self.symmetric_agg_dialect(),
                         ),
                         Some(Aggregation::Count) => build_symmetric_aggregate_sql_with_key_expr(
                             &raw_alias,
-                            &primary_key_expr,
+                            &grain_expr,
                             SymmetricAggType::Count,
                             Some(&alias),
                             self.symmetric_agg_dialect(),
@@ -495,7 +871,7 @@ impl<'a> SqlGenerator<'a> {
                         Some(Aggregation::CountDistinct) => {
                             build_symmetric_aggregate_sql_with_key_expr(
                                 &raw_alias,
-                                &primary_key_expr,
+                                &grain_expr,
                                 SymmetricAggType::CountDistinct,
                                 Some(&alias),
                                 self.symmetric_agg_dialect(),
@@ -504,20 +880,25 @@ impl<'a> SqlGenerator<'a> {
                         // Min/Max/None don't need symmetric aggregates
                         _ => {
                             if let Some(agg) = &metric.agg {
-                                format!("{}({raw_col})", agg.as_sql())
+                                self.aggregate_call_sql(agg, &raw_col)?
                             } else {
-                                metric.to_sql(Some(&alias))
+                                self.metric_sql(metric, &metric_ref.model, &alias)?
                             }
                         }
                     }
                 }
+                // Every base-model row is counted exactly once without fan-out,
+                // so an argument-less count is a plain row count.
+                MetricType::Simple
+                    if metric_ref.model == base_model && Self::is_row_count_metric(metric) =>
+                {
+                    self.row_count_sql()
+                }
                 MetricType::Simple => match &metric.agg {
-                    Some(Aggregation::CountDistinct) => format!("COUNT(DISTINCT {raw_col})"),
-                    Some(Aggregation::Count) => format!("COUNT({raw_col})"),
                     Some(agg) if agg != &Aggregation::Expression => {
-                        format!("{}({raw_col})", agg.as_sql())
+                        self.aggregate_call_sql(agg, &raw_col)?
                     }
-                    _ => metric.to_sql(Some(&alias)),
+                    _ => self.metric_sql(metric, &metric_ref.model, &alias)?,
                 },
                 MetricType::Derived => {
                     // For derived metrics, we need to expand referenced metrics
@@ -529,29 +910,43 @@ impl<'a> SqlGenerator<'a> {
                     let denom = metric.denominator.as_deref().unwrap_or("1");
                     let num_sql = self.expand_derived_metric(num, &metric_ref.model)?;
                     let denom_sql = self.expand_derived_metric(denom, &metric_ref.model)?;
-                    format!("({num_sql}) / NULLIF({denom_sql}, 0)")
+                    Self::ratio_sql(&num_sql, &denom_sql)
                 }
                 MetricType::Cumulative
                 | MetricType::TimeComparison
                 | MetricType::Retention
-                | MetricType::Cohort => {
+                | MetricType::Cohort
+                | MetricType::PctOfTotal => {
                     // Complex metric types use to_sql which generates placeholder SQL
-                    metric.to_sql(Some(&alias))
+                    self.metric_sql(metric, &metric_ref.model, &alias)?
                 }
-                MetricType::Conversion => metric.to_sql(Some(&alias)),
+                MetricType::Conversion => self.metric_sql(metric, &metric_ref.model, &alias)?,
             };
+            metric_select_exprs.insert(
+                (metric_ref.model.clone(), metric_ref.name.clone()),
+                sql_expr.clone(),
+            );
 
             select_parts.push(format!(
-                "  {} AS {}",
+                "  {} AS {}{}",
                 sql_expr,
-                self.quote_identifier(&output_alias)
+                self.quote_identifier(&output_alias),
+                self.debug_comment(&format!(
+                    "{}.{} (metric)",
+                    metric_ref.model, metric_ref.name
+                ))
             ));
         }
 
         // Add table calculations to SELECT
         for calc in &query.table_calculations {
             let calc_sql = calc.to_sql().map_err(SidemanticError::Validation)?;
-            select_parts.push(format!("  {} AS {}", calc_sql, calc.name));
+            select_parts.push(format!(
+                "  {} AS {}{}",
+                calc_sql,
+                calc.name,
+                self.debug_comment(&format!("{} (table calculation)", calc.name))
+            ));
         }
 
         sql.push_str(&select_parts.join(",\n"));
@@ -559,21 +954,31 @@ impl<'a> SqlGenerator<'a> {
 
         // FROM clause
         sql.push_str(&format!(
-            "FROM {}_cte AS {}\n",
+            "FROM {}_cte AS {}{}\n",
             base_model,
-            self.model_alias(&base_model)
+            self.model_alias(&base_model),
+            self.debug_comment(&format!("base model {base_model}"))
         ));
 
-        // JOIN clauses
-        let mut joined_steps: HashSet<(String, String)> = HashSet::new();
-        for (model_name, path) in &join_paths {
+        // JOIN clauses. Paths to different models share prefixes, so each
+        // model is joined once, at its first step; shorter paths go first so
+        // every step's source model is already in scope.
+        let mut ordered_paths: Vec<(&String, &JoinPath)> = join_paths.iter().collect();
+        ordered_paths.sort_by(|(a_name, a_path), (b_name, b_path)| {
+            a_path
+                .steps
+                .len()
+                .cmp(&b_path.steps.len())
+                .then_with(|| a_name.cmp(b_name))
+        });
+        let mut joined_models: HashSet<String> = HashSet::from([base_model.clone()]);
+        for (model_name, path) in ordered_paths {
             if model_name == &base_model {
                 continue;
             }
 
             for step in &path.steps {
-                let step_key = (step.from_model.clone(), step.to_model.clone());
-                if !joined_steps.insert(step_key) {
+                if !joined_models.insert(step.to_model.clone()) {
                     continue;
                 }
                 let from_alias = self.model_alias(&step.from_model);
@@ -594,17 +999,37 @@ impl<'a> SqlGenerator<'a> {
                     )?
                 };
 
+                let join_condition = match join_filters.get(&step.to_model) {
+                    Some(filters) => format!("{join_condition} AND {}", filters.join(" AND ")),
+                    None => join_condition,
+                };
+
+                // Filters pushed into the joined model's CTE must still drop
+                // unmatched rows, whatever join the relationship declares.
                 let join_type = if cte_where_filters
                     .get(&step.to_model)
                     .is_some_and(|filters| !filters.is_empty())
                 {
                     "INNER JOIN"
                 } else {
-                    "LEFT JOIN"
+                    step.join_type
+                        .map_or("LEFT JOIN", |join_type| join_type.as_sql())
+                };
+                let relationship = match step.relationship_type {
+                    RelationshipType::ManyToOne => "many_to_one",
+                    RelationshipType::OneToOne => "one_to_one",
+                    RelationshipType::OneToMany => "one_to_many",
+                    RelationshipType::ManyToMany => "many_to_many",
                 };
                 sql.push_str(&format!(
-                    "{join_type} {}_cte AS {} ON {}\n",
-                    step.to_model, to_alias, join_condition
+                    "{join_type} {}_cte AS {} ON {}{}\n",
+                    step.to_model,
+                    to_alias,
+                    join_condition,
+                    self.debug_comment(&format!(
+                        "{} -> {} ({relationship})",
+                        step.from_model, step.to_model
+                    ))
                 ));
             }
         }
@@ -622,9 +1047,25 @@ impl<'a> SqlGenerator<'a> {
         }
 
         if !having_filters.is_empty() {
+            let having_filters = self.render_having_filters(
+                &having_filters,
+                &metric_select_exprs,
+                &alias_collisions,
+            );
             sql.push_str(&format!("HAVING {}\n", having_filters.join(" AND ")));
         }
 
+        if let Some(label) = &query.grand_total {
+            return self.append_grand_total(
+                sql.trim_end(),
+                label,
+                query,
+                &dimension_refs,
+                &metric_refs,
+                &alias_collisions,
+            );
+        }
+
         // ORDER BY clause
         if !query.order_by.is_empty() {
             let order_by = self.rewrite_order_by_items(
@@ -636,15 +1077,228 @@ impl<'a> SqlGenerator<'a> {
             sql.push_str(&format!("ORDER BY {}\n", order_by.join(", ")));
         }
 
-        // LIMIT clause
-        if let Some(limit) = query.limit {
-            sql.push_str(&format!("LIMIT {limit}\n"));
+        sql.truncate(sql.trim_end().len());
+        self.push_limit_offset(
+            &mut sql,
+            Some(select_keyword_end),
+            !query.order_by.is_empty(),
+            query.limit,
+            query.offset,
+        );
+
+        Ok(sql)
+    }
+
+    /// Union a grouped query with an ungrouped total row whose dimension
+    /// columns hold `label` (categorical) or NULL (other types). Categorical
+    /// columns are cast to the dialect's string type on both sides, so the
+    /// label unions cleanly with numeric or boolean category values.
+    fn append_grand_total(
+        &self,
+        breakdown_sql: &str,
+        label: &str,
+        query: &SemanticQuery,
+        dimension_refs: &[DimensionRef],
+        metric_refs: &[MetricRef],
+        alias_collisions: &HashMap<String, usize>,
+    ) -> Result<String> {
+        if dimension_refs.is_empty() || metric_refs.is_empty() || query.ungrouped {
+            return Err(SidemanticError::Validation(
+                "grand_total requires a grouped query with dimensions and metrics".into(),
+            ));
         }
-        if let Some(offset) = query.offset {
-            sql.push_str(&format!("OFFSET {offset}\n"));
+        if query.top_n_per.is_some() || !query.table_calculations.is_empty() {
+            return Err(SidemanticError::Validation(
+                "grand_total cannot be combined with top_n_per or table calculations".into(),
+            ));
         }
 
-        Ok(sql.trim_end().to_string())
+        let total_query = SemanticQuery {
+            dimensions: Vec::new(),
+            order_by: Vec::new(),
+            limit: None,
+            offset: None,
+            skip_default_time_dimensions: true,
+            grand_total: None,
+            ..query.clone()
+        };
+        let total_sql = self.generate(&total_query)?;
+
+        let string_type = crate::core::string_type_sql(self.dialect);
+        let label_sql = format!("'{}'", label.replace('\'', "''"));
+        let mut breakdown_columns: Vec<String> = Vec::new();
+        let mut placeholders: Vec<String> = Vec::new();
+        for dim_ref in dimension_refs {
+            let categorical = self
+                .graph
+                .get_model(&dim_ref.model)
+                .and_then(|model| model.get_dimension(&dim_ref.name))
+                .is_some_and(|dimension| {
+                    dimension.r#type == crate::core::DimensionType::Categorical
+                        && dim_ref.granularity.is_none()
+                });
+            let alias = self.quote_identifier(&self.output_alias(
+                &dim_ref.model,
+                &dim_ref.alias,
+                alias_collisions,
+            ));
+            if categorical {
+                breakdown_columns.push(format!(
+                    "CAST(breakdown.{alias} AS {string_type}) AS {alias}"
+                ));
+                placeholders.push(format!("{label_sql} AS {alias}"));
+            } else {
+                breakdown_columns.push(format!("breakdown.{alias}"));
+                placeholders.push(format!("NULL AS {alias}"));
+            }
+        }
+        for metric_ref in metric_refs {
+            let alias = self.output_alias(&metric_ref.model, &metric_ref.alias, alias_collisions);
+            breakdown_columns.push(format!("breakdown.{}", self.quote_identifier(&alias)));
+        }
+
+        let mut sql = format!(
+            "SELECT\n  {}\nFROM (\n{breakdown_sql}\n) AS breakdown\nUNION ALL\nSELECT\n  {},\n  grand_total.*\nFROM (\n{total_sql}\n) AS grand_total\n",
+            breakdown_columns.join(",\n  "),
+            placeholders.join(",\n  ")
+        );
+
+        if !query.order_by.is_empty() {
+            let order_by = self.rewrite_order_by_items(
+                &query.order_by,
+                dimension_refs,
+                metric_refs,
+                alias_collisions,
+            );
+            sql.push_str(&format!("ORDER BY {}\n", order_by.join(", ")));
+        } else if self.dialect == DialectType::TSQL
+            && (query.limit.is_some() || query.offset.is_some())
+        {
+            // OFFSET/FETCH needs an ORDER BY, and a UNION may only order by
+            // its output columns.
+            sql.push_str("ORDER BY 1\n");
+        }
+        sql.truncate(sql.trim_end().len());
+        self.push_limit_offset(&mut sql, None, true, query.limit, query.offset);
+
+        Ok(sql)
+    }
+
+    /// Generate the grouped rows through whichever path the query needs
+    /// (cumulative, pre-aggregated, ...) and keep the top N per partition.
+    fn generate_top_n_per(&self, query: &SemanticQuery, top_n_per: &TopNPer) -> Result<String> {
+        if query.grand_total.is_some() {
+            return Err(SidemanticError::Validation(
+                "grand_total cannot be combined with top_n_per or table calculations".into(),
+            ));
+        }
+        let inner_query = SemanticQuery {
+            top_n_per: None,
+            order_by: Vec::new(),
+            limit: None,
+            offset: None,
+            ..query.clone()
+        };
+        let inner_sql = self.generate(&inner_query)?;
+        let (dimension_refs, metric_refs, alias_collisions) = self.output_refs(query)?;
+        self.wrap_top_n_per(
+            &inner_sql,
+            top_n_per,
+            query,
+            &dimension_refs,
+            &metric_refs,
+            &alias_collisions,
+        )
+    }
+
+    /// Wrap a grouped query so only the top N rows per partition survive.
+    fn wrap_top_n_per(
+        &self,
+        inner_sql: &str,
+        top_n_per: &TopNPer,
+        query: &SemanticQuery,
+        dimension_refs: &[DimensionRef],
+        metric_refs: &[MetricRef],
+        alias_collisions: &HashMap<String, usize>,
+    ) -> Result<String> {
+        if top_n_per.n == 0 {
+            return Err(SidemanticError::Validation(
+                "top_n_per requires n greater than 0".into(),
+            ));
+        }
+
+        let (dimension_model, dimension_name, dimension_granularity) =
+            self.graph.parse_reference(&top_n_per.dimension)?;
+        let Some(partition_ref) = dimension_refs.iter().find(|dim_ref| {
+            dim_ref.model == dimension_model
+                && dim_ref.name == dimension_name
+                && dim_ref.granularity == dimension_granularity
+        }) else {
+            return Err(SidemanticError::Validation(format!(
+                "top_n_per dimension '{}' must be one of the query dimensions",
+                top_n_per.dimension
+            )));
+        };
+        let partition = self.quote_identifier(&self.output_alias(
+            &partition_ref.model,
+            &partition_ref.alias,
+            alias_collisions,
+        ));
+
+        let rank_order = self.rewrite_order_by_item(
+            &top_n_per.order_by,
+            dimension_refs,
+            metric_refs,
+            alias_collisions,
+        );
+        let rank_order = if rank_order.contains(char::is_whitespace) {
+            rank_order
+        } else {
+            format!("{rank_order} DESC")
+        };
+
+        let mut columns: Vec<String> = dimension_refs
+            .iter()
+            .map(|dim_ref| self.output_alias(&dim_ref.model, &dim_ref.alias, alias_collisions))
+            .chain(metric_refs.iter().map(|metric_ref| {
+                self.output_alias(&metric_ref.model, &metric_ref.alias, alias_collisions)
+            }))
+            .map(|alias| self.quote_identifier(&alias))
+            .collect();
+        columns.extend(
+            query
+                .table_calculations
+                .iter()
+                .map(|calc| calc.name.clone()),
+        );
+
+        let mut sql = format!(
+            "SELECT\n  {}\nFROM (\n  SELECT\n    *,\n    ROW_NUMBER() OVER (PARTITION BY {partition} ORDER BY {rank_order}) AS __top_n_rank\n  FROM (\n{inner_sql}\n  ) AS top_n_base\n) AS top_n_ranked\nWHERE __top_n_rank <= {}\n",
+            columns.join(",\n  "),
+            top_n_per.n
+        );
+
+        if query.order_by.is_empty() {
+            sql.push_str(&format!("ORDER BY {partition}, __top_n_rank\n"));
+        } else {
+            let order_by = self.rewrite_order_by_items(
+                &query.order_by,
+                dimension_refs,
+                metric_refs,
+                alias_collisions,
+            );
+            sql.push_str(&format!("ORDER BY {}\n", order_by.join(", ")));
+        }
+        sql.truncate(sql.trim_end().len());
+        self.push_limit_offset(
+            &mut sql,
+            Some("SELECT".len()),
+            true,
+            query.limit,
+            query.offset,
+        );
+
+        Ok(sql)
     }
 
     fn build_default_join_condition_sql(
@@ -681,9 +1335,6 @@ impl<'a> SqlGenerator<'a> {
 
         for dim in dimensions {
             let (model, name, granularity) = self.graph.parse_reference(dim)?;
-            if let Some(granularity) = granularity.as_deref() {
-                self.validate_time_granularity(&model, &name, granularity)?;
-            }
 
             // Create alias: model_field or model_field__granularity
             let alias = if let Some(ref g) = granularity {
@@ -692,8 +1343,14 @@ impl<'a> SqlGenerator<'a> {
                 name.clone()
             };
 
+            let (target_model, name) = self.resolve_pass_through_dimension(model.clone(), name)?;
+            if let Some(granularity) = granularity.as_deref() {
+                self.validate_time_granularity(&target_model, &name, granularity)?;
+            }
+
             refs.push(DimensionRef {
-                model,
+                exposed_by: (target_model != model).then_some(model),
+                model: target_model,
                 name,
                 granularity,
                 alias,
@@ -703,6 +1360,47 @@ impl<'a> SqlGenerator<'a> {
         Ok(refs)
     }
 
+    /// Follow `ref:` pass-through dimensions to the model and dimension they
+    /// expose, so selecting them joins the referenced model.
+    fn resolve_pass_through_dimension(
+        &self,
+        mut model: String,
+        mut name: String,
+    ) -> Result<(String, String)> {
+        let mut seen = HashSet::new();
+        loop {
+            let Some(reference) = self
+                .graph
+                .get_model(&model)
+                .and_then(|m| m.get_dimension(&name))
+                .and_then(|dimension| dimension.reference.clone())
+            else {
+                return Ok((model, name));
+            };
+            if !seen.insert(format!("{model}.{name}")) {
+                return Err(SidemanticError::Validation(format!(
+                    "Circular dimension reference through '{model}.{name}'"
+                )));
+            }
+            let (target_model, target_name, _) = self.graph.parse_reference(&reference)?;
+            let target = self.graph.get_model(&target_model).ok_or_else(|| {
+                let available: Vec<&str> = self.graph.models().map(|m| m.name.as_str()).collect();
+                SidemanticError::model_not_found(&target_model, &available)
+            })?;
+            if target.get_dimension(&target_name).is_none() {
+                let available: Vec<&str> =
+                    target.dimensions.iter().map(|d| d.name.as_str()).collect();
+                return Err(SidemanticError::dimension_not_found(
+                    &target_model,
+                    &target_name,
+                    &available,
+                ));
+            }
+            model = target_model;
+            name = target_name;
+        }
+    }
+
     /// Parse metric references from query
     fn parse_metric_refs(&self, metrics: &[String]) -> Result<Vec<MetricRef>> {
         let mut refs = Vec::new();
@@ -741,18 +1439,214 @@ impl<'a> SqlGenerator<'a> {
         Ok(refs)
     }
 
-    /// Derive the output columns (alias + Postgres data type) a structured query projects,
-    /// matching `generate()`'s aliasing: bare leaf, or `{model}_{leaf}` on a leaf collision.
-    pub fn result_schema(&self, query: &SemanticQuery) -> Result<Vec<(String, String)>> {
-        let effective_dimensions = if query.skip_default_time_dimensions {
-            query.dimensions.clone()
-        } else {
+    /// Data-quality query returning primary key values that occur more than once.
+    ///
+    /// An empty result means the model's primary key is unique.
+    pub fn uniqueness_check(&self, model: &Model) -> String {
+        let keys = model.primary_keys().join(", ");
+        format!(
+            "SELECT {keys}, {count} AS row_count\nFROM {}\nGROUP BY {keys}\nHAVING {count} > 1",
+            self.model_from_clause(model, Some(&model.name)),
+            count = self.row_count_sql()
+        )
+    }
+
+    /// Data-quality query returning rows with a NULL primary key column.
+    ///
+    /// An empty result means every primary key column is populated.
+    pub fn not_null_check(&self, model: &Model) -> String {
+        let keys = model.primary_keys();
+        let conditions: Vec<String> = keys.iter().map(|key| format!("{key} IS NULL")).collect();
+        format!(
+            "SELECT {}\nFROM {}\nWHERE {}",
+            keys.join(", "),
+            self.model_from_clause(model, Some(&model.name)),
+            conditions.join(" OR ")
+        )
+    }
+
+    /// Exploration query returning up to `limit` raw rows of `model`'s public
+    /// dimensions, with the model's default filters applied.
+    pub fn preview(&self, model: &Model, limit: usize) -> Result<String> {
+        let columns: Vec<String> = model
+            .dimensions
+            .iter()
+            .filter(|dimension| dimension.public && dimension.reference.is_none())
+            .map(|dimension| {
+                let expr = self.normalize_cte_source_expression(
+                    dimension
+                        .window
+                        .as_deref()
+                        .unwrap_or_else(|| dimension.sql_expr()),
+                );
+                let alias = self.quote_identifier(&dimension.name);
+                if expr == alias {
+                    alias
+                } else {
+                    format!("{expr} AS {alias}")
+                }
+            })
+            .collect();
+        let projection = if columns.is_empty() {
+            "*".to_string()
+        } else {
+            columns.join(",\n  ")
+        };
+
+        let mut sql = format!(
+            "SELECT\n  {projection}\nFROM {}",
+            self.model_from_clause(model, Some(&model.name))
+        );
+        let filters = self.expand_filters_for_cte(&model.name, &model.default_filters)?;
+        if !filters.is_empty() {
+            sql.push_str(&format!("\nWHERE {}", filters.join(" AND ")));
+        }
+        self.push_limit_offset(&mut sql, Some("SELECT".len()), false, Some(limit), None);
+        Ok(sql)
+    }
+
+    /// Profiling query estimating how many distinct values `dimension` has,
+    /// e.g. to fill in its `cardinality_hint`.
+    pub fn cardinality_probe(&self, model: &Model, dimension: &crate::core::Dimension) -> String {
+        let expr = self.qualified_dimension_sql(dimension, &model.name);
+        format!(
+            "SELECT {} AS cardinality\nFROM {}",
+            self.approx_count_distinct_sql(&expr),
+            self.model_from_clause(model, Some(&model.name))
+        )
+    }
+
+    /// Derive the output columns (alias + Postgres data type) a structured query projects,
+    /// matching `generate()`'s aliasing: bare leaf, or `{model}_{leaf}` on a leaf collision.
+    pub fn result_schema(&self, query: &SemanticQuery) -> Result<Vec<(String, String)>> {
+        let (dimension_refs, metric_refs, alias_collisions) = self.output_refs(query)?;
+
+        let mut columns: Vec<(String, String)> = Vec::new();
+        for dim_ref in &dimension_refs {
+            let alias = self.output_alias(&dim_ref.model, &dim_ref.alias, &alias_collisions);
+            columns.push((alias, self.dimension_ref_data_type(dim_ref).to_string()));
+        }
+        for metric_ref in &metric_refs {
+            let alias = self.output_alias(&metric_ref.model, &metric_ref.alias, &alias_collisions);
+            columns.push((alias, self.metric_ref_data_type(metric_ref).to_string()));
+        }
+        Ok(columns)
+    }
+
+    /// Physical tables a structured query reads, base model first, for
+    /// checking read permissions before running the generated SQL. Models
+    /// defined by SQL are listed as their parenthesized query.
+    pub fn physical_tables(&self, query: &SemanticQuery) -> Result<Vec<String>> {
+        let effective_dimensions = if query.skip_default_time_dimensions {
+            query.dimensions.clone()
+        } else {
+            self.apply_default_time_dimensions(&query.metrics, &query.dimensions)?
+        };
+        let dimension_refs = self.parse_dimension_refs(&effective_dimensions)?;
+        let metric_refs = self.parse_metric_refs(&query.metrics)?;
+
+        let mut tables = Vec::new();
+        for model_name in self.query_models(&dimension_refs, &metric_refs, query)? {
+            let model = self.graph.get_model(&model_name).ok_or_else(|| {
+                let available: Vec<&str> = self.graph.models().map(|m| m.name.as_str()).collect();
+                SidemanticError::model_not_found(&model_name, &available)
+            })?;
+            let table = self.model_from_clause(model, None);
+            if !tables.contains(&table) {
+                tables.push(table);
+            }
+        }
+        Ok(tables)
+    }
+
+    /// Describe how to fold a structured query's flat rows into nested JSON:
+    /// which output columns are grouping keys and which are measures. Column
+    /// names match `result_schema`.
+    pub fn result_shape(&self, query: &SemanticQuery) -> Result<ResultShape> {
+        let (dimension_refs, metric_refs, alias_collisions) = self.output_refs(query)?;
+        Ok(ResultShape {
+            keys: dimension_refs
+                .iter()
+                .map(|dim_ref| self.output_alias(&dim_ref.model, &dim_ref.alias, &alias_collisions))
+                .collect(),
+            measures: metric_refs
+                .iter()
+                .map(|metric_ref| {
+                    self.output_alias(&metric_ref.model, &metric_ref.alias, &alias_collisions)
+                })
+                .collect(),
+        })
+    }
+
+    /// Compile filters and segments on one model into a standalone WHERE
+    /// predicate over the model's own columns, for splicing into a caller's
+    /// query against the model's table. Segments may be given as `model.segment`
+    /// or just `segment`. Each condition is parenthesized and they are ANDed;
+    /// no conditions yields an empty string.
+    pub fn compile_filters(
+        &self,
+        model: &str,
+        filters: &[String],
+        segments: &[String],
+    ) -> Result<String> {
+        if self.graph.get_model(model).is_none() {
+            let available: Vec<&str> = self.graph.models().map(|m| m.name.as_str()).collect();
+            return Err(SidemanticError::model_not_found(model, &available));
+        }
+
+        let mut conditions = filters.to_vec();
+        for segment in segments {
+            let seg_ref = if segment.contains('.') {
+                segment.clone()
+            } else {
+                format!("{model}.{segment}")
+            };
+            conditions.push(self.resolve_segment_sql(&seg_ref)?);
+        }
+
+        for condition in &conditions {
+            if !self.metric_references(condition).is_empty() {
+                return Err(SidemanticError::Validation(format!(
+                    "Filter '{condition}' references a metric and cannot be compiled to a WHERE predicate"
+                )));
+            }
+            let mut others: Vec<String> = self
+                .find_filter_models(std::slice::from_ref(condition))
+                .into_iter()
+                .filter(|name| name != model)
+                .collect();
+            if !others.is_empty() {
+                others.sort();
+                return Err(SidemanticError::Validation(format!(
+                    "Filter '{condition}' references models other than '{model}': {}",
+                    others.join(", ")
+                )));
+            }
+        }
+
+        let expanded = self.expand_filters_for_cte(model, &conditions)?;
+        Ok(match expanded.as_slice() {
+            [single] => single.clone(),
+            _ => expanded
+                .iter()
+                .map(|condition| format!("({condition})"))
+                .collect::<Vec<_>>()
+                .join(" AND "),
+        })
+    }
+
+    /// Resolve the dimension and metric refs a query projects, plus the leaf alias
+    /// counts `output_alias` uses to disambiguate collisions.
+    fn output_refs(&self, query: &SemanticQuery) -> Result<OutputRefs> {
+        let effective_dimensions = if query.skip_default_time_dimensions {
+            query.dimensions.clone()
+        } else {
             self.apply_default_time_dimensions(&query.metrics, &query.dimensions)?
         };
         let dimension_refs = self.parse_dimension_refs(&effective_dimensions)?;
         let metric_refs = self.parse_metric_refs(&query.metrics)?;
         // Reject queries `generate`/`compile` would refuse (e.g. refs from two unrelated
-        // models with no join path) instead of returning a schema for an impossible query.
+        // models with no join path) instead of describing an impossible query.
         self.ensure_query_joinable(&dimension_refs, &metric_refs, query)?;
 
         let mut alias_collisions: HashMap<String, usize> = HashMap::new();
@@ -764,17 +1658,7 @@ impl<'a> SqlGenerator<'a> {
                 .entry(metric_ref.alias.clone())
                 .or_insert(0) += 1;
         }
-
-        let mut columns: Vec<(String, String)> = Vec::new();
-        for dim_ref in &dimension_refs {
-            let alias = self.output_alias(&dim_ref.model, &dim_ref.alias, &alias_collisions);
-            columns.push((alias, self.dimension_ref_data_type(dim_ref).to_string()));
-        }
-        for metric_ref in &metric_refs {
-            let alias = self.output_alias(&metric_ref.model, &metric_ref.alias, &alias_collisions);
-            columns.push((alias, self.metric_ref_data_type(metric_ref).to_string()));
-        }
-        Ok(columns)
+        Ok((dimension_refs, metric_refs, alias_collisions))
     }
 
     /// Validate that every model a query references is joinable from a base model,
@@ -787,8 +1671,20 @@ impl<'a> SqlGenerator<'a> {
         metric_refs: &[MetricRef],
         query: &SemanticQuery,
     ) -> Result<()> {
+        self.query_models(dimension_refs, metric_refs, query)
+            .map(|_| ())
+    }
+
+    /// Models a query reads: the base model first, then every referenced or
+    /// intermediate model on its join paths, by name.
+    fn query_models(
+        &self,
+        dimension_refs: &[DimensionRef],
+        metric_refs: &[MetricRef],
+        query: &SemanticQuery,
+    ) -> Result<Vec<String>> {
         let mut required_models = self.find_required_models(dimension_refs, metric_refs)?;
-        let segment_filters = self.resolve_segments(&query.segments)?;
+        let segment_filters = self.resolve_segments(query)?;
         let all_filters: Vec<String> = query
             .filters
             .iter()
@@ -798,6 +1694,9 @@ impl<'a> SqlGenerator<'a> {
         for model_name in self.find_filter_models(&all_filters) {
             required_models.insert(model_name);
         }
+        for model_name in self.find_filter_models(&query.join_filters) {
+            required_models.insert(model_name);
+        }
         for metric_ref in metric_refs {
             self.collect_metric_referenced_models(
                 metric_ref,
@@ -810,12 +1709,15 @@ impl<'a> SqlGenerator<'a> {
         // Dimension-first base selection, mirroring `generate`.
         let base_model = dimension_refs
             .first()
-            .map(|d| d.model.clone())
+            .map(|d| d.query_model().to_string())
             .or_else(|| metric_refs.first().map(|m| m.model.clone()));
-        if let Some(base_model) = base_model {
-            self.build_join_paths(&base_model, &required_models)?;
-        }
-        Ok(())
+        let Some(base_model) = base_model else {
+            return Ok(Vec::new());
+        };
+        let join_paths = self.build_join_paths(&base_model, &required_models)?;
+        let mut models = self.collect_models_in_join_plan(&base_model, &join_paths);
+        models[1..].sort();
+        Ok(models)
     }
 
     fn dimension_ref_data_type(&self, dim_ref: &DimensionRef) -> &'static str {
@@ -863,7 +1765,14 @@ impl<'a> SqlGenerator<'a> {
         const VALID_GRANULARITIES: &[&str] = &[
             "second", "minute", "hour", "day", "week", "month", "quarter", "year",
         ];
-        if !VALID_GRANULARITIES.contains(&granularity) {
+        let known = VALID_GRANULARITIES.contains(&granularity);
+        // Passed-through grains are spliced into SQL, so they must still be
+        // plain words.
+        let passthrough = self.granularity_policy == GranularityPolicy::Passthrough
+            && granularity
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !known && !passthrough {
             return Err(SidemanticError::Validation(format!(
                 "Invalid time granularity '{granularity}'"
             )));
@@ -1042,7 +1951,7 @@ impl<'a> SqlGenerator<'a> {
                 .into_iter()
                 .flatten()
                 .collect(),
-            MetricType::Cumulative | MetricType::TimeComparison => {
+            MetricType::Cumulative | MetricType::TimeComparison | MetricType::PctOfTotal => {
                 [metric.base_metric.as_deref(), metric.sql.as_deref()]
                     .into_iter()
                     .flatten()
@@ -1151,6 +2060,7 @@ impl<'a> SqlGenerator<'a> {
 
         for dim in dimension_refs {
             models.insert(dim.model.clone());
+            models.extend(dim.exposed_by.clone());
         }
 
         for metric in metric_refs {
@@ -1160,6 +2070,295 @@ impl<'a> SqlGenerator<'a> {
         Ok(models)
     }
 
+    /// Reject filters that are not a single boolean predicate when the query
+    /// asks for validation. Relationship count filters are structured and
+    /// always accepted.
+    fn validate_filters(&self, query: &SemanticQuery) -> Result<()> {
+        let allow_subqueries = match query.filter_validation {
+            FilterValidation::Trusted => return Ok(()),
+            FilterValidation::Predicate => false,
+            FilterValidation::PredicateWithSubqueries => true,
+        };
+        let count_re = relationship_count_filter_regex();
+        let filters = query
+            .filters
+            .iter()
+            .chain(&query.join_filters)
+            .filter(|filter| !count_re.is_match(filter))
+            .map(|filter| self.expand_date_range_filter(filter))
+            .collect::<Result<Vec<_>>>()?;
+        if filters.is_empty() {
+            return Ok(());
+        }
+
+        // Parse with the target dialect's tokenizer, so quoting, escapes and
+        // comments are read the way the database will read them. Wrapped in
+        // parentheses, the filter must close them itself to parse as one
+        // WHERE expression of one SELECT.
+        let parsed = parse_all_with_large_stack(
+            filters
+                .iter()
+                .map(|filter| format!("SELECT 1 WHERE ({filter})"))
+                .collect(),
+            self.dialect,
+        )?;
+        for (filter, statements) in filters.iter().zip(parsed) {
+            self.validate_filter_predicate(filter, statements, allow_subqueries)?;
+        }
+        Ok(())
+    }
+
+    fn validate_filter_predicate(
+        &self,
+        filter: &str,
+        statements: std::result::Result<Vec<Expression>, String>,
+        allow_subqueries: bool,
+    ) -> Result<()> {
+        let reject = |reason: &str| {
+            Err(SidemanticError::Validation(format!(
+                "Rejected filter '{filter}': {reason}"
+            )))
+        };
+
+        let Ok(statements) = statements else {
+            return reject("not a valid predicate expression");
+        };
+        let [Expression::Select(select)] = statements.as_slice() else {
+            return reject("not a single predicate expression");
+        };
+        let Some(predicate) = select
+            .where_clause
+            .as_ref()
+            .map(|where_clause| &where_clause.this)
+        else {
+            return reject("not a single predicate expression");
+        };
+        let unwrapped = match predicate {
+            Expression::Paren(paren) => &paren.this,
+            other => other,
+        };
+        if matches!(unwrapped, Expression::Literal(_)) {
+            return reject("not a boolean predicate");
+        }
+        let is_query = |node: &Expression| {
+            matches!(
+                node,
+                Expression::Select(_)
+                    | Expression::Subquery(_)
+                    | Expression::Union(_)
+                    | Expression::Intersect(_)
+                    | Expression::Except(_)
+                    | Expression::Exists(_)
+            )
+        };
+        if !allow_subqueries && predicate.contains(is_query) {
+            return reject("subqueries are not allowed");
+        }
+        // A comment could swallow the SQL that follows the filter.
+        let has_comment = Dialect::get(self.dialect)
+            .tokenize(filter)
+            .map_or(true, |tokens| {
+                tokens.iter().any(|token| {
+                    !token.comments.is_empty()
+                        || !token.trailing_comments.is_empty()
+                        || matches!(
+                            token.token_type,
+                            TokenType::BlockComment | TokenType::LineComment
+                        )
+                })
+            });
+        if has_comment {
+            return reject("comments are not allowed");
+        }
+        Ok(())
+    }
+
+    /// Render `typed_filters` as SQL predicates on their canonical `model.field`
+    /// references and append them to the string filters.
+    fn expand_typed_filters<'q>(&self, query: &'q SemanticQuery) -> Result<Cow<'q, SemanticQuery>> {
+        if query.typed_filters.is_empty() {
+            return Ok(Cow::Borrowed(query));
+        }
+
+        let mut rewritten = query.clone();
+        for filter in std::mem::take(&mut rewritten.typed_filters) {
+            let (model_name, field, granularity) = self.graph.parse_reference(filter.field())?;
+            if granularity.is_some() {
+                return Err(SidemanticError::Validation(format!(
+                    "Filter field '{}' cannot specify a granularity",
+                    filter.field()
+                )));
+            }
+            let model = self.graph.get_model(&model_name).ok_or_else(|| {
+                let available: Vec<&str> = self.graph.models().map(|m| m.name.as_str()).collect();
+                SidemanticError::model_not_found(&model_name, &available)
+            })?;
+            if model.get_dimension(&field).is_none() && model.get_metric(&field).is_none() {
+                return Err(SidemanticError::Validation(format!(
+                    "Filter field '{}' must be a dimension or metric",
+                    filter.field()
+                )));
+            }
+            rewritten
+                .filters
+                .push(filter.to_sql(&format!("{model_name}.{field}"), self.dialect)?);
+        }
+        Ok(Cow::Owned(rewritten))
+    }
+
+    /// Expand relative date filters against the current date in
+    /// `query.timezone`, so day boundaries follow the user's locale rather
+    /// than the database session's.
+    fn localize_relative_dates<'q>(
+        &self,
+        query: &'q SemanticQuery,
+    ) -> Result<Cow<'q, SemanticQuery>> {
+        let Some(timezone) = query.timezone.as_deref() else {
+            return Ok(Cow::Borrowed(query));
+        };
+        let today = self.current_date_sql(timezone);
+        let current_date_re =
+            regex::Regex::new(r"\bCURRENT_DATE\b").expect("valid current date regex");
+
+        let mut rewritten = query.clone();
+        for filter in &mut rewritten.filters {
+            let expanded = self.expand_relative_dates(&self.expand_date_range_filter(filter)?);
+            if expanded != *filter {
+                *filter = current_date_re
+                    .replace_all(&expanded, regex::NoExpand(&today))
+                    .into_owned();
+            }
+        }
+        Ok(Cow::Owned(rewritten))
+    }
+
+    /// The current date in `timezone`.
+    fn current_date_sql(&self, timezone: &str) -> String {
+        let timezone = timezone.replace('\'', "''");
+        match self.dialect {
+            DialectType::BigQuery => format!("CURRENT_DATE('{timezone}')"),
+            DialectType::Snowflake => {
+                format!("CAST(CONVERT_TIMEZONE('{timezone}', CURRENT_TIMESTAMP()) AS DATE)")
+            }
+            _ => format!("CAST(CURRENT_TIMESTAMP AT TIME ZONE '{timezone}' AS DATE)"),
+        }
+    }
+
+    /// Rewrite `parent HAVING COUNT(child) > n` filters into a key membership
+    /// predicate on the parent, grouped over the child's foreign key.
+    fn expand_relationship_count_filters<'q>(
+        &self,
+        query: &'q SemanticQuery,
+    ) -> Result<Cow<'q, SemanticQuery>> {
+        let count_re = relationship_count_filter_regex();
+        if !query.filters.iter().any(|filter| count_re.is_match(filter)) {
+            return Ok(Cow::Borrowed(query));
+        }
+
+        let mut rewritten = query.clone();
+        for filter in &mut rewritten.filters {
+            let Some(cap) = count_re.captures(filter) else {
+                continue;
+            };
+            let count: u64 = cap[4].parse().map_err(|_| {
+                SidemanticError::Validation(format!("Invalid count in filter '{filter}'"))
+            })?;
+            *filter = self.relationship_count_predicate(&cap[1], &cap[2], &cap[3], count)?;
+        }
+        Ok(Cow::Owned(rewritten))
+    }
+
+    fn relationship_count_predicate(
+        &self,
+        parent: &str,
+        child: &str,
+        op: &str,
+        count: u64,
+    ) -> Result<String> {
+        let available = || {
+            self.graph
+                .models()
+                .map(|m| m.name.as_str())
+                .collect::<Vec<_>>()
+        };
+        if self.graph.get_model(parent).is_none() {
+            return Err(SidemanticError::model_not_found(parent, &available()));
+        }
+        let child_model = self
+            .graph
+            .get_model(child)
+            .ok_or_else(|| SidemanticError::model_not_found(child, &available()))?;
+
+        let path = self.find_join_path(parent, child)?;
+        let step = match path.steps.as_slice() {
+            [step]
+                if step.relationship_type == RelationshipType::OneToMany
+                    && step.custom_condition.is_none()
+                    && !step.to_keys.is_empty()
+                    && step.from_keys.len() == step.to_keys.len() =>
+            {
+                step
+            }
+            _ => {
+                return Err(SidemanticError::Validation(format!(
+                    "Counting '{child}' per '{parent}' requires a direct one_to_many relationship from '{parent}' to '{child}'"
+                )))
+            }
+        };
+
+        let parent_alias = self.model_alias(parent);
+        let parent_keys: Vec<String> = step
+            .from_keys
+            .iter()
+            .map(|key| format!("{parent_alias}.{key}"))
+            .collect();
+        let parent_key_sql = match parent_keys.as_slice() {
+            [key] => key.clone(),
+            keys => format!("({})", keys.join(", ")),
+        };
+        let child_keys = step.to_keys.join(", ");
+        let child_keys_present = match step.to_keys.as_slice() {
+            [key] => format!("{key} IS NOT NULL"),
+            keys => format!(
+                "NOT ({})",
+                keys.iter()
+                    .map(|key| format!("{key} IS NULL"))
+                    .collect::<Vec<_>>()
+                    .join(" OR ")
+            ),
+        };
+        let child_source = self.model_from_clause(child_model, Some("t"));
+        // Children hidden by the model's default filters don't count.
+        let mut child_filters = vec![child_keys_present];
+        child_filters
+            .extend(self.expand_filters_for_cte(&child_model.name, &child_model.default_filters)?);
+        let child_where = child_filters.join(" AND ");
+
+        // Parents without children never appear in the grouped subquery, so
+        // when a count of zero satisfies the comparison, exclude the parents
+        // that fail it instead.
+        let op = if op == "!=" { "<>" } else { op };
+        let zero_matches = match op {
+            ">=" | "=" => count == 0,
+            "<>" => count != 0,
+            "<" => count > 0,
+            "<=" => true,
+            _ => false,
+        };
+        let (membership, having) = if zero_matches {
+            (
+                "NOT IN",
+                format!("NOT ({} {op} {count})", self.row_count_sql()),
+            )
+        } else {
+            ("IN", format!("{} {op} {count}", self.row_count_sql()))
+        };
+
+        Ok(format!(
+            "{parent_key_sql} {membership} (SELECT {child_keys} FROM {child_source} WHERE {child_where} GROUP BY {child_keys} HAVING {having})"
+        ))
+    }
+
     fn find_filter_models(&self, filters: &[String]) -> HashSet<String> {
         let mut models = HashSet::new();
         let ref_re = regex::Regex::new(r"\b([A-Za-z_][A-Za-z0-9_]*)\.([A-Za-z_][A-Za-z0-9_]*)\b")
@@ -1212,6 +2411,9 @@ impl<'a> SqlGenerator<'a> {
         for expr in &exprs {
             self.collect_models_from_sql_references(expr, models);
         }
+        for filter in &metric.filters {
+            self.collect_models_from_sql_references(filter, models);
+        }
 
         if metric.r#type == MetricType::Simple {
             visiting.remove(&key);
@@ -1276,7 +2478,7 @@ impl<'a> SqlGenerator<'a> {
         let mut paths = HashMap::new();
 
         for model in required_models {
-            let path = self.graph.find_join_path(base_model, model)?;
+            let path = self.find_join_path(base_model, model)?;
             paths.insert(model.clone(), path);
         }
 
@@ -1317,7 +2519,7 @@ impl<'a> SqlGenerator<'a> {
                 if metric.sql.as_deref().is_none_or(str::is_empty)
                     || metric.sql.as_deref() == Some("*") =>
             {
-                self.model_primary_key_expr(model, None)
+                self.model_grain_expr(model, None)
             }
             Some(Aggregation::Count)
                 if metric.sql.as_deref().is_none_or(str::is_empty)
@@ -1462,39 +2664,38 @@ impl<'a> SqlGenerator<'a> {
                     &metric_ref.model,
                     deps,
                 )?;
+                for (is_aggregate, segment) in Self::split_aggregate_calls(metric.sql_expr()) {
+                    if !is_aggregate {
+                        self.collect_metric_token_dependencies(
+                            &segment,
+                            &metric_ref.model,
+                            deps,
+                            visiting,
+                        )?;
+                    }
+                }
             }
             MetricType::Derived => {
-                let ref_re = regex::Regex::new(
-                    r"\b([A-Za-z_][A-Za-z0-9_]*\.[A-Za-z_][A-Za-z0-9_]*|[A-Za-z_][A-Za-z0-9_]*)\b",
-                )
-                .expect("valid metric token regex");
-                for cap in ref_re.captures_iter(metric.sql_expr()) {
-                    let Some(token_match) = cap.get(1) else {
-                        continue;
-                    };
-                    let token = token_match.as_str();
-                    let Some((model, name, graph_metric)) =
-                        self.resolve_metric_reference_location(token, &metric_ref.model)?
-                    else {
-                        continue;
-                    };
-                    self.collect_inline_metric_column_dependencies(
-                        &MetricRef {
-                            model,
-                            name: name.clone(),
-                            alias: name,
-                            graph_metric,
-                        },
-                        deps,
-                        visiting,
-                    )?;
-                }
+                self.collect_metric_token_dependencies(
+                    metric.sql_expr(),
+                    &metric_ref.model,
+                    deps,
+                    visiting,
+                )?;
             }
             MetricType::Ratio => {
                 for expr in [metric.numerator.as_deref(), metric.denominator.as_deref()]
                     .into_iter()
                     .flatten()
                 {
+                    if Self::is_inline_aggregate_expression(expr) {
+                        self.collect_inline_metric_column_dependencies_from_expr(
+                            expr,
+                            &metric_ref.model,
+                            deps,
+                        )?;
+                        continue;
+                    }
                     if let Some((model, name, graph_metric)) =
                         self.resolve_metric_reference_location(expr, &metric_ref.model)?
                     {
@@ -1518,16 +2719,52 @@ impl<'a> SqlGenerator<'a> {
         Ok(())
     }
 
-    fn collect_inline_metric_column_dependencies_from_expr(
+    /// Collect column dependencies for every metric referenced in `expr`.
+    fn collect_metric_token_dependencies(
         &self,
         expr: &str,
         default_model: &str,
         deps: &mut HashSet<(String, String)>,
+        visiting: &mut HashSet<(String, String, bool)>,
     ) -> Result<()> {
-        let ref_re = regex::Regex::new(r"\b([A-Za-z_][A-Za-z0-9_]*)\.([A-Za-z_][A-Za-z0-9_]*)\b")
-            .expect("valid model.field regex");
-        for cap in ref_re.captures_iter(expr) {
-            let Some(model_match) = cap.get(1) else {
+        let ref_re = regex::Regex::new(
+            r"\b([A-Za-z_][A-Za-z0-9_]*\.[A-Za-z_][A-Za-z0-9_]*|[A-Za-z_][A-Za-z0-9_]*)\b",
+        )
+        .expect("valid metric token regex");
+        for cap in ref_re.captures_iter(expr) {
+            let Some(token_match) = cap.get(1) else {
+                continue;
+            };
+            let token = token_match.as_str();
+            let Some((model, name, graph_metric)) =
+                self.resolve_metric_reference_location(token, default_model)?
+            else {
+                continue;
+            };
+            self.collect_inline_metric_column_dependencies(
+                &MetricRef {
+                    model,
+                    name: name.clone(),
+                    alias: name,
+                    graph_metric,
+                },
+                deps,
+                visiting,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn collect_inline_metric_column_dependencies_from_expr(
+        &self,
+        expr: &str,
+        default_model: &str,
+        deps: &mut HashSet<(String, String)>,
+    ) -> Result<()> {
+        let ref_re = regex::Regex::new(r"\b([A-Za-z_][A-Za-z0-9_]*)\.([A-Za-z_][A-Za-z0-9_]*)\b")
+            .expect("valid model.field regex");
+        for cap in ref_re.captures_iter(expr) {
+            let Some(model_match) = cap.get(1) else {
                 continue;
             };
             let Some(field_match) = cap.get(2) else {
@@ -1685,10 +2922,12 @@ impl<'a> SqlGenerator<'a> {
         trimmed.to_string()
     }
 
-    fn model_primary_key_expr(&self, model: &crate::core::Model, alias: Option<&str>) -> String {
-        let primary_keys = model.primary_keys();
-        if primary_keys.len() <= 1 {
-            return primary_keys
+    /// Row identity for deduplication: the model's grain column, or its grain
+    /// columns concatenated when the grain is composite.
+    fn model_grain_expr(&self, model: &crate::core::Model, alias: Option<&str>) -> String {
+        let grain = model.grain_columns();
+        if grain.len() <= 1 {
+            return grain
                 .first()
                 .map(|column| match alias {
                     Some(alias) => format!("{alias}.{column}"),
@@ -1697,7 +2936,7 @@ impl<'a> SqlGenerator<'a> {
                 .unwrap_or_else(|| model.primary_key.clone());
         }
 
-        let parts = primary_keys
+        let parts = grain
             .iter()
             .flat_map(|column| {
                 let qualified = match alias {
@@ -1727,7 +2966,9 @@ impl<'a> SqlGenerator<'a> {
                 || metric.r#type == MetricType::Conversion
                 || metric.r#type == MetricType::Retention
                 || metric.r#type == MetricType::Cohort
+                || metric.r#type == MetricType::PctOfTotal
                 || (metric.r#type == MetricType::Ratio && metric.offset_window.is_some())
+                || self.depends_on_window_metric(metric, &metric_ref.model, &mut HashSet::new())?
             {
                 return Ok(true);
             }
@@ -1735,6 +2976,220 @@ impl<'a> SqlGenerator<'a> {
         Ok(false)
     }
 
+    /// Whether a derived or ratio metric depends, directly or through other
+    /// derived metrics, on a metric computed with a window function. Such
+    /// metrics are evaluated over the windowed results instead of being
+    /// inlined into the aggregate query.
+    fn depends_on_window_metric(
+        &self,
+        metric: &Metric,
+        model_name: &str,
+        visited: &mut HashSet<String>,
+    ) -> Result<bool> {
+        if !matches!(metric.r#type, MetricType::Derived | MetricType::Ratio)
+            || metric.offset_window.is_some()
+        {
+            return Ok(false);
+        }
+        for dependency in crate::core::extract_dependencies_with_context(
+            metric,
+            Some(self.graph),
+            Some(model_name),
+        ) {
+            let Some((dep_model, dep_name, graph_metric)) =
+                self.resolve_metric_reference_location(&dependency, model_name)?
+            else {
+                continue;
+            };
+            if !visited.insert(format!("{dep_model}.{dep_name}")) {
+                continue;
+            }
+            let dep_metric =
+                self.metric_for_model_with_source(&dep_model, &dep_name, graph_metric)?;
+            let is_window_metric = matches!(
+                dep_metric.r#type,
+                MetricType::Cumulative | MetricType::TimeComparison | MetricType::PctOfTotal
+            ) || (dep_metric.r#type == MetricType::Ratio
+                && dep_metric.offset_window.is_some());
+            if is_window_metric || self.depends_on_window_metric(dep_metric, &dep_model, visited)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Generate a query containing derived metrics built on window metrics:
+    /// the window metrics they reference are computed by an inner query and
+    /// the derived expressions are evaluated over its columns. Percent-of-total
+    /// metrics divide their base column by its `SUM(...) OVER ()` here too.
+    fn generate_with_window_dependent_metrics(
+        &self,
+        query: &SemanticQuery,
+        effective_dimensions: &[String],
+        dimension_refs: &[DimensionRef],
+        metric_refs: &[MetricRef],
+    ) -> Result<String> {
+        let mut inner_metrics: Vec<String> = Vec::new();
+        let mut seen_metrics: HashSet<String> = HashSet::new();
+        let mut select_exprs: Vec<String> = dimension_refs
+            .iter()
+            .map(|dim_ref| format!("base.{}", dim_ref.alias))
+            .collect();
+
+        for metric_ref in metric_refs {
+            let metric = self.metric_for_ref(metric_ref)?;
+            if metric.r#type != MetricType::PctOfTotal
+                && !self.depends_on_window_metric(metric, &metric_ref.model, &mut HashSet::new())?
+            {
+                let explicit_ref = format!("{}.{}", metric_ref.model, metric_ref.name);
+                if seen_metrics.insert(explicit_ref.clone()) {
+                    inner_metrics.push(explicit_ref);
+                }
+                select_exprs.push(format!("base.{}", metric_ref.alias));
+                continue;
+            }
+
+            let mut column_for = |reference: &str| -> Result<Option<String>> {
+                let Some((dep_model, dep_name, _)) =
+                    self.resolve_metric_reference_location(reference, &metric_ref.model)?
+                else {
+                    return Ok(None);
+                };
+                let qualified = format!("{dep_model}.{dep_name}");
+                if seen_metrics.insert(qualified.clone()) {
+                    inner_metrics.push(qualified);
+                }
+                Ok(Some(format!("base.{dep_name}")))
+            };
+
+            let expr = if metric.r#type == MetricType::PctOfTotal {
+                let base = metric.base_metric.as_deref().ok_or_else(|| {
+                    SidemanticError::Validation(format!(
+                        "pct_of_total metric '{}' requires base_metric",
+                        metric.name
+                    ))
+                })?;
+                let column = column_for(base)?.ok_or_else(|| {
+                    SidemanticError::Validation(format!(
+                        "pct_of_total metric '{}' references unknown metric '{base}'",
+                        metric.name
+                    ))
+                })?;
+                format!("{column} / NULLIF(SUM({column}) OVER (), 0)")
+            } else if metric.r#type == MetricType::Ratio {
+                let numerator = metric.numerator.as_deref().unwrap_or("1");
+                let denominator = metric.denominator.as_deref().unwrap_or("1");
+                let num_sql = column_for(numerator)?.unwrap_or_else(|| numerator.to_string());
+                let den_sql = column_for(denominator)?.unwrap_or_else(|| denominator.to_string());
+                format!("({num_sql}) / NULLIF({den_sql}, 0)")
+            } else {
+                self.expand_derived_metric_with(metric.sql_expr(), &mut column_for)?
+            };
+            select_exprs.push(format!("{expr} AS {}", metric_ref.alias));
+        }
+
+        let inner_query = Self::inner_query(query, inner_metrics, effective_dimensions);
+        let inner_sql = self.generate(&inner_query)?;
+
+        let mut sql = format!(
+            "SELECT\n  {}\nFROM (\n{inner_sql}\n) AS base",
+            select_exprs.join(",\n  ")
+        );
+        self.push_outer_order_and_limit(&mut sql, query);
+        Ok(sql)
+    }
+
+    /// Grouped query over `metrics` and `dimensions` that a window query wraps
+    /// as `base`, keeping the outer query's row filters, join filters and
+    /// segments so the window sees the same rows.
+    fn inner_query(
+        query: &SemanticQuery,
+        metrics: Vec<String>,
+        dimensions: &[String],
+    ) -> SemanticQuery {
+        let mut inner_query = SemanticQuery::new()
+            .with_metrics(metrics)
+            .with_dimensions(dimensions.to_vec())
+            .with_filters(query.filters.clone())
+            .with_join_filters(query.join_filters.clone())
+            .with_segments(query.segments.clone())
+            .with_ungrouped(false);
+        inner_query.segment_expr = query.segment_expr.clone();
+        inner_query.approximate = query.approximate;
+        inner_query
+    }
+
+    /// Append ORDER BY / LIMIT / OFFSET to a query selecting from a `base`
+    /// subquery, ordering by output aliases rather than semantic references.
+    fn push_outer_order_and_limit(&self, sql: &mut String, query: &SemanticQuery) {
+        if !query.order_by.is_empty() {
+            let order_parts: Vec<String> = query
+                .order_by
+                .iter()
+                .map(|order| {
+                    let mut tokens = order.split_whitespace();
+                    let field = tokens.next().unwrap_or(order);
+                    let alias = field.rsplit('.').next().unwrap_or(field);
+                    std::iter::once(alias)
+                        .chain(tokens)
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect();
+            sql.push_str(&format!("\nORDER BY {}", order_parts.join(", ")));
+        }
+        self.push_limit_offset(
+            sql,
+            None,
+            !query.order_by.is_empty(),
+            query.limit,
+            query.offset,
+        );
+    }
+
+    /// Generate a query whose percent-of-total table calculations divide by a
+    /// grand total from a `CROSS JOIN`ed subquery, for engines without window
+    /// functions.
+    fn generate_with_cross_join_totals(&self, query: &SemanticQuery) -> Result<String> {
+        let mut calc_exprs = Vec::with_capacity(query.table_calculations.len());
+        let mut total_exprs = Vec::with_capacity(query.table_calculations.len());
+        for calc in &query.table_calculations {
+            let field = match (&calc.calc_type, &calc.field, &calc.partition_by) {
+                (TableCalcType::PercentOfTotal, Some(field), None) => field,
+                _ => {
+                    return Err(SidemanticError::Validation(format!(
+                        "Table calculation '{}' requires window functions; only unpartitioned \
+                         percent_of_total calculations are supported without them",
+                        calc.name
+                    )))
+                }
+            };
+            let total_alias = format!("{}_total", calc.name);
+            total_exprs.push(format!("SUM({field}) AS {total_alias}"));
+            calc_exprs.push(format!(
+                "base.{field} * 100.0 / NULLIF(totals.{total_alias}, 0) AS {}",
+                calc.name
+            ));
+        }
+
+        let inner_query = SemanticQuery {
+            table_calculations: Vec::new(),
+            order_by: Vec::new(),
+            limit: None,
+            offset: None,
+            ..query.clone()
+        };
+        let inner_sql = self.generate(&inner_query)?;
+
+        let mut sql = format!(
+            "WITH base AS (\n{inner_sql}\n)\nSELECT\n  base.*,\n  {}\nFROM base\nCROSS JOIN (SELECT {} FROM base) AS totals",
+            calc_exprs.join(",\n  "),
+            total_exprs.join(", ")
+        );
+        self.push_outer_order_and_limit(&mut sql, query);
+        Ok(sql)
+    }
+
     fn needs_preaggregation_for_fanout(&self, metric_refs: &[MetricRef]) -> Result<bool> {
         if metric_refs.len() < 2 {
             return Ok(false);
@@ -1750,12 +3205,15 @@ impl<'a> SqlGenerator<'a> {
         if metric_models.len() < 2 {
             return Ok(false);
         }
+        if self.strategy == GenerationStrategy::PreAggregate {
+            return Ok(true);
+        }
 
         for i in 0..metric_models.len() {
             for model_b in metric_models.iter().skip(i + 1) {
                 let model_a = &metric_models[i];
                 for (from_model, to_model) in [(model_a, model_b), (model_b, model_a)] {
-                    if let Ok(path) = self.graph.find_join_path(from_model, to_model) {
+                    if let Ok(path) = self.find_join_path(from_model, to_model) {
                         if path
                             .steps
                             .iter()
@@ -1817,7 +3275,7 @@ impl<'a> SqlGenerator<'a> {
         let (pushdown_by_model, shared_filters) =
             self.classify_filters_for_cte_pushdown(&non_window_filters, &model_order)?;
 
-        let mut cte_defs = Vec::new();
+        let mut subquery_sqls = Vec::new();
         let mut cte_names = Vec::new();
         for model_name in &model_order {
             let cte_name = format!("{model_name}_preagg");
@@ -1845,7 +3303,12 @@ impl<'a> SqlGenerator<'a> {
                 })
                 .with_ungrouped(false)
                 .with_skip_default_time_dimensions(true);
-            let subquery_sql = self.generate(&subquery)?;
+            subquery_sqls.push(self.generate(&subquery)?);
+        }
+
+        let mut cte_defs = self.share_model_sources(&mut subquery_sqls);
+        cte_defs.extend(Self::hoist_subquery_ctes(&model_order, &mut subquery_sqls));
+        for (cte_name, subquery_sql) in cte_names.iter().zip(&subquery_sqls) {
             cte_defs.push(format!("{cte_name} AS (\n{subquery_sql}\n)"));
         }
 
@@ -1933,14 +3396,122 @@ impl<'a> SqlGenerator<'a> {
             sql.push_str(&format!("\nORDER BY {}", order_by.join(", ")));
         }
 
-        if let Some(limit) = query.limit {
-            sql.push_str(&format!("\nLIMIT {limit}"));
+        self.push_limit_offset(
+            &mut sql,
+            None,
+            !query.order_by.is_empty(),
+            query.limit,
+            query.offset,
+        );
+
+        Ok(sql)
+    }
+
+    /// Hoist SQL-defined models inlined by more than one pre-aggregated
+    /// subquery into a shared `{model}_source` CTE so their query runs once.
+    /// Returns the shared CTE definitions.
+    fn share_model_sources(&self, subquery_sqls: &mut [String]) -> Vec<String> {
+        let mut models: Vec<&Model> = self.graph.models().filter(|m| m.sql.is_some()).collect();
+        models.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut shared = Vec::new();
+        for model in models {
+            let Some(model_sql) = model.sql.as_deref() else {
+                continue;
+            };
+            let inline_source = format!("({model_sql}) AS t");
+            let readers = subquery_sqls
+                .iter()
+                .filter(|sql| sql.contains(&inline_source))
+                .count();
+            if readers < 2 {
+                continue;
+            }
+            let source_name = format!("{}_source", model.name);
+            for sql in subquery_sqls.iter_mut() {
+                *sql = sql.replace(&inline_source, &format!("{source_name} AS t"));
+            }
+            shared.push(format!("{source_name} AS (\n{model_sql}\n)"));
         }
-        if let Some(offset) = query.offset {
-            sql.push_str(&format!("\nOFFSET {offset}"));
+        shared
+    }
+
+    /// Move each pre-aggregated subquery's leading `WITH` CTEs to the outer
+    /// query, since not every dialect accepts a `WITH` nested inside a CTE.
+    ///
+    /// Identical model CTEs are shared; a same-named CTE whose definition
+    /// differs (e.g. different pushed-down filters) is renamed with the
+    /// subquery's model as prefix. Returns the hoisted CTE definitions.
+    fn hoist_subquery_ctes(model_order: &[String], subquery_sqls: &mut [String]) -> Vec<String> {
+        let mut hoisted: Vec<(String, String)> = Vec::new();
+        for (model_name, sql) in model_order.iter().zip(subquery_sqls.iter_mut()) {
+            let Some((ctes, main)) = Self::split_leading_ctes(sql) else {
+                continue;
+            };
+            let mut main = main.to_string();
+            for (name, body) in ctes {
+                if hoisted
+                    .iter()
+                    .any(|(existing, existing_body)| *existing == name && *existing_body == body)
+                {
+                    continue;
+                }
+                let mut hoisted_name = name.clone();
+                if hoisted.iter().any(|(existing, _)| *existing == name) {
+                    hoisted_name = format!("{model_name}_{name}");
+                    for keyword in ["FROM", "JOIN"] {
+                        main = main.replace(
+                            &format!("{keyword} {name} AS "),
+                            &format!("{keyword} {hoisted_name} AS "),
+                        );
+                    }
+                }
+                hoisted.push((hoisted_name, body));
+            }
+            *sql = main;
         }
+        hoisted
+            .into_iter()
+            .map(|(name, body)| format!("{name} AS {body}"))
+            .collect()
+    }
 
-        Ok(sql)
+    /// Split `WITH a AS (...), b AS (...) SELECT ...` into its CTEs, as
+    /// `(name, parenthesized body)` pairs, and the main statement.
+    fn split_leading_ctes(sql: &str) -> Option<(Vec<(String, String)>, &str)> {
+        let mut rest = sql.strip_prefix("WITH ")?;
+        let mut ctes = Vec::new();
+        loop {
+            let (name, _) = rest.split_once(" AS (")?;
+            if !Self::is_simple_identifier(name) {
+                return None;
+            }
+            let body_start = name.len() + " AS ".len();
+            let mut depth = 0usize;
+            let mut in_string = false;
+            let mut body_end = None;
+            for (offset, ch) in rest[body_start..].char_indices() {
+                match ch {
+                    '\'' => in_string = !in_string,
+                    '(' if !in_string => depth += 1,
+                    ')' if !in_string => {
+                        depth -= 1;
+                        if depth == 0 {
+                            body_end = Some(body_start + offset + 1);
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            let body_end = body_end?;
+            ctes.push((name.to_string(), rest[body_start..body_end].to_string()));
+            rest = &rest[body_end..];
+            match rest.strip_prefix(",\n") {
+                Some(next) => rest = next,
+                None => return Some((ctes, rest.trim_start())),
+            }
+        }
     }
 
     fn rewrite_filters_for_preaggregation(
@@ -1971,6 +3542,20 @@ impl<'a> SqlGenerator<'a> {
         dimension_refs: &[DimensionRef],
         metric_refs: &[MetricRef],
     ) -> Result<String> {
+        for metric_ref in metric_refs {
+            let metric = self.metric_for_ref(metric_ref)?;
+            if metric.r#type == MetricType::PctOfTotal
+                || self.depends_on_window_metric(metric, &metric_ref.model, &mut HashSet::new())?
+            {
+                return self.generate_with_window_dependent_metrics(
+                    query,
+                    effective_dimensions,
+                    dimension_refs,
+                    metric_refs,
+                );
+            }
+        }
+
         let mut base_metrics: Vec<String> = Vec::new();
         let mut seen_metrics: HashSet<String> = HashSet::new();
         let mut cumulative_metrics: Vec<MetricRef> = Vec::new();
@@ -2146,12 +3731,7 @@ impl<'a> SqlGenerator<'a> {
             );
         }
 
-        let inner_query = SemanticQuery::new()
-            .with_metrics(base_metrics.clone())
-            .with_dimensions(effective_dimensions.to_vec())
-            .with_filters(query.filters.clone())
-            .with_segments(query.segments.clone())
-            .with_ungrouped(false);
+        let inner_query = Self::inner_query(query, base_metrics.clone(), effective_dimensions);
 
         let inner_sql = self.generate(&inner_query)?;
         let mut select_exprs: Vec<String> = Vec::new();
@@ -2167,6 +3747,10 @@ impl<'a> SqlGenerator<'a> {
             select_exprs.push(format!("base.{alias}"));
             lag_cte_columns.push(alias);
         }
+        // A window metric that another one in the query is built on (e.g. MoM
+        // growth and its change) already comes computed from the inner query.
+        cumulative_metrics.retain(|metric_ref| !lag_cte_columns.contains(&metric_ref.alias));
+        time_comparison_metrics.retain(|metric_ref| !lag_cte_columns.contains(&metric_ref.alias));
 
         for metric_ref in &cumulative_metrics {
             let model = self.graph.get_model(&metric_ref.model).ok_or_else(|| {
@@ -2178,11 +3762,12 @@ impl<'a> SqlGenerator<'a> {
                 SidemanticError::metric_not_found(&metric_ref.model, &metric_ref.name, &available)
             })?;
 
-            let (order_col, _) = if let Some(window_order) = metric.window_order.as_ref() {
-                (format!("base.{window_order}"), None)
-            } else {
-                self.find_time_order_column(dimension_refs, Some(&metric_ref.model))?
-            };
+            let (order_col, order_granularity) =
+                if let Some(window_order) = metric.window_order.as_ref() {
+                    (format!("base.{window_order}"), None)
+                } else {
+                    self.find_time_order_column(dimension_refs, Some(&metric_ref.model))?
+                };
 
             if let Some(window_expr) = metric.window_expression.as_ref() {
                 let frame = metric
@@ -2226,7 +3811,38 @@ impl<'a> SqlGenerator<'a> {
                     crate::core::TimeGrain::Year => "year",
                 };
                 format!(
-                    "PARTITION BY DATE_TRUNC('{grain}', {order_col}) ORDER BY {order_col} ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW"
+                    "PARTITION BY {} ORDER BY {order_col} ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW",
+                    self.date_trunc_sql(grain, &order_col)
+                )
+            } else if let Some(periods) = metric.window_periods {
+                if periods == 0 {
+                    return Err(SidemanticError::Validation(format!(
+                        "Cumulative metric '{}' requires window_periods of at least 1",
+                        metric_ref.alias
+                    )));
+                }
+                // The frame counts rows, so each row must be exactly one period.
+                if let Some(grain) = metric.window_grain.as_ref() {
+                    if order_granularity.as_deref() != Some(grain.as_str()) {
+                        return Err(SidemanticError::Validation(format!(
+                            "Cumulative metric '{}' trails {periods} {grain} periods; query its time dimension at {grain} grain (e.g. __{grain})",
+                            metric_ref.alias,
+                            grain = grain.as_str()
+                        )));
+                    }
+                }
+                // Each group is its own series, so the frame never reaches
+                // into rows of another group.
+                let partition_cols =
+                    self.time_comparison_partition_columns(dimension_refs, &order_col);
+                let partition = if partition_cols.is_empty() {
+                    String::new()
+                } else {
+                    format!("PARTITION BY {} ", partition_cols.join(", "))
+                };
+                format!(
+                    "{partition}ORDER BY {order_col} ROWS BETWEEN {} PRECEDING AND CURRENT ROW",
+                    periods - 1
                 )
             } else if let Some(window) = metric.window.as_ref() {
                 let parts: Vec<&str> = window.split_whitespace().collect();
@@ -2253,6 +3869,7 @@ impl<'a> SqlGenerator<'a> {
                 .iter()
                 .map(|column| format!("base.{column}"))
                 .collect();
+            let mut offset_joins: Vec<String> = Vec::new();
 
             for metric_ref in &time_comparison_metrics {
                 let model = self.graph.get_model(&metric_ref.model).ok_or_else(|| {
@@ -2278,11 +3895,43 @@ impl<'a> SqlGenerator<'a> {
                     ))
                 })?;
                 let base_alias = self.metric_alias_from_ref(base_ref);
+                let prev_alias = format!("{}_prev_value", metric_ref.alias);
+                if let Some(time_offset) = metric.time_offset.as_deref() {
+                    // Custom offsets join each row to the one exactly one
+                    // calendar offset earlier, so months and years of uneven
+                    // length line up.
+                    let (count, unit) = Self::parse_time_offset(
+                        time_offset,
+                        time_granularity.as_deref().unwrap_or("day"),
+                    )
+                    .map_err(|reason| {
+                        SidemanticError::Validation(format!(
+                            "time_comparison metric '{}' has invalid time_offset '{time_offset}': {reason}",
+                            metric_ref.alias
+                        ))
+                    })?;
+                    let prior = format!("{}_prior", metric_ref.alias);
+                    let time_name = time_col.strip_prefix("base.").unwrap_or(&time_col);
+                    let mut conditions = vec![format!(
+                        "{prior}.{time_name} = {}",
+                        self.date_sub_sql(&time_col, count, unit)
+                    )];
+                    for column in self.time_comparison_partition_columns(dimension_refs, &time_col)
+                    {
+                        let name = column.strip_prefix("base.").unwrap_or(&column);
+                        conditions.push(format!("{prior}.{name} IS NOT DISTINCT FROM {column}"));
+                    }
+                    offset_joins.push(format!(
+                        "LEFT JOIN base AS {prior} ON {}",
+                        conditions.join(" AND ")
+                    ));
+                    lag_selects.push(format!("{prior}.{base_alias} AS {prev_alias}"));
+                    continue;
+                }
                 let lag_offset = self.calculate_lag_offset(
                     metric.comparison_type.as_ref(),
                     time_granularity.as_deref(),
                 );
-                let prev_alias = format!("{}_prev_value", metric_ref.alias);
                 let window_clause =
                     self.lag_window_clause(dimension_refs, &time_col, Some(lag_offset));
                 lag_selects.push(format!(
@@ -2321,11 +3970,22 @@ impl<'a> SqlGenerator<'a> {
             }
 
             let mut lag_cte_sql = String::new();
-            lag_cte_sql.push_str("WITH lag_cte AS (\n  SELECT\n    ");
-            lag_cte_sql.push_str(&lag_selects.join(",\n    "));
-            lag_cte_sql.push_str("\n  FROM (\n");
-            lag_cte_sql.push_str(&inner_sql);
-            lag_cte_sql.push_str("\n  ) AS base\n)");
+            if offset_joins.is_empty() {
+                lag_cte_sql.push_str("WITH lag_cte AS (\n  SELECT\n    ");
+                lag_cte_sql.push_str(&lag_selects.join(",\n    "));
+                lag_cte_sql.push_str("\n  FROM (\n");
+                lag_cte_sql.push_str(&inner_sql);
+                lag_cte_sql.push_str("\n  ) AS base\n)");
+            } else {
+                // Offset joins read the base rows twice, so name them once.
+                lag_cte_sql.push_str("WITH base AS (\n");
+                lag_cte_sql.push_str(&inner_sql);
+                lag_cte_sql.push_str("\n),\nlag_cte AS (\n  SELECT\n    ");
+                lag_cte_sql.push_str(&lag_selects.join(",\n    "));
+                lag_cte_sql.push_str("\n  FROM base\n  ");
+                lag_cte_sql.push_str(&offset_joins.join("\n  "));
+                lag_cte_sql.push_str("\n)");
+            }
 
             let mut final_selects = lag_cte_columns.clone();
 
@@ -2367,6 +4027,9 @@ impl<'a> SqlGenerator<'a> {
                     crate::core::ComparisonCalculation::Ratio => {
                         format!("({base_alias} / NULLIF({prev_value_col}, 0)) AS {}", metric_ref.alias)
                     }
+                    crate::core::ComparisonCalculation::PreviousValue => {
+                        format!("{prev_value_col} AS {}", metric_ref.alias)
+                    }
                 };
                 final_selects.push(expr);
             }
@@ -2434,12 +4097,13 @@ impl<'a> SqlGenerator<'a> {
             sql.push_str(&format!("\nORDER BY {}", order_parts.join(", ")));
         }
 
-        if let Some(limit) = query.limit {
-            sql.push_str(&format!("\nLIMIT {limit}"));
-        }
-        if let Some(offset) = query.offset {
-            sql.push_str(&format!("\nOFFSET {offset}"));
-        }
+        self.push_limit_offset(
+            &mut sql,
+            None,
+            !query.order_by.is_empty(),
+            query.limit,
+            query.offset,
+        );
 
         Ok(sql.trim_end().to_string())
     }
@@ -2482,6 +4146,37 @@ impl<'a> SqlGenerator<'a> {
             .unwrap_or_else(|| reference.to_string())
     }
 
+    /// Reject queries requesting a metric that orders a window by time (see
+    /// [`Metric::requires_time_dimension`]) without any time dimension,
+    /// either requested or added from a model default.
+    fn ensure_time_dimension_for_window_metrics(
+        &self,
+        dimension_refs: &[DimensionRef],
+        metric_refs: &[MetricRef],
+    ) -> Result<()> {
+        let has_time_dimension = dimension_refs.iter().any(|dim_ref| {
+            self.graph
+                .get_model(&dim_ref.model)
+                .and_then(|model| model.get_dimension(&dim_ref.name))
+                .is_some_and(|dim| dim.r#type == crate::core::DimensionType::Time)
+        });
+        if has_time_dimension {
+            return Ok(());
+        }
+        for metric_ref in metric_refs {
+            let metric = self.metric_for_ref(metric_ref)?;
+            if metric.requires_time_dimension() {
+                return Err(SidemanticError::Validation(format!(
+                    "Metric '{}.{}' requires a time dimension in the query; add one \
+                     (e.g. '{}.<time_dimension>__month') or set a default time dimension \
+                     on model '{}'",
+                    metric_ref.model, metric_ref.name, metric_ref.model, metric_ref.model
+                )));
+            }
+        }
+        Ok(())
+    }
+
     fn find_time_order_column(
         &self,
         dimension_refs: &[DimensionRef],
@@ -2603,6 +4298,42 @@ impl<'a> SqlGenerator<'a> {
         }
     }
 
+    /// Parse a custom `time_offset` such as `"3 months"` into a count and a
+    /// calendar unit that steps from one `granularity` period start to another.
+    fn parse_time_offset(
+        time_offset: &str,
+        granularity: &str,
+    ) -> std::result::Result<(i64, &'static str), String> {
+        const UNITS: [&str; 8] = [
+            "second", "minute", "hour", "day", "week", "month", "quarter", "year",
+        ];
+        let parts: Vec<&str> = time_offset.split_whitespace().collect();
+        let [count, unit] = parts.as_slice() else {
+            return Err("expected '<count> <unit>'".to_string());
+        };
+        let count: i64 = count
+            .parse()
+            .ok()
+            .filter(|count| *count > 0)
+            .ok_or_else(|| format!("'{count}' is not a positive integer"))?;
+        let unit = unit.to_ascii_lowercase();
+        let unit = unit.strip_suffix('s').unwrap_or(&unit);
+        let rank = |name: &str| UNITS.iter().position(|candidate| *candidate == name);
+        let unit_rank = rank(unit).ok_or_else(|| format!("unknown unit '{unit}'"))?;
+        // Month-based offsets do not land on week starts.
+        let fits = match rank(granularity) {
+            Some(_) if granularity == "week" => unit == "week",
+            Some(grain_rank) => unit_rank >= grain_rank,
+            None => false,
+        };
+        if !fits {
+            return Err(format!(
+                "a {unit} offset cannot be expressed in {granularity} periods"
+            ));
+        }
+        Ok((count, UNITS[unit_rank]))
+    }
+
     fn generate_conversion_query(
         &self,
         metric_ref: &MetricRef,
@@ -2705,7 +4436,7 @@ impl<'a> SqlGenerator<'a> {
             let mut sql_col = dim_obj.sql_expr().to_string();
             let alias = if let Some(gran) = dim_ref.granularity.as_ref() {
                 if dim_obj.r#type == crate::core::DimensionType::Time {
-                    sql_col = format!("DATE_TRUNC('{gran}', {sql_col})");
+                    sql_col = self.date_trunc_sql(gran, &sql_col);
                     format!("{}__{gran}", dim_ref.name)
                 } else {
                     dim_ref.name.clone()
@@ -2774,18 +4505,20 @@ impl<'a> SqlGenerator<'a> {
             order_clause = format!("\nORDER BY {}", order_fields.join(", "));
         }
 
-        let limit_clause = limit
-            .map(|value| format!("\nLIMIT {value}"))
-            .unwrap_or_default();
-        let offset_clause = offset
-            .map(|value| format!("\nOFFSET {value}"))
-            .unwrap_or_default();
+        let mut limit_clause = String::new();
+        self.push_limit_offset(
+            &mut limit_clause,
+            None,
+            !order_clause.is_empty(),
+            limit,
+            offset,
+        );
         let base_event_lit = self.escape_sql_literal(base_event);
         let conversion_event_lit = self.escape_sql_literal(conversion_event);
         let interval = self.interval_sql(window_num, window_unit);
 
         Ok(format!(
-            "WITH base_events AS (\n  SELECT\n    {entity} AS entity,\n    {timestamp_dim} AS event_time{extra_base_cols}\n  FROM {from_clause}\n  WHERE {event_type_dim} = '{base_event_lit}'{filter_clause}\n),\nconversion_events AS (\n  SELECT\n    {entity} AS entity,\n    {timestamp_dim} AS event_time{extra_conv_cols}\n  FROM {from_clause}\n  WHERE {event_type_dim} = '{conversion_event_lit}'{filter_clause}\n),\nconversions AS (\n  SELECT DISTINCT\n    base.entity{extra_conversions_cols}\n  FROM base_events base\n  JOIN conversion_events conv\n    ON base.entity = conv.entity\n    AND conv.event_time BETWEEN base.event_time AND base.event_time + {interval}\n)\nSELECT\n{dim_select}  COUNT(DISTINCT conversions.entity)::FLOAT / NULLIF(COUNT(DISTINCT base_events.entity), 0) AS {}\nFROM base_events\nLEFT JOIN conversions ON {join_condition}{group_by}{order_clause}{limit_clause}{offset_clause}",
+            "WITH base_events AS (\n  SELECT\n    {entity} AS entity,\n    {timestamp_dim} AS event_time{extra_base_cols}\n  FROM {from_clause}\n  WHERE {event_type_dim} = '{base_event_lit}'{filter_clause}\n),\nconversion_events AS (\n  SELECT\n    {entity} AS entity,\n    {timestamp_dim} AS event_time{extra_conv_cols}\n  FROM {from_clause}\n  WHERE {event_type_dim} = '{conversion_event_lit}'{filter_clause}\n),\nconversions AS (\n  SELECT DISTINCT\n    base.entity{extra_conversions_cols}\n  FROM base_events base\n  JOIN conversion_events conv\n    ON base.entity = conv.entity\n    AND conv.event_time BETWEEN base.event_time AND base.event_time + {interval}\n)\nSELECT\n{dim_select}  COUNT(DISTINCT conversions.entity)::FLOAT / NULLIF(COUNT(DISTINCT base_events.entity), 0) AS {}\nFROM base_events\nLEFT JOIN conversions ON {join_condition}{group_by}{order_clause}{limit_clause}",
             metric.name
         ))
     }
@@ -2968,15 +4701,17 @@ impl<'a> SqlGenerator<'a> {
             )
         };
         let order_clause = self.simple_order_clause(order_by);
-        let limit_clause = limit
-            .map(|value| format!("\nLIMIT {value}"))
-            .unwrap_or_default();
-        let offset_clause = offset
-            .map(|value| format!("\nOFFSET {value}"))
-            .unwrap_or_default();
+        let mut limit_clause = String::new();
+        self.push_limit_offset(
+            &mut limit_clause,
+            None,
+            !order_clause.is_empty(),
+            limit,
+            offset,
+        );
 
         Ok(format!(
-            "WITH {}\nSELECT\n  {}\nFROM step_1{join_section}{group_by}{order_clause}{limit_clause}{offset_clause}",
+            "WITH {}\nSELECT\n  {}\nFROM step_1{join_section}{group_by}{order_clause}{limit_clause}",
             ctes.join(",\n"),
             select_parts.join(",\n  ")
         ))
@@ -3065,15 +4800,17 @@ impl<'a> SqlGenerator<'a> {
         } else {
             self.simple_order_clause(order_by)
         };
-        let limit_clause = limit
-            .map(|value| format!("\nLIMIT {value}"))
-            .unwrap_or_default();
-        let offset_clause = offset
-            .map(|value| format!("\nOFFSET {value}"))
-            .unwrap_or_default();
+        let mut limit_clause = String::new();
+        self.push_limit_offset(
+            &mut limit_clause,
+            None,
+            !order_clause.is_empty(),
+            limit,
+            offset,
+        );
 
         Ok(format!(
-            "WITH cohorts AS (\n  SELECT {entity_select}, MIN({trunc_expr}) AS cohort_date\n  FROM {from_clause}\n  WHERE {}{filter_clause}\n  GROUP BY {entity_sql}\n),\nactivity AS (\n  SELECT DISTINCT {entity_select}, {trunc_expr} AS active_date\n  FROM {from_clause}\n  WHERE {}{filter_clause}\n),\nretention AS (\n  SELECT\n    c.cohort_date,\n    CAST({diff_expr} AS INTEGER) AS periods_since,\n    COUNT(DISTINCT c.{entity}) AS active_users\n  FROM cohorts c\n  JOIN activity a ON c.{entity} = a.{entity} AND a.active_date >= c.cohort_date\n  WHERE CAST({diff_expr} AS INTEGER) <= {periods}\n  GROUP BY 1, 2\n),\ncohort_sizes AS (\n  SELECT cohort_date, COUNT(DISTINCT {entity}) AS cohort_size\n  FROM cohorts GROUP BY 1\n)\nSELECT\n  r.cohort_date,\n  r.periods_since AS {periods_label},\n  r.active_users,\n  c.cohort_size,\n  ROUND(r.active_users * 100.0 / c.cohort_size, 1) AS retention_pct\nFROM retention r\nJOIN cohort_sizes c ON r.cohort_date = c.cohort_date{order_clause}{limit_clause}{offset_clause}",
+            "WITH cohorts AS (\n  SELECT {entity_select}, MIN({trunc_expr}) AS cohort_date\n  FROM {from_clause}\n  WHERE {}{filter_clause}\n  GROUP BY {entity_sql}\n),\nactivity AS (\n  SELECT DISTINCT {entity_select}, {trunc_expr} AS active_date\n  FROM {from_clause}\n  WHERE {}{filter_clause}\n),\nretention AS (\n  SELECT\n    c.cohort_date,\n    CAST({diff_expr} AS INTEGER) AS periods_since,\n    COUNT(DISTINCT c.{entity}) AS active_users\n  FROM cohorts c\n  JOIN activity a ON c.{entity} = a.{entity} AND a.active_date >= c.cohort_date\n  WHERE CAST({diff_expr} AS INTEGER) <= {periods}\n  GROUP BY 1, 2\n),\ncohort_sizes AS (\n  SELECT cohort_date, COUNT(DISTINCT {entity}) AS cohort_size\n  FROM cohorts GROUP BY 1\n)\nSELECT\n  r.cohort_date,\n  r.periods_since AS {periods_label},\n  r.active_users,\n  c.cohort_size,\n  ROUND(r.active_users * 100.0 / c.cohort_size, 1) AS retention_pct\nFROM retention r\nJOIN cohort_sizes c ON r.cohort_date = c.cohort_date{order_clause}{limit_clause}",
             self.raw_filter_for_model(model, cohort_event)?,
             self.raw_filter_for_model(model, activity_event)?
         ))
@@ -3128,6 +4865,7 @@ impl<'a> SqlGenerator<'a> {
                     name: dim_name.clone(),
                     granularity: None,
                     alias: dim_name,
+                    exposed_by: None,
                 });
             }
         }
@@ -3185,15 +4923,17 @@ impl<'a> SqlGenerator<'a> {
             )
         };
         let order_clause = self.simple_order_clause(order_by);
-        let limit_clause = limit
-            .map(|value| format!("\nLIMIT {value}"))
-            .unwrap_or_default();
-        let offset_clause = offset
-            .map(|value| format!("\nOFFSET {value}"))
-            .unwrap_or_default();
+        let mut limit_clause = String::new();
+        self.push_limit_offset(
+            &mut limit_clause,
+            None,
+            !order_clause.is_empty(),
+            limit,
+            offset,
+        );
 
         Ok(format!(
-            "WITH cohort_sub AS (\n  SELECT\n    {}\n  FROM {from_clause}{filter_clause}\n  GROUP BY\n    {}\n  HAVING {}\n)\nSELECT\n  {}\nFROM cohort_sub{group_by}{order_clause}{limit_clause}{offset_clause}",
+            "WITH cohort_sub AS (\n  SELECT\n    {}\n  FROM {from_clause}{filter_clause}\n  GROUP BY\n    {}\n  HAVING {}\n)\nSELECT\n  {}\nFROM cohort_sub{group_by}{order_clause}{limit_clause}",
             select_parts.join(",\n    "),
             group_parts.join(",\n    "),
             self.raw_filter_for_model(model, having)?,
@@ -3373,7 +5113,7 @@ impl<'a> SqlGenerator<'a> {
         let expr = match agg {
             Aggregation::Count => match inner.sql.as_deref() {
                 Some(sql) => resolve_sql(sql)?,
-                None => "*".to_string(),
+                None => self.count_style.argument().to_string(),
             },
             Aggregation::CountDistinct => {
                 let Some(sql) = inner.sql.as_ref() else {
@@ -3394,7 +5134,11 @@ impl<'a> SqlGenerator<'a> {
                 resolve_sql(sql)?
             }
         };
-        Ok(format!("{}({expr}) AS {}", agg.as_sql(), inner.name))
+        Ok(format!(
+            "{} AS {}",
+            self.aggregate_call_sql(agg, &expr)?,
+            inner.name
+        ))
     }
 
     fn cohort_outer_metric_sql(&self, metric: &Metric) -> Result<String> {
@@ -3415,7 +5159,7 @@ impl<'a> SqlGenerator<'a> {
                         "cohort metric with non-count agg requires a 'sql' field".to_string(),
                     ));
                 };
-                Ok(format!("{}({})", agg.as_sql(), self.cohort_outer_expr(sql)))
+                self.aggregate_call_sql(agg, &self.cohort_outer_expr(sql))
             }
         }
     }
@@ -3426,17 +5170,145 @@ impl<'a> SqlGenerator<'a> {
     }
 
     fn date_trunc_sql(&self, granularity: &str, column_expr: &str) -> String {
-        if self.dialect == DialectType::BigQuery {
-            format!(
-                "DATE_TRUNC({}, {})",
-                column_expr,
-                granularity.to_ascii_uppercase()
-            )
-        } else {
-            format!("DATE_TRUNC('{granularity}', {column_expr})")
+        crate::core::date_trunc_sql(granularity, column_expr, self.dialect)
+    }
+
+    fn date_sub_sql(&self, column_expr: &str, count: i64, unit: &str) -> String {
+        crate::core::date_add_sql(column_expr, -count, unit, self.dialect)
+    }
+
+    /// Render a JSON scalar extraction (`column->>'path'`) for the target dialect.
+    fn json_path_sql(&self, column_expr: &str, path: &str) -> String {
+        let keys: Vec<&str> = path
+            .trim_start_matches('$')
+            .split('.')
+            .filter(|key| !key.is_empty())
+            .collect();
+        let dotted = keys.join(".");
+        let quoted_keys = keys
+            .iter()
+            .map(|key| format!("'{key}'"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        match self.dialect {
+            DialectType::BigQuery => format!("JSON_VALUE({column_expr}, '$.{dotted}')"),
+            DialectType::Snowflake => format!("JSON_EXTRACT_PATH_TEXT({column_expr}, '{dotted}')"),
+            DialectType::Redshift => {
+                format!("JSON_EXTRACT_PATH_TEXT({column_expr}, {quoted_keys})")
+            }
+            DialectType::Databricks | DialectType::Spark => {
+                format!("get_json_object({column_expr}, '$.{dotted}')")
+            }
+            DialectType::ClickHouse => format!("JSONExtractString({column_expr}, {quoted_keys})"),
+            DialectType::PostgreSQL
+            | DialectType::CockroachDB
+            | DialectType::Materialize
+            | DialectType::RisingWave
+                if keys.len() > 1 =>
+            {
+                format!("{column_expr} #>> '{{{}}}'", keys.join(","))
+            }
+            _ => format!("{column_expr}->>'{path}'"),
+        }
+    }
+
+    /// Render a single-argument aggregate call for the target dialect.
+    ///
+    /// Postgres-family engines lack `MEDIAN`, so it becomes the equivalent
+    /// ordered-set aggregate `PERCENTILE_CONT(0.5) WITHIN GROUP (ORDER BY ...)`.
+    /// BigQuery only has approximate quantiles, so exact percentiles are an
+    /// error there.
+    fn aggregate_call_sql(&self, agg: &Aggregation, expr: &str) -> Result<String> {
+        Ok(match (agg, self.dialect) {
+            (
+                Aggregation::Median,
+                DialectType::PostgreSQL
+                | DialectType::CockroachDB
+                | DialectType::Materialize
+                | DialectType::RisingWave,
+            ) => format!("PERCENTILE_CONT(0.5) WITHIN GROUP (ORDER BY {expr})"),
+            (Aggregation::ApproxCountDistinct, _) => self.approx_count_distinct_sql(expr),
+            (
+                Aggregation::ApproxPercentile(fraction),
+                DialectType::Snowflake | DialectType::Databricks | DialectType::Spark,
+            ) => format!("APPROX_PERCENTILE({expr}, {fraction})"),
+            (
+                Aggregation::ApproxPercentile(fraction),
+                DialectType::PostgreSQL
+                | DialectType::CockroachDB
+                | DialectType::Materialize
+                | DialectType::RisingWave,
+            ) => Aggregation::Percentile(*fraction).call_sql(expr),
+            (Aggregation::ApproxPercentile(fraction), DialectType::BigQuery) => {
+                bigquery_approx_quantile_sql(expr, *fraction)
+            }
+            (Aggregation::Percentile(fraction), DialectType::BigQuery) => {
+                return Err(SidemanticError::Validation(format!(
+                    "BigQuery has no exact percentile aggregate; use approx_percentile({fraction}) instead"
+                )))
+            }
+            _ => agg.call_sql(expr),
+        })
+    }
+
+    /// Render an approximate distinct count, falling back to an exact
+    /// `COUNT(DISTINCT ...)` on dialects without one.
+    fn approx_count_distinct_sql(&self, expr: &str) -> String {
+        match self.dialect {
+            DialectType::PostgreSQL
+            | DialectType::CockroachDB
+            | DialectType::Materialize
+            | DialectType::RisingWave => format!("COUNT(DISTINCT {expr})"),
+            DialectType::Redshift => format!("APPROXIMATE COUNT(DISTINCT {expr})"),
+            DialectType::ClickHouse => format!("uniq({expr})"),
+            _ => format!("APPROX_COUNT_DISTINCT({expr})"),
+        }
+    }
+
+    /// Render an array membership test for the target dialect.
+    fn array_contains_sql(&self, array_expr: &str, value_expr: &str) -> String {
+        match self.dialect {
+            DialectType::PostgreSQL
+            | DialectType::Redshift
+            | DialectType::CockroachDB
+            | DialectType::Materialize
+            | DialectType::RisingWave => format!("{value_expr} = ANY({array_expr})"),
+            DialectType::BigQuery => format!("{value_expr} IN UNNEST({array_expr})"),
+            DialectType::Snowflake => {
+                format!("ARRAY_CONTAINS({value_expr}::VARIANT, {array_expr})")
+            }
+            DialectType::ClickHouse => format!("has({array_expr}, {value_expr})"),
+            DialectType::Databricks | DialectType::Spark => {
+                format!("array_contains({array_expr}, {value_expr})")
+            }
+            _ => format!("list_contains({array_expr}, {value_expr})"),
         }
     }
 
+    /// Rewrite `column->>'path'` JSON extraction into the target dialect.
+    fn render_json_path_operators(&self, sql: &str) -> String {
+        let json_re = regex::Regex::new(
+            r"([A-Za-z_][A-Za-z0-9_]*(?:\.[A-Za-z_][A-Za-z0-9_]*)*)\s*->>\s*'([^']*)'",
+        )
+        .expect("valid JSON path regex");
+        replace_outside_string_literals(&json_re, sql, |caps| {
+            self.json_path_sql(&caps[1], &caps[2])
+        })
+    }
+
+    /// Rewrite semi-structured helpers (`->>` JSON paths and
+    /// `array_contains(array, value)`) into the target dialect.
+    fn render_semi_structured_sql(&self, sql: &str) -> String {
+        let array_re = regex::Regex::new(
+            r"(?i)\b(?:array_contains|list_contains)\s*\(\s*([^,()]+?)\s*,\s*('(?:[^']|'')*'|[^,()]+?)\s*\)",
+        )
+        .expect("valid array_contains regex");
+        let rendered = replace_outside_string_literals(&array_re, sql, |caps| {
+            self.array_contains_sql(&caps[1], &caps[2])
+        });
+        self.render_json_path_operators(&rendered)
+    }
+
     fn is_relationship_foreign_key_dimension(model: &Model, dimension_name: &str) -> bool {
         model.relationships.iter().any(|relationship| {
             relationship
@@ -3450,6 +5322,43 @@ impl<'a> SqlGenerator<'a> {
         format!("INTERVAL '{num} {unit}'")
     }
 
+    /// Append LIMIT / OFFSET in the dialect's syntax, one clause per line.
+    /// SQL Server has no LIMIT: a bare limit becomes `TOP n` when `top_at`
+    /// points just past the outer SELECT keyword, and anything else pages
+    /// with OFFSET/FETCH, which needs an ORDER BY (`ordered` says whether
+    /// the query already ends with one).
+    fn push_limit_offset(
+        &self,
+        sql: &mut String,
+        top_at: Option<usize>,
+        ordered: bool,
+        limit: Option<usize>,
+        offset: Option<usize>,
+    ) {
+        if self.dialect != DialectType::TSQL {
+            if let Some(limit) = limit {
+                sql.push_str(&format!("\nLIMIT {limit}"));
+            }
+            if let Some(offset) = offset {
+                sql.push_str(&format!("\nOFFSET {offset}"));
+            }
+            return;
+        }
+        match (limit, offset, top_at) {
+            (None, None, _) => {}
+            (Some(limit), None, Some(top_at)) => sql.insert_str(top_at, &format!(" TOP {limit}")),
+            (limit, offset, _) => {
+                if !ordered {
+                    sql.push_str("\nORDER BY (SELECT NULL)");
+                }
+                sql.push_str(&format!("\nOFFSET {} ROWS", offset.unwrap_or(0)));
+                if let Some(limit) = limit {
+                    sql.push_str(&format!("\nFETCH NEXT {limit} ROWS ONLY"));
+                }
+            }
+        }
+    }
+
     fn simple_order_clause(&self, order_by: &[String]) -> String {
         if order_by.is_empty() {
             return String::new();
@@ -3722,7 +5631,10 @@ impl<'a> SqlGenerator<'a> {
                 | Aggregation::Stddev
                 | Aggregation::StddevPop
                 | Aggregation::Variance
-                | Aggregation::VariancePop,
+                | Aggregation::VariancePop
+                | Aggregation::Percentile(_)
+                | Aggregation::ApproxPercentile(_)
+                | Aggregation::ApproxCountDistinct,
             ) => false,
             Some(Aggregation::Median | Aggregation::Expression) => true,
         }
@@ -3873,14 +5785,21 @@ impl<'a> SqlGenerator<'a> {
                         select_parts.push(format!("{preagg_col} AS {}__{query_grain}", dim_name));
                     } else {
                         select_parts.push(format!(
-                            "DATE_TRUNC('{query_grain}', {preagg_col}) AS {}__{query_grain}",
+                            "{} AS {}__{query_grain}",
+                            self.date_trunc_sql(query_grain, &preagg_col),
                             dim_name
                         ));
                     }
                     continue;
                 }
             }
-            select_parts.push(dim_name.clone());
+            let labelled = model
+                .get_dimension(dim_name)
+                .and_then(|dimension| dimension.null_label_sql(dim_name));
+            match labelled {
+                Some(expr) => select_parts.push(format!("{expr} AS {dim_name}")),
+                None => select_parts.push(dim_name.clone()),
+            }
         }
 
         let preagg_measures: HashSet<String> = preagg
@@ -3967,60 +5886,126 @@ impl<'a> SqlGenerator<'a> {
             sql.push_str(&format!("\nORDER BY {}", order_clauses.join(", ")));
         }
 
-        if let Some(limit) = limit {
-            sql.push_str(&format!("\nLIMIT {limit}"));
-        }
-        if let Some(offset) = offset {
-            sql.push_str(&format!("\nOFFSET {offset}"));
-        }
+        self.push_limit_offset(&mut sql, None, !order_by.is_empty(), limit, offset);
 
         sql
     }
 
-    fn split_filters(
+    /// Split filters into WHERE filters and HAVING filters, the latter being
+    /// those that reference a metric.
+    fn split_filters(&self, filters: &[String]) -> (Vec<String>, Vec<String>) {
+        filters
+            .iter()
+            .cloned()
+            .partition(|filter| self.metric_references(filter).is_empty())
+    }
+
+    /// `model.metric` references in a filter, as (full reference, model, metric).
+    fn metric_references(&self, filter: &str) -> Vec<(String, String, String)> {
+        let ref_re = regex::Regex::new(r"\b([A-Za-z_][A-Za-z0-9_]*)\.([A-Za-z_][A-Za-z0-9_]*)\b")
+            .expect("valid model.field regex");
+        ref_re
+            .captures_iter(filter)
+            .filter(|cap| {
+                self.graph
+                    .get_model(&cap[1])
+                    .is_some_and(|model| model.get_metric(&cap[2]).is_some())
+            })
+            .map(|cap| (cap[0].to_string(), cap[1].to_string(), cap[2].to_string()))
+            .collect()
+    }
+
+    /// Rewrite metric references in HAVING filters to the metric's aggregate
+    /// expression from the SELECT list, since not every dialect resolves output
+    /// aliases in HAVING. Metrics the query doesn't select fall back to their
+    /// output alias.
+    fn render_having_filters(
         &self,
         filters: &[String],
+        metric_select_exprs: &HashMap<(String, String), String>,
         collisions: &HashMap<String, usize>,
-    ) -> Result<(Vec<String>, Vec<String>)> {
-        let mut where_filters = Vec::new();
-        let mut having_filters = Vec::new();
-        let ref_re = regex::Regex::new(r"\b([A-Za-z_][A-Za-z0-9_]*)\.([A-Za-z_][A-Za-z0-9_]*)\b")
-            .expect("valid model.field regex");
+    ) -> Vec<String> {
+        filters
+            .iter()
+            .map(|filter| {
+                let mut rewritten = filter.clone();
+                for (full_ref, model_name, metric_name) in self.metric_references(filter) {
+                    let replacement =
+                        match metric_select_exprs.get(&(model_name.clone(), metric_name.clone())) {
+                            Some(expr) if Self::is_single_function_call(expr) => expr.clone(),
+                            Some(expr) => format!("({expr})"),
+                            None => self.output_alias(&model_name, &metric_name, collisions),
+                        };
+                    let pattern = regex::Regex::new(&format!(r"\b{}\b", regex::escape(&full_ref)))
+                        .expect("escaped metric reference regex");
+                    rewritten = pattern
+                        .replace_all(&rewritten, regex::NoExpand(&replacement))
+                        .into_owned();
+                }
+                rewritten
+            })
+            .collect()
+    }
 
-        for filter in filters {
-            let mut rewritten = filter.clone();
-            let mut uses_metric = false;
+    /// `(numerator) / NULLIF(denominator, 0)`, without doubling parentheses
+    /// the expanded operands already carry.
+    fn ratio_sql(numerator: &str, denominator: &str) -> String {
+        let numerator = if Self::is_parenthesized(numerator) {
+            numerator.to_string()
+        } else {
+            format!("({numerator})")
+        };
+        let mut denominator = denominator;
+        while Self::is_parenthesized(denominator) {
+            denominator = &denominator[1..denominator.len() - 1];
+        }
+        format!("{numerator} / NULLIF({denominator}, 0)")
+    }
 
-            for cap in ref_re.captures_iter(filter) {
-                let Some(model_match) = cap.get(1) else {
-                    continue;
-                };
-                let Some(field_match) = cap.get(2) else {
-                    continue;
-                };
-                let model_name = model_match.as_str();
-                let field_name = field_match.as_str();
-                let Some(model) = self.graph.get_model(model_name) else {
-                    continue;
-                };
-                if model.get_metric(field_name).is_none() {
-                    continue;
+    /// Whether `expr` is wrapped in one pair of parentheses, e.g. `(a + b)`
+    /// but not `(a) + (b)`.
+    fn is_parenthesized(expr: &str) -> bool {
+        if !expr.starts_with('(') {
+            return false;
+        }
+        let mut depth = 0usize;
+        for (index, c) in expr.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return index == expr.len() - 1;
+                    }
                 }
-
-                uses_metric = true;
-                let replacement = self.output_alias(model_name, field_name, collisions);
-                let full_ref = format!("{model_name}.{field_name}");
-                rewritten = rewritten.replace(&full_ref, &replacement);
+                _ => {}
             }
+        }
+        false
+    }
 
-            if uses_metric {
-                having_filters.push(rewritten);
-            } else {
-                where_filters.push(filter.clone());
+    /// Whether `expr` is one function call such as `SUM(x)`, which needs no
+    /// parentheses when spliced into a larger expression.
+    fn is_single_function_call(expr: &str) -> bool {
+        let call_re =
+            regex::Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*\(").expect("valid function call regex");
+        let Some(open) = call_re.find(expr) else {
+            return false;
+        };
+        let mut depth = 0usize;
+        for (index, c) in expr.char_indices().skip(open.end() - 1) {
+            match c {
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return index == expr.len() - 1;
+                    }
+                }
+                _ => {}
             }
         }
-
-        Ok((where_filters, having_filters))
+        false
     }
 
     fn classify_filters_for_cte_pushdown(
@@ -4042,6 +6027,12 @@ impl<'a> SqlGenerator<'a> {
                 if !references_metric
                     && !references_window_dimension
                     && referenced_models.len() == 1
+                    && !self.has_window_dimensions(
+                        referenced_models
+                            .iter()
+                            .next()
+                            .expect("one referenced model"),
+                    )
                 {
                     let model_name = referenced_models
                         .iter()
@@ -4056,6 +6047,7 @@ impl<'a> SqlGenerator<'a> {
                     && !references_window_dimension
                     && referenced_models.is_empty()
                     && cte_models.len() == 1
+                    && !self.has_window_dimensions(&cte_models[0])
                     && !filter_part.contains('.')
                 {
                     pushdown_filters
@@ -4071,6 +6063,46 @@ impl<'a> SqlGenerator<'a> {
         Ok((pushdown_filters, main_filters))
     }
 
+    /// Expand join filters and key them by the joined model whose `ON` clause
+    /// they extend. Each filter must reference exactly one joined model other
+    /// than the base model, and no metrics.
+    fn classify_join_filters(
+        &self,
+        filters: &[String],
+        base_model: &str,
+        cte_models: &[String],
+    ) -> Result<HashMap<String, Vec<String>>> {
+        let cte_model_set: HashSet<&str> = cte_models.iter().map(String::as_str).collect();
+        let mut join_filters: HashMap<String, Vec<String>> = HashMap::new();
+
+        for filter in filters {
+            if self.filter_references_metric(filter, &cte_model_set) {
+                return Err(SidemanticError::Validation(format!(
+                    "Join filter '{filter}' cannot reference a metric"
+                )));
+            }
+            let mut referenced_models: Vec<String> = self
+                .filter_referenced_models(filter, &cte_model_set)
+                .into_iter()
+                .collect();
+            let model_name = match referenced_models.pop() {
+                Some(model_name) if referenced_models.is_empty() && model_name != base_model => {
+                    model_name
+                }
+                _ => {
+                    return Err(SidemanticError::Validation(format!(
+                        "Join filter '{filter}' must reference exactly one joined model \
+                         other than the base model '{base_model}'"
+                    )))
+                }
+            };
+            let expanded = self.expand_filters(std::slice::from_ref(filter))?;
+            join_filters.entry(model_name).or_default().extend(expanded);
+        }
+
+        Ok(join_filters)
+    }
+
     fn split_conjunctive_filter(&self, filter: &str) -> Vec<String> {
         let upper = filter.to_ascii_uppercase();
         if upper.contains(" OR ") || upper.contains(" BETWEEN ") {
@@ -4158,6 +6190,18 @@ impl<'a> SqlGenerator<'a> {
         false
     }
 
+    /// Whether `model_name` projects window dimensions in its CTE. Filters on
+    /// such a model stay out of the CTE's WHERE so windows such as a
+    /// customer's first order see every row, not just the filtered ones.
+    fn has_window_dimensions(&self, model_name: &str) -> bool {
+        self.graph.get_model(model_name).is_some_and(|model| {
+            model
+                .dimensions
+                .iter()
+                .any(|dimension| dimension.window.is_some())
+        })
+    }
+
     fn filter_references_window_dimension(&self, filter: &str, cte_models: &HashSet<&str>) -> bool {
         !self
             .filter_window_dimension_models(filter, cte_models)
@@ -4226,7 +6270,7 @@ impl<'a> SqlGenerator<'a> {
         let mut expanded = Vec::with_capacity(filters.len());
 
         for filter in filters {
-            let mut filter_sql = filter.clone();
+            let mut filter_sql = self.expand_date_range_filter(filter)?;
             for dim in &model.dimensions {
                 let source_expr = self.normalize_cte_source_expression(dim.sql_expr());
                 for qualified in [
@@ -4247,6 +6291,7 @@ impl<'a> SqlGenerator<'a> {
             }
             filter_sql = filter_sql.replace("{model}.", "");
             filter_sql = filter_sql.replace("{model}", "");
+            filter_sql = self.render_semi_structured_sql(&filter_sql);
             expanded.push(self.expand_relative_dates(&filter_sql));
         }
 
@@ -4272,6 +6317,101 @@ impl<'a> SqlGenerator<'a> {
         rendered.join(" AND ")
     }
 
+    /// Condition keeping only the rows of a semi-additive metric's snapshot:
+    /// those at the latest (or earliest) value of its `non_additive_dimension`
+    /// within each group of the query's dimensions on the same model.
+    fn non_additive_snapshot_condition(
+        &self,
+        metric: &Metric,
+        model: &Model,
+        dimension_refs: &[DimensionRef],
+    ) -> Result<Option<String>> {
+        let Some(dimension_name) = metric.non_additive_dimension.as_deref() else {
+            return Ok(None);
+        };
+        let dimension = model.get_dimension(dimension_name).ok_or_else(|| {
+            SidemanticError::Validation(format!(
+                "Metric '{}.{}' has non_additive_dimension '{dimension_name}', \
+                 which is not a dimension of '{}'",
+                model.name, metric.name, model.name
+            ))
+        })?;
+
+        // The snapshot is picked inside the model CTE, before any join, so it
+        // can only be partitioned by the model's own dimensions.
+        if let Some(foreign) = dimension_refs.iter().find(|d| d.model != model.name) {
+            return Err(SidemanticError::Validation(format!(
+                "Semi-additive metric '{}.{}' cannot be grouped by '{}.{}': \
+                 non_additive_dimension snapshots are only supported with dimensions of '{}'",
+                model.name, metric.name, foreign.model, foreign.name, model.name
+            )));
+        }
+
+        let mut partition = Vec::new();
+        for dim_ref in dimension_refs {
+            let Some(grouped) = model.get_dimension(&dim_ref.name) else {
+                continue;
+            };
+            let expr = match dim_ref
+                .granularity
+                .as_deref()
+                .or(grouped.granularity.as_deref())
+            {
+                Some(granularity) => self.date_trunc_sql(granularity, grouped.sql_expr()),
+                None => grouped.sql_expr().to_string(),
+            };
+            partition.push(self.normalize_cte_source_expression(&expr));
+        }
+        let over = if partition.is_empty() {
+            "OVER ()".to_string()
+        } else {
+            format!("OVER (PARTITION BY {})", partition.join(", "))
+        };
+
+        let snapshot_column = self.normalize_cte_source_expression(dimension.sql_expr());
+        Ok(Some(format!(
+            "{snapshot_column} = {}({snapshot_column}) {over}",
+            metric.non_additive_window.aggregate()
+        )))
+    }
+
+    /// Split metric filters into those answerable inside the metric's own CTE
+    /// and those that reference dimensions on joined models.
+    fn partition_metric_filters(
+        &self,
+        filters: &[String],
+        model_name: &str,
+    ) -> (Vec<String>, Vec<String>) {
+        filters.iter().cloned().partition(|filter| {
+            let mut models = HashSet::new();
+            self.collect_models_from_sql_references(filter, &mut models);
+            models.iter().all(|name| name == model_name)
+        })
+    }
+
+    /// Wrap a raw metric column in `CASE WHEN` for filters on joined models.
+    ///
+    /// Own-model filters are already applied in the CTE; joined-model filters
+    /// can only be evaluated after the join, so they are qualified with the
+    /// joined CTE aliases and applied inside the aggregate.
+    fn filtered_raw_column(
+        &self,
+        metric: &crate::core::Metric,
+        model_name: &str,
+        raw_col: String,
+    ) -> Result<String> {
+        let (_, joined_filters) = self.partition_metric_filters(&metric.filters, model_name);
+        if joined_filters.is_empty() {
+            return Ok(raw_col);
+        }
+        let joined_filters: Vec<String> = joined_filters
+            .iter()
+            .map(|filter| filter.replace("{model}", model_name))
+            .collect();
+        let condition = self.expand_filters(&joined_filters)?.join(" AND ");
+        Ok(format!("CASE WHEN {condition} THEN {raw_col} END"))
+    }
+
     fn normalize_cte_source_expression(&self, expr: &str) -> String {
         expr.replace("{model}.", "").replace("{model}", "")
     }
@@ -4285,11 +6425,128 @@ impl<'a> SqlGenerator<'a> {
         dimension: &crate::core::Dimension,
         alias: &str,
     ) -> String {
+        let qualified = self.qualified_dimension_sql(dimension, alias);
+        self.render_semi_structured_sql(&qualified)
+    }
+
+    /// Qualify a dimension's SQL with `alias`. A bare column becomes
+    /// `alias.column`; in an expression (e.g. a CASE bucket) every unqualified
+    /// column reference is qualified.
+    fn qualified_dimension_sql(&self, dimension: &crate::core::Dimension, alias: &str) -> String {
         let expr = dimension.sql_expr();
         if expr.contains("{model}") {
-            self.normalize_select_expression(expr, alias)
+            return self.normalize_select_expression(expr, alias);
+        }
+        if Self::is_simple_identifier(expr) {
+            return format!("{alias}.{expr}");
+        }
+        self.qualify_expression_columns(expr, alias)
+    }
+
+    /// Prefix every unqualified column reference in `expr` with `alias`. An
+    /// expression the SQL parser rejects is returned as written.
+    fn qualify_expression_columns(&self, expr: &str, alias: &str) -> String {
+        let projection = match self.expression_dimensions().get(expr) {
+            Some(projection) => projection.clone(),
+            None => match Self::parse_projections(vec![expr.to_string()]).remove(expr) {
+                Some(projection) => projection,
+                None => return expr.to_string(),
+            },
+        };
+        // Stay in the source dialect; dialect rendering happens afterwards.
+        Self::qualify_columns(projection, alias)
+            .and_then(|qualified| polyglot_sql::generate(&qualified, SOURCE_DIALECT))
+            .map(|sql| sql.trim_end().to_string())
+            .unwrap_or_else(|_| expr.to_string())
+    }
+
+    /// Every expression dimension in the graph, parsed together the first time
+    /// one is needed so a query spawns at most one parser thread for them.
+    fn expression_dimensions(&self) -> &HashMap<String, Expression> {
+        self.expression_dimensions.get_or_init(|| {
+            let exprs: HashSet<String> = self
+                .graph
+                .models()
+                .flat_map(|model| &model.dimensions)
+                .map(|dimension| dimension.sql_expr())
+                .filter(|expr| !expr.contains("{model}") && !Self::is_simple_identifier(expr))
+                .map(str::to_string)
+                .collect();
+            Self::parse_projections(exprs.into_iter().collect())
+        })
+    }
+
+    /// Parse each expression as a single SELECT projection, keyed by the
+    /// expression; expressions the parser rejects are left out.
+    fn parse_projections(exprs: Vec<String>) -> HashMap<String, Expression> {
+        if exprs.is_empty() {
+            return HashMap::new();
+        }
+        let sqls = exprs.iter().map(|expr| format!("SELECT {expr}")).collect();
+        let Ok(parsed) = parse_all_with_large_stack(sqls, SOURCE_DIALECT) else {
+            return HashMap::new();
+        };
+        exprs
+            .into_iter()
+            .zip(parsed)
+            .filter_map(|(expr, statements)| {
+                let Ok([Expression::Select(select)]) = statements.as_deref() else {
+                    return None;
+                };
+                let [projection] = select.expressions.as_slice() else {
+                    return None;
+                };
+                Some((expr, projection.clone()))
+            })
+            .collect()
+    }
+
+    fn qualify_columns(expr: Expression, alias: &str) -> polyglot_sql::Result<Expression> {
+        polyglot_sql::transform_map(expr, &|node| {
+            // transform_map does not descend into typed aggregates.
+            Ok(match node {
+                Expression::Column(mut column) if column.table.is_none() => {
+                    column.table = Some(Identifier::new(alias));
+                    Expression::Column(column)
+                }
+                Expression::Sum(mut agg) => {
+                    agg.this = Self::qualify_columns(agg.this, alias)?;
+                    Expression::Sum(agg)
+                }
+                Expression::Avg(mut agg) => {
+                    agg.this = Self::qualify_columns(agg.this, alias)?;
+                    Expression::Avg(agg)
+                }
+                Expression::Min(mut agg) => {
+                    agg.this = Self::qualify_columns(agg.this, alias)?;
+                    Expression::Min(agg)
+                }
+                Expression::Max(mut agg) => {
+                    agg.this = Self::qualify_columns(agg.this, alias)?;
+                    Expression::Max(agg)
+                }
+                Expression::Median(mut agg) => {
+                    agg.this = Self::qualify_columns(agg.this, alias)?;
+                    Expression::Median(agg)
+                }
+                Expression::Count(mut count) => {
+                    if let Some(this) = count.this.take() {
+                        count.this = Some(Self::qualify_columns(this, alias)?);
+                    }
+                    Expression::Count(count)
+                }
+                other => other,
+            })
+        })
+    }
+
+    /// Dimension SQL for splicing into a larger expression such as a filter.
+    fn dimension_operand_sql(&self, dimension: &crate::core::Dimension, alias: &str) -> String {
+        let qualified = self.qualified_dimension_sql(dimension, alias);
+        if Self::is_simple_identifier(dimension.sql_expr()) {
+            qualified
         } else {
-            format!("{}.{}", alias, expr)
+            format!("({qualified})")
         }
     }
 
@@ -4301,9 +6558,13 @@ impl<'a> SqlGenerator<'a> {
 
     fn quote_identifier(&self, identifier: &str) -> String {
         if Self::is_simple_identifier(identifier) {
-            identifier.to_string()
-        } else {
-            format!("\"{}\"", identifier.replace('"', "\"\""))
+            return identifier.to_string();
+        }
+        match self.dialect {
+            DialectType::BigQuery | DialectType::Databricks | DialectType::Spark => {
+                format!("`{}`", identifier.replace('`', "``"))
+            }
+            _ => format!("\"{}\"", identifier.replace('"', "\"\"")),
         }
     }
 
@@ -4385,18 +6646,21 @@ impl<'a> SqlGenerator<'a> {
         &self,
         metric: &crate::core::Metric,
         metric_name: &str,
+        model_name: &str,
         alias: &str,
-    ) -> String {
+    ) -> Result<String> {
         let raw_alias = format!("{metric_name}_raw");
-        let raw_col = format!("{alias}.{}", self.quote_identifier(&raw_alias));
-        match metric.agg.as_ref() {
-            Some(Aggregation::CountDistinct) => format!("COUNT(DISTINCT {raw_col})"),
-            Some(Aggregation::Count) => format!("COUNT({raw_col})"),
+        let raw_col = self.filtered_raw_column(
+            metric,
+            model_name,
+            format!("{alias}.{}", self.quote_identifier(&raw_alias)),
+        )?;
+        Ok(match metric.agg.as_ref() {
             Some(agg) if agg != &Aggregation::Expression => {
-                format!("{}({raw_col})", agg.as_sql())
+                self.aggregate_call_sql(agg, &raw_col)?
             }
             _ => format!("SUM({raw_col})"),
-        }
+        })
     }
 
     fn metric_expression_for_reference(
@@ -4420,28 +6684,45 @@ impl<'a> SqlGenerator<'a> {
 
         let alias = self.model_alias(&model_name);
         let expanded = match metric.r#type {
-            MetricType::Simple => self.simple_metric_reference_sql(metric, &metric_name, &alias),
+            MetricType::Simple => {
+                self.simple_metric_reference_sql(metric, &metric_name, &model_name, &alias)?
+            }
             MetricType::Derived => {
                 self.expand_derived_metric_inner(metric.sql_expr(), &model_name, visited)?
             }
             MetricType::Ratio => {
                 let num_ref = metric.numerator.as_deref().unwrap_or("1");
                 let den_ref = metric.denominator.as_deref().unwrap_or("1");
-                let num_sql = self
-                    .metric_expression_for_reference(num_ref, &model_name, visited)?
-                    .unwrap_or_else(|| num_ref.to_string());
-                let den_sql = self
-                    .metric_expression_for_reference(den_ref, &model_name, visited)?
-                    .unwrap_or_else(|| den_ref.to_string());
-                format!("({num_sql}) / NULLIF({den_sql}, 0)")
+                let num_sql = self.ratio_side_sql(num_ref, &model_name, visited)?;
+                let den_sql = self.ratio_side_sql(den_ref, &model_name, visited)?;
+                Self::ratio_sql(&num_sql, &den_sql)
             }
-            _ => metric.to_sql(Some(&alias)),
+            _ => self.metric_sql(metric, &model_name, &alias)?,
         };
 
         visited.remove(&key);
         Ok(Some(expanded))
     }
 
+    /// SQL for one side of a nested ratio: a metric reference, or an inline
+    /// (typically filtered) aggregate such as `COUNT(CASE WHEN ... END)`.
+    fn ratio_side_sql(
+        &self,
+        side: &str,
+        model_name: &str,
+        visited: &mut HashSet<(String, String, bool)>,
+    ) -> Result<String> {
+        if side.trim() == Metric::ROW_COUNT {
+            return Ok(self.row_count_sql());
+        }
+        if Self::is_inline_aggregate_expression(side) {
+            return self.rewrite_inline_aggregate_expression(side, model_name);
+        }
+        Ok(self
+            .metric_expression_for_reference(side, model_name, visited)?
+            .unwrap_or_else(|| side.to_string()))
+    }
+
     /// Expand a derived metric expression, replacing metric references with their SQL
     fn expand_derived_metric(&self, expr: &str, default_model: &str) -> Result<String> {
         let mut visited = HashSet::new();
@@ -4455,27 +6736,146 @@ impl<'a> SqlGenerator<'a> {
         visited: &mut HashSet<(String, String, bool)>,
     ) -> Result<String> {
         if Self::is_inline_aggregate_expression(expr) {
-            return self.rewrite_inline_aggregate_expression(expr, default_model);
+            let row_count_re = regex::Regex::new(&format!(r"\b{}\b", Metric::ROW_COUNT))
+                .expect("valid row count regex");
+            let row_count_sql = self.row_count_sql();
+            let expr = row_count_re.replace_all(expr, row_count_sql.as_str());
+            return self.expand_mixed_inline_aggregate_expression(&expr, default_model, visited);
         }
 
-        let mut result = expr.to_string();
-        let ref_re = regex::Regex::new(
-            r"\b([A-Za-z_][A-Za-z0-9_]*\.[A-Za-z_][A-Za-z0-9_]*|[A-Za-z_][A-Za-z0-9_]*)\b",
-        )
-        .expect("valid metric token regex");
+        self.expand_metric_tokens(expr, default_model, visited, true)
+    }
 
-        let mut replacements: Vec<(String, String)> = Vec::new();
-        let mut unresolved_tokens: Vec<String> = Vec::new();
-        for cap in ref_re.captures_iter(expr) {
-            let Some(token_match) = cap.get(1) else {
+    /// Expand an expression containing explicit aggregate calls. Metric
+    /// references outside those calls are expanded; when any are present, bare
+    /// physical columns inside the calls are qualified with the model alias.
+    fn expand_mixed_inline_aggregate_expression(
+        &self,
+        expr: &str,
+        default_model: &str,
+        visited: &mut HashSet<(String, String, bool)>,
+    ) -> Result<String> {
+        let segments = Self::split_aggregate_calls(expr);
+        let mut expanded_outside = Vec::with_capacity(segments.len());
+        let mut mixed = false;
+        for (is_aggregate, segment) in &segments {
+            if *is_aggregate {
+                expanded_outside.push(None);
                 continue;
-            };
-            let token = token_match.as_str();
-            if let Some(expanded) =
-                self.metric_expression_for_reference(token, default_model, visited)?
-            {
-                replacements.push((token.to_string(), expanded));
-            } else if self.should_error_for_unresolved_derived_token(token) {
+            }
+            let expanded = self.expand_metric_tokens(segment, default_model, visited, false)?;
+            mixed |= &expanded != segment;
+            expanded_outside.push(Some(expanded));
+        }
+        if !mixed {
+            return self.rewrite_inline_aggregate_expression(expr, default_model);
+        }
+
+        let alias = self.model_alias(default_model);
+        let mut result = String::with_capacity(expr.len());
+        for ((_, segment), expanded) in segments.iter().zip(expanded_outside) {
+            match expanded {
+                Some(expanded) => result.push_str(&expanded),
+                None => {
+                    let rewritten =
+                        self.rewrite_inline_aggregate_expression(segment, default_model)?;
+                    result.push_str(&self.qualify_expression_columns(&rewritten, &alias));
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Split `expr` into alternating plain and aggregate-call segments, flagging
+    /// the aggregate calls (including their arguments) with `true`.
+    fn split_aggregate_calls(expr: &str) -> Vec<(bool, String)> {
+        let aggregate_re = Self::inline_aggregate_regex();
+        let mut segments = Vec::new();
+        let mut cursor = 0;
+        while let Some(found) = aggregate_re.find_at(expr, cursor) {
+            let mut depth = 1;
+            let mut in_string = false;
+            let mut end = expr.len();
+            for (offset, c) in expr[found.end()..].char_indices() {
+                match c {
+                    '\'' => in_string = !in_string,
+                    '(' if !in_string => depth += 1,
+                    ')' if !in_string => {
+                        depth -= 1;
+                        if depth == 0 {
+                            end = found.end() + offset + 1;
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            if found.start() > cursor {
+                segments.push((false, expr[cursor..found.start()].to_string()));
+            }
+            segments.push((true, expr[found.start()..end].to_string()));
+            cursor = end;
+        }
+        if cursor < expr.len() {
+            segments.push((false, expr[cursor..].to_string()));
+        }
+        segments
+    }
+
+    /// Replace metric tokens in `expr` with their parenthesized SQL. With
+    /// `strict`, tokens that resolve to nothing are reported as missing metrics.
+    fn expand_metric_tokens(
+        &self,
+        expr: &str,
+        default_model: &str,
+        visited: &mut HashSet<(String, String, bool)>,
+        strict: bool,
+    ) -> Result<String> {
+        self.substitute_metric_tokens(expr, strict, &mut |token| {
+            let expanded = if token == Metric::ROW_COUNT {
+                Some(self.row_count_sql())
+            } else {
+                self.metric_expression_for_reference(token, default_model, visited)?
+            };
+            Ok(expanded.map(|sql| format!("({sql})")))
+        })
+    }
+
+    /// Expand a derived metric expression, replacing each metric reference with
+    /// the SQL `resolve` returns for it, e.g. a column of an inner query.
+    /// Unresolved references are reported as missing metrics.
+    fn expand_derived_metric_with(
+        &self,
+        expr: &str,
+        resolve: &mut dyn FnMut(&str) -> Result<Option<String>>,
+    ) -> Result<String> {
+        self.substitute_metric_tokens(expr, true, resolve)
+    }
+
+    /// Replace every token in `expr` that `resolve` maps to SQL. With `strict`,
+    /// unresolved tokens that look like metric references are errors.
+    fn substitute_metric_tokens(
+        &self,
+        expr: &str,
+        strict: bool,
+        resolve: &mut dyn FnMut(&str) -> Result<Option<String>>,
+    ) -> Result<String> {
+        let mut result = expr.to_string();
+        let ref_re = regex::Regex::new(
+            r"\b([A-Za-z_][A-Za-z0-9_]*\.[A-Za-z_][A-Za-z0-9_]*|[A-Za-z_][A-Za-z0-9_]*)\b",
+        )
+        .expect("valid metric token regex");
+
+        let mut replacements: Vec<(String, String)> = Vec::new();
+        let mut unresolved_tokens: Vec<String> = Vec::new();
+        for cap in ref_re.captures_iter(expr) {
+            let Some(token_match) = cap.get(1) else {
+                continue;
+            };
+            let token = token_match.as_str();
+            if let Some(replacement) = resolve(token)? {
+                replacements.push((token.to_string(), replacement));
+            } else if strict && self.should_error_for_unresolved_derived_token(token) {
                 unresolved_tokens.push(token.to_string());
             }
         }
@@ -4494,7 +6894,7 @@ impl<'a> SqlGenerator<'a> {
             let pattern = regex::Regex::new(&format!(r"\b{}\b", regex::escape(&token)))
                 .expect("escaped token regex");
             result = pattern
-                .replace_all(&result, format!("({replacement})"))
+                .replace_all(&result, replacement.as_str())
                 .into_owned();
         }
 
@@ -4512,12 +6912,16 @@ impl<'a> SqlGenerator<'a> {
         true
     }
 
-    fn is_inline_aggregate_expression(expr: &str) -> bool {
-        let aggregate_re = regex::Regex::new(
+    /// Matches the opening of an explicit aggregate call such as `SUM(`.
+    fn inline_aggregate_regex() -> regex::Regex {
+        regex::Regex::new(
             r"(?i)\b(SUM|AVG|COUNT|MIN|MAX|MEDIAN|MODE|PERCENTILE_CONT|PERCENTILE_DISC|QUANTILE_CONT|QUANTILE_DISC|STDDEV|STDDEV_POP|VARIANCE|VARIANCE_POP|VAR_POP)\s*\(",
         )
-        .expect("valid aggregate regex");
-        aggregate_re.is_match(expr)
+        .expect("valid aggregate regex")
+    }
+
+    fn is_inline_aggregate_expression(expr: &str) -> bool {
+        Self::inline_aggregate_regex().is_match(expr)
     }
 
     fn rewrite_inline_aggregate_expression(
@@ -4607,7 +7011,7 @@ impl<'a> SqlGenerator<'a> {
 
         let rewritten = polyglot_sql::transform_map(parsed, &|node| {
             if let Expression::Literal(Literal::String(value)) = &node {
-                if let Some(sql_date) = RelativeDate::parse(value) {
+                if let Some(sql_date) = RelativeDate::parse_for_dialect(value, self.dialect) {
                     return Ok(Expression::Raw(Raw { sql: sql_date }));
                 }
             }
@@ -4617,10 +7021,9 @@ impl<'a> SqlGenerator<'a> {
                     if let Some(model) = graph.get_model(&table.name) {
                         if let Some(dimension) = model.get_dimension(&col.name.name) {
                             return Ok(Expression::Raw(Raw {
-                                sql: format!(
-                                    "{}.{}",
-                                    self.model_alias(&model.name),
-                                    dimension.sql_expr()
+                                sql: self.dimension_operand_sql(
+                                    dimension,
+                                    &self.model_alias(&model.name),
                                 ),
                             }));
                         }
@@ -4635,14 +7038,74 @@ impl<'a> SqlGenerator<'a> {
         self.emit_expression(&rewritten)
     }
 
+    /// Render `metric` as an aggregate in the target dialect, with its `filters`
+    /// expanded the way `expand_filters` expands query filters: `{model}` and
+    /// `model.dimension` references resolve to the dimension SQL on `alias`.
+    pub(crate) fn metric_sql(
+        &self,
+        metric: &Metric,
+        model_name: &str,
+        alias: &str,
+    ) -> Result<String> {
+        if Self::is_row_count_metric(metric) {
+            return Ok(self.row_count_sql());
+        }
+        let count_argument = self.count_style.argument();
+        if metric.filters.is_empty() {
+            return Ok(metric.to_sql_with_count_argument(
+                Some(alias),
+                self.dialect,
+                count_argument,
+            ));
+        }
+        let mut metric = metric.clone();
+        metric.filters = metric
+            .filters
+            .iter()
+            .map(|filter| self.expand_metric_filter(filter, model_name, alias))
+            .collect::<Result<_>>()?;
+        Ok(metric.to_sql_with_count_argument(Some(alias), self.dialect, count_argument))
+    }
+
+    fn expand_metric_filter(&self, filter: &str, model_name: &str, alias: &str) -> Result<String> {
+        let filter = self.expand_relative_dates(&filter.replace("{model}", model_name));
+        let parsed = self.parse_where_expr(&filter)?;
+        let model = self.graph.get_model(model_name);
+
+        let rewritten = polyglot_sql::transform_map(parsed, &|node| {
+            if let Expression::Column(col) = &node {
+                let own = col
+                    .table
+                    .as_ref()
+                    .is_some_and(|table| table.name == model_name || table.name == alias);
+                if own {
+                    if let Some(dimension) = model.and_then(|m| m.get_dimension(&col.name.name)) {
+                        return Ok(Expression::Raw(Raw {
+                            sql: self.dimension_operand_sql(dimension, alias),
+                        }));
+                    }
+                    let mut col = col.clone();
+                    col.table = Some(Identifier::new(alias));
+                    return Ok(Expression::Column(col));
+                }
+            }
+            Ok(node)
+        })
+        .map_err(|e| SidemanticError::SqlGeneration(e.to_string()))?;
+
+        self.emit_expression(&rewritten)
+    }
+
     /// Expand filter expressions, replacing model.field references and relative dates
     fn expand_filters(&self, filters: &[String]) -> Result<Vec<String>> {
         let mut expanded = Vec::new();
 
         for filter in filters {
-            let relative_expanded = self.expand_relative_dates(filter);
+            let filter = self.expand_date_range_filter(filter)?;
+            let rendered = self.render_semi_structured_sql(&filter);
+            let relative_expanded = self.expand_relative_dates(&rendered);
             if let Ok(expanded_filter) = self.expand_filter_with_polyglot(&relative_expanded) {
-                expanded.push(expanded_filter);
+                expanded.push(self.render_json_path_operators(&expanded_filter));
                 continue;
             }
 
@@ -4655,7 +7118,7 @@ impl<'a> SqlGenerator<'a> {
 
                 // Replace model references with aliases
                 for dim in &model.dimensions {
-                    let replacement = format!("{}.{}", alias, dim.sql_expr());
+                    let replacement = self.dimension_operand_sql(dim, &alias);
                     let model_pattern = format!("{}.{}", model.name, dim.name);
                     let cte_pattern = format!("{}.{}", cte_name, dim.name);
                     expanded_filter = expanded_filter.replace(&model_pattern, &replacement);
@@ -4667,12 +7130,57 @@ impl<'a> SqlGenerator<'a> {
             // e.g., "created_at >= 'last 7 days'" -> "created_at >= CURRENT_DATE - 7"
             expanded_filter = self.expand_relative_dates(&expanded_filter);
 
-            expanded.push(expanded_filter);
+            expanded.push(self.render_json_path_operators(&expanded_filter));
         }
 
         Ok(expanded)
     }
 
+    /// Rewrite a `model.time_dimension in <range>` filter (e.g. `in last 7 days`,
+    /// `in 2024-Q1`) into a half-open comparison on the same reference. Other
+    /// filters are returned unchanged.
+    fn expand_date_range_filter(&self, filter: &str) -> Result<String> {
+        let range_re = regex::Regex::new(
+            r#"(?is)^\s*([A-Za-z_][A-Za-z0-9_]*)\.([A-Za-z_][A-Za-z0-9_]*)\s+in\s+(?:'([^']*)'|"([^"]*)"|([^'"(].*?))\s*$"#,
+        )
+        .expect("valid date range filter regex");
+        let Some(cap) = range_re.captures(filter) else {
+            return Ok(filter.to_string());
+        };
+        let value = cap
+            .get(3)
+            .or_else(|| cap.get(4))
+            .or_else(|| cap.get(5))
+            .map(|m| m.as_str())
+            .unwrap_or("");
+        let (model_name, dimension_name) = (&cap[1], &cap[2]);
+        let Some(dimension) = self
+            .graph
+            .get_model(model_name)
+            .and_then(|model| model.get_dimension(dimension_name))
+        else {
+            return Ok(filter.to_string());
+        };
+        let is_time = dimension.r#type == crate::core::DimensionType::Time;
+        let range = match DateRange::parse(value) {
+            Some(range) if is_time => range,
+            Some(_) => {
+                return Err(SidemanticError::Validation(format!(
+                    "Date range filter '{filter}' requires a time dimension, \
+                     but '{model_name}.{dimension_name}' is not one"
+                )));
+            }
+            None if is_time => {
+                return Err(SidemanticError::Validation(format!(
+                    "Filter '{filter}' has an unrecognized date range '{value}'"
+                )));
+            }
+            None => return Ok(filter.to_string()),
+        };
+
+        Ok(range.to_sql(&format!("{model_name}.{dimension_name}"), self.dialect))
+    }
+
     /// Expand relative date expressions in a filter string
     fn expand_relative_dates(&self, filter: &str) -> String {
         let comparison_re = regex::Regex::new(r#"^(.+?)\s*(>=|<=|>|<|=)\s*['"](.+?)['"]$"#)
@@ -4684,11 +7192,13 @@ impl<'a> SqlGenerator<'a> {
 
             if RelativeDate::is_relative_date(value) {
                 if operator == "=" {
-                    if let Some(range_sql) = RelativeDate::to_range(value, column) {
+                    if let Some(range_sql) =
+                        RelativeDate::to_range_for_dialect(value, column, self.dialect)
+                    {
                         return range_sql;
                     }
                 } else if matches!(operator, ">=" | ">") {
-                    if let Some(sql_date) = RelativeDate::parse(value) {
+                    if let Some(sql_date) = RelativeDate::parse_for_dialect(value, self.dialect) {
                         return format!("{column} {operator} {sql_date}");
                     }
                 }
@@ -4703,7 +7213,7 @@ impl<'a> SqlGenerator<'a> {
             let quoted = &cap[0];
             let inner = &cap[1];
 
-            if let Some(sql_date) = RelativeDate::parse(inner) {
+            if let Some(sql_date) = RelativeDate::parse_for_dialect(inner, self.dialect) {
                 result = result.replace(quoted, &sql_date);
             }
         }
@@ -4722,15 +7232,18 @@ impl<'a> SqlGenerator<'a> {
 
         // For each model we join to, check if the path has fan-out
         for (model, path) in join_paths {
-            if path.has_fan_out() {
-                // All models BEFORE the fan-out boundary are at risk
-                // The base model's metrics can be inflated if we join to a "many" side
-                if let Some(boundary) = path.fan_out_boundary() {
-                    // If we're joining to a model that causes fan-out,
-                    // the base model's metrics are at risk
-                    if model != boundary {
-                        at_risk.insert(base_model.to_string());
-                    }
+            // All models BEFORE the fan-out boundary are at risk
+            // The base model's metrics can be inflated if we join to a "many" side
+            let boundary = path
+                .steps
+                .iter()
+                .find(|step| self.graph.step_fans_out(step))
+                .map(|step| step.to_model.as_str());
+            if let Some(boundary) = boundary {
+                // If we're joining to a model that causes fan-out,
+                // the base model's metrics are at risk
+                if model != boundary {
+                    at_risk.insert(base_model.to_string());
                 }
             }
         }
@@ -4745,12 +7258,16 @@ impl<'a> SqlGenerator<'a> {
             // then metrics from this model might be duplicated
             // This is detected by checking if any step is one_to_many
             for step in &path.steps {
-                if step.causes_fan_out() {
+                if self.graph.step_fans_out(step) {
                     // The TO model of this step's metrics would be duplicated
                     // when viewed from the base model's grain
                     at_risk.insert(step.from_model.clone());
                 }
-                if step.relationship_type == RelationshipType::ManyToOne {
+                if step.relationship_type == RelationshipType::ManyToOne
+                    && !self
+                        .graph
+                        .keys_cover_grain(&step.from_model, &step.from_keys)
+                {
                     // Joining from a many-side base grain to a one-side model duplicates
                     // metrics owned by the one-side model across the base rows.
                     at_risk.insert(step.to_model.clone());
@@ -4761,31 +7278,70 @@ impl<'a> SqlGenerator<'a> {
         at_risk
     }
 
-    /// Resolve segment references to SQL filter expressions
-    fn resolve_segments(&self, segments: &[String]) -> Result<Vec<String>> {
-        let mut filters = Vec::new();
+    /// Resolve a query's segments to WHERE predicates: one per entry in
+    /// `segments`, plus one for `segment_expr` when set.
+    fn resolve_segments(&self, query: &SemanticQuery) -> Result<Vec<String>> {
+        let mut filters = query
+            .segments
+            .iter()
+            .map(|seg_ref| self.resolve_segment_sql(seg_ref))
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(expr) = &query.segment_expr {
+            filters.push(self.render_segment_expr(expr)?);
+        }
+        Ok(filters)
+    }
 
-        for seg_ref in segments {
-            // Parse model.segment format
-            let (model_name, segment_name, _) = self.graph.parse_reference(seg_ref)?;
+    /// Render a segment expression with each segment's SQL parenthesized. `Or`
+    /// is always parenthesized so the result can be ANDed with other filters.
+    fn render_segment_expr(&self, expr: &SegmentFilter) -> Result<String> {
+        let render_operand = |operand: &SegmentFilter| -> Result<String> {
+            let sql = self.render_segment_expr(operand)?;
+            Ok(match operand {
+                SegmentFilter::And(_) => format!("({sql})"),
+                _ => sql,
+            })
+        };
+        let render_all = |operands: &[SegmentFilter], op: &str| -> Result<String> {
+            if operands.is_empty() {
+                return Err(SidemanticError::Validation(format!(
+                    "Segment expression {op} needs at least one operand"
+                )));
+            }
+            Ok(operands
+                .iter()
+                .map(render_operand)
+                .collect::<Result<Vec<_>>>()?
+                .join(&format!(" {op} ")))
+        };
 
-            let model = self.graph.get_model(&model_name).ok_or_else(|| {
-                let available: Vec<&str> = self.graph.models().map(|m| m.name.as_str()).collect();
-                SidemanticError::model_not_found(&model_name, &available)
-            })?;
+        match expr {
+            SegmentFilter::Segment(seg_ref) => {
+                Ok(format!("({})", self.resolve_segment_sql(seg_ref)?))
+            }
+            SegmentFilter::And(operands) => render_all(operands, "AND"),
+            SegmentFilter::Or(operands) => Ok(format!("({})", render_all(operands, "OR")?)),
+            SegmentFilter::Not(operand) => Ok(format!("NOT {}", render_operand(operand)?)),
+        }
+    }
 
-            let segment = model.get_segment(&segment_name).ok_or_else(|| {
-                let available: Vec<&str> = model.segments.iter().map(|s| s.name.as_str()).collect();
-                SidemanticError::segment_not_found(&model_name, &segment_name, &available)
-            })?;
+    fn resolve_segment_sql(&self, seg_ref: &str) -> Result<String> {
+        // Parse model.segment format
+        let (model_name, segment_name, _) = self.graph.parse_reference(seg_ref)?;
 
-            // Get SQL with model alias replaced
-            let alias = self.model_alias(&model_name);
-            let filter_sql = segment.get_sql(&alias);
-            filters.push(filter_sql);
-        }
+        let model = self.graph.get_model(&model_name).ok_or_else(|| {
+            let available: Vec<&str> = self.graph.models().map(|m| m.name.as_str()).collect();
+            SidemanticError::model_not_found(&model_name, &available)
+        })?;
 
-        Ok(filters)
+        let segment = model.get_segment(&segment_name).ok_or_else(|| {
+            let available: Vec<&str> = model.segments.iter().map(|s| s.name.as_str()).collect();
+            SidemanticError::segment_not_found(&model_name, &segment_name, &available)
+        })?;
+
+        // Get SQL with model alias replaced
+        let alias = self.model_alias(&model_name);
+        Ok(segment.get_sql(&alias))
     }
 }
 
@@ -4793,10 +7349,21 @@ impl<'a> SqlGenerator<'a> {
 mod tests {
     use super::*;
     use crate::core::{
-        Aggregation, CohortInnerMetric, ComparisonType, Dimension, Metric, MetricType, Model,
-        Relationship,
+        Aggregation, CohortInnerMetric, ComparisonCalculation, ComparisonType, Dimension, JoinType,
+        Metric, MetricType, Model, PreAggregation, PreAggregationType, Relationship, Segment,
+        TimeGrain,
     };
 
+    /// Parse generated SQL on a large stack; nested window/derived subqueries
+    /// exceed the default test thread stack in polyglot's debug-build parser.
+    fn assert_parses(sql: &str) {
+        let parsed = parse_all_with_large_stack(vec![sql.to_string()], SOURCE_DIALECT).unwrap();
+        let [Ok(statements)] = parsed.as_slice() else {
+            panic!("failed to parse: {parsed:?}\n{sql}");
+        };
+        assert_eq!(statements.len(), 1, "{sql}");
+    }
+
     fn create_test_graph() -> SemanticGraph {
         let mut graph = SemanticGraph::new();
 
@@ -4991,6 +7558,93 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_derived_metric_divides_by_implicit_row_count() {
+        let mut graph = SemanticGraph::new();
+        let orders = Model::new("orders", "order_id")
+            .with_table("orders")
+            .with_dimension(Dimension::categorical("status"))
+            .with_metric(Metric::sum("revenue", "amount"))
+            .with_metric(Metric::derived("revenue_per_row", "revenue / __row_count"))
+            .with_metric(Metric::derived(
+                "amount_per_row",
+                "SUM(amount) / __row_count",
+            ));
+        graph.add_model(orders).unwrap();
+
+        let sql = SqlGenerator::new(&graph)
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec![
+                        "orders.revenue_per_row".into(),
+                        "orders.amount_per_row".into(),
+                    ])
+                    .with_dimensions(vec!["orders.status".into()]),
+            )
+            .unwrap();
+
+        assert!(
+            sql.contains("(SUM(orders_cte.revenue_raw)) / (COUNT(*)) AS revenue_per_row"),
+            "{sql}"
+        );
+        assert!(sql.contains("/ COUNT(*) AS amount_per_row"), "{sql}");
+        assert!(!sql.contains("__row_count"), "{sql}");
+        assert!(!sql.contains("SUM(COUNT(*))"), "{sql}");
+    }
+
+    #[test]
+    fn test_count_style_controls_row_count_rendering() {
+        let mut graph = SemanticGraph::new();
+        let orders = Model::new("orders", "order_id")
+            .with_table("orders")
+            .with_dimension(Dimension::categorical("status"))
+            .with_metric(Metric::sum("revenue", "amount"))
+            .with_metric(Metric::derived("revenue_per_row", "revenue / __row_count"));
+        graph.add_model(orders).unwrap();
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue_per_row".into()])
+            .with_dimensions(vec!["orders.status".into()]);
+
+        let star = SqlGenerator::new(&graph).generate(&query).unwrap();
+        assert!(star.contains("/ (COUNT(*)) AS revenue_per_row"), "{star}");
+
+        let one = SqlGenerator::new(&graph)
+            .with_count_style(CountStyle::One)
+            .generate(&query)
+            .unwrap();
+        assert!(one.contains("/ (COUNT(1)) AS revenue_per_row"), "{one}");
+        assert!(!one.contains("COUNT(*)"), "{one}");
+
+        let count_query = SemanticQuery::new()
+            .with_metrics(vec!["orders.order_count".into()])
+            .with_dimensions(vec!["orders.status".into()]);
+        let mut graph = create_test_graph();
+        let one = SqlGenerator::new(&graph)
+            .with_count_style(CountStyle::One)
+            .generate(&count_query)
+            .unwrap();
+        assert!(one.contains("COUNT(1) AS order_count"), "{one}");
+
+        // Counting a joined model keeps counting its raw column, so
+        // unmatched rows are not counted.
+        let customers = graph
+            .get_model("customers")
+            .unwrap()
+            .clone()
+            .with_metric(Metric::count("customer_count"));
+        graph.replace_model(customers).unwrap();
+        let joined = SqlGenerator::new(&graph)
+            .with_count_style(CountStyle::One)
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec!["customers.customer_count".into()])
+                    .with_dimensions(vec!["orders.status".into()]),
+            )
+            .unwrap();
+        assert!(joined.contains("customer_count_raw"), "{joined}");
+        assert!(!joined.contains("COUNT(1)"), "{joined}");
+    }
+
     #[test]
     fn test_derived_metric_includes_simple_raw_dependencies() {
         let mut graph = SemanticGraph::new();
@@ -5052,6 +7706,34 @@ models:
         );
     }
 
+    #[test]
+    fn test_derived_metric_mixes_metric_reference_and_inline_aggregate() {
+        let graph = crate::config::load_from_string(
+            r#"
+models:
+  - name: orders
+    table: orders
+    primary_key: order_id
+    metrics:
+      - name: revenue
+        agg: sum
+        sql: amount
+      - name: revenue_vs_target
+        type: derived
+        sql: revenue / MAX(target)
+"#,
+        )
+        .unwrap();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new().with_metrics(vec!["orders.revenue_vs_target".into()]);
+
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(sql.contains("MAX(orders_cte.target)"), "{sql}");
+        assert!(!sql.contains("revenue / MAX"), "{sql}");
+    }
+
     #[test]
     fn test_ordered_set_aggregate_metric_is_not_treated_as_metric_reference() {
         let mut graph = SemanticGraph::new();
@@ -5093,415 +7775,2562 @@ models:
     }
 
     #[test]
-    fn test_semantic_order_by_rewrites_to_output_aliases() {
+    fn test_metric_filter_routes_to_having_with_aggregate() {
         let graph = create_test_graph();
         let generator = SqlGenerator::new(&graph);
 
         let query = SemanticQuery::new()
             .with_metrics(vec!["orders.revenue".into()])
             .with_dimensions(vec!["orders.status".into()])
-            .with_order_by(vec![
-                "orders.revenue DESC".into(),
-                "orders.status ASC".into(),
+            .with_filters(vec![
+                "orders.revenue > 1000".into(),
+                "orders.status = 'completed'".into(),
             ]);
 
         let sql = generator.generate(&query).unwrap();
 
         assert!(
-            sql.contains("ORDER BY revenue DESC, status ASC"),
-            "expected semantic order by refs to use output aliases: {sql}"
-        );
-        assert!(
-            !sql.contains("ORDER BY orders.revenue"),
-            "semantic metric ref leaked into ORDER BY: {sql}"
+            sql.contains("HAVING SUM(orders_cte.revenue_raw) > 1000"),
+            "{sql}"
         );
+        assert!(sql.contains("WHERE status = 'completed'"), "{sql}");
+        let where_clause = sql
+            .split("WHERE")
+            .nth(1)
+            .and_then(|rest| rest.lines().next())
+            .unwrap_or_default();
+        assert!(!where_clause.contains("revenue"), "{sql}");
     }
 
     #[test]
-    fn test_time_comparison_lag_partitions_by_non_time_dimensions() {
+    fn test_having_filter_expands_ratio_metric() {
         let mut graph = SemanticGraph::new();
         let orders = Model::new("orders", "order_id")
             .with_table("orders")
-            .with_dimension(Dimension::time("order_date").with_sql("created_at"))
             .with_dimension(Dimension::categorical("status"))
             .with_metric(Metric::sum("revenue", "amount"))
-            .with_metric(Metric::time_comparison(
-                "revenue_mom",
-                "revenue",
-                ComparisonType::Mom,
-            ));
+            .with_metric(Metric::sum("profit", "amount - cost"))
+            .with_metric(Metric::ratio("profit_margin", "profit", "revenue"));
         graph.add_model(orders).unwrap();
-
         let generator = SqlGenerator::new(&graph);
+
         let query = SemanticQuery::new()
-            .with_metrics(vec!["orders.revenue_mom".into()])
-            .with_dimensions(vec![
-                "orders.order_date__month".into(),
-                "orders.status".into(),
+            .with_metrics(vec!["orders.profit_margin".into()])
+            .with_dimensions(vec!["orders.status".into()])
+            .with_filters(vec![
+                "orders.profit_margin > 0.1".into(),
+                "orders.status = 'completed'".into(),
             ]);
 
         let sql = generator.generate(&query).unwrap();
 
         assert!(
             sql.contains(
-                "LAG(base.revenue, 1) OVER (PARTITION BY base.status ORDER BY base.order_date__month)"
+                "HAVING ((SUM(orders_cte.profit_raw)) / NULLIF(SUM(orders_cte.revenue_raw), 0)) > 0.1"
             ),
-            "time comparison lag must partition by non-time dimensions: {sql}"
+            "{sql}"
         );
+        assert!(sql.contains("WHERE status = 'completed'"), "{sql}");
+        assert!(!sql.contains("profit_margin > 0.1"), "{sql}");
     }
 
     #[test]
-    fn test_conversion_query_applies_filters_limit_and_offset() {
+    fn test_attainment_metric_divides_actual_by_target() {
         let mut graph = SemanticGraph::new();
-        let events = Model::new("events", "event_id")
-            .with_table("events")
-            .with_dimension(Dimension::categorical("user_id"))
-            .with_dimension(Dimension::categorical("event_type"))
-            .with_dimension(Dimension::time("event_date"))
+        let sales = Model::new("sales", "sale_id")
+            .with_table("sales")
             .with_dimension(Dimension::categorical("region"))
-            .with_metric(Metric {
-                name: "signup_conversion".to_string(),
-                r#type: MetricType::Conversion,
-                agg: None,
-                entity: Some("user_id".to_string()),
-                base_event: Some("signup".to_string()),
-                conversion_event: Some("purchase".to_string()),
-                conversion_window: Some("7 days".to_string()),
-                filters: vec!["events.region = 'US'".to_string()],
-                ..Metric::new("signup_conversion")
-            });
-        graph.add_model(events).unwrap();
-
+            .with_metric(Metric::sum("revenue", "amount"))
+            .with_metric(Metric::sum("target_revenue", "target_amount"))
+            .with_metric(Metric::attainment(
+                "revenue_attainment",
+                "revenue",
+                "target_revenue",
+            ))
+            .with_metric(Metric::variance_to_target(
+                "revenue_variance",
+                "revenue",
+                "target_revenue",
+            ));
+        graph.add_model(sales).unwrap();
         let generator = SqlGenerator::new(&graph);
+
         let query = SemanticQuery::new()
-            .with_metrics(vec!["events.signup_conversion".into()])
-            .with_dimensions(vec!["events.region".into()])
-            .with_filters(vec!["events.event_date >= '2024-01-01'".into()])
-            .with_order_by(vec!["events.signup_conversion DESC".into()])
-            .with_limit(5)
-            .with_offset(10);
+            .with_metrics(vec![
+                "sales.revenue_attainment".into(),
+                "sales.revenue_variance".into(),
+            ])
+            .with_dimensions(vec!["sales.region".into()]);
 
         let sql = generator.generate(&query).unwrap();
 
-        assert!(sql.contains("event_type = 'signup'"), "{sql}");
-        assert!(sql.contains("event_type = 'purchase'"), "{sql}");
-        assert!(sql.contains("(event_date >= '2024-01-01')"), "{sql}");
-        assert!(sql.contains("(region = 'US')"), "{sql}");
+        assert!(sql.contains("amount AS revenue_raw"), "{sql}");
+        assert!(sql.contains("target_amount AS target_revenue_raw"), "{sql}");
         assert!(
-            sql.contains("base_events.region IS NOT DISTINCT FROM conversions.region"),
+            sql.contains(
+                "(SUM(sales_cte.revenue_raw)) / NULLIF(SUM(sales_cte.target_revenue_raw), 0) AS revenue_attainment"
+            ),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(
+                "(SUM(sales_cte.revenue_raw)) - (SUM(sales_cte.target_revenue_raw)) AS revenue_variance"
+            ),
             "{sql}"
         );
-        assert!(sql.contains("ORDER BY signup_conversion DESC"), "{sql}");
-        assert!(sql.contains("LIMIT 5"), "{sql}");
-        assert!(sql.contains("OFFSET 10"), "{sql}");
     }
 
     #[test]
-    fn test_multistep_conversion_query_generates_step_ctes() {
-        let mut graph = SemanticGraph::new();
-        let events = Model::new("events", "event_id")
-            .with_table("events")
-            .with_dimension(Dimension::categorical("user_id"))
-            .with_dimension(Dimension::categorical("event_type"))
-            .with_dimension(Dimension::time("event_date"))
-            .with_dimension(Dimension::categorical("region"))
-            .with_metric(Metric {
-                name: "signup_funnel".to_string(),
-                r#type: MetricType::Conversion,
-                agg: None,
-                entity: Some("user_id".to_string()),
-                steps: Some(vec![
-                    "event_type = 'signup'".to_string(),
-                    "event_type = 'purchase'".to_string(),
-                ]),
-                ..Metric::new("signup_funnel")
-            });
-        graph.add_model(events).unwrap();
-
+    fn test_semantic_order_by_rewrites_to_output_aliases() {
+        let graph = create_test_graph();
         let generator = SqlGenerator::new(&graph);
+
         let query = SemanticQuery::new()
-            .with_metrics(vec!["events.signup_funnel".into()])
-            .with_dimensions(vec!["events.region".into()])
-            .with_filters(vec!["events.region = 'US'".into()]);
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.status".into()])
+            .with_order_by(vec![
+                "orders.revenue DESC".into(),
+                "orders.status ASC".into(),
+            ]);
 
         let sql = generator.generate(&query).unwrap();
 
-        assert!(sql.contains("step_1 AS"), "{sql}");
-        assert!(sql.contains("step_2 AS"), "{sql}");
-        assert!(sql.contains("s.__ts >= step_1.step_1_ts"), "{sql}");
         assert!(
-            sql.contains("s.__dim_0 IS NOT DISTINCT FROM step_1.region"),
-            "{sql}"
+            sql.contains("ORDER BY revenue DESC, status ASC"),
+            "expected semantic order by refs to use output aliases: {sql}"
         );
-        assert!(sql.contains("s.__step_match AND s.__filter_match"), "{sql}");
         assert!(
-            sql.contains("COUNT(DISTINCT step_2.entity) AS signup_funnel"),
-            "{sql}"
+            !sql.contains("ORDER BY orders.revenue"),
+            "semantic metric ref leaked into ORDER BY: {sql}"
         );
-        assert!(sql.contains("LEFT JOIN step_2"), "{sql}");
     }
 
     #[test]
-    fn test_retention_query_generates_retention_ctes() {
+    fn test_time_comparison_lag_partitions_by_non_time_dimensions() {
         let mut graph = SemanticGraph::new();
-        let events = Model::new("events", "event_id")
-            .with_table("events")
-            .with_dimension(Dimension::categorical("user_id"))
-            .with_dimension(Dimension::categorical("event_type"))
-            .with_dimension(Dimension::time("event_date"))
-            .with_metric(Metric {
-                name: "signup_retention".to_string(),
-                r#type: MetricType::Retention,
-                agg: None,
-                entity: Some("user_id".to_string()),
-                cohort_event: Some("event_type = 'signup'".to_string()),
-                activity_event: Some("event_type = 'active'".to_string()),
-                periods: Some(7),
-                retention_granularity: Some("day".to_string()),
-                ..Metric::new("signup_retention")
-            });
-        graph.add_model(events).unwrap();
+        let orders = Model::new("orders", "order_id")
+            .with_table("orders")
+            .with_dimension(Dimension::time("order_date").with_sql("created_at"))
+            .with_dimension(Dimension::categorical("status"))
+            .with_metric(Metric::sum("revenue", "amount"))
+            .with_metric(Metric::time_comparison(
+                "revenue_mom",
+                "revenue",
+                ComparisonType::Mom,
+            ));
+        graph.add_model(orders).unwrap();
 
         let generator = SqlGenerator::new(&graph);
         let query = SemanticQuery::new()
-            .with_metrics(vec!["events.signup_retention".into()])
-            .with_limit(5)
-            .with_offset(10);
+            .with_metrics(vec!["orders.revenue_mom".into()])
+            .with_dimensions(vec![
+                "orders.order_date__month".into(),
+                "orders.status".into(),
+            ]);
 
         let sql = generator.generate(&query).unwrap();
 
-        assert!(sql.contains("WITH cohorts AS"), "{sql}");
-        assert!(sql.contains("retention AS"), "{sql}");
-        assert!(sql.contains("cohort_sizes AS"), "{sql}");
-        assert!(sql.contains("r.periods_since AS days_since"), "{sql}");
-        assert!(sql.contains("retention_pct"), "{sql}");
-        assert!(sql.contains("<= 7"), "{sql}");
-        assert!(sql.contains("OFFSET 10"), "{sql}");
+        assert!(
+            sql.contains(
+                "LAG(base.revenue, 1) OVER (PARTITION BY base.status ORDER BY base.order_date__month)"
+            ),
+            "time comparison lag must partition by non-time dimensions: {sql}"
+        );
     }
 
     #[test]
-    fn test_cohort_query_generates_inner_and_outer_aggregation() {
+    fn test_time_comparison_yoy_percent_change_and_custom_offset() {
         let mut graph = SemanticGraph::new();
-        let events = Model::new("events", "event_id")
-            .with_table("events")
-            .with_dimension(Dimension::categorical("user_id"))
-            .with_dimension(Dimension::categorical("platform").with_sql("raw_platform"))
-            .with_dimension(Dimension::categorical("region"))
-            .with_metric(Metric {
-                name: "multi_platform_users".to_string(),
-                r#type: MetricType::Cohort,
-                agg: Some(Aggregation::Count),
-                entity: Some("user_id".to_string()),
-                inner_metrics: Some(vec![CohortInnerMetric {
-                    name: "platform_count".to_string(),
-                    agg: Some(Aggregation::CountDistinct),
-                    sql: Some("platform".to_string()),
-                }]),
-                entity_dimensions: Some(vec!["region".to_string()]),
-                having: Some("platform_count >= 2".to_string()),
-                ..Metric::new("multi_platform_users")
-            });
-        graph.add_model(events).unwrap();
+        let orders = Model::new("orders", "order_id")
+            .with_table("orders")
+            .with_dimension(Dimension::time("order_date").with_sql("created_at"))
+            .with_metric(Metric::sum("revenue", "amount"))
+            .with_metric(
+                Metric::time_comparison("revenue_yoy", "revenue", ComparisonType::Yoy)
+                    .with_calculation(ComparisonCalculation::PercentChange),
+            )
+            .with_metric(
+                Metric::time_comparison(
+                    "revenue_vs_last_quarter",
+                    "revenue",
+                    ComparisonType::PriorPeriod,
+                )
+                .with_time_offset("1 quarter")
+                .with_calculation(ComparisonCalculation::Difference),
+            )
+            .with_metric(
+                Metric::time_comparison(
+                    "revenue_vs_yesterday",
+                    "revenue",
+                    ComparisonType::PriorPeriod,
+                )
+                .with_time_offset("1 day"),
+            );
+        graph.add_model(orders).unwrap();
 
         let generator = SqlGenerator::new(&graph);
-        let query = SemanticQuery::new().with_metrics(vec!["events.multi_platform_users".into()]);
-
+        let query = SemanticQuery::new()
+            .with_metrics(vec![
+                "orders.revenue_yoy".into(),
+                "orders.revenue_vs_last_quarter".into(),
+            ])
+            .with_dimensions(vec!["orders.order_date__month".into()]);
         let sql = generator.generate(&query).unwrap();
 
-        assert!(sql.contains("WITH cohort_sub AS"), "{sql}");
         assert!(
-            sql.contains("COUNT(DISTINCT raw_platform) AS platform_count"),
+            sql.contains("SUM(orders_cte.revenue_raw) AS revenue"),
             "{sql}"
         );
-        assert!(sql.contains("region AS region"), "{sql}");
-        assert!(sql.contains("cohort_sub.region"), "{sql}");
-        assert!(sql.contains("HAVING platform_count >= 2"), "{sql}");
         assert!(
-            sql.contains("COUNT(DISTINCT cohort_sub.entity) AS multi_platform_users"),
+            sql.contains(
+                "LAG(base.revenue, 12) OVER (ORDER BY base.order_date__month) AS revenue_yoy_prev_value"
+            ),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(
+                "((revenue - revenue_yoy_prev_value) / NULLIF(revenue_yoy_prev_value, 0) * 100) AS revenue_yoy"
+            ),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(
+                "LEFT JOIN base AS revenue_vs_last_quarter_prior ON revenue_vs_last_quarter_prior.order_date__month = base.order_date__month - INTERVAL '3 months'"
+            ),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(
+                "revenue_vs_last_quarter_prior.revenue AS revenue_vs_last_quarter_prev_value"
+            ),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(
+                "(revenue - revenue_vs_last_quarter_prev_value) AS revenue_vs_last_quarter"
+            ),
+            "{sql}"
+        );
+        assert_parses(&sql);
+
+        let err = generator
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec!["orders.revenue_vs_yesterday".into()])
+                    .with_dimensions(vec!["orders.order_date__month".into()]),
+            )
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("a day offset cannot be expressed in month periods"),
+            "{err}"
+        );
+
+        let sql = SqlGenerator::new_with_dialect(&graph, DialectType::Snowflake)
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec!["orders.revenue_vs_last_quarter".into()])
+                    .with_dimensions(vec!["orders.order_date__month".into()]),
+            )
+            .unwrap();
+        assert!(
+            sql.contains(
+                "revenue_vs_last_quarter_prior.order_date__month = DATEADD(quarter, -1, base.order_date__month)"
+            ),
             "{sql}"
         );
-        assert!(sql.contains("GROUP BY 1"), "{sql}");
     }
 
     #[test]
-    fn test_conversion_metric_cannot_mix_with_regular_metric() {
+    fn test_time_comparison_over_derived_of_cumulative_metrics() {
         let mut graph = SemanticGraph::new();
-        let events = Model::new("events", "event_id")
-            .with_table("events")
-            .with_dimension(Dimension::categorical("user_id"))
-            .with_dimension(Dimension::categorical("event_type"))
-            .with_dimension(Dimension::time("event_date"))
-            .with_metric(Metric::count("event_count"))
+        let orders = Model::new("orders", "order_id")
+            .with_table("orders")
+            .with_dimension(Dimension::time("order_date").with_sql("created_at"))
+            .with_metric(Metric::sum("gbv", "amount"))
+            .with_metric(Metric::count("bookings"))
             .with_metric(Metric {
-                name: "signup_conversion".to_string(),
-                r#type: MetricType::Conversion,
-                agg: None,
-                entity: Some("user_id".to_string()),
-                base_event: Some("signup".to_string()),
-                conversion_event: Some("purchase".to_string()),
-                ..Metric::new("signup_conversion")
-            });
-        graph.add_model(events).unwrap();
+                grain_to_date: Some(TimeGrain::Year),
+                ..Metric::cumulative("ytd_gbv", "gbv")
+            })
+            .with_metric(Metric {
+                grain_to_date: Some(TimeGrain::Year),
+                ..Metric::cumulative("ytd_bookings", "bookings")
+            })
+            .with_metric(Metric::derived("ytd_abv", "ytd_gbv / ytd_bookings"))
+            .with_metric(Metric::time_comparison(
+                "yoy_ytd_abv_growth",
+                "ytd_abv",
+                ComparisonType::Yoy,
+            ));
+        graph.add_model(orders).unwrap();
 
-        let generator = SqlGenerator::new(&graph);
-        let query = SemanticQuery::new().with_metrics(vec![
-            "events.signup_conversion".into(),
-            "events.event_count".into(),
-        ]);
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.yoy_ytd_abv_growth".into()])
+            .with_dimensions(vec!["orders.order_date__month".into()]);
+        let sql = SqlGenerator::new(&graph).generate(&query).unwrap();
 
-        let err = generator.generate(&query).unwrap_err();
         assert!(
-            err.to_string()
-                .contains("Conversion metrics cannot be combined"),
-            "{err}"
+            sql.contains(
+                "SUM(base.gbv) OVER (PARTITION BY DATE_TRUNC('year', base.order_date__month)"
+            ),
+            "{sql}"
+        );
+        assert!(
+            sql.contains("base.ytd_gbv / base.ytd_bookings AS ytd_abv"),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(
+                "LAG(base.ytd_abv, 12) OVER (ORDER BY base.order_date__month) AS yoy_ytd_abv_growth_prev_value"
+            ),
+            "{sql}"
+        );
+        assert_parses(&sql);
+    }
+
+    #[test]
+    fn test_semi_additive_metric_sums_last_snapshot_per_group() {
+        let mut graph = SemanticGraph::new();
+        let mut ending_balance = Metric::sum("ending_balance", "balance");
+        ending_balance.non_additive_dimension = Some("snapshot_date".into());
+        let accounts = Model::new("balances", "balance_id")
+            .with_table("balances")
+            .with_dimension(Dimension::categorical("account_type"))
+            .with_dimension(Dimension::time("snapshot_date"))
+            .with_metric(ending_balance);
+        graph.add_model(accounts).unwrap();
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["balances.ending_balance".into()])
+            .with_dimensions(vec![
+                "balances.account_type".into(),
+                "balances.snapshot_date__month".into(),
+            ]);
+        let sql = SqlGenerator::new(&graph).generate(&query).unwrap();
+
+        assert!(
+            sql.contains(
+                "CASE WHEN snapshot_date = MAX(snapshot_date) OVER (PARTITION BY account_type, \
+                 DATE_TRUNC('month', snapshot_date)) THEN balance END AS ending_balance_raw"
+            ),
+            "{sql}"
+        );
+        assert!(
+            sql.contains("SUM(balances_cte.ending_balance_raw) AS ending_balance"),
+            "{sql}"
+        );
+        assert_parses(&sql);
+    }
+
+    #[test]
+    fn test_semi_additive_metric_rejects_joined_dimension() {
+        let mut graph = SemanticGraph::new();
+        let mut ending_balance = Metric::sum("ending_balance", "balance");
+        ending_balance.non_additive_dimension = Some("snapshot_date".into());
+        let balances = Model::new("balances", "balance_id")
+            .with_table("balances")
+            .with_dimension(Dimension::time("snapshot_date"))
+            .with_relationship(Relationship::many_to_one("accounts"))
+            .with_metric(ending_balance);
+        let accounts = Model::new("accounts", "account_id")
+            .with_table("accounts")
+            .with_dimension(Dimension::categorical("region"));
+        graph.add_model(balances).unwrap();
+        graph.add_model(accounts).unwrap();
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["balances.ending_balance".into()])
+            .with_dimensions(vec!["accounts.region".into()]);
+        let err = SqlGenerator::new(&graph).generate(&query).unwrap_err();
+
+        let SidemanticError::Validation(message) = err else {
+            panic!("expected a validation error, got {err}");
+        };
+        assert_eq!(
+            message,
+            "Semi-additive metric 'balances.ending_balance' cannot be grouped by \
+             'accounts.region': non_additive_dimension snapshots are only supported \
+             with dimensions of 'balances'"
+        );
+    }
+
+    #[test]
+    fn test_pct_of_total_metric_uses_window_over_grouped_result() {
+        let mut graph = SemanticGraph::new();
+        let orders = Model::new("orders", "order_id")
+            .with_table("orders")
+            .with_dimension(Dimension::categorical("status"))
+            .with_metric(Metric::sum("revenue", "amount"))
+            .with_metric(Metric::pct_of_total("revenue_pct_of_total", "revenue"));
+        graph.add_model(orders).unwrap();
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue_pct_of_total".into()])
+            .with_dimensions(vec!["orders.status".into()]);
+        let sql = SqlGenerator::new(&graph).generate(&query).unwrap();
+
+        assert!(
+            sql.contains(
+                "base.revenue / NULLIF(SUM(base.revenue) OVER (), 0) AS revenue_pct_of_total"
+            ),
+            "{sql}"
+        );
+        assert!(
+            sql.contains("SUM(orders_cte.revenue_raw) AS revenue"),
+            "{sql}"
+        );
+        assert_parses(&sql);
+    }
+
+    #[test]
+    fn test_derived_metric_over_window_metric_rejects_unknown_reference() {
+        let mut graph = SemanticGraph::new();
+        let orders = Model::new("orders", "order_id")
+            .with_table("orders")
+            .with_dimension(Dimension::time("order_date").with_sql("created_at"))
+            .with_metric(Metric::sum("revenue", "amount"))
+            .with_metric(Metric::time_comparison(
+                "mom_growth",
+                "revenue",
+                ComparisonType::Mom,
+            ))
+            .with_metric(Metric::derived("growth_gap", "mom_growth - mom_grwth"));
+        graph.add_model(orders).unwrap();
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.growth_gap".into()])
+            .with_dimensions(vec!["orders.order_date__month".into()]);
+        let err = SqlGenerator::new(&graph).generate(&query).unwrap_err();
+
+        assert!(err.to_string().contains("mom_grwth"), "{err}");
+    }
+
+    #[test]
+    fn test_derived_metric_over_chained_time_comparisons() {
+        let mut graph = SemanticGraph::new();
+        let orders = Model::new("orders", "order_id")
+            .with_table("orders")
+            .with_dimension(Dimension::time("order_date").with_sql("created_at"))
+            .with_metric(Metric::sum("revenue", "amount"))
+            .with_metric(Metric::time_comparison(
+                "mom_growth",
+                "revenue",
+                ComparisonType::Mom,
+            ))
+            .with_metric(
+                Metric::time_comparison("prior_mom_growth", "mom_growth", ComparisonType::Mom)
+                    .with_calculation(ComparisonCalculation::PreviousValue),
+            )
+            .with_metric(Metric::derived(
+                "mom_acceleration",
+                "mom_growth - prior_mom_growth",
+            ));
+        graph.add_model(orders).unwrap();
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.mom_acceleration".into()])
+            .with_dimensions(vec!["orders.order_date__month".into()]);
+        let sql = SqlGenerator::new(&graph).generate(&query).unwrap();
+
+        assert!(
+            sql.contains("base.mom_growth - base.prior_mom_growth AS mom_acceleration"),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(
+                "LAG(base.mom_growth, 1) OVER (ORDER BY base.order_date__month) AS prior_mom_growth_prev_value"
+            ),
+            "{sql}"
+        );
+        assert!(
+            sql.contains("prior_mom_growth_prev_value AS prior_mom_growth"),
+            "{sql}"
+        );
+        // MoM growth is computed once and passed up, not recomputed beside its change.
+        assert_eq!(
+            sql.matches("AS mom_growth\n").count() + sql.matches("AS mom_growth,").count(),
+            1,
+            "{sql}"
+        );
+        assert_parses(&sql);
+    }
+
+    #[test]
+    fn test_time_comparison_over_filtered_metric_filters_both_periods() {
+        let mut graph = SemanticGraph::new();
+        let orders = Model::new("orders", "order_id")
+            .with_table("orders")
+            .with_dimension(Dimension::time("order_date").with_sql("created_at"))
+            .with_dimension(Dimension::categorical("status"))
+            .with_metric(
+                Metric::sum("completed_revenue", "amount")
+                    .with_filter("{model}.status = 'completed'"),
+            )
+            .with_metric(Metric::time_comparison(
+                "completed_revenue_yoy",
+                "completed_revenue",
+                ComparisonType::Yoy,
+            ));
+        graph.add_model(orders).unwrap();
+
+        let generator = SqlGenerator::new(&graph);
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.completed_revenue_yoy".into()])
+            .with_dimensions(vec!["orders.order_date__month".into()]);
+
+        let sql = generator.generate(&query).unwrap();
+
+        // The filter is applied once, to the base column that feeds both the
+        // current period value and the lagged prior period value.
+        let filtered_raw =
+            "CASE WHEN status = 'completed' THEN amount END AS completed_revenue_raw";
+        assert_eq!(sql.matches(filtered_raw).count(), 1, "{sql}");
+        assert!(
+            sql.contains("SUM(orders_cte.completed_revenue_raw) AS completed_revenue"),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(
+                "LAG(base.completed_revenue, 12) OVER (ORDER BY base.order_date__month) AS completed_revenue_yoy_prev_value"
+            ),
+            "{sql}"
+        );
+        assert!(
+            sql.contains("((completed_revenue - completed_revenue_yoy_prev_value)"),
+            "{sql}"
+        );
+        assert!(!sql.contains("SUM(orders_cte.amount)"), "{sql}");
+    }
+
+    #[test]
+    fn test_time_comparison_over_metric_filtered_by_joined_dimension() {
+        let mut graph = SemanticGraph::new();
+        let orders = Model::new("orders", "order_id")
+            .with_table("orders")
+            .with_dimension(Dimension::time("order_date").with_sql("created_at"))
+            .with_metric(
+                Metric::sum("us_revenue", "amount").with_filter("customers.country = 'US'"),
+            )
+            .with_metric(Metric::time_comparison(
+                "us_revenue_mom",
+                "us_revenue",
+                ComparisonType::Mom,
+            ))
+            .with_relationship(Relationship::many_to_one("customers"));
+        let customers = Model::new("customers", "id")
+            .with_table("customers")
+            .with_dimension(Dimension::categorical("country"));
+        graph.add_model(orders).unwrap();
+        graph.add_model(customers).unwrap();
+
+        let generator = SqlGenerator::new(&graph);
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.us_revenue_mom".into()])
+            .with_dimensions(vec!["orders.order_date__month".into()]);
+
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(
+            sql.contains("LEFT JOIN customers_cte AS customers_cte"),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(
+                "SUM(CASE WHEN customers_cte.country = 'US' THEN orders_cte.us_revenue_raw END) AS us_revenue"
+            ),
+            "{sql}"
+        );
+        assert!(
+            sql.contains("LAG(base.us_revenue, 1) OVER (ORDER BY base.order_date__month)"),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_conversion_query_applies_filters_limit_and_offset() {
+        let mut graph = SemanticGraph::new();
+        let events = Model::new("events", "event_id")
+            .with_table("events")
+            .with_dimension(Dimension::categorical("user_id"))
+            .with_dimension(Dimension::categorical("event_type"))
+            .with_dimension(Dimension::time("event_date"))
+            .with_dimension(Dimension::categorical("region"))
+            .with_metric(Metric {
+                name: "signup_conversion".to_string(),
+                r#type: MetricType::Conversion,
+                agg: None,
+                entity: Some("user_id".to_string()),
+                base_event: Some("signup".to_string()),
+                conversion_event: Some("purchase".to_string()),
+                conversion_window: Some("7 days".to_string()),
+                filters: vec!["events.region = 'US'".to_string()],
+                ..Metric::new("signup_conversion")
+            });
+        graph.add_model(events).unwrap();
+
+        let generator = SqlGenerator::new(&graph);
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["events.signup_conversion".into()])
+            .with_dimensions(vec!["events.region".into()])
+            .with_filters(vec!["events.event_date >= '2024-01-01'".into()])
+            .with_order_by(vec!["events.signup_conversion DESC".into()])
+            .with_limit(5)
+            .with_offset(10);
+
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(sql.contains("event_type = 'signup'"), "{sql}");
+        assert!(sql.contains("event_type = 'purchase'"), "{sql}");
+        assert!(sql.contains("(event_date >= '2024-01-01')"), "{sql}");
+        assert!(sql.contains("(region = 'US')"), "{sql}");
+        assert!(
+            sql.contains("base_events.region IS NOT DISTINCT FROM conversions.region"),
+            "{sql}"
+        );
+        assert!(sql.contains("ORDER BY signup_conversion DESC"), "{sql}");
+        assert!(sql.contains("LIMIT 5"), "{sql}");
+        assert!(sql.contains("OFFSET 10"), "{sql}");
+    }
+
+    #[test]
+    fn test_multistep_conversion_query_generates_step_ctes() {
+        let mut graph = SemanticGraph::new();
+        let events = Model::new("events", "event_id")
+            .with_table("events")
+            .with_dimension(Dimension::categorical("user_id"))
+            .with_dimension(Dimension::categorical("event_type"))
+            .with_dimension(Dimension::time("event_date"))
+            .with_dimension(Dimension::categorical("region"))
+            .with_metric(Metric {
+                name: "signup_funnel".to_string(),
+                r#type: MetricType::Conversion,
+                agg: None,
+                entity: Some("user_id".to_string()),
+                steps: Some(vec![
+                    "event_type = 'signup'".to_string(),
+                    "event_type = 'purchase'".to_string(),
+                ]),
+                ..Metric::new("signup_funnel")
+            });
+        graph.add_model(events).unwrap();
+
+        let generator = SqlGenerator::new(&graph);
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["events.signup_funnel".into()])
+            .with_dimensions(vec!["events.region".into()])
+            .with_filters(vec!["events.region = 'US'".into()]);
+
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(sql.contains("step_1 AS"), "{sql}");
+        assert!(sql.contains("step_2 AS"), "{sql}");
+        assert!(sql.contains("s.__ts >= step_1.step_1_ts"), "{sql}");
+        assert!(
+            sql.contains("s.__dim_0 IS NOT DISTINCT FROM step_1.region"),
+            "{sql}"
+        );
+        assert!(sql.contains("s.__step_match AND s.__filter_match"), "{sql}");
+        assert!(
+            sql.contains("COUNT(DISTINCT step_2.entity) AS signup_funnel"),
+            "{sql}"
+        );
+        assert!(sql.contains("LEFT JOIN step_2"), "{sql}");
+    }
+
+    #[test]
+    fn test_retention_query_generates_retention_ctes() {
+        let mut graph = SemanticGraph::new();
+        let events = Model::new("events", "event_id")
+            .with_table("events")
+            .with_dimension(Dimension::categorical("user_id"))
+            .with_dimension(Dimension::categorical("event_type"))
+            .with_dimension(Dimension::time("event_date"))
+            .with_metric(Metric {
+                name: "signup_retention".to_string(),
+                r#type: MetricType::Retention,
+                agg: None,
+                entity: Some("user_id".to_string()),
+                cohort_event: Some("event_type = 'signup'".to_string()),
+                activity_event: Some("event_type = 'active'".to_string()),
+                periods: Some(7),
+                retention_granularity: Some("day".to_string()),
+                ..Metric::new("signup_retention")
+            });
+        graph.add_model(events).unwrap();
+
+        let generator = SqlGenerator::new(&graph);
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["events.signup_retention".into()])
+            .with_limit(5)
+            .with_offset(10);
+
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(sql.contains("WITH cohorts AS"), "{sql}");
+        assert!(sql.contains("retention AS"), "{sql}");
+        assert!(sql.contains("cohort_sizes AS"), "{sql}");
+        assert!(sql.contains("r.periods_since AS days_since"), "{sql}");
+        assert!(sql.contains("retention_pct"), "{sql}");
+        assert!(sql.contains("<= 7"), "{sql}");
+        assert!(sql.contains("OFFSET 10"), "{sql}");
+    }
+
+    #[test]
+    fn test_cohort_query_generates_inner_and_outer_aggregation() {
+        let mut graph = SemanticGraph::new();
+        let events = Model::new("events", "event_id")
+            .with_table("events")
+            .with_dimension(Dimension::categorical("user_id"))
+            .with_dimension(Dimension::categorical("platform").with_sql("raw_platform"))
+            .with_dimension(Dimension::categorical("region"))
+            .with_metric(Metric {
+                name: "multi_platform_users".to_string(),
+                r#type: MetricType::Cohort,
+                agg: Some(Aggregation::Count),
+                entity: Some("user_id".to_string()),
+                inner_metrics: Some(vec![CohortInnerMetric {
+                    name: "platform_count".to_string(),
+                    agg: Some(Aggregation::CountDistinct),
+                    sql: Some("platform".to_string()),
+                }]),
+                entity_dimensions: Some(vec!["region".to_string()]),
+                having: Some("platform_count >= 2".to_string()),
+                ..Metric::new("multi_platform_users")
+            });
+        graph.add_model(events).unwrap();
+
+        let generator = SqlGenerator::new(&graph);
+        let query = SemanticQuery::new().with_metrics(vec!["events.multi_platform_users".into()]);
+
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(sql.contains("WITH cohort_sub AS"), "{sql}");
+        assert!(
+            sql.contains("COUNT(DISTINCT raw_platform) AS platform_count"),
+            "{sql}"
+        );
+        assert!(sql.contains("region AS region"), "{sql}");
+        assert!(sql.contains("cohort_sub.region"), "{sql}");
+        assert!(sql.contains("HAVING platform_count >= 2"), "{sql}");
+        assert!(
+            sql.contains("COUNT(DISTINCT cohort_sub.entity) AS multi_platform_users"),
+            "{sql}"
+        );
+        assert!(sql.contains("GROUP BY 1"), "{sql}");
+    }
+
+    #[test]
+    fn test_conversion_metric_cannot_mix_with_regular_metric() {
+        let mut graph = SemanticGraph::new();
+        let events = Model::new("events", "event_id")
+            .with_table("events")
+            .with_dimension(Dimension::categorical("user_id"))
+            .with_dimension(Dimension::categorical("event_type"))
+            .with_dimension(Dimension::time("event_date"))
+            .with_metric(Metric::count("event_count"))
+            .with_metric(Metric {
+                name: "signup_conversion".to_string(),
+                r#type: MetricType::Conversion,
+                agg: None,
+                entity: Some("user_id".to_string()),
+                base_event: Some("signup".to_string()),
+                conversion_event: Some("purchase".to_string()),
+                ..Metric::new("signup_conversion")
+            });
+        graph.add_model(events).unwrap();
+
+        let generator = SqlGenerator::new(&graph);
+        let query = SemanticQuery::new().with_metrics(vec![
+            "events.signup_conversion".into(),
+            "events.event_count".into(),
+        ]);
+
+        let err = generator.generate(&query).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Conversion metrics cannot be combined"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_query_with_join() {
+        let graph = create_test_graph();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["customers.country".into()]);
+
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(sql.contains("LEFT JOIN orders_cte AS orders_cte"));
+        assert!(sql.contains("customers_cte.id = orders_cte.customers_id"));
+    }
+
+    #[test]
+    fn test_percentile_metrics_render_exact_and_approximate_forms() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("requests", "request_id")
+                    .with_table("requests")
+                    .with_dimension(Dimension::categorical("endpoint"))
+                    .with_metric(Metric::percentile("p95_latency", "latency_ms", 0.95))
+                    .with_metric(Metric {
+                        agg: Some(Aggregation::ApproxPercentile(0.95)),
+                        ..Metric::percentile("approx_p95_latency", "latency_ms", 0.95)
+                    }),
+            )
+            .unwrap();
+        let query = SemanticQuery::new()
+            .with_metrics(vec![
+                "requests.p95_latency".into(),
+                "requests.approx_p95_latency".into(),
+            ])
+            .with_dimensions(vec!["requests.endpoint".into()]);
+
+        let sql = SqlGenerator::new(&graph).generate(&query).unwrap();
+        assert!(
+            sql.contains(
+                "PERCENTILE_CONT(0.95) WITHIN GROUP (ORDER BY requests_cte.p95_latency_raw) AS p95_latency"
+            ),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(
+                "APPROX_QUANTILE(requests_cte.approx_p95_latency_raw, 0.95) AS approx_p95_latency"
+            ),
+            "{sql}"
+        );
+
+        let snowflake = SqlGenerator::new(&graph)
+            .with_dialect(DialectType::Snowflake)
+            .generate(&query)
+            .unwrap();
+        assert!(
+            snowflake.contains(
+                "APPROX_PERCENTILE(requests_cte.approx_p95_latency_raw, 0.95) AS approx_p95_latency"
+            ),
+            "{snowflake}"
+        );
+
+        let bigquery = SqlGenerator::new(&graph).with_dialect(DialectType::BigQuery);
+        let err = bigquery.generate(&query).unwrap_err();
+        assert!(err.to_string().contains("no exact percentile"), "{err}");
+        let approx = SemanticQuery::new()
+            .with_metrics(vec!["requests.approx_p95_latency".into()])
+            .with_dimensions(vec!["requests.endpoint".into()]);
+        let sql = bigquery.generate(&approx).unwrap();
+        assert!(
+            sql.contains("APPROX_QUANTILES(requests_cte.approx_p95_latency_raw, 100)[OFFSET(95)]"),
+            "{sql}"
+        );
+        assert_eq!(
+            bigquery_approx_quantile_sql("x", 0.999),
+            "APPROX_QUANTILES(x, 1000)[OFFSET(999)]"
+        );
+    }
+
+    #[test]
+    fn test_approximate_query_swaps_count_distinct() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_dimension(Dimension::categorical("status"))
+                    .with_metric(Metric::count_distinct("customer_count", "customer_id"))
+                    .with_metric(Metric::sum("revenue", "amount")),
+            )
+            .unwrap();
+        let query = SemanticQuery::new()
+            .with_metrics(vec![
+                "orders.customer_count".into(),
+                "orders.revenue".into(),
+            ])
+            .with_dimensions(vec!["orders.status".into()]);
+
+        let exact = SqlGenerator::new(&graph).generate(&query).unwrap();
+        assert!(
+            exact.contains("COUNT(DISTINCT orders_cte.customer_count_raw) AS customer_count"),
+            "{exact}"
+        );
+
+        let approximate_query = query.with_approximate(true);
+        let approximate = SqlGenerator::new(&graph)
+            .generate(&approximate_query)
+            .unwrap();
+        assert!(
+            approximate
+                .contains("APPROX_COUNT_DISTINCT(orders_cte.customer_count_raw) AS customer_count"),
+            "{approximate}"
+        );
+        assert!(
+            approximate.contains("SUM(orders_cte.revenue_raw) AS revenue"),
+            "{approximate}"
+        );
+
+        let postgres = SqlGenerator::new(&graph)
+            .with_dialect(DialectType::PostgreSQL)
+            .generate(&approximate_query)
+            .unwrap();
+        assert!(
+            postgres.contains("COUNT(DISTINCT orders_cte.customer_count_raw) AS customer_count"),
+            "{postgres}"
+        );
+    }
+
+    #[test]
+    fn test_debug_comments_annotate_fields_and_joins() {
+        let graph = create_test_graph();
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["customers.country".into()]);
+
+        let plain = SqlGenerator::new(&graph).generate(&query).unwrap();
+        assert!(!plain.contains("/*"), "{plain}");
+
+        let sql = SqlGenerator::new(&graph)
+            .with_debug_comments(true)
+            .generate(&query)
+            .unwrap();
+        assert!(
+            sql.contains("customers_cte.country AS country /* customers.country (dimension) */"),
+            "{sql}"
+        );
+        assert!(
+            sql.contains("AS revenue /* orders.revenue (metric) */"),
+            "{sql}"
+        );
+        assert!(
+            sql.contains("FROM customers_cte AS customers_cte /* base model customers */"),
+            "{sql}"
+        );
+        assert!(
+            sql.contains("/* customers -> orders (one_to_many) */"),
+            "{sql}"
+        );
+        polyglot_sql::parse_one(&sql, SOURCE_DIALECT).unwrap();
+    }
+
+    #[test]
+    fn test_pass_through_dimension_joins_referenced_model() {
+        let mut graph = create_test_graph();
+        let orders = graph.get_model("orders").unwrap().clone().with_dimension(
+            Dimension::categorical("customer_country").with_reference("customers.country"),
+        );
+        graph.replace_model(orders).unwrap();
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.customer_country".into()]);
+        let sql = SqlGenerator::new(&graph).generate(&query).unwrap();
+
+        assert!(sql.contains("FROM orders_cte AS orders_cte"), "{sql}");
+        assert!(
+            sql.contains("LEFT JOIN customers_cte AS customers_cte"),
+            "{sql}"
+        );
+        assert!(
+            sql.contains("customers_cte.country AS customer_country"),
+            "{sql}"
+        );
+        assert!(!sql.contains("orders_cte.customer_country"), "{sql}");
+    }
+
+    #[test]
+    fn test_flatten_exposes_one_hop_dimensions() {
+        let graph = crate::config::load_from_string(
+            r#"
+flatten: true
+models:
+  - name: orders
+    table: orders
+    primary_key: order_id
+    relationships:
+      - name: customer
+        type: many_to_one
+        foreign_key: customer_id
+    metrics:
+      - name: revenue
+        agg: sum
+        sql: amount
+  - name: customer
+    table: customers
+    primary_key: id
+    dimensions:
+      - name: country
+        type: categorical
+"#,
+        )
+        .unwrap();
+
+        let flattened = graph
+            .get_model("orders")
+            .and_then(|model| model.get_dimension("customer__country"))
+            .unwrap();
+        assert_eq!(flattened.reference.as_deref(), Some("customer.country"));
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.customer__country".into()]);
+        let sql = SqlGenerator::new(&graph).generate(&query).unwrap();
+
+        assert!(
+            sql.contains("LEFT JOIN customer_cte AS customer_cte"),
+            "{sql}"
+        );
+        assert!(
+            sql.contains("customer_cte.country AS customer__country"),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_relationship_foreign_key_dimension_rejects_granularity_suffix() {
+        let mut graph = SemanticGraph::new();
+
+        let orders = Model::new("orders", "order_id")
+            .with_table("orders")
+            .with_metric(Metric::count("order_count"))
+            .with_relationship(
+                Relationship::many_to_one("customers").with_keys("customer_id", "id"),
+            );
+        let customers = Model::new("customers", "id").with_table("customers");
+
+        graph.add_model(orders).unwrap();
+        graph.add_model(customers).unwrap();
+
+        let generator = SqlGenerator::new(&graph);
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.order_count".into()])
+            .with_dimensions(vec!["orders.customer_id__month".into()]);
+
+        let err = generator.generate(&query).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Cannot apply granularity to non-time dimension 'customer_id'"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_multi_hop_paths_join_each_model_once() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_dimension(Dimension::categorical("status"))
+                    .with_metric(Metric::sum("revenue", "amount"))
+                    .with_relationship(Relationship::many_to_one("customers")),
+            )
+            .unwrap();
+        graph
+            .add_model(
+                Model::new("customers", "id")
+                    .with_table("customers")
+                    .with_metric(Metric::sum("total_credit", "credit_limit"))
+                    .with_relationship(Relationship::many_to_one("regions")),
+            )
+            .unwrap();
+        graph
+            .add_model(
+                Model::new("regions", "id")
+                    .with_table("regions")
+                    .with_dimension(Dimension::categorical("name")),
+            )
+            .unwrap();
+
+        let generator = SqlGenerator::new(&graph);
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.status".into(), "regions.name".into()]);
+
+        let sql = generator.generate(&query).unwrap();
+
+        assert_eq!(sql.matches("JOIN customers_cte").count(), 1, "{sql}");
+        assert_eq!(sql.matches("JOIN regions_cte").count(), 1, "{sql}");
+        let customers_join = sql.find("JOIN customers_cte").unwrap();
+        let regions_join = sql.find("JOIN regions_cte").unwrap();
+        assert!(customers_join < regions_join, "{sql}");
+        assert!(
+            sql.contains("ON customers_cte.regions_id = regions_cte.id"),
+            "{sql}"
+        );
+
+        // With a second fact the query is pre-aggregated per fact, and each
+        // `_preagg` CTE joins the path once.
+        let multi_fact = SemanticQuery::new()
+            .with_metrics(vec![
+                "orders.revenue".into(),
+                "customers.total_credit".into(),
+            ])
+            .with_dimensions(vec!["orders.status".into(), "regions.name".into()]);
+        let sql = generator.generate(&multi_fact).unwrap();
+        for cte in sql.split("_preagg AS (").skip(1) {
+            let cte = cte.split_once("\n)").expect("CTE end").0;
+            assert_eq!(cte.matches("JOIN regions_cte").count(), 1, "{sql}");
+        }
+    }
+
+    #[test]
+    fn test_joined_model_default_filter_stays_in_its_cte() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_metric(Metric::sum("revenue", "amount"))
+                    .with_relationship(Relationship::many_to_one("customers")),
+            )
+            .unwrap();
+        graph
+            .add_model(
+                Model::new("customers", "id")
+                    .with_sql("SELECT * FROM raw.customers")
+                    .with_dimension(Dimension::categorical("region"))
+                    .with_default_filter("{model}.deleted_at IS NULL"),
+            )
+            .unwrap();
+
+        let generator = SqlGenerator::new(&graph);
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["customers.region".into()]);
+
+        let sql = generator.generate(&query).unwrap();
+
+        let customers_cte = sql
+            .split("customers_cte AS (")
+            .nth(1)
+            .and_then(|rest| rest.split("\n)").next())
+            .unwrap();
+        assert!(customers_cte.contains("WHERE deleted_at IS NULL"), "{sql}");
+        assert!(sql.contains("LEFT JOIN orders_cte"), "{sql}");
+        assert_eq!(sql.matches("deleted_at IS NULL").count(), 1, "{sql}");
+    }
+
+    #[test]
+    fn test_relationship_join_type_emits_inner_join() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_dimension(Dimension::categorical("status"))
+                    .with_metric(Metric::sum("revenue", "amount"))
+                    .with_relationship(
+                        Relationship::many_to_one("customers").with_join_type(JoinType::Inner),
+                    ),
+            )
+            .unwrap();
+        graph
+            .add_model(
+                Model::new("customers", "id")
+                    .with_table("customers")
+                    .with_dimension(Dimension::categorical("region")),
+            )
+            .unwrap();
+        let generator = SqlGenerator::new(&graph);
+
+        let sql = generator
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec!["orders.revenue".into()])
+                    .with_dimensions(vec!["orders.status".into(), "customers.region".into()]),
+            )
+            .unwrap();
+        assert!(sql.contains("INNER JOIN customers_cte"), "{sql}");
+
+        // Traversed from customers, the reverse edge keeps the inner join.
+        let sql = generator
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec!["orders.revenue".into()])
+                    .with_dimensions(vec!["customers.region".into()]),
+            )
+            .unwrap();
+        assert!(sql.contains("INNER JOIN orders_cte"), "{sql}");
+    }
+
+    #[test]
+    fn test_query_with_composite_join() {
+        let mut graph = SemanticGraph::new();
+
+        let shipments = Model::new("shipments", "shipment_id")
+            .with_table("shipments")
+            .with_metric(Metric::count("shipment_count"))
+            .with_relationship(Relationship::many_to_one("order_items").with_key_columns(
+                vec!["order_id".to_string(), "item_id".to_string()],
+                vec!["order_id".to_string(), "item_id".to_string()],
+            ));
+        let order_items = Model::new("order_items", "order_id")
+            .with_primary_key_columns(vec!["order_id".to_string(), "item_id".to_string()])
+            .with_table("order_items")
+            .with_dimension(Dimension::categorical("sku"));
+
+        graph.add_model(shipments).unwrap();
+        graph.add_model(order_items).unwrap();
+
+        let generator = SqlGenerator::new(&graph);
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["shipments.shipment_count".into()])
+            .with_dimensions(vec!["order_items.sku".into()]);
+
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(sql.contains("order_items_cte.order_id = shipments_cte.order_id"));
+        assert!(sql.contains("order_items_cte.item_id = shipments_cte.item_id"));
+        assert!(sql.contains(" AND "));
+    }
+
+    #[test]
+    fn test_join_with_comma_separated_keys_and_custom_condition() {
+        let mut graph = SemanticGraph::new();
+
+        let orders = Model::new("orders", "order_id")
+            .with_table("orders")
+            .with_dimension(Dimension::categorical("status"))
+            .with_metric(Metric::sum("revenue", "amount"))
+            .with_relationship(
+                Relationship::many_to_one("customers")
+                    .with_keys("tenant_id, customer_id", "tenant_id, id"),
+            )
+            .with_relationship(
+                Relationship {
+                    r#type: RelationshipType::OneToOne,
+                    ..Relationship::new("invoices")
+                }
+                .with_condition(
+                    "{from}.order_id = {to}.order_id AND {from}.tenant_id = {to}.tenant_id",
+                ),
+            );
+        let customers = Model::new("customers", "id")
+            .with_table("customers")
+            .with_dimension(Dimension::categorical("region"));
+        let invoices = Model::new("invoices", "invoice_id")
+            .with_table("invoices")
+            .with_dimension(Dimension::categorical("number"));
+
+        graph.add_model(orders).unwrap();
+        graph.add_model(customers).unwrap();
+        graph.add_model(invoices).unwrap();
+
+        let generator = SqlGenerator::new(&graph);
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec![
+                "orders.status".into(),
+                "customers.region".into(),
+                "invoices.number".into(),
+            ]);
+
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(
+            sql.contains(
+                "ON orders_cte.tenant_id = customers_cte.tenant_id AND orders_cte.customer_id = customers_cte.id"
+            ),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(
+                "ON orders_cte.order_id = invoices_cte.order_id AND orders_cte.tenant_id = invoices_cte.tenant_id"
+            ),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_query_with_composite_many_to_many_through_join() {
+        let mut graph = SemanticGraph::new();
+
+        let orders = Model::new("orders", "tenant_id")
+            .with_primary_key_columns(vec!["tenant_id".to_string(), "order_id".to_string()])
+            .with_table("orders")
+            .with_metric(Metric::sum("revenue", "amount"))
+            .with_relationship(Relationship {
+                name: "products".to_string(),
+                r#type: RelationshipType::ManyToMany,
+                foreign_key: None,
+                foreign_key_columns: None,
+                primary_key: None,
+                primary_key_columns: None,
+                through: Some("order_items".to_string()),
+                through_foreign_key: Some("order_id".to_string()),
+                through_foreign_key_columns: Some(vec![
+                    "tenant_id".to_string(),
+                    "order_id".to_string(),
+                ]),
+                related_foreign_key: Some("product_id".to_string()),
+                related_foreign_key_columns: Some(vec![
+                    "tenant_id".to_string(),
+                    "product_id".to_string(),
+                ]),
+                sql: None,
+                join_type: None,
+                metadata: None,
+            });
+        let order_items = Model::new("order_items", "tenant_id")
+            .with_primary_key_columns(vec![
+                "tenant_id".to_string(),
+                "order_id".to_string(),
+                "product_id".to_string(),
+            ])
+            .with_table("order_items");
+        let products = Model::new("products", "tenant_id")
+            .with_primary_key_columns(vec!["tenant_id".to_string(), "product_id".to_string()])
+            .with_table("products")
+            .with_dimension(Dimension::categorical("name"));
+
+        graph.add_model(orders).unwrap();
+        graph.add_model(order_items).unwrap();
+        graph.add_model(products).unwrap();
+
+        let generator = SqlGenerator::new(&graph);
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["products.name".into()]);
+
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(sql.contains("products_cte.tenant_id = order_items_cte.tenant_id"));
+        assert!(sql.contains("products_cte.product_id = order_items_cte.product_id"));
+        assert!(sql.contains("order_items_cte.tenant_id = orders_cte.tenant_id"));
+        assert!(sql.contains("order_items_cte.order_id = orders_cte.order_id"));
+    }
+
+    #[test]
+    fn test_query_with_filter() {
+        let graph = create_test_graph();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.status".into()])
+            .with_filters(vec!["orders.status = 'completed'".into()]);
+
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(sql.contains("WHERE status = 'completed'"));
+    }
+
+    #[test]
+    fn test_cube_segment_resolves_in_generated_query() {
+        let graph = crate::config::load_from_string(
+            r#"
+cubes:
+  - name: orders
+    sql_table: orders
+    dimensions:
+      - name: status
+        sql: "${CUBE}.status"
+        type: string
+    measures:
+      - name: revenue
+        sql: "${CUBE}.amount"
+        type: sum
+    segments:
+      - name: completed
+        sql: "${CUBE}.status = 'completed'"
+"#,
+        )
+        .unwrap();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.status".into()])
+            .with_segments(vec!["orders.completed".into()]);
+
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(sql.contains("WHERE status = 'completed'"), "{sql}");
+        assert!(!sql.contains("${CUBE}"), "{sql}");
+        assert!(!sql.contains("{model}"), "{sql}");
+    }
+
+    #[test]
+    fn test_segment_expr_combines_segments_with_or() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_dimension(Dimension::categorical("status"))
+                    .with_dimension(Dimension::categorical("region"))
+                    .with_metric(Metric::sum("revenue", "amount"))
+                    .with_segment(
+                        Segment::new("completed").with_sql("{model}.status = 'completed'"),
+                    )
+                    .with_segment(Segment::new("shipped").with_sql("{model}.status = 'shipped'"))
+                    .with_segment(Segment::new("domestic").with_sql("{model}.region = 'US'")),
+            )
+            .unwrap();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_filters(vec!["orders.region = 'EU'".into()])
+            .with_segment_expr(
+                SegmentFilter::segment("orders.completed")
+                    .or(SegmentFilter::segment("orders.shipped")),
+            );
+        let sql = generator.generate(&query).unwrap();
+        assert!(
+            sql.contains(
+                "WHERE region = 'EU' AND ((status = 'completed') OR (status = 'shipped'))"
+            ),
+            "{sql}"
+        );
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_segment_expr(
+                SegmentFilter::segment("orders.completed")
+                    .or(SegmentFilter::segment("orders.shipped"))
+                    .and(!SegmentFilter::segment("orders.domestic")),
+            );
+        let sql = generator.generate(&query).unwrap();
+        assert!(
+            sql.contains(
+                "((status = 'completed') OR (status = 'shipped')) AND NOT (region = 'US')"
+            ),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_declared_columns_replace_wildcard_projection() {
+        let graph = crate::config::load_from_string(
+            r#"
+models:
+  - name: orders
+    sql: SELECT * FROM raw_orders
+    primary_key: order_id
+    columns: [order_id, status, amount]
+    dimensions:
+      - name: status
+        type: categorical
+    metrics:
+      - name: revenue
+        agg: sum
+        sql: amount
+"#,
+        )
+        .unwrap();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.status".into()]);
+
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(
+            sql.contains("SELECT order_id,\n    status,\n    amount,\n    amount AS revenue_raw"),
+            "{sql}"
+        );
+        assert!(
+            sql.contains("FROM (SELECT * FROM raw_orders) AS t"),
+            "{sql}"
+        );
+        assert!(!sql.contains("SELECT *,"), "{sql}");
+    }
+
+    fn create_events_graph() -> SemanticGraph {
+        let mut graph = SemanticGraph::new();
+        let events = Model::new("events", "event_id")
+            .with_table("events")
+            .with_dimension(Dimension::categorical("plan").with_sql("properties->>'plan'"))
+            .with_dimension(Dimension::categorical("tags"))
+            .with_metric(Metric::count("event_count"));
+        graph.add_model(events).unwrap();
+        graph
+    }
+
+    #[test]
+    fn test_date_truncation_and_limit_follow_dialect() {
+        let graph = create_test_graph();
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.order_date__month".into()])
+            .with_limit(10);
+        let generate = |dialect| {
+            SqlGenerator::new_with_dialect(&graph, dialect)
+                .generate(&query)
+                .unwrap()
+        };
+
+        let duckdb = generate(DialectType::DuckDB);
+        assert!(duckdb.contains("DATE_TRUNC('month', "), "{duckdb}");
+        assert!(duckdb.ends_with("LIMIT 10"), "{duckdb}");
+
+        let postgres = generate(DialectType::PostgreSQL);
+        assert!(postgres.contains("DATE_TRUNC('month', "), "{postgres}");
+
+        let bigquery = generate(DialectType::BigQuery);
+        assert!(bigquery.contains("TIMESTAMP_TRUNC("), "{bigquery}");
+        assert!(
+            bigquery.contains("created_at, MONTH) AS order_date__month"),
+            "{bigquery}"
+        );
+        assert!(!bigquery.contains("DATE_TRUNC"), "{bigquery}");
+
+        let snowflake = generate(DialectType::Snowflake);
+        assert!(snowflake.contains("DATE_TRUNC(month, "), "{snowflake}");
+
+        let tsql = generate(DialectType::TSQL);
+        assert!(tsql.contains("SELECT TOP 10\n"), "{tsql}");
+        assert!(!tsql.contains("LIMIT"), "{tsql}");
+
+        let dimension = Dimension::time("order_date").with_sql("created_at");
+        assert_eq!(
+            dimension.sql_with_granularity(Some("week"), DialectType::BigQuery),
+            "TIMESTAMP_TRUNC(created_at, WEEK)"
+        );
+    }
+
+    fn tsql_generate(graph: &SemanticGraph, query: &SemanticQuery) -> String {
+        let sql = SqlGenerator::new_with_dialect(graph, DialectType::TSQL)
+            .generate(query)
+            .unwrap();
+        assert!(!sql.contains("LIMIT"), "{sql}");
+        sql
+    }
+
+    #[test]
+    fn test_tsql_grand_total_pages_with_offset_fetch() {
+        let graph = create_test_graph();
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.status".into()])
+            .with_grand_total("Total")
+            .with_limit(5);
+
+        let sql = tsql_generate(&graph, &query);
+        assert!(
+            sql.ends_with(") AS grand_total\nORDER BY 1\nOFFSET 0 ROWS\nFETCH NEXT 5 ROWS ONLY"),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_tsql_top_n_per_uses_top() {
+        let graph = create_test_graph();
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.status".into(), "customers.country".into()])
+            .with_top_n_per(TopNPer::new("orders.status", 3, "orders.revenue"))
+            .with_limit(10);
+
+        let sql = tsql_generate(&graph, &query);
+        assert!(sql.starts_with("SELECT TOP 10\n  status,"), "{sql}");
+        assert_eq!(sql.matches("TOP 10").count(), 1, "{sql}");
+    }
+
+    #[test]
+    fn test_tsql_window_metric_pages_with_offset_fetch() {
+        let mut graph = create_test_graph();
+        let orders = graph
+            .get_model("orders")
+            .unwrap()
+            .clone()
+            .with_metric(Metric::pct_of_total("revenue_share", "revenue"));
+        graph.replace_model(orders).unwrap();
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue_share".into()])
+            .with_dimensions(vec!["orders.status".into()])
+            .with_order_by(vec!["orders.revenue_share DESC".into()])
+            .with_limit(5)
+            .with_offset(10);
+
+        let sql = tsql_generate(&graph, &query);
+        assert!(
+            sql.ends_with(
+                ") AS base\nORDER BY revenue_share DESC\nOFFSET 10 ROWS\nFETCH NEXT 5 ROWS ONLY"
+            ),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_tsql_cumulative_metric_pages_with_offset_fetch() {
+        let mut graph = create_test_graph();
+        let orders = graph
+            .get_model("orders")
+            .unwrap()
+            .clone()
+            .with_metric(Metric::cumulative("running_revenue", "orders.revenue"));
+        graph.replace_model(orders).unwrap();
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.running_revenue".into()])
+            .with_dimensions(vec!["orders.order_date__month".into()])
+            .with_limit(10);
+
+        let sql = tsql_generate(&graph, &query);
+        assert!(
+            sql.ends_with("\nORDER BY (SELECT NULL)\nOFFSET 0 ROWS\nFETCH NEXT 10 ROWS ONLY"),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_tsql_pre_aggregate_strategy_pages_with_offset_fetch() {
+        let mut graph = create_test_graph();
+        graph
+            .add_model(
+                Model::new("returns", "return_id")
+                    .with_table("returns")
+                    .with_metric(Metric::count("return_count"))
+                    .with_relationship(Relationship::many_to_one("orders")),
+            )
+            .unwrap();
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into(), "returns.return_count".into()])
+            .with_dimensions(vec!["orders.status".into()])
+            .with_order_by(vec!["orders.revenue DESC".into()])
+            .with_limit(5);
+
+        let sql = SqlGenerator::new_with_dialect(&graph, DialectType::TSQL)
+            .with_strategy(GenerationStrategy::PreAggregate)
+            .generate(&query)
+            .unwrap();
+        assert!(!sql.contains("LIMIT"), "{sql}");
+        assert!(
+            sql.ends_with("DESC\nOFFSET 0 ROWS\nFETCH NEXT 5 ROWS ONLY"),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_tsql_rollup_pages_with_offset_fetch() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_dimension(Dimension::categorical("status"))
+                    .with_metric(Metric::sum("revenue", "amount"))
+                    .with_pre_aggregation(PreAggregation {
+                        name: "by_status".to_string(),
+                        preagg_type: PreAggregationType::Rollup,
+                        measures: Some(vec!["revenue".to_string()]),
+                        dimensions: Some(vec!["status".to_string()]),
+                        time_dimension: None,
+                        granularity: None,
+                        partition_granularity: None,
+                        build_range_start: None,
+                        build_range_end: None,
+                        scheduled_refresh: true,
+                        refresh_key: None,
+                        indexes: None,
+                        sql: None,
+                        meta: None,
+                    }),
+            )
+            .unwrap();
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.status".into()])
+            .with_use_preaggregations(true)
+            .with_limit(5)
+            .with_offset(10);
+
+        let sql = tsql_generate(&graph, &query);
+        assert!(sql.contains("FROM orders_preagg_by_status"), "{sql}");
+        assert!(
+            sql.contains(
+                "GROUP BY 1\nORDER BY (SELECT NULL)\nOFFSET 10 ROWS\nFETCH NEXT 5 ROWS ONLY\n"
+            ),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_tsql_event_metrics_page_with_offset_fetch() {
+        let mut graph = SemanticGraph::new();
+        let events = Model::new("events", "event_id")
+            .with_table("events")
+            .with_dimension(Dimension::categorical("user_id"))
+            .with_dimension(Dimension::categorical("event_type"))
+            .with_dimension(Dimension::categorical("platform"))
+            .with_dimension(Dimension::time("event_date"))
+            .with_metric(Metric {
+                name: "signup_conversion".to_string(),
+                r#type: MetricType::Conversion,
+                agg: None,
+                entity: Some("user_id".to_string()),
+                base_event: Some("signup".to_string()),
+                conversion_event: Some("purchase".to_string()),
+                conversion_window: Some("7 days".to_string()),
+                ..Metric::new("signup_conversion")
+            })
+            .with_metric(Metric {
+                name: "signup_funnel".to_string(),
+                r#type: MetricType::Conversion,
+                agg: None,
+                entity: Some("user_id".to_string()),
+                steps: Some(vec![
+                    "event_type = 'signup'".to_string(),
+                    "event_type = 'purchase'".to_string(),
+                ]),
+                ..Metric::new("signup_funnel")
+            })
+            .with_metric(Metric {
+                name: "signup_retention".to_string(),
+                r#type: MetricType::Retention,
+                agg: None,
+                entity: Some("user_id".to_string()),
+                cohort_event: Some("event_type = 'signup'".to_string()),
+                activity_event: Some("event_type = 'active'".to_string()),
+                periods: Some(7),
+                retention_granularity: Some("day".to_string()),
+                ..Metric::new("signup_retention")
+            })
+            .with_metric(Metric {
+                name: "multi_platform_users".to_string(),
+                r#type: MetricType::Cohort,
+                agg: Some(Aggregation::Count),
+                entity: Some("user_id".to_string()),
+                inner_metrics: Some(vec![CohortInnerMetric {
+                    name: "platform_count".to_string(),
+                    agg: Some(Aggregation::CountDistinct),
+                    sql: Some("platform".to_string()),
+                }]),
+                having: Some("platform_count >= 2".to_string()),
+                ..Metric::new("multi_platform_users")
+            });
+        graph.add_model(events).unwrap();
+
+        for metric in [
+            "signup_conversion",
+            "signup_funnel",
+            "signup_retention",
+            "multi_platform_users",
+        ] {
+            let query = SemanticQuery::new()
+                .with_metrics(vec![format!("events.{metric}")])
+                .with_limit(5)
+                .with_offset(10);
+            let sql = tsql_generate(&graph, &query);
+            assert!(
+                sql.ends_with("\nOFFSET 10 ROWS\nFETCH NEXT 5 ROWS ONLY"),
+                "{metric}: {sql}"
+            );
+            assert!(sql.contains("\nORDER BY "), "{metric}: {sql}");
+        }
+    }
+
+    #[test]
+    fn test_tsql_preview_uses_top() {
+        let graph = create_test_graph();
+        let orders = graph.get_model("orders").unwrap();
+
+        assert_eq!(
+            SqlGenerator::new_with_dialect(&graph, DialectType::TSQL)
+                .preview(orders, 10)
+                .unwrap(),
+            "SELECT TOP 10\n  status,\n  created_at AS order_date\nFROM orders"
+        );
+    }
+
+    #[test]
+    fn test_json_path_dimension_renders_per_dialect() {
+        let graph = create_events_graph();
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["events.event_count".into()])
+            .with_dimensions(vec!["events.plan".into()]);
+
+        let duckdb_sql = SqlGenerator::new(&graph).generate(&query).unwrap();
+        assert!(
+            duckdb_sql.contains("events_cte.properties->>'plan' AS plan"),
+            "{duckdb_sql}"
+        );
+
+        let bigquery_sql = SqlGenerator::new(&graph)
+            .with_dialect(DialectType::BigQuery)
+            .generate(&query)
+            .unwrap();
+        assert!(
+            bigquery_sql.contains("JSON_VALUE(events_cte.properties, '$.plan') AS plan"),
+            "{bigquery_sql}"
+        );
+    }
+
+    #[test]
+    fn test_array_contains_filter_renders_per_dialect() {
+        let graph = create_events_graph();
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["events.event_count".into()])
+            .with_filters(vec!["array_contains(events.tags, 'beta')".into()]);
+
+        let duckdb_sql = SqlGenerator::new(&graph).generate(&query).unwrap();
+        assert!(
+            duckdb_sql.contains("WHERE list_contains(tags, 'beta')"),
+            "{duckdb_sql}"
+        );
+
+        let postgres_sql = SqlGenerator::new(&graph)
+            .with_dialect(DialectType::PostgreSQL)
+            .generate(&query)
+            .unwrap();
+        assert!(
+            postgres_sql.contains("WHERE 'beta' = ANY(tags)"),
+            "{postgres_sql}"
+        );
+
+        let bigquery_sql = SqlGenerator::new(&graph)
+            .with_dialect(DialectType::BigQuery)
+            .generate(&query)
+            .unwrap();
+        assert!(
+            bigquery_sql.contains("WHERE 'beta' IN UNNEST(tags)"),
+            "{bigquery_sql}"
+        );
+    }
+
+    #[test]
+    fn test_nested_json_path_uses_postgres_path_operator() {
+        let graph = SemanticGraph::new();
+        let generator = SqlGenerator::new(&graph).with_dialect(DialectType::PostgreSQL);
+        assert_eq!(
+            generator.render_semi_structured_sql("e.properties->>'$.billing.plan' = 'pro'"),
+            "e.properties #>> '{billing,plan}' = 'pro'"
+        );
+    }
+
+    #[test]
+    fn test_semi_structured_rewrites_skip_string_literals() {
+        let graph = SemanticGraph::new();
+        let generator = SqlGenerator::new(&graph).with_dialect(DialectType::BigQuery);
+        assert_eq!(
+            generator.render_semi_structured_sql(
+                "note = 'see array_contains(tags, 1) and a->>''b''' AND props->>'plan' = 'pro'"
+            ),
+            "note = 'see array_contains(tags, 1) and a->>''b''' AND JSON_VALUE(props, '$.plan') = 'pro'"
+        );
+    }
+
+    #[test]
+    fn test_top_n_per_category_ranks_within_partition() {
+        let mut graph = SemanticGraph::new();
+        let products = Model::new("products", "product_id")
+            .with_table("products")
+            .with_dimension(Dimension::categorical("category"))
+            .with_dimension(Dimension::categorical("name"))
+            .with_metric(Metric::sum("revenue", "amount"));
+        graph.add_model(products).unwrap();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["products.revenue".into()])
+            .with_dimensions(vec!["products.category".into(), "products.name".into()])
+            .with_top_n_per(TopNPer::new("products.category", 5, "products.revenue"));
+
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(
+            sql.contains(
+                "ROW_NUMBER() OVER (PARTITION BY category ORDER BY revenue DESC) AS __top_n_rank"
+            ),
+            "{sql}"
+        );
+        assert!(sql.contains("WHERE __top_n_rank <= 5"), "{sql}");
+        assert!(sql.contains("GROUP BY 1, 2"), "{sql}");
+        assert!(
+            sql.starts_with("SELECT\n  category,\n  name,\n  revenue\n"),
+            "{sql}"
+        );
+        assert!(!sql.contains("LIMIT"), "{sql}");
+    }
+
+    #[test]
+    fn test_top_n_per_wraps_cumulative_metrics() {
+        let mut graph = create_test_graph();
+        let orders = graph
+            .get_model("orders")
+            .unwrap()
+            .clone()
+            .with_metric(Metric::cumulative("running_revenue", "orders.revenue"));
+        graph.replace_model(orders).unwrap();
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.running_revenue".into()])
+            .with_dimensions(vec![
+                "orders.status".into(),
+                "orders.order_date__month".into(),
+            ])
+            .with_top_n_per(TopNPer::new("orders.status", 3, "orders.running_revenue"))
+            .with_limit(10);
+        let sql = SqlGenerator::new(&graph).generate(&query).unwrap();
+
+        let rank = sql
+            .find("ROW_NUMBER() OVER (PARTITION BY status ORDER BY running_revenue DESC)")
+            .unwrap_or_else(|| panic!("{sql}"));
+        let running = sql.find("ROWS BETWEEN UNBOUNDED PRECEDING").expect(&sql);
+        assert!(rank < running, "{sql}");
+        assert!(sql.contains("WHERE __top_n_rank <= 3"), "{sql}");
+        assert!(sql.trim_end().ends_with("LIMIT 10"), "{sql}");
+        assert_eq!(sql.matches("LIMIT").count(), 1, "{sql}");
+    }
+
+    #[test]
+    fn test_top_n_per_requires_query_dimension() {
+        let graph = create_test_graph();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.status".into()])
+            .with_top_n_per(TopNPer::new("customers.country", 3, "orders.revenue"));
+
+        let err = generator.generate(&query).unwrap_err();
+        assert!(err.to_string().contains("top_n_per dimension"), "{err}");
+    }
+
+    #[test]
+    fn test_single_model_query_emits_no_joins() {
+        let graph = create_test_graph();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into(), "orders.order_count".into()])
+            .with_dimensions(vec![
+                "orders.status".into(),
+                "orders.order_date__month".into(),
+            ])
+            .with_filters(vec!["orders.status = 'completed'".into()]);
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(sql.contains("FROM orders_cte AS orders_cte"), "{sql}");
+        assert!(!sql.contains("JOIN"), "{sql}");
+        assert!(!sql.contains("customers"), "{sql}");
+    }
+
+    #[test]
+    fn test_filter_customers_having_more_than_3_orders() {
+        let graph = create_test_graph();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["customers.country".into()])
+            .with_filters(vec!["customers having count(orders) > 3".into()]);
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(
+            sql.contains(
+                "id IN (SELECT customers_id FROM orders WHERE customers_id IS NOT NULL \
+                 GROUP BY customers_id HAVING COUNT(*) > 3)"
+            ),
+            "{sql}"
+        );
+        assert!(
+            !sql.to_lowercase().contains("having count(orders)"),
+            "{sql}"
+        );
+
+        // Customers without orders satisfy "= 0", so they are kept by exclusion.
+        let query = SemanticQuery::new()
+            .with_dimensions(vec!["customers.country".into()])
+            .with_filters(vec!["customers HAVING COUNT(orders) = 0".into()]);
+        let sql = generator.generate(&query).unwrap();
+        assert!(
+            sql.contains("NOT IN (SELECT customers_id FROM orders"),
+            "{sql}"
+        );
+        assert!(sql.contains("HAVING NOT (COUNT(*) = 0))"), "{sql}");
+    }
+
+    #[test]
+    fn test_relationship_count_filter_applies_child_default_filters() {
+        let mut graph = create_test_graph();
+        let orders = graph
+            .get_model("orders")
+            .unwrap()
+            .clone()
+            .with_sql("SELECT * FROM raw_orders")
+            .with_default_filter("{model}.status <> 'cancelled'");
+        graph.replace_model(orders).unwrap();
+
+        let query = SemanticQuery::new()
+            .with_dimensions(vec!["customers.country".into()])
+            .with_filters(vec!["customers HAVING COUNT(orders) > 3".into()]);
+        let sql = SqlGenerator::new(&graph).generate(&query).unwrap();
+
+        assert!(
+            sql.contains(
+                "IN (SELECT customers_id FROM (SELECT * FROM raw_orders) AS t \
+                 WHERE customers_id IS NOT NULL AND status <> 'cancelled' \
+                 GROUP BY customers_id HAVING COUNT(*) > 3)"
+            ),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_filter_validation_rejects_injection() {
+        let graph = create_test_graph();
+        let generator = SqlGenerator::new(&graph);
+        let query = |filter: &str| {
+            SemanticQuery::new()
+                .with_metrics(vec!["orders.revenue".into()])
+                .with_filters(vec![filter.to_string()])
+                .with_filter_validation(FilterValidation::Predicate)
+        };
+
+        for filter in [
+            "orders.status = 'x'; DROP TABLE orders",
+            "1 = 1) UNION SELECT secret FROM credentials WHERE (1 = 1",
+            "orders.status = 'x' --",
+            "orders.status IN (SELECT status FROM other)",
+            "'completed'",
+            "orders.status = 'x' AND EXISTS (SELECT 1 FROM credentials)",
+            "orders.status = 'x' /* hidden */",
+        ] {
+            let err = generator.generate(&query(filter)).unwrap_err();
+            assert!(
+                matches!(err, SidemanticError::Validation(ref msg) if msg.starts_with("Rejected filter")),
+                "{filter}: {err}"
+            );
+        }
+
+        // Backslash escapes are read the way the target database reads them:
+        // on MySQL the quote after `a\'` closes the string, exposing the rest.
+        let mysql = SqlGenerator::new_with_dialect(&graph, DialectType::MySQL);
+        let err = mysql
+            .generate(&query("orders.status = 'a\\'' ; DROP TABLE orders; --'"))
+            .unwrap_err();
+        assert!(err.to_string().contains("Rejected filter"), "{err}");
+
+        // Without validation, filters stay trusted.
+        assert!(generator
+            .generate(&query("1 = 1 --").with_filter_validation(FilterValidation::Trusted))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_filter_validation_accepts_predicates() {
+        let graph = create_test_graph();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_filters(vec![
+                "orders.status = 'a;b' AND (orders.status <> 'x' OR orders.status IS NULL)".into(),
+            ])
+            .with_filter_validation(FilterValidation::Predicate);
+        let sql = generator.generate(&query).unwrap();
+        assert!(sql.contains("'a;b'"), "{sql}");
+
+        let subquery = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_filters(vec!["orders.status IN (SELECT status FROM allowed)".into()])
+            .with_filter_validation(FilterValidation::PredicateWithSubqueries);
+        assert!(generator.generate(&subquery).is_ok());
+    }
+
+    #[test]
+    fn test_qualify_expression_columns() {
+        let graph = SemanticGraph::new();
+        let generator = SqlGenerator::new(&graph);
+        let qualify = |expr: &str| generator.qualify_expression_columns(expr, "orders_cte");
+
+        assert_eq!(
+            qualify("CAST(\"Net Amount\" AS INT) + amount - t.fee"),
+            "CAST(orders_cte.\"Net Amount\" AS INT) + orders_cte.amount - t.fee"
+        );
+        // Literals, function names and aggregate arguments are handled by the
+        // parser rather than by token guessing.
+        assert_eq!(
+            qualify("CASE WHEN status = 'amount' THEN UPPER(region) END"),
+            "CASE WHEN orders_cte.status = 'amount' THEN UPPER(orders_cte.region) END"
+        );
+        assert_eq!(
+            qualify("SUM(amount) / COUNT(DISTINCT customer_id)"),
+            "SUM(orders_cte.amount) / COUNT(DISTINCT orders_cte.customer_id)"
+        );
+        assert_eq!(qualify("not valid sql ("), "not valid sql (");
+    }
+
+    #[test]
+    fn test_group_by_case_expression_dimension() {
+        let mut graph = create_test_graph();
+        let orders = graph
+            .get_model("orders")
+            .unwrap()
+            .clone()
+            .with_dimension(Dimension::categorical("order_size").with_sql(
+            "CASE WHEN amount >= 100 THEN 'large' WHEN amount >= 20 THEN 'medium' ELSE 'small' END",
+        ));
+        graph.replace_model(orders).unwrap();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.order_size".into()]);
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(sql.contains("orders_cte.amount >= 100"), "{sql}");
+        assert!(sql.contains("orders_cte.amount >= 20"), "{sql}");
+        assert!(sql.contains("END AS order_size"), "{sql}");
+        assert!(!sql.contains("orders_cte.CASE"), "{sql}");
+        assert!(!sql.contains("orders_cte.'"), "{sql}");
+        assert!(
+            sql.contains("SUM(orders_cte.revenue_raw) AS revenue"),
+            "{sql}"
+        );
+        assert!(sql.contains("GROUP BY 1"), "{sql}");
+    }
+
+    #[test]
+    fn test_grand_total_row_appended_with_union_all() {
+        let graph = create_test_graph();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec![
+                "orders.status".into(),
+                "orders.order_date__month".into(),
+            ])
+            .with_order_by(vec!["orders.revenue DESC".into()])
+            .with_grand_total("All");
+        let sql = generator.generate(&query).unwrap();
+
+        let (breakdown, total) = sql.split_once("\nUNION ALL\n").expect("UNION ALL");
+        assert!(
+            breakdown.starts_with(
+                "SELECT\n  CAST(breakdown.status AS VARCHAR) AS status,\n  \
+                 breakdown.order_date__month,\n  breakdown.revenue\nFROM (\n"
+            ),
+            "{sql}"
+        );
+        assert!(breakdown.contains("GROUP BY 1, 2"), "{sql}");
+        assert!(breakdown.ends_with(") AS breakdown"), "{sql}");
+
+        assert!(
+            total.starts_with(
+                "SELECT\n  'All' AS status,\n  NULL AS order_date__month,\n  grand_total.*\nFROM ("
+            ),
+            "{sql}"
+        );
+        assert!(
+            total.contains("SUM(orders_cte.revenue_raw) AS revenue"),
+            "{sql}"
+        );
+        assert!(!total.contains("GROUP BY"), "{sql}");
+        assert!(
+            total.ends_with(") AS grand_total\nORDER BY revenue DESC"),
+            "{sql}"
+        );
+
+        let bigquery_sql = SqlGenerator::new_with_dialect(&graph, DialectType::BigQuery)
+            .generate(&query)
+            .unwrap();
+        assert!(
+            bigquery_sql.contains("CAST(breakdown.status AS STRING) AS status"),
+            "{bigquery_sql}"
+        );
+    }
+
+    #[test]
+    fn test_trailing_30_day_cumulative_metric() {
+        let mut graph = create_test_graph();
+        let orders = graph
+            .get_model("orders")
+            .unwrap()
+            .clone()
+            .with_metric(Metric::trailing(
+                "revenue_t30d",
+                "orders.revenue",
+                30,
+                TimeGrain::Day,
+            ));
+        graph.replace_model(orders).unwrap();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue_t30d".into()])
+            .with_dimensions(vec!["orders.order_date__day".into()]);
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(
+            sql.contains(
+                "SUM(base.revenue) OVER (ORDER BY base.order_date__day ROWS BETWEEN 29 PRECEDING AND CURRENT ROW) AS revenue_t30d"
+            ),
+            "{sql}"
+        );
+
+        let err = generator
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec!["orders.revenue_t30d".into()])
+                    .with_dimensions(vec!["orders.order_date__month".into()]),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("at day grain"), "{err}");
+
+        let sql = generator
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec!["orders.revenue_t30d".into()])
+                    .with_dimensions(vec![
+                        "orders.order_date__day".into(),
+                        "orders.status".into(),
+                    ]),
+            )
+            .unwrap();
+        assert!(
+            sql.contains(
+                "SUM(base.revenue) OVER (PARTITION BY base.status ORDER BY base.order_date__day ROWS BETWEEN 29 PRECEDING AND CURRENT ROW) AS revenue_t30d"
+            ),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_cumulative_metric_without_time_dimension_is_rejected() {
+        let mut graph = create_test_graph();
+        let orders = graph
+            .get_model("orders")
+            .unwrap()
+            .clone()
+            .with_metric(Metric::cumulative("running_revenue", "orders.revenue"));
+        graph.replace_model(orders).unwrap();
+        let generator = SqlGenerator::new(&graph);
+
+        let err = generator
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec!["orders.running_revenue".into()])
+                    .with_dimensions(vec!["orders.status".into()]),
+            )
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Metric 'orders.running_revenue' requires a time dimension"),
+            "{err}"
+        );
+
+        let sql = generator
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec!["orders.running_revenue".into()])
+                    .with_dimensions(vec![
+                        "orders.status".into(),
+                        "orders.order_date__month".into(),
+                    ]),
+            )
+            .unwrap();
+        assert!(sql.contains("running_revenue"), "{sql}");
+    }
+
+    #[test]
+    fn test_null_label_coalesces_dimension_for_select_and_grouping() {
+        let mut graph = create_test_graph();
+        let mut orders = graph.get_model("orders").unwrap().clone();
+        orders.dimensions[0] = Dimension::categorical("status").with_null_label("Unknown");
+        graph.replace_model(orders).unwrap();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.status".into()]);
+
+        let sql = generator.generate(&query).unwrap();
+        assert!(
+            sql.contains("COALESCE(orders_cte.status, 'Unknown') AS status"),
+            "{sql}"
+        );
+        // GROUP BY is positional, so it groups on the COALESCE expression.
+        assert!(sql.contains("GROUP BY 1"), "{sql}");
+    }
+
+    #[test]
+    fn test_null_label_skips_non_categorical_dimensions() {
+        let mut graph = create_test_graph();
+        let mut orders = graph.get_model("orders").unwrap().clone();
+        orders.dimensions[1] = Dimension::time("order_date")
+            .with_sql("created_at")
+            .with_null_label("Unknown");
+        graph.replace_model(orders).unwrap();
+        let generator = SqlGenerator::new(&graph);
+
+        let sql = generator
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec!["orders.revenue".into()])
+                    .with_dimensions(vec!["orders.order_date__month".into()]),
+            )
+            .unwrap();
+        assert!(!sql.contains("'Unknown'"), "{sql}");
+    }
+
+    #[test]
+    fn test_null_label_applies_to_pre_aggregate_strategy_and_rollups() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_dimension(Dimension::categorical("status").with_null_label("Unknown"))
+                    .with_metric(Metric::sum("revenue", "amount"))
+                    .with_pre_aggregation(PreAggregation {
+                        name: "by_status".to_string(),
+                        preagg_type: PreAggregationType::Rollup,
+                        measures: Some(vec!["revenue".to_string()]),
+                        dimensions: Some(vec!["status".to_string()]),
+                        time_dimension: None,
+                        granularity: None,
+                        partition_granularity: None,
+                        build_range_start: None,
+                        build_range_end: None,
+                        scheduled_refresh: true,
+                        refresh_key: None,
+                        indexes: None,
+                        sql: None,
+                        meta: None,
+                    }),
+            )
+            .unwrap();
+        graph
+            .add_model(
+                Model::new("returns", "return_id")
+                    .with_table("returns")
+                    .with_metric(Metric::count("return_count"))
+                    .with_relationship(Relationship::many_to_one("orders")),
+            )
+            .unwrap();
+
+        let rollup_sql = SqlGenerator::new(&graph)
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec!["orders.revenue".into()])
+                    .with_dimensions(vec!["orders.status".into()])
+                    .with_use_preaggregations(true),
+            )
+            .unwrap();
+        assert!(
+            rollup_sql.contains("FROM orders_preagg_by_status"),
+            "{rollup_sql}"
+        );
+        assert!(
+            rollup_sql.contains("COALESCE(status, 'Unknown') AS status"),
+            "{rollup_sql}"
+        );
+
+        let strategy_sql = SqlGenerator::new(&graph)
+            .with_strategy(GenerationStrategy::PreAggregate)
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec!["orders.revenue".into(), "returns.return_count".into()])
+                    .with_dimensions(vec!["orders.status".into()]),
+            )
+            .unwrap();
+        assert_eq!(
+            strategy_sql.matches(", 'Unknown') AS status").count(),
+            2,
+            "{strategy_sql}"
+        );
+    }
+
+    #[test]
+    fn test_value_labels_map_to_case_when_enabled() {
+        let mut graph = create_test_graph();
+        let mut orders = graph.get_model("orders").unwrap().clone();
+        orders.dimensions[0] = Dimension::categorical("status").with_value_labels([
+            ("P", "Pending"),
+            ("S", "Shipped"),
+            ("X", "Rider's cancel"),
+        ]);
+        graph.replace_model(orders).unwrap();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.status".into()]);
+
+        let sql = generator.generate(&query).unwrap();
+        assert!(sql.contains("orders_cte.status AS status"), "{sql}");
+        assert!(!sql.contains("CASE CAST"), "{sql}");
+
+        let sql = generator
+            .generate(&query.clone().with_value_labels(true))
+            .unwrap();
+        assert!(
+            sql.contains(
+                "CASE CAST(orders_cte.status AS VARCHAR) WHEN 'P' THEN 'Pending' \
+                 WHEN 'S' THEN 'Shipped' WHEN 'X' THEN 'Rider''s cancel' \
+                 ELSE CAST(orders_cte.status AS VARCHAR) END AS status"
+            ),
+            "{sql}"
+        );
+
+        let bigquery_sql = SqlGenerator::new_with_dialect(&graph, DialectType::BigQuery)
+            .generate(&query.with_value_labels(true))
+            .unwrap();
+        assert!(
+            bigquery_sql.contains("CASE CAST(orders_cte.status AS STRING) WHEN 'P'"),
+            "{bigquery_sql}"
+        );
+        assert!(!bigquery_sql.contains("VARCHAR"), "{bigquery_sql}");
+    }
+
+    #[test]
+    fn test_metric_filter_on_own_model_stays_in_cte() {
+        let mut graph = create_test_graph();
+        let orders = graph.get_model("orders").unwrap().clone().with_metric(
+            Metric::sum("completed_revenue", "amount").with_filter("{model}.status = 'completed'"),
+        );
+        graph.replace_model(orders).unwrap();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new().with_metrics(vec!["orders.completed_revenue".into()]);
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(
+            sql.contains("CASE WHEN status = 'completed' THEN amount END AS completed_revenue_raw"),
+            "{sql}"
         );
+        assert!(!sql.contains("customers_cte"), "{sql}");
     }
 
     #[test]
-    fn test_query_with_join() {
-        let graph = create_test_graph();
+    fn test_metric_filter_on_joined_model_dimension() {
+        let mut graph = create_test_graph();
+        let orders = graph.get_model("orders").unwrap().clone().with_metric(
+            Metric::sum("us_revenue", "amount").with_filter("customers.country = 'US'"),
+        );
+        graph.replace_model(orders).unwrap();
         let generator = SqlGenerator::new(&graph);
 
         let query = SemanticQuery::new()
-            .with_metrics(vec!["orders.revenue".into()])
-            .with_dimensions(vec!["customers.country".into()]);
-
+            .with_metrics(vec!["orders.us_revenue".into()])
+            .with_dimensions(vec!["orders.status".into()]);
         let sql = generator.generate(&query).unwrap();
 
-        assert!(sql.contains("LEFT JOIN orders_cte AS orders_cte"));
-        assert!(sql.contains("customers_cte.id = orders_cte.customers_id"));
+        assert!(sql.contains("amount AS us_revenue_raw"), "{sql}");
+        assert!(
+            sql.contains("LEFT JOIN customers_cte AS customers_cte"),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(
+                "SUM(CASE WHEN customers_cte.country = 'US' THEN orders_cte.us_revenue_raw END) AS us_revenue"
+            ),
+            "{sql}"
+        );
+        assert!(!sql.contains("WHERE customers_cte.country"), "{sql}");
     }
 
     #[test]
-    fn test_relationship_foreign_key_dimension_rejects_granularity_suffix() {
-        let mut graph = SemanticGraph::new();
-
-        let orders = Model::new("orders", "order_id")
-            .with_table("orders")
-            .with_metric(Metric::count("order_count"))
-            .with_relationship(
-                Relationship::many_to_one("customers").with_keys("customer_id", "id"),
-            );
-        let customers = Model::new("customers", "id").with_table("customers");
-
-        graph.add_model(orders).unwrap();
-        graph.add_model(customers).unwrap();
-
+    fn test_join_filter_goes_in_on_clause_and_keeps_unmatched_rows() {
+        let graph = create_test_graph();
         let generator = SqlGenerator::new(&graph);
         let query = SemanticQuery::new()
-            .with_metrics(vec!["orders.order_count".into()])
-            .with_dimensions(vec!["orders.customer_id__month".into()]);
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.status".into(), "customers.name".into()]);
+
+        // As a WHERE filter, orders without a US customer are dropped.
+        let where_sql = generator
+            .generate(
+                &query
+                    .clone()
+                    .with_filters(vec!["customers.country = 'US'".into()]),
+            )
+            .unwrap();
+        assert!(
+            where_sql.contains("INNER JOIN customers_cte AS customers_cte"),
+            "{where_sql}"
+        );
+        assert!(where_sql.contains("WHERE country = 'US'"), "{where_sql}");
 
-        let err = generator.generate(&query).unwrap_err();
+        // As a join filter, those orders survive with a NULL customer name.
+        let join_sql = generator
+            .generate(&query.with_join_filters(vec!["customers.country = 'US'".into()]))
+            .unwrap();
         assert!(
-            err.to_string()
-                .contains("Cannot apply granularity to non-time dimension 'customer_id'"),
+            join_sql.contains("= customers_cte.id AND customers_cte.country = 'US'"),
+            "{join_sql}"
+        );
+        assert!(!join_sql.contains("INNER JOIN"), "{join_sql}");
+        assert!(!join_sql.contains("WHERE"), "{join_sql}");
+
+        let err = generator
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec!["orders.revenue".into()])
+                    .with_join_filters(vec!["orders.status = 'completed'".into()]),
+            )
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("other than the base model"),
             "{err}"
         );
     }
 
     #[test]
-    fn test_query_with_composite_join() {
-        let mut graph = SemanticGraph::new();
-
-        let shipments = Model::new("shipments", "shipment_id")
-            .with_table("shipments")
-            .with_metric(Metric::count("shipment_count"))
-            .with_relationship(Relationship::many_to_one("order_items").with_key_columns(
-                vec!["order_id".to_string(), "item_id".to_string()],
-                vec!["order_id".to_string(), "item_id".to_string()],
-            ));
-        let order_items = Model::new("order_items", "order_id")
-            .with_primary_key_columns(vec!["order_id".to_string(), "item_id".to_string()])
-            .with_table("order_items")
-            .with_dimension(Dimension::categorical("sku"));
-
-        graph.add_model(shipments).unwrap();
-        graph.add_model(order_items).unwrap();
-
+    fn test_window_metric_inner_query_keeps_join_filters() {
+        let mut graph = create_test_graph();
+        let orders = graph
+            .get_model("orders")
+            .unwrap()
+            .clone()
+            .with_metric(Metric::cumulative("running_revenue", "orders.revenue"))
+            .with_metric(Metric::pct_of_total("revenue_share", "revenue"));
+        graph.replace_model(orders).unwrap();
         let generator = SqlGenerator::new(&graph);
-        let query = SemanticQuery::new()
-            .with_metrics(vec!["shipments.shipment_count".into()])
-            .with_dimensions(vec!["order_items.sku".into()]);
-
-        let sql = generator.generate(&query).unwrap();
 
-        assert!(sql.contains("order_items_cte.order_id = shipments_cte.order_id"));
-        assert!(sql.contains("order_items_cte.item_id = shipments_cte.item_id"));
-        assert!(sql.contains(" AND "));
+        for metric in ["orders.running_revenue", "orders.revenue_share"] {
+            let sql = generator
+                .generate(
+                    &SemanticQuery::new()
+                        .with_metrics(vec![metric.into()])
+                        .with_dimensions(vec![
+                            "orders.order_date__month".into(),
+                            "customers.name".into(),
+                        ])
+                        .with_join_filters(vec!["customers.country = 'US'".into()]),
+                )
+                .unwrap();
+            assert!(
+                sql.contains("= customers_cte.id AND customers_cte.country = 'US'"),
+                "{sql}"
+            );
+        }
     }
 
     #[test]
-    fn test_query_with_composite_many_to_many_through_join() {
+    fn test_weighted_avg_metric_generates_ratio_of_sums() {
         let mut graph = SemanticGraph::new();
-
-        let orders = Model::new("orders", "tenant_id")
-            .with_primary_key_columns(vec!["tenant_id".to_string(), "order_id".to_string()])
-            .with_table("orders")
-            .with_metric(Metric::sum("revenue", "amount"))
-            .with_relationship(Relationship {
-                name: "products".to_string(),
-                r#type: RelationshipType::ManyToMany,
-                foreign_key: None,
-                foreign_key_columns: None,
-                primary_key: None,
-                primary_key_columns: None,
-                through: Some("order_items".to_string()),
-                through_foreign_key: Some("order_id".to_string()),
-                through_foreign_key_columns: Some(vec![
-                    "tenant_id".to_string(),
-                    "order_id".to_string(),
-                ]),
-                related_foreign_key: Some("product_id".to_string()),
-                related_foreign_key_columns: Some(vec![
-                    "tenant_id".to_string(),
-                    "product_id".to_string(),
-                ]),
-                sql: None,
-                metadata: None,
-            });
-        let order_items = Model::new("order_items", "tenant_id")
-            .with_primary_key_columns(vec![
-                "tenant_id".to_string(),
-                "order_id".to_string(),
-                "product_id".to_string(),
-            ])
-            .with_table("order_items");
-        let products = Model::new("products", "tenant_id")
-            .with_primary_key_columns(vec!["tenant_id".to_string(), "product_id".to_string()])
-            .with_table("products")
-            .with_dimension(Dimension::categorical("name"));
-
-        graph.add_model(orders).unwrap();
+        let order_items = Model::new("order_items", "item_id")
+            .with_table("order_items")
+            .with_dimension(Dimension::categorical("category"))
+            .with_metric(Metric::weighted_avg(
+                "avg_unit_price",
+                "unit_price",
+                "quantity",
+            ));
         graph.add_model(order_items).unwrap();
-        graph.add_model(products).unwrap();
-
-        let generator = SqlGenerator::new(&graph);
-        let query = SemanticQuery::new()
-            .with_metrics(vec!["orders.revenue".into()])
-            .with_dimensions(vec!["products.name".into()]);
-
-        let sql = generator.generate(&query).unwrap();
-
-        assert!(sql.contains("products_cte.tenant_id = order_items_cte.tenant_id"));
-        assert!(sql.contains("products_cte.product_id = order_items_cte.product_id"));
-        assert!(sql.contains("order_items_cte.tenant_id = orders_cte.tenant_id"));
-        assert!(sql.contains("order_items_cte.order_id = orders_cte.order_id"));
-    }
-
-    #[test]
-    fn test_query_with_filter() {
-        let graph = create_test_graph();
         let generator = SqlGenerator::new(&graph);
 
         let query = SemanticQuery::new()
-            .with_metrics(vec!["orders.revenue".into()])
-            .with_dimensions(vec!["orders.status".into()])
-            .with_filters(vec!["orders.status = 'completed'".into()]);
-
+            .with_metrics(vec!["order_items.avg_unit_price".into()])
+            .with_dimensions(vec!["order_items.category".into()]);
         let sql = generator.generate(&query).unwrap();
 
-        assert!(sql.contains("WHERE status = 'completed'"));
+        assert!(
+            sql.contains(
+                "SUM(order_items_cte.unit_price * order_items_cte.quantity) / NULLIF(SUM(order_items_cte.quantity), 0) AS avg_unit_price"
+            ),
+            "{sql}"
+        );
+        assert!(sql.contains("GROUP BY 1"), "{sql}");
     }
 
     #[test]
@@ -5544,6 +10373,211 @@ models:
         );
     }
 
+    #[test]
+    fn test_hoisted_subquery_ctes_rename_conflicting_definitions() {
+        let mut sqls = vec![
+            "WITH customers_cte AS (\n  SELECT *\n  FROM customers\n  WHERE (tier = 'a)')\n),\norders_cte AS (\n  SELECT *\n  FROM orders\n)\nSELECT 1\nFROM customers_cte AS customers_cte\nLEFT JOIN orders_cte AS orders_cte ON TRUE".to_string(),
+            "WITH customers_cte AS (\n  SELECT *\n  FROM customers\n)\nSELECT 2\nFROM customers_cte AS customers_cte".to_string(),
+        ];
+        let hoisted = SqlGenerator::hoist_subquery_ctes(
+            &["orders".to_string(), "tickets".to_string()],
+            &mut sqls,
+        );
+
+        assert_eq!(
+            hoisted,
+            vec![
+                "customers_cte AS (\n  SELECT *\n  FROM customers\n  WHERE (tier = 'a)')\n)",
+                "orders_cte AS (\n  SELECT *\n  FROM orders\n)",
+                "tickets_customers_cte AS (\n  SELECT *\n  FROM customers\n)",
+            ]
+        );
+        assert!(sqls[0].starts_with("SELECT 1\n"), "{}", sqls[0]);
+        assert_eq!(
+            sqls[1],
+            "SELECT 2\nFROM tickets_customers_cte AS customers_cte"
+        );
+    }
+
+    #[test]
+    fn test_pre_aggregate_shares_sql_model_read_by_both_facts() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_metric(Metric::sum("revenue", "amount"))
+                    .with_relationship(Relationship::many_to_one("customers")),
+            )
+            .unwrap();
+        graph
+            .add_model(
+                Model::new("support_tickets", "ticket_id")
+                    .with_table("support_tickets")
+                    .with_metric(Metric::count("ticket_count"))
+                    .with_relationship(Relationship::many_to_one("customers")),
+            )
+            .unwrap();
+        graph
+            .add_model(
+                Model::new("customers", "id")
+                    .with_sql("SELECT * FROM raw.customers WHERE NOT deleted")
+                    .with_dimension(Dimension::categorical("country")),
+            )
+            .unwrap();
+
+        let sql = SqlGenerator::new(&graph)
+            .with_strategy(GenerationStrategy::PreAggregate)
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec![
+                        "orders.revenue".into(),
+                        "support_tickets.ticket_count".into(),
+                    ])
+                    .with_dimensions(vec!["customers.country".into()]),
+            )
+            .unwrap();
+
+        assert_eq!(sql.matches("raw.customers").count(), 1, "{sql}");
+        assert!(
+            sql.starts_with(
+                "WITH customers_source AS (\nSELECT * FROM raw.customers WHERE NOT deleted\n)"
+            ),
+            "{sql}"
+        );
+        // Both subqueries define the same customers CTE, so it is hoisted once.
+        assert_eq!(
+            sql.matches("FROM customers_source AS t").count(),
+            1,
+            "{sql}"
+        );
+        assert_eq!(sql.matches("customers_cte AS (").count(), 1, "{sql}");
+    }
+
+    #[test]
+    fn test_pre_aggregate_strategy_aggregates_each_fact_before_joining() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_metric(Metric::sum("revenue", "amount"))
+                    .with_relationship(Relationship::many_to_one("customers"))
+                    .with_relationship(Relationship {
+                        r#type: RelationshipType::OneToOne,
+                        ..Relationship::new("invoices")
+                    }),
+            )
+            .unwrap();
+        graph
+            .add_model(
+                Model::new("support_tickets", "ticket_id")
+                    .with_table("support_tickets")
+                    .with_metric(Metric::count("ticket_count"))
+                    .with_relationship(Relationship::many_to_one("customers")),
+            )
+            .unwrap();
+        graph
+            .add_model(
+                Model::new("customers", "id")
+                    .with_table("customers")
+                    .with_dimension(Dimension::categorical("country")),
+            )
+            .unwrap();
+        graph
+            .add_model(
+                Model::new("invoices", "id")
+                    .with_table("invoices")
+                    .with_metric(Metric::sum("invoiced", "total")),
+            )
+            .unwrap();
+        let generator = SqlGenerator::new(&graph).with_strategy(GenerationStrategy::PreAggregate);
+
+        let sql = generator
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec![
+                        "orders.revenue".into(),
+                        "support_tickets.ticket_count".into(),
+                    ])
+                    .with_dimensions(vec!["customers.country".into()]),
+            )
+            .unwrap();
+        let (orders_cte, tickets_cte) = sql
+            .split_once("support_tickets_preagg AS (")
+            .expect("support_tickets CTE");
+        let orders_cte = orders_cte
+            .split_once("orders_preagg AS (")
+            .expect("orders CTE")
+            .1;
+        let tickets_cte = tickets_cte.split_once("\n)").expect("CTE end").0;
+        assert!(sql.starts_with("WITH "), "{sql}");
+        assert_eq!(sql.matches("WITH ").count(), 1, "{sql}");
+        assert!(orders_cte.contains("SUM(orders_cte.revenue_raw)"), "{sql}");
+        assert!(!orders_cte.contains("ticket_count"), "{sql}");
+        assert!(
+            tickets_cte.contains("COUNT(support_tickets_cte.ticket_count_raw)"),
+            "{sql}"
+        );
+        assert!(!tickets_cte.contains("revenue"), "{sql}");
+        assert!(
+            sql.contains(
+                "FULL OUTER JOIN support_tickets_preagg ON \
+                 orders_preagg.country IS NOT DISTINCT FROM support_tickets_preagg.country"
+            ),
+            "{sql}"
+        );
+
+        // A one-to-one join has no fan-out, so only the explicit strategy
+        // splits it into per-model aggregates.
+        let one_to_one = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into(), "invoices.invoiced".into()]);
+        let auto_sql = SqlGenerator::new(&graph).generate(&one_to_one).unwrap();
+        assert!(!auto_sql.contains("_preagg"), "{auto_sql}");
+        let preagg_sql = generator.generate(&one_to_one).unwrap();
+        assert!(preagg_sql.contains("invoices_preagg AS ("), "{preagg_sql}");
+        assert!(
+            preagg_sql.contains("CROSS JOIN invoices_preagg"),
+            "{preagg_sql}"
+        );
+    }
+
+    #[test]
+    fn test_count_distinct_across_fan_out_join_is_not_rewritten() {
+        let mut graph = SemanticGraph::new();
+
+        let orders = Model::new("orders", "order_id")
+            .with_table("orders")
+            .with_dimension(Dimension::categorical("status"))
+            .with_relationship(Relationship::many_to_one("customers"));
+
+        let customers = Model::new("customers", "id")
+            .with_table("customers")
+            .with_metric(Metric::sum("total_credit", "credit_limit"))
+            .with_metric(Metric::count_distinct("country_count", "country"));
+
+        graph.add_model(orders).unwrap();
+        graph.add_model(customers).unwrap();
+
+        let generator = SqlGenerator::new(&graph);
+        let query = SemanticQuery::new()
+            .with_metrics(vec![
+                "customers.total_credit".into(),
+                "customers.country_count".into(),
+            ])
+            .with_dimensions(vec!["orders.status".into()]);
+
+        let sql = generator.generate(&query).unwrap();
+
+        // The additive measure is deduplicated by primary key...
+        assert!(sql.contains("HASH(customers_cte.id)"), "{sql}");
+        // ...but the distinct count is already immune to fan-out.
+        assert!(
+            sql.contains("COUNT(DISTINCT customers_cte.country_count_raw) AS country_count"),
+            "{sql}"
+        );
+    }
+
     #[test]
     fn test_symmetric_aggregate_uses_target_dialect() {
         let mut graph = SemanticGraph::new();
@@ -5684,12 +10718,65 @@ models:
 
         let generator = SqlGenerator::new(&graph);
         let query = SemanticQuery::new()
-            .with_metrics(vec!["order_items.item_revenue".into()])
-            .with_dimensions(vec!["shipments.status".into()]);
+            .with_metrics(vec!["order_items.item_revenue".into()])
+            .with_dimensions(vec!["shipments.status".into()]);
+
+        let sql = generator.generate(&query).unwrap();
+        assert!(sql.contains("HASH(CONCAT(COALESCE(CAST(order_items_cte.order_id AS VARCHAR), '')"));
+        assert!(sql.contains("CAST(order_items_cte.item_id AS VARCHAR)"));
+    }
+
+    #[test]
+    fn test_declared_grain_drives_fan_out_dedup() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_grain(vec!["tenant_id".to_string(), "order_id".to_string()])
+                    .with_metric(Metric::sum("revenue", "amount"))
+                    .with_relationship(
+                        Relationship::one_to_many("line_items").with_keys("order_id", "order_id"),
+                    )
+                    .with_relationship(
+                        Relationship::one_to_many("order_facts").with_keys("order_id", "order_id"),
+                    ),
+            )
+            .unwrap();
+        graph
+            .add_model(
+                Model::new("line_items", "line_id")
+                    .with_table("line_items")
+                    .with_dimension(Dimension::categorical("sku")),
+            )
+            .unwrap();
+        // One row per order: the join key covers the grain, so no fan-out.
+        graph
+            .add_model(
+                Model::new("order_facts", "fact_id")
+                    .with_table("order_facts")
+                    .with_grain(vec!["order_id".to_string()])
+                    .with_dimension(Dimension::categorical("channel")),
+            )
+            .unwrap();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["line_items.sku".into()]);
+        let sql = generator.generate(&query).unwrap();
+        assert!(sql.contains("SUM(DISTINCT"), "{sql}");
+        assert!(
+            sql.contains("CAST(orders_cte.tenant_id AS VARCHAR)"),
+            "symmetric aggregate should key on the declared grain: {sql}"
+        );
 
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["order_facts.channel".into()]);
         let sql = generator.generate(&query).unwrap();
-        assert!(sql.contains("HASH(CONCAT(COALESCE(CAST(order_items_cte.order_id AS VARCHAR), '')"));
-        assert!(sql.contains("CAST(order_items_cte.item_id AS VARCHAR)"));
+        assert!(!sql.contains("SUM(DISTINCT"), "{sql}");
+        assert!(sql.contains("SUM(orders_cte.revenue_raw)"), "{sql}");
     }
 
     #[test]
@@ -5729,6 +10816,45 @@ models:
         );
     }
 
+    #[test]
+    fn test_percent_of_total_cross_joins_grand_total_without_window_functions() {
+        let graph = create_test_graph();
+        let generator = SqlGenerator::new(&graph).with_window_functions(false);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.status".into()])
+            .with_order_by(vec!["orders.revenue DESC".into()])
+            .with_table_calculations(vec![TableCalculation::new(
+                "pct_total",
+                TableCalcType::PercentOfTotal,
+            )
+            .with_field("revenue")]);
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(sql.starts_with("WITH base AS (\n"), "{sql}");
+        assert!(
+            sql.contains("base.revenue * 100.0 / NULLIF(totals.pct_total_total, 0) AS pct_total"),
+            "{sql}"
+        );
+        assert!(
+            sql.contains("CROSS JOIN (SELECT SUM(revenue) AS pct_total_total FROM base) AS totals"),
+            "{sql}"
+        );
+        assert!(sql.ends_with("ORDER BY revenue DESC"), "{sql}");
+        assert!(!sql.contains(" OVER ("), "{sql}");
+
+        let err = generator
+            .generate(&query.with_table_calculations(vec![
+                TableCalculation::new("revenue_rank", TableCalcType::Rank).with_field("revenue"),
+            ]))
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("requires window functions"),
+            "{err}"
+        );
+    }
+
     #[test]
     fn test_source_uri_only_model_rejects_query_generation() {
         let mut graph = SemanticGraph::new();
@@ -5773,4 +10899,452 @@ models:
             "Relative date should be expanded: {sql}"
         );
     }
+
+    #[test]
+    fn test_relative_date_filter_uses_query_timezone() {
+        let graph = create_test_graph();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_filters(vec!["orders.order_date >= 'last 7 days'".into()])
+            .with_timezone("America/New_York");
+
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(
+            sql.contains("CURRENT_TIMESTAMP AT TIME ZONE 'America/New_York'"),
+            "{sql}"
+        );
+        assert!(!sql.contains("CURRENT_DATE"), "{sql}");
+        assert!(!sql.contains("'last 7 days'"), "{sql}");
+    }
+
+    #[test]
+    fn test_typed_filters_render_quoted_sql() {
+        let graph = create_test_graph();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_filters(vec!["orders.order_date >= '2024-01-01'".into()])
+            .with_typed_filters(vec![Filter::in_values("orders.status", ["a", "b'c"])]);
+        let sql = generator.generate(&query).unwrap();
+        assert!(sql.contains("status IN ('a', 'b''c')"), "{sql}");
+        assert!(sql.contains("created_at >= '2024-01-01'"), "{sql}");
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_typed_filters(vec![Filter::equals("orders.missing", 1)]);
+        assert!(generator.generate(&query).is_err());
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_typed_filters(vec![Filter::equals("orders.status", "a\\'b")]);
+        let bigquery = SqlGenerator::new_with_dialect(&graph, DialectType::BigQuery);
+        let sql = bigquery.generate(&query).unwrap();
+        assert!(sql.contains(r"status = 'a\\''b'"), "{sql}");
+    }
+
+    #[test]
+    fn test_compile_filters_returns_where_predicate() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_dimension(Dimension::categorical("status"))
+                    .with_dimension(Dimension::time("order_date").with_sql("created_at"))
+                    .with_metric(Metric::sum("revenue", "amount"))
+                    .with_segment(
+                        Segment::new("open")
+                            .with_sql("{model}.status = 'open' OR {model}.status = 'new'"),
+                    ),
+            )
+            .unwrap();
+        let generator = SqlGenerator::new(&graph);
+
+        let predicate = generator
+            .compile_filters(
+                "orders",
+                &["orders.order_date >= '2024-01-01'".to_string()],
+                &["open".to_string()],
+            )
+            .unwrap();
+        assert_eq!(
+            predicate,
+            "(created_at >= '2024-01-01') AND (status = 'open' OR status = 'new')"
+        );
+
+        assert_eq!(
+            generator
+                .compile_filters("orders", &[], &["orders.open".to_string()])
+                .unwrap(),
+            "status = 'open' OR status = 'new'"
+        );
+        assert!(generator
+            .compile_filters("orders", &["orders.revenue > 10".to_string()], &[])
+            .is_err());
+    }
+
+    #[test]
+    fn test_result_shape_nests_metrics_under_dimensions() {
+        let graph = create_test_graph();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into(), "orders.order_count".into()])
+            .with_dimensions(vec!["customers.name".into(), "orders.status".into()]);
+        let shape = generator.result_shape(&query).unwrap();
+
+        assert_eq!(
+            shape,
+            ResultShape {
+                keys: vec!["name".into(), "status".into()],
+                measures: vec!["revenue".into(), "order_count".into()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_physical_tables_lists_base_and_joined_tables() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("analytics.fct_orders")
+                    .with_dimension(Dimension::categorical("status"))
+                    .with_metric(Metric::sum("revenue", "amount"))
+                    .with_relationship(Relationship::many_to_one("customers")),
+            )
+            .unwrap();
+        graph
+            .add_model(
+                Model::new("customers", "id")
+                    .with_table("analytics.dim_customers")
+                    .with_dimension(Dimension::categorical("country")),
+            )
+            .unwrap();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.status".into()]);
+        assert_eq!(
+            generator.physical_tables(&query).unwrap(),
+            vec!["analytics.fct_orders".to_string()]
+        );
+
+        let query = query.with_filters(vec!["customers.country = 'US'".into()]);
+        assert_eq!(
+            generator.physical_tables(&query).unwrap(),
+            vec![
+                "analytics.fct_orders".to_string(),
+                "analytics.dim_customers".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_new_vs_returning_window_dimension() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_dimension(Dimension::time("order_date").with_sql("created_at"))
+                    .with_dimension(Dimension::new_vs_returning(
+                        "customer_type",
+                        "customer_id",
+                        "created_at",
+                    ))
+                    .with_metric(Metric::sum("revenue", "amount")),
+            )
+            .unwrap();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.customer_type".into()])
+            .with_filters(vec!["orders.customer_type = 'returning'".into()]);
+        let sql = generator.generate(&query).unwrap();
+
+        assert!(
+            sql.contains(
+                "CASE WHEN created_at = MIN(created_at) OVER (PARTITION BY customer_id) \
+                 THEN 'new' ELSE 'returning' END AS customer_type"
+            ),
+            "classification should be projected in the model CTE: {sql}"
+        );
+        assert!(sql.contains("orders_cte.customer_type"), "{sql}");
+        let cte_end = sql.find("SELECT\n").unwrap();
+        assert!(
+            !sql[..cte_end].contains("WHERE"),
+            "window dimension filters must apply after the window is computed: {sql}"
+        );
+
+        // First-seen is computed over all rows: other filters on the model are
+        // applied after the window too, so earlier orders still count.
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.customer_type".into()])
+            .with_filters(vec!["orders.order_date >= '2024-01-01'".into()]);
+        let sql = generator.generate(&query).unwrap();
+        let cte_end = sql.find("SELECT\n").unwrap();
+        assert!(!sql[..cte_end].contains("WHERE"), "{sql}");
+        assert!(
+            sql[cte_end..].contains("WHERE orders_cte.created_at >= '2024-01-01'"),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn test_date_range_filter_on_time_dimension() {
+        let graph = create_test_graph();
+        let generator = SqlGenerator::new(&graph);
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_filters(vec!["orders.order_date in last 7 days".into()]);
+        let sql = generator.generate(&query).unwrap();
+        assert!(
+            sql.contains("created_at >= CURRENT_DATE - INTERVAL '7 days'\n"),
+            "Expected trailing window lower bound: {sql}"
+        );
+        assert!(!sql.contains("last 7 days"), "{sql}");
+        assert!(!sql.contains("CURRENT_DATE + 1"), "{sql}");
+
+        let sql = SqlGenerator::new_with_dialect(&graph, DialectType::BigQuery)
+            .generate(&query)
+            .unwrap();
+        assert!(
+            sql.contains(">= DATE_SUB(CURRENT_DATE, INTERVAL 7 DAY)"),
+            "{sql}"
+        );
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_filters(vec!["orders.order_date in '2024-Q1'".into()]);
+        let sql = generator.generate(&query).unwrap();
+        assert!(sql.contains("DATE '2024-01-01'"), "{sql}");
+        assert!(sql.contains("DATE '2024-04-01'"), "{sql}");
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_filters(vec!["orders.status in last 7 days".into()]);
+        let err = generator.generate(&query).unwrap_err();
+        assert!(
+            err.to_string().contains("requires a time dimension"),
+            "{err}"
+        );
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_filters(vec!["orders.order_date in last week".into()]);
+        let sql = generator.generate(&query).unwrap();
+        assert!(
+            sql.contains(
+                "created_at >= DATE_TRUNC('week', CURRENT_DATE) - INTERVAL '1 week' \
+                 AND created_at < DATE_TRUNC('week', CURRENT_DATE)"
+            ),
+            "{sql}"
+        );
+        assert!(!sql.contains("last week"), "{sql}");
+
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_filters(vec!["orders.order_date in sometime soon".into()]);
+        let err = generator.generate(&query).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("unrecognized date range 'sometime soon'"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_primary_key_uniqueness_and_not_null_checks() {
+        let graph = create_test_graph();
+        let generator = SqlGenerator::new(&graph);
+        let orders = graph.get_model("orders").unwrap();
+
+        assert_eq!(
+            generator.uniqueness_check(orders),
+            "SELECT order_id, COUNT(*) AS row_count\nFROM orders\nGROUP BY order_id\nHAVING COUNT(*) > 1"
+        );
+        assert_eq!(
+            generator.not_null_check(orders),
+            "SELECT order_id\nFROM orders\nWHERE order_id IS NULL"
+        );
+
+        let line_items = Model::new("line_items", "order_id")
+            .with_primary_key_columns(vec!["order_id".into(), "line_number".into()])
+            .with_sql("SELECT * FROM raw.line_items");
+        assert_eq!(
+            generator.uniqueness_check(&line_items),
+            "SELECT order_id, line_number, COUNT(*) AS row_count\n\
+             FROM (SELECT * FROM raw.line_items) AS line_items\n\
+             GROUP BY order_id, line_number\nHAVING COUNT(*) > 1"
+        );
+        assert_eq!(
+            generator.not_null_check(&line_items),
+            "SELECT order_id, line_number\n\
+             FROM (SELECT * FROM raw.line_items) AS line_items\n\
+             WHERE order_id IS NULL OR line_number IS NULL"
+        );
+    }
+
+    #[test]
+    fn test_granularity_policy_for_unknown_grain() {
+        let graph = create_test_graph();
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.revenue".into()])
+            .with_dimensions(vec!["orders.order_date__isoweek".into()]);
+
+        let err = SqlGenerator::new(&graph).generate(&query).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Invalid time granularity 'isoweek'"),
+            "{err}"
+        );
+
+        let sql = SqlGenerator::new(&graph)
+            .with_granularity_policy(GranularityPolicy::Passthrough)
+            .generate(&query)
+            .unwrap();
+        assert!(sql.contains("DATE_TRUNC('isoweek', "), "{sql}");
+        assert!(sql.contains("AS order_date__isoweek"), "{sql}");
+    }
+
+    #[test]
+    fn test_preview_selects_public_dimensions_with_default_filters() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_dimension(Dimension::categorical("status"))
+                    .with_dimension(Dimension::time("order_date").with_sql("created_at"))
+                    .with_dimension(Dimension {
+                        public: false,
+                        ..Dimension::categorical("internal_code")
+                    })
+                    .with_metric(Metric::sum("revenue", "amount"))
+                    .with_default_filter("{model}.deleted_at IS NULL"),
+            )
+            .unwrap();
+        let orders = graph.get_model("orders").unwrap();
+
+        assert_eq!(
+            SqlGenerator::new(&graph).preview(orders, 10).unwrap(),
+            "SELECT\n  status,\n  created_at AS order_date\nFROM orders\n\
+             WHERE deleted_at IS NULL\nLIMIT 10"
+        );
+    }
+
+    #[test]
+    fn test_cardinality_probe_and_hint() {
+        let graph = create_test_graph();
+        let orders = graph.get_model("orders").unwrap();
+        let status = Dimension::categorical("status").with_cardinality_hint(5);
+
+        assert_eq!(
+            SqlGenerator::new(&graph).cardinality_probe(orders, &status),
+            "SELECT APPROX_COUNT_DISTINCT(orders.status) AS cardinality\nFROM orders"
+        );
+        assert_eq!(
+            SqlGenerator::new_with_dialect(&graph, DialectType::PostgreSQL)
+                .cardinality_probe(orders, &status),
+            "SELECT COUNT(DISTINCT orders.status) AS cardinality\nFROM orders"
+        );
+
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["cardinality_hint"], 5);
+    }
+
+    #[test]
+    fn test_median_renders_per_dialect() {
+        let mut graph = create_test_graph();
+        let orders = graph
+            .get_model("orders")
+            .unwrap()
+            .clone()
+            .with_metric(Metric {
+                agg: Some(Aggregation::Median),
+                sql: Some("amount".into()),
+                ..Metric::new("median_amount")
+            });
+        graph.replace_model(orders).unwrap();
+        let query = SemanticQuery::new()
+            .with_metrics(vec!["orders.median_amount".into()])
+            .with_dimensions(vec!["orders.status".into()]);
+
+        let duckdb_sql = SqlGenerator::new(&graph).generate(&query).unwrap();
+        assert!(
+            duckdb_sql.contains("MEDIAN(orders_cte.median_amount_raw) AS median_amount"),
+            "{duckdb_sql}"
+        );
+
+        let postgres_sql = SqlGenerator::new(&graph)
+            .with_dialect(DialectType::PostgreSQL)
+            .generate(&query)
+            .unwrap();
+        assert!(
+            postgres_sql.contains(
+                "PERCENTILE_CONT(0.5) WITHIN GROUP (ORDER BY orders_cte.median_amount_raw) AS median_amount"
+            ),
+            "{postgres_sql}"
+        );
+        assert!(!postgres_sql.contains("MEDIAN("), "{postgres_sql}");
+    }
+
+    #[test]
+    fn test_conversion_rate_ratio_with_per_side_filters() {
+        let mut graph = SemanticGraph::new();
+        let sessions = Model::new("sessions", "session_id")
+            .with_table("sessions")
+            .with_dimension(Dimension::categorical("channel"))
+            .with_dimension(Dimension::new("converted"))
+            .with_dimension(Dimension::new("visited"))
+            .with_metric(Metric::ratio(
+                "conversion_rate",
+                "COUNT(CASE WHEN {model}.converted THEN 1 END)",
+                "COUNT(CASE WHEN {model}.visited THEN 1 END)",
+            ))
+            .with_metric(Metric::count("converted_sessions").with_filter("{model}.converted"))
+            .with_metric(Metric::count("visited_sessions").with_filter("{model}.visited"))
+            .with_metric(Metric::ratio(
+                "filtered_conversion_rate",
+                "converted_sessions",
+                "visited_sessions",
+            ));
+        graph.add_model(sessions).unwrap();
+        let generator = SqlGenerator::new(&graph);
+
+        let sql = generator
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec!["sessions.conversion_rate".into()])
+                    .with_dimensions(vec!["sessions.channel".into()]),
+            )
+            .unwrap();
+        assert!(
+            sql.contains(
+                "(COUNT(CASE WHEN sessions_cte.converted THEN 1 END)) / NULLIF(COUNT(CASE WHEN sessions_cte.visited THEN 1 END), 0) AS conversion_rate"
+            ),
+            "{sql}"
+        );
+
+        let sql = generator
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(vec!["sessions.filtered_conversion_rate".into()])
+                    .with_dimensions(vec!["sessions.channel".into()]),
+            )
+            .unwrap();
+        assert!(sql.contains("AS converted_sessions_raw"), "{sql}");
+        assert!(sql.contains("AS visited_sessions_raw"), "{sql}");
+        assert_eq!(sql.matches("CASE WHEN").count(), 2, "{sql}");
+        assert!(sql.contains("/ NULLIF("), "{sql}");
+    }
 }
@@ -1,7 +1,16 @@
 //! SQL generation and query rewriting
 
+#[cfg(feature = "datafusion")]
+mod datafusion_plan;
+mod filter;
 mod generator;
 mod rewriter;
 
-pub use generator::{SemanticQuery, SqlGenerator};
+#[cfg(feature = "datafusion")]
+pub use datafusion_plan::DataFusionPlanner;
+pub use filter::{Filter, FilterValue, SegmentFilter};
+pub use generator::{
+    CountStyle, FilterValidation, GenerationStrategy, GranularityPolicy, ResultShape,
+    SemanticQuery, SqlGenerator, TopNPer,
+};
 pub use rewriter::QueryRewriter;
@@ -2,12 +2,10 @@
 
 use std::collections::{HashMap, HashSet};
 
-#[cfg(not(target_arch = "wasm32"))]
-use polyglot_sql::parse as polyglot_parse;
 use polyglot_sql::{
     expressions::{
-        BinaryOp, Cte, From, GroupBy, Having, Identifier, Join, JoinKind, Select, TableRef, Where,
-        With,
+        Alias, BinaryOp, Cte, From, GroupBy, Having, Identifier, Join, JoinKind, Select, TableRef,
+        Where, With,
     },
     generate as polyglot_generate, traversal, DialectType, Expression,
 };
@@ -15,14 +13,37 @@ use polyglot_sql::{
 use crate::core::{DimensionType, MetricType, SemanticGraph};
 use crate::error::{Result, SidemanticError};
 
+use super::generator::{CountStyle, SemanticQuery, SqlGenerator};
+
 /// SQL query rewriter using semantic definitions
 pub struct QueryRewriter<'a> {
     graph: &'a SemanticGraph,
+    /// Error on `model.field` references to unknown fields of known models
+    /// instead of passing them through.
+    strict: bool,
+    count_style: CountStyle,
 }
 
 impl<'a> QueryRewriter<'a> {
     pub fn new(graph: &'a SemanticGraph) -> Self {
-        Self { graph }
+        Self {
+            graph,
+            strict: false,
+            count_style: CountStyle::Star,
+        }
+    }
+
+    /// Reject references like `orders.reveneu` whose model is known but whose
+    /// field is neither a metric nor a dimension, wherever they appear.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Render argument-less count metrics as `COUNT(*)` or `COUNT(1)`.
+    pub fn with_count_style(mut self, count_style: CountStyle) -> Self {
+        self.count_style = count_style;
+        self
     }
 
     /// Rewrite a SQL query using semantic layer definitions
@@ -45,8 +66,29 @@ impl<'a> QueryRewriter<'a> {
     fn rewrite_statement(&self, statement: Expression) -> Result<Expression> {
         match statement {
             Expression::Select(select) => {
+                let quoted = quoted_identifiers(&Expression::Select(select.clone()));
                 let rewritten_select = self.rewrite_select(*select)?;
-                Ok(Expression::Select(Box::new(rewritten_select)))
+                requote_identifiers(Expression::Select(Box::new(rewritten_select)), &quoted)
+            }
+            // Each branch of a set operation is its own semantic query.
+            Expression::Union(mut union) => {
+                union.left = self.rewrite_statement(union.left)?;
+                union.right = self.rewrite_statement(union.right)?;
+                Ok(Expression::Union(union))
+            }
+            Expression::Intersect(mut intersect) => {
+                intersect.left = self.rewrite_statement(intersect.left)?;
+                intersect.right = self.rewrite_statement(intersect.right)?;
+                Ok(Expression::Intersect(intersect))
+            }
+            Expression::Except(mut except) => {
+                except.left = self.rewrite_statement(except.left)?;
+                except.right = self.rewrite_statement(except.right)?;
+                Ok(Expression::Except(except))
+            }
+            Expression::Paren(mut paren) => {
+                paren.this = self.rewrite_statement(paren.this)?;
+                Ok(Expression::Paren(paren))
             }
             other => Ok(other),
         }
@@ -66,8 +108,17 @@ impl<'a> QueryRewriter<'a> {
             }
         }
 
-        // Find semantic model references in FROM clause
+        // Find semantic model references in FROM clause. A CTE shadows a model
+        // of the same name, so its references are left as they are.
         let mut model_refs = self.find_model_references(select.from.as_ref());
+        if let Some(with_clause) = &select.with {
+            let cte_names: HashSet<&str> = with_clause
+                .ctes
+                .iter()
+                .map(|cte| cte.alias.name.as_str())
+                .collect();
+            model_refs.retain(|(model_name, _)| !cte_names.contains(model_name.as_str()));
+        }
         let from_metrics = is_from_metrics(select.from.as_ref());
 
         if model_refs.is_empty() {
@@ -139,6 +190,11 @@ impl<'a> QueryRewriter<'a> {
         if let Some(having) = &select.having {
             self.collect_model_refs_from_expr(&having.this, &mut referenced_models);
         }
+        if let Some(group_by) = &select.group_by {
+            for expr in &group_by.expressions {
+                self.collect_model_refs_from_expr(expr, &mut referenced_models);
+            }
+        }
         self.collect_order_by_model_refs(select.order_by.as_ref(), &mut referenced_models);
         let mut query_models = referenced_models.clone();
         query_models.extend(model_refs.iter().map(|(model_name, _)| model_name.clone()));
@@ -161,6 +217,12 @@ impl<'a> QueryRewriter<'a> {
             model_refs.push((model_name.clone(), alias));
         }
 
+        // Window metrics (cumulative, time comparison) are computed over the
+        // grouped result, so the generator builds them and we wrap its output.
+        if self.references_window_metric(&select, &model_refs) {
+            return self.rewrite_window_select(select, &model_refs);
+        }
+
         let original_projection = select.expressions.clone();
         let projection_aliases = self.projection_alias_lookup(&original_projection, &model_refs);
         let output_aliases = projection_aliases
@@ -212,18 +274,309 @@ impl<'a> QueryRewriter<'a> {
             ));
         }
 
-        // Add GROUP BY whenever we select non-aggregated dimensions.
-        let has_dimensions = self.has_non_aggregated_columns(&select.expressions);
-
-        if has_dimensions {
-            select.group_by = Some(self.build_group_by(&select.expressions));
-        }
+        // Add GROUP BY whenever we select non-aggregated dimensions, merged
+        // with any grouping the user wrote.
+        let user_group_by = select.group_by.take();
+        let generated_group_by = self
+            .has_non_aggregated_columns(&select.expressions)
+            .then(|| self.build_group_by(&select.expressions));
+        select.group_by = self.merge_group_by(
+            user_group_by,
+            generated_group_by,
+            &select.expressions,
+            &model_refs,
+        )?;
 
         select = self.wrap_simple_select_with_cte(select, &model_refs)?;
 
         Ok(select)
     }
 
+    /// Check whether any clause of the query references a window metric
+    fn references_window_metric(&self, select: &Select, model_refs: &[(String, String)]) -> bool {
+        let mut exprs: Vec<&Expression> = select.expressions.iter().collect();
+        exprs.extend(select.where_clause.as_ref().map(|w| &w.this));
+        exprs.extend(select.having.as_ref().map(|h| &h.this));
+        if let Some(order_by) = &select.order_by {
+            exprs.extend(order_by.expressions.iter().map(|ordered| &ordered.this));
+        }
+
+        exprs.into_iter().any(|expr| {
+            // Wildcards expand to dimensions only.
+            if is_wildcard(expr) {
+                return false;
+            }
+            self.expr_references_window_metric(expr, model_refs)
+        })
+    }
+
+    fn expr_references_window_metric(
+        &self,
+        expr: &Expression,
+        model_refs: &[(String, String)],
+    ) -> bool {
+        traversal::get_columns(&strip_subqueries(expr))
+            .into_iter()
+            .any(|column_ref| {
+                let Expression::Column(column) = column_ref else {
+                    return false;
+                };
+                matches!(
+                    self.window_query_field(column, model_refs),
+                    Ok(WindowQueryField::Metric { window: true, .. })
+                )
+            })
+    }
+
+    /// Resolve a column of a window metric query to its semantic reference
+    fn window_query_field(
+        &self,
+        column: &polyglot_sql::expressions::Column,
+        model_refs: &[(String, String)],
+    ) -> Result<WindowQueryField> {
+        let Some((model_name, _, base_field, granularity)) =
+            resolve_model_field(column, model_refs)
+        else {
+            return Err(SidemanticError::Validation(format!(
+                "Cannot resolve column: {}",
+                column.name.name
+            )));
+        };
+        let model = self.graph.get_model(model_name).ok_or_else(|| {
+            SidemanticError::Validation(format!("Model '{model_name}' not found"))
+        })?;
+
+        if let Some(metric) = model.get_metric(base_field) {
+            return Ok(WindowQueryField::Metric {
+                reference: format!("{model_name}.{}", metric.name),
+                window: is_window_metric(metric),
+            });
+        }
+
+        if model.get_dimension(base_field).is_some() {
+            let column = match granularity {
+                Some(granularity) => format!("{base_field}__{granularity}"),
+                None => base_field.to_string(),
+            };
+            return Ok(WindowQueryField::Dimension {
+                reference: format!("{model_name}.{column}"),
+            });
+        }
+
+        Err(SidemanticError::Validation(format!(
+            "Field '{model_name}.{base_field}' not found"
+        )))
+    }
+
+    /// Rewrite a query selecting window metrics by generating the semantic
+    /// query and wrapping it, so filters and ordering on window metrics apply
+    /// to the computed values rather than to raw rows.
+    fn rewrite_window_select(
+        &self,
+        select: Select,
+        model_refs: &[(String, String)],
+    ) -> Result<Select> {
+        if select.distinct_on.is_some() {
+            return Err(SidemanticError::Validation(
+                "DISTINCT ON is not supported in queries with window metrics".into(),
+            ));
+        }
+        let mut fields = WindowQueryFields::default();
+
+        for item in &select.expressions {
+            match item {
+                item if is_wildcard(item) => {
+                    let (model_name, _) = wildcard_model(item, model_refs)?;
+                    let model = self.graph.get_model(model_name).ok_or_else(|| {
+                        SidemanticError::Validation(format!("Model '{model_name}' not found"))
+                    })?;
+                    let excluded = star_exclusions(item, model)?;
+                    for dimension in model
+                        .dimensions
+                        .iter()
+                        .filter(|d| !excluded.contains(&d.name))
+                    {
+                        fields.add(
+                            WindowQueryField::Dimension {
+                                reference: format!("{model_name}.{}", dimension.name),
+                            },
+                            Some(Identifier::new(dimension.name.clone())),
+                        );
+                    }
+                }
+                Expression::Column(column) => {
+                    let field = self.window_query_field(column, model_refs)?;
+                    fields.add(field, Some(column.name.clone()));
+                }
+                Expression::Alias(alias) => {
+                    let Expression::Column(column) = &alias.this else {
+                        return Err(SidemanticError::Validation(
+                            "Queries with window metrics can only select metrics and dimensions"
+                                .into(),
+                        ));
+                    };
+                    let field = self.window_query_field(column, model_refs)?;
+                    fields.add(field, Some(alias.alias.clone()));
+                }
+                _ => {
+                    return Err(SidemanticError::Validation(
+                        "Queries with window metrics can only select metrics and dimensions".into(),
+                    ));
+                }
+            }
+        }
+
+        // Row-level predicates are pushed into the semantic query; predicates
+        // on window metrics and HAVING conditions filter the windowed result.
+        let mut inner_filters: Vec<String> = Vec::new();
+        let mut outer_conditions: Vec<Expression> = Vec::new();
+        if let Some(where_clause) = &select.where_clause {
+            for condition in split_conjunction(&where_clause.this) {
+                if self.expr_references_window_metric(&condition, model_refs) {
+                    outer_conditions.push(condition);
+                } else {
+                    inner_filters.push(expr_to_sql(
+                        &self.semantic_filter_expr(condition, model_refs)?,
+                    )?);
+                }
+            }
+        }
+        if let Some(having) = &select.having {
+            outer_conditions.push(having.this.clone());
+        }
+
+        let mut order_by = select.order_by.clone();
+        let mut outer_exprs: Vec<&Expression> = outer_conditions.iter().collect();
+        if let Some(order_by) = &order_by {
+            outer_exprs.extend(order_by.expressions.iter().map(|ordered| &ordered.this));
+        }
+        for expr in outer_exprs {
+            for column_ref in traversal::get_columns(&strip_subqueries(expr)) {
+                let Expression::Column(column) = column_ref else {
+                    continue;
+                };
+                match self.window_query_field(column, model_refs) {
+                    Ok(WindowQueryField::Dimension { reference, .. })
+                        if !fields.dimensions.contains(&reference) =>
+                    {
+                        return Err(SidemanticError::Validation(format!(
+                            "Dimension '{reference}' must be selected to filter or order a query with window metrics"
+                        )));
+                    }
+                    Ok(field) => fields.add(field, None),
+                    Err(_) => {}
+                }
+            }
+        }
+
+        let generated = SqlGenerator::new(self.graph)
+            .with_count_style(self.count_style)
+            .generate(
+                &SemanticQuery::new()
+                    .with_metrics(fields.metrics.clone())
+                    .with_dimensions(fields.dimensions.clone())
+                    .with_filters(inner_filters),
+            )?;
+        let output_columns = fields.output_columns(&generated)?;
+
+        let mut outer_sql = format!("SELECT * FROM ({generated}) AS {WINDOW_QUERY_ALIAS}");
+        if !outer_conditions.is_empty() {
+            let conditions = outer_conditions
+                .into_iter()
+                .map(|condition| {
+                    let condition =
+                        self.window_outer_expr(condition, model_refs, &output_columns)?;
+                    Ok(format!("({})", expr_to_sql(&condition)?))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            outer_sql.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+        }
+
+        let statements = parse_sql_with_large_stack(&outer_sql)?;
+        let Some(Expression::Select(outer)) = statements.into_iter().next() else {
+            return Err(SidemanticError::SqlGeneration(
+                "Failed to build window metric query".into(),
+            ));
+        };
+        let mut outer = *outer;
+        // Aliases are set as identifiers so quoted names like "Running Total"
+        // survive without being spliced into SQL text.
+        outer.expressions = fields
+            .projection
+            .into_iter()
+            .map(|(reference, output_alias)| {
+                Expression::Alias(Box::new(Alias::new(
+                    Expression::qualified_column(
+                        WINDOW_QUERY_ALIAS,
+                        output_columns[&reference].clone(),
+                    ),
+                    output_alias,
+                )))
+            })
+            .collect();
+
+        if let Some(order_by) = &mut order_by {
+            for ordered in &mut order_by.expressions {
+                ordered.this =
+                    self.window_outer_expr(ordered.this.clone(), model_refs, &output_columns)?;
+            }
+        }
+        outer.with = select.with;
+        outer.distinct = select.distinct;
+        outer.order_by = order_by;
+        outer.limit = select.limit;
+        outer.offset = select.offset;
+
+        Ok(outer)
+    }
+
+    /// Rewrite semantic references to columns of the windowed subquery
+    fn window_outer_expr(
+        &self,
+        expr: Expression,
+        model_refs: &[(String, String)],
+        output_columns: &HashMap<String, String>,
+    ) -> Result<Expression> {
+        polyglot_sql::transform_map(expr, &|node| {
+            if let Expression::Column(column) = &node {
+                if let Ok(
+                    WindowQueryField::Metric { reference, .. }
+                    | WindowQueryField::Dimension { reference, .. },
+                ) = self.window_query_field(column, model_refs)
+                {
+                    if let Some(output_column) = output_columns.get(&reference) {
+                        return Ok(Expression::qualified_column(
+                            WINDOW_QUERY_ALIAS,
+                            output_column.clone(),
+                        ));
+                    }
+                }
+            }
+            Ok(node)
+        })
+        .map_err(|e| SidemanticError::SqlGeneration(e.to_string()))
+    }
+
+    /// Rewrite a row-level predicate into a semantic filter (`model.field`)
+    fn semantic_filter_expr(
+        &self,
+        expr: Expression,
+        model_refs: &[(String, String)],
+    ) -> Result<Expression> {
+        polyglot_sql::transform_map(expr, &|node| {
+            if let Expression::Column(column) = &node {
+                if let Some((model_name, _, _, _)) = resolve_model_field(column, model_refs) {
+                    return Ok(Expression::qualified_column(
+                        model_name.to_string(),
+                        column.name.name.clone(),
+                    ));
+                }
+            }
+            Ok(node)
+        })
+        .map_err(|e| SidemanticError::SqlGeneration(e.to_string()))
+    }
+
     fn wrap_simple_select_with_cte(
         &self,
         select: Select,
@@ -237,7 +590,10 @@ impl<'a> QueryRewriter<'a> {
             || select.limit.is_some()
             || select.offset.is_some()
             || select.distinct
-            || select.group_by.is_none()
+            || !select
+                .group_by
+                .as_ref()
+                .is_some_and(|group_by| group_by.expressions.iter().all(is_positional_ref))
         {
             return Ok(select);
         }
@@ -254,6 +610,9 @@ impl<'a> QueryRewriter<'a> {
             let Expression::Alias(alias) = projection else {
                 return Ok(select);
             };
+            if is_window_function(&alias.this) {
+                return Ok(select);
+            }
 
             if self.is_aggregation(&alias.this) {
                 metrics.push((alias.this.clone(), alias.alias.name.clone()));
@@ -283,6 +642,9 @@ impl<'a> QueryRewriter<'a> {
         }
 
         for (metric_expr, alias_name) in &metrics {
+            if is_row_count(metric_expr) {
+                continue;
+            }
             let Some(raw_expr) = extract_aggregate_input(metric_expr) else {
                 return Ok(select);
             };
@@ -321,6 +683,13 @@ impl<'a> QueryRewriter<'a> {
         }
 
         for (metric_expr, alias_name) in &metrics {
+            // The CTE keeps one row per source row, so row counts need no raw column.
+            if is_row_count(metric_expr) {
+                outer_select
+                    .expressions
+                    .push(metric_expr.clone().alias(alias_name.clone()));
+                continue;
+            }
             let raw_col =
                 Expression::qualified_column(cte_alias.clone(), format!("{alias_name}_raw"));
             let Some(outer_metric_expr) = rebuild_aggregate_with_input(metric_expr, raw_col) else {
@@ -353,6 +722,9 @@ impl<'a> QueryRewriter<'a> {
                 subquery.this = self.rewrite_nested_query_expr(subquery.this)?;
                 Ok(Expression::Subquery(subquery))
             }
+            set_operation @ (Expression::Union(_)
+            | Expression::Intersect(_)
+            | Expression::Except(_)) => self.rewrite_statement(set_operation),
             Expression::Alias(mut alias) => {
                 alias.this = self.rewrite_nested_query_expr(alias.this)?;
                 Ok(Expression::Alias(alias))
@@ -404,8 +776,12 @@ impl<'a> QueryRewriter<'a> {
     }
 
     /// Collect model references from an expression
+    ///
+    /// Subqueries are skipped: they are rewritten against their own FROM clause
+    /// and must not pull extra joins into the enclosing query.
     fn collect_model_refs_from_expr(&self, expr: &Expression, models: &mut HashSet<String>) {
-        for column_ref in traversal::get_columns(expr) {
+        let expr = strip_subqueries(expr);
+        for column_ref in traversal::get_columns(&expr) {
             if let Expression::Column(column) = column_ref {
                 if let Some(table) = &column.table {
                     if self.graph.get_model(&table.name).is_some() {
@@ -437,21 +813,22 @@ impl<'a> QueryRewriter<'a> {
 
         for item in projection {
             match item {
-                Expression::Star(_) if model_refs.len() == 1 => {
-                    let (model_name, _) = (&model_refs[0].0, &model_refs[0].1);
+                item if is_wildcard(item) => {
+                    let Ok((model_name, _)) = wildcard_model(item, model_refs) else {
+                        continue;
+                    };
                     if let Some(model) = self.graph.get_model(model_name) {
-                        for dimension in &model.dimensions {
+                        let excluded = star_exclusions(item, model).unwrap_or_default();
+                        for dimension in model
+                            .dimensions
+                            .iter()
+                            .filter(|d| !excluded.contains(&d.name))
+                        {
                             aliases.insert(
                                 semantic_field_key(model_name, &dimension.name, None),
                                 dimension.name.clone(),
                             );
                         }
-                        for metric in &model.metrics {
-                            aliases.insert(
-                                semantic_field_key(model_name, &metric.name, None),
-                                metric.name.clone(),
-                            );
-                        }
                     }
                 }
                 Expression::Alias(alias) => {
@@ -486,31 +863,21 @@ impl<'a> QueryRewriter<'a> {
 
         for item in projection {
             match item {
-                Expression::Star(_) => {
-                    if model_refs.len() != 1 {
-                        return Err(SidemanticError::Validation(
-                            "SELECT * requires a FROM clause with a single table".into(),
-                        ));
-                    }
-
-                    let (model_name, alias) = (&model_refs[0].0, &model_refs[0].1);
+                item if is_wildcard(item) => {
+                    let (model_name, alias) = wildcard_model(item, model_refs)?;
                     let model = self.graph.get_model(model_name).ok_or_else(|| {
                         SidemanticError::Validation(format!("Model '{model_name}' not found"))
                     })?;
+                    let excluded = star_exclusions(item, model)?;
 
-                    for dimension in &model.dimensions {
-                        result.push(
-                            Expression::qualified_column(
-                                alias.clone(),
-                                dimension.sql_expr().to_string(),
-                            )
-                            .alias(dimension.name.clone()),
-                        );
-                    }
-                    for metric in &model.metrics {
+                    for dimension in model
+                        .dimensions
+                        .iter()
+                        .filter(|d| !excluded.contains(&d.name))
+                    {
                         result.push(
-                            self.metric_to_expr(metric, alias)
-                                .alias(metric.name.clone()),
+                            dimension_select_expr(alias, dimension, None)
+                                .alias(dimension.name.clone()),
                         );
                     }
                 }
@@ -571,11 +938,11 @@ impl<'a> QueryRewriter<'a> {
                 })?;
 
                 if let Some(metric) = model.get_metric(base_field) {
-                    return Ok(self.metric_to_expr(metric, alias_name));
+                    return self.metric_to_expr(metric, model_name, alias_name);
                 }
 
                 if let Some(dimension) = model.get_dimension(base_field) {
-                    return Ok(dimension_to_expr(alias_name, dimension, granularity));
+                    return Ok(dimension_select_expr(alias_name, dimension, granularity));
                 }
 
                 return Err(SidemanticError::Validation(format!(
@@ -609,39 +976,25 @@ impl<'a> QueryRewriter<'a> {
     }
 
     /// Convert a metric to an expression
-    fn metric_to_expr(&self, metric: &crate::core::Metric, alias: &str) -> Expression {
-        match metric.r#type {
-            MetricType::Simple => {
-                // Handle Expression type: sql field contains the full expression
-                if let Some(crate::core::Aggregation::Expression) = &metric.agg {
-                    if let Some(expr) = parse_select_expr(metric.sql_expr()) {
-                        return expr;
-                    }
-                    // Fallback: return as identifier
-                    return Expression::identifier(metric.name.clone());
-                }
-
-                if let Some(expr) = parse_select_expr(&metric.to_sql(Some(alias))) {
-                    return expr;
-                }
-
-                // Fallback: return as identifier
-                Expression::identifier(metric.name.clone())
-            }
-            MetricType::Derived
-            | MetricType::Ratio
-            | MetricType::Cumulative
-            | MetricType::TimeComparison
-            | MetricType::Conversion
-            | MetricType::Retention
-            | MetricType::Cohort => {
-                if let Some(expr) = parse_select_expr(&metric.to_sql(Some(alias))) {
-                    return expr;
-                }
-                // Fallback: return as identifier
-                Expression::identifier(metric.name.clone())
-            }
+    fn metric_to_expr(
+        &self,
+        metric: &crate::core::Metric,
+        model_name: &str,
+        alias: &str,
+    ) -> Result<Expression> {
+        // Handle Expression type: sql field contains the full expression
+        if metric.r#type == MetricType::Simple
+            && metric.agg == Some(crate::core::Aggregation::Expression)
+        {
+            return Ok(parse_select_expr(metric.sql_expr())
+                .unwrap_or_else(|| Expression::identifier(metric.name.clone())));
         }
+
+        let sql = SqlGenerator::new(self.graph)
+            .with_count_style(self.count_style)
+            .metric_sql(metric, model_name, alias)?;
+        // Fallback: return as identifier
+        Ok(parse_select_expr(&sql).unwrap_or_else(|| Expression::identifier(metric.name.clone())))
     }
 
     /// Rewrite FROM clause with JOINs for cross-model references
@@ -830,12 +1183,18 @@ impl<'a> QueryRewriter<'a> {
                     })?;
 
                     if let Some(metric) = model.get_metric(base_field) {
-                        return Ok(self.metric_to_expr(metric, alias_name));
+                        return self.metric_to_expr(metric, model_name, alias_name);
                     }
 
                     if let Some(dimension) = model.get_dimension(base_field) {
                         return Ok(dimension_to_expr(alias_name, dimension, granularity));
                     }
+
+                    if self.strict {
+                        return Err(SidemanticError::Validation(format!(
+                            "Field '{model_name}.{base_field}' not found"
+                        )));
+                    }
                 }
 
                 Ok(Expression::Column(column))
@@ -941,10 +1300,85 @@ impl<'a> QueryRewriter<'a> {
                 is_null.this = self.rewrite_expr(is_null.this, model_refs)?;
                 Ok(Expression::IsNull(is_null))
             }
+            Expression::Subquery(subquery) => {
+                self.rewrite_nested_query_expr(Expression::Subquery(subquery))
+            }
+            Expression::Select(select) => {
+                self.rewrite_nested_query_expr(Expression::Select(select))
+            }
+            // Function arguments and window specifications (PARTITION BY,
+            // ORDER BY) can reference semantic fields too.
+            expr @ (Expression::Function(_) | Expression::WindowFunction(_)) => {
+                self.rewrite_nested_columns(expr, model_refs)
+            }
             other => Ok(other),
         }
     }
 
+    /// Rewrite every semantic column reference nested anywhere inside `expr`
+    fn rewrite_nested_columns(
+        &self,
+        expr: Expression,
+        model_refs: &[(String, String)],
+    ) -> Result<Expression> {
+        // transform_map only carries polyglot errors; keep ours to return as-is.
+        let rewrite_error = std::cell::RefCell::new(None);
+        let rewritten = polyglot_sql::transform_map(expr, &|node| {
+            self.rewrite_nested_node(node, model_refs).map_err(|e| {
+                let message = e.to_string();
+                rewrite_error.borrow_mut().get_or_insert(e);
+                polyglot_sql::Error::Internal(message)
+            })
+        });
+        if let Some(error) = rewrite_error.into_inner() {
+            return Err(error);
+        }
+        rewritten.map_err(|e| SidemanticError::SqlGeneration(e.to_string()))
+    }
+
+    fn rewrite_nested_node(
+        &self,
+        node: Expression,
+        model_refs: &[(String, String)],
+    ) -> Result<Expression> {
+        if let Expression::Column(column) = &node {
+            if resolve_model_field(column, model_refs).is_some() {
+                return self.rewrite_expr(node, model_refs);
+            }
+        }
+        // transform_map does not descend into typed aggregates such as the
+        // `SUM(...)` of `SUM(...) OVER (...)`.
+        Ok(match node {
+            Expression::Sum(mut agg) => {
+                agg.this = self.rewrite_nested_columns(agg.this, model_refs)?;
+                Expression::Sum(agg)
+            }
+            Expression::Avg(mut agg) => {
+                agg.this = self.rewrite_nested_columns(agg.this, model_refs)?;
+                Expression::Avg(agg)
+            }
+            Expression::Min(mut agg) => {
+                agg.this = self.rewrite_nested_columns(agg.this, model_refs)?;
+                Expression::Min(agg)
+            }
+            Expression::Max(mut agg) => {
+                agg.this = self.rewrite_nested_columns(agg.this, model_refs)?;
+                Expression::Max(agg)
+            }
+            Expression::Median(mut agg) => {
+                agg.this = self.rewrite_nested_columns(agg.this, model_refs)?;
+                Expression::Median(agg)
+            }
+            Expression::Count(mut count) => {
+                if let Some(this) = count.this.take() {
+                    count.this = Some(self.rewrite_nested_columns(this, model_refs)?);
+                }
+                Expression::Count(count)
+            }
+            other => other,
+        })
+    }
+
     fn rewrite_binary_op(
         &self,
         mut binary: BinaryOp,
@@ -997,7 +1431,51 @@ impl<'a> QueryRewriter<'a> {
 
     /// Check if projection has non-aggregated columns
     fn has_non_aggregated_columns(&self, projection: &[Expression]) -> bool {
-        projection.iter().any(|expr| !self.is_aggregation(expr))
+        projection
+            .iter()
+            .any(|expr| !self.is_aggregation(expr) && !is_window_function(expr))
+    }
+
+    /// Merge a user-written GROUP BY with the generated positional one.
+    /// User items that match a projected expression become positional, so
+    /// nothing is grouped twice.
+    fn merge_group_by(
+        &self,
+        user: Option<GroupBy>,
+        generated: Option<GroupBy>,
+        projection: &[Expression],
+        model_refs: &[(String, String)],
+    ) -> Result<Option<GroupBy>> {
+        let Some(mut group_by) = user else {
+            return Ok(generated);
+        };
+
+        let projected = projection
+            .iter()
+            .map(|item| match item {
+                Expression::Alias(alias) => expr_to_sql(&alias.this),
+                other => expr_to_sql(other),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut seen = HashSet::new();
+        let mut expressions = Vec::new();
+        let items = std::mem::take(&mut group_by.expressions)
+            .into_iter()
+            .chain(generated.into_iter().flat_map(|g| g.expressions));
+        for item in items {
+            let mut expr = self.rewrite_expr(item, model_refs)?;
+            let sql = expr_to_sql(&expr)?;
+            if let Some(position) = projected.iter().position(|p| *p == sql) {
+                expr = Expression::number((position + 1) as i64);
+            }
+            if seen.insert(expr_to_sql(&expr)?) {
+                expressions.push(expr);
+            }
+        }
+        group_by.expressions = expressions;
+
+        Ok(Some(group_by))
     }
 
     /// Build GROUP BY clause from non-aggregated columns
@@ -1005,7 +1483,7 @@ impl<'a> QueryRewriter<'a> {
         let mut group_by_exprs = Vec::new();
 
         for (i, expr) in projection.iter().enumerate() {
-            if !self.is_aggregation(expr) {
+            if !self.is_aggregation(expr) && !is_window_function(expr) {
                 // Use positional reference
                 group_by_exprs.push(Expression::number((i + 1) as i64));
             }
@@ -1020,6 +1498,118 @@ impl<'a> QueryRewriter<'a> {
     }
 }
 
+/// Alias of the generated subquery wrapped by window metric queries
+const WINDOW_QUERY_ALIAS: &str = "windowed";
+
+/// A column of a window metric query resolved to its semantic reference
+enum WindowQueryField {
+    Metric { reference: String, window: bool },
+    Dimension { reference: String },
+}
+
+/// Fields of a window metric query: the semantic metrics and dimensions to
+/// generate, and the outer projection over the generated columns.
+#[derive(Default)]
+struct WindowQueryFields {
+    metrics: Vec<String>,
+    dimensions: Vec<String>,
+    /// `(reference, output alias)` pairs of the outer projection
+    projection: Vec<(String, Identifier)>,
+}
+
+impl WindowQueryFields {
+    fn add(&mut self, field: WindowQueryField, output_alias: Option<Identifier>) {
+        let (fields, reference) = match field {
+            WindowQueryField::Metric { reference, .. } => (&mut self.metrics, reference),
+            WindowQueryField::Dimension { reference, .. } => (&mut self.dimensions, reference),
+        };
+        if !fields.contains(&reference) {
+            fields.push(reference.clone());
+        }
+        if let Some(output_alias) = output_alias {
+            self.projection.push((reference, output_alias));
+        }
+    }
+
+    /// Map each field reference to the column the generated query projects
+    /// for it. The generator prefixes names shared by several models with the
+    /// model (`orders_status`), so the names are read back from its projection.
+    fn output_columns(&self, generated: &str) -> Result<HashMap<String, String>> {
+        let statements = parse_sql_with_large_stack(generated)?;
+        let Some(Expression::Select(select)) = statements.first() else {
+            return Err(SidemanticError::SqlGeneration(
+                "Failed to read the generated window metric query".into(),
+            ));
+        };
+        let projected: HashSet<&str> = select
+            .expressions
+            .iter()
+            .filter_map(|expr| match expr {
+                Expression::Alias(alias) => Some(alias.alias.name.as_str()),
+                Expression::Column(column) => Some(column.name.name.as_str()),
+                _ => None,
+            })
+            .collect();
+
+        self.dimensions
+            .iter()
+            .chain(&self.metrics)
+            .map(|reference| {
+                let (model, column) = reference.split_once('.').unwrap_or(("", reference));
+                let prefixed = format!("{model}_{column}");
+                let output = [column, prefixed.as_str()]
+                    .into_iter()
+                    .find(|name| projected.contains(name))
+                    .ok_or_else(|| {
+                        SidemanticError::SqlGeneration(format!(
+                            "Generated window metric query has no column for '{reference}'"
+                        ))
+                    })?;
+                Ok((reference.clone(), output.to_string()))
+            })
+            .collect()
+    }
+}
+
+/// Whether a GROUP BY item is a positional reference like `1`
+fn is_positional_ref(expr: &Expression) -> bool {
+    expr_to_sql(expr).is_ok_and(|sql| sql.parse::<usize>().is_ok())
+}
+
+/// Window functions are evaluated over the grouped result, so they are
+/// neither aggregates nor GROUP BY keys.
+fn is_window_function(expr: &Expression) -> bool {
+    match expr {
+        Expression::Alias(alias) => is_window_function(&alias.this),
+        Expression::WindowFunction(_) => true,
+        _ => false,
+    }
+}
+
+/// Metrics computed with window functions over the grouped result
+fn is_window_metric(metric: &crate::core::Metric) -> bool {
+    match metric.r#type {
+        MetricType::Cumulative | MetricType::TimeComparison | MetricType::PctOfTotal => true,
+        MetricType::Ratio => metric.offset_window.is_some(),
+        _ => false,
+    }
+}
+
+/// Split a predicate into its top-level AND conjuncts
+fn split_conjunction(expr: &Expression) -> Vec<Expression> {
+    match expr {
+        Expression::And(binary) => {
+            let mut conjuncts = split_conjunction(&binary.left);
+            conjuncts.extend(split_conjunction(&binary.right));
+            conjuncts
+        }
+        Expression::Paren(paren) if matches!(paren.this, Expression::And(_)) => {
+            split_conjunction(&paren.this)
+        }
+        other => vec![other.clone()],
+    }
+}
+
 fn parse_sql_with_large_stack(sql: &str) -> Result<Vec<Expression>> {
     #[cfg(target_arch = "wasm32")]
     {
@@ -1031,20 +1621,56 @@ fn parse_sql_with_large_stack(sql: &str) -> Result<Vec<Expression>> {
 
     #[cfg(not(target_arch = "wasm32"))]
     {
-        let sql_owned = sql.to_string();
-        let handle = std::thread::Builder::new()
-            .stack_size(16 * 1024 * 1024)
-            .spawn(move || {
-                polyglot_parse(&sql_owned, DialectType::Generic).map_err(|e| e.to_string())
-            })
-            .map_err(|e| SidemanticError::SqlParse(e.to_string()))?;
+        parse_all_with_large_stack(vec![sql.to_string()], DialectType::Generic)?
+            .pop()
+            .expect("one parse result per statement")
+            .map_err(SidemanticError::SqlParse)
+    }
+}
 
-        let parse_result = handle
-            .join()
-            .map_err(|_| SidemanticError::SqlParse("Polyglot parser thread panicked".into()))?;
+/// Parse each of `sqls` for `dialect` on a single thread with a large stack;
+/// polyglot's recursive-descent parser can exhaust a default-sized stack on
+/// nested expressions. Returns one result per input, in order.
+pub(super) fn parse_all_with_large_stack(
+    sqls: Vec<String>,
+    dialect: DialectType,
+) -> Result<Vec<std::result::Result<Vec<Expression>, String>>> {
+    run_with_large_stack(move || {
+        sqls.iter()
+            .map(|sql| polyglot_sql::parse(sql, dialect).map_err(|e| e.to_string()))
+            .collect::<Vec<_>>()
+    })
+    .map_err(|_| SidemanticError::SqlParse("Polyglot parser thread panicked".into()))
+}
 
-        parse_result.map_err(SidemanticError::SqlParse)
+/// Run `f` on a thread with a large stack, for polyglot calls that recurse
+/// once per level of a deeply nested tree.
+fn run_with_large_stack<T: Send>(f: impl FnOnce() -> T + Send) -> std::thread::Result<T> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        Ok(f())
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::thread::scope(|scope| {
+            std::thread::Builder::new()
+                .stack_size(16 * 1024 * 1024)
+                .spawn_scoped(scope, f)
+                .map_err(|e| Box::new(e) as Box<dyn std::any::Any + Send>)?
+                .join()
+        })
+    }
+}
+
+/// Replace nested queries with a placeholder so column collection only sees
+/// references that belong to the enclosing SELECT.
+fn strip_subqueries(expr: &Expression) -> Expression {
+    polyglot_sql::transform_map(expr.clone(), &|node| match node {
+        Expression::Subquery(_) | Expression::Select(_) => Ok(Expression::number(0)),
+        other => Ok(other),
+    })
+    .unwrap_or_else(|_| expr.clone())
 }
 
 fn parse_select_expr(expr_sql: &str) -> Option<Expression> {
@@ -1063,6 +1689,92 @@ fn parse_where_expr(condition_sql: &str) -> Option<Expression> {
     select.where_clause.as_ref().map(|w| w.this.clone())
 }
 
+/// Identifiers the user wrote quoted. Columns are keyed by their qualifier,
+/// so quoting `"Orders"."Status"` does not quote an unrelated `status`.
+#[derive(Default)]
+struct QuotedIdentifiers {
+    tables: HashSet<String>,
+    columns: HashSet<(Option<String>, String)>,
+    aliases: HashSet<String>,
+}
+
+impl QuotedIdentifiers {
+    fn is_empty(&self) -> bool {
+        self.tables.is_empty() && self.columns.is_empty() && self.aliases.is_empty()
+    }
+}
+
+fn quoted_identifiers(expr: &Expression) -> QuotedIdentifiers {
+    let quoted = std::cell::RefCell::new(QuotedIdentifiers::default());
+    let _ = polyglot_sql::transform_map(expr.clone(), &|node| {
+        let mut quoted = quoted.borrow_mut();
+        match &node {
+            Expression::Table(table) => {
+                for ident in std::iter::once(&table.name).chain(&table.alias) {
+                    if ident.quoted {
+                        quoted.tables.insert(ident.name.clone());
+                    }
+                }
+            }
+            Expression::Column(column) => {
+                if let Some(table) = column.table.as_ref().filter(|table| table.quoted) {
+                    quoted.tables.insert(table.name.clone());
+                }
+                if column.name.quoted {
+                    let qualifier = column.table.as_ref().map(|table| table.name.clone());
+                    quoted.columns.insert((qualifier, column.name.name.clone()));
+                    // A projected column becomes an output alias of that name.
+                    quoted.aliases.insert(column.name.name.clone());
+                }
+            }
+            Expression::Alias(alias) if alias.alias.quoted => {
+                quoted.aliases.insert(alias.alias.name.clone());
+            }
+            _ => {}
+        }
+        Ok(node)
+    });
+    quoted.into_inner()
+}
+
+/// Restore quoting on rewritten identifiers whose names were quoted in the input,
+/// so case-sensitive names survive the round trip. Runs on a large stack since
+/// a rewritten window query nests the whole generated query.
+fn requote_identifiers(expr: Expression, quoted: &QuotedIdentifiers) -> Result<Expression> {
+    if quoted.is_empty() {
+        return Ok(expr);
+    }
+    run_with_large_stack(|| requote_identifiers_in(expr, quoted))
+        .map_err(|_| SidemanticError::SqlGeneration("Identifier requoting panicked".into()))
+}
+
+fn requote_identifiers_in(expr: Expression, quoted: &QuotedIdentifiers) -> Expression {
+    polyglot_sql::transform_map(expr.clone(), &|mut node| {
+        match &mut node {
+            Expression::Table(table) => {
+                for ident in std::iter::once(&mut table.name).chain(table.alias.iter_mut()) {
+                    ident.quoted |= quoted.tables.contains(&ident.name);
+                }
+            }
+            Expression::Column(column) => {
+                let qualifier = column.table.as_ref().map(|table| table.name.clone());
+                column.name.quoted |= quoted
+                    .columns
+                    .contains(&(qualifier, column.name.name.clone()));
+                if let Some(table) = column.table.as_mut() {
+                    table.quoted |= quoted.tables.contains(&table.name);
+                }
+            }
+            Expression::Alias(alias) => {
+                alias.alias.quoted |= quoted.aliases.contains(&alias.alias.name);
+            }
+            _ => {}
+        }
+        Ok(node)
+    })
+    .unwrap_or(expr)
+}
+
 fn expr_to_sql(expr: &Expression) -> Result<String> {
     polyglot_generate(expr, DialectType::Generic)
         .map_err(|e| SidemanticError::SqlGeneration(e.to_string()))
@@ -1145,10 +1857,87 @@ fn column_alias_name(
     resolve_model_field(column, model_refs).map(|(_, _, _, _)| column.name.name.clone())
 }
 
+/// Dimensions removed from a wildcard expansion by `* EXCEPT (...)` or
+/// `* EXCLUDE (...)`. They are left out of the expansion rather than passed
+/// through, since the expanded dimensions also form the GROUP BY.
+fn star_exclusions(star: &Expression, model: &crate::core::Model) -> Result<HashSet<String>> {
+    let Expression::Star(star) = star else {
+        return Ok(HashSet::new());
+    };
+
+    let mut excluded = HashSet::new();
+    for ident in star.except.iter().flatten() {
+        let name = ident.name.as_str();
+        if model.get_dimension(name).is_none() {
+            let reason = if model.get_metric(name).is_some() {
+                "not a wildcard-expanded field"
+            } else {
+                "unknown field"
+            };
+            return Err(SidemanticError::Validation(format!(
+                "Cannot exclude '{}.{name}' from SELECT *: {reason}",
+                model.name
+            )));
+        }
+        excluded.insert(name.to_string());
+    }
+
+    Ok(excluded)
+}
+
 fn has_star_projection(projection: &[Expression]) -> bool {
-    projection
-        .iter()
-        .any(|expr| matches!(expr, Expression::Star(_)))
+    projection.iter().any(is_wildcard)
+}
+
+/// Whether a projection item is `*` or a qualified `model.*`
+fn is_wildcard(expr: &Expression) -> bool {
+    match expr {
+        Expression::Star(_) => true,
+        Expression::Column(column) => column.name.name == "*",
+        _ => false,
+    }
+}
+
+/// Table qualifier of a `model.*` wildcard, or `None` for a bare `*`
+fn wildcard_qualifier(expr: &Expression) -> Option<String> {
+    match expr {
+        Expression::Column(column) if column.name.name == "*" => {
+            column.table.as_ref().map(|table| table.name.clone())
+        }
+        Expression::Star(star) => star.table.as_ref().map(|table| table.name.clone()),
+        _ => None,
+    }
+}
+
+/// The model (and its alias) a wildcard expands: the qualifier of `model.*`,
+/// otherwise the single model in FROM.
+fn wildcard_model<'a>(
+    expr: &Expression,
+    model_refs: &'a [(String, String)],
+) -> Result<(&'a str, &'a str)> {
+    match wildcard_qualifier(expr) {
+        Some(table) => resolve_model_ref(&table, model_refs).ok_or_else(|| {
+            SidemanticError::Validation(format!("Unknown table '{table}' in {table}.*"))
+        }),
+        None if model_refs.len() == 1 => Ok((&model_refs[0].0, &model_refs[0].1)),
+        None => Err(SidemanticError::Validation(
+            "SELECT * requires a FROM clause with a single table".into(),
+        )),
+    }
+}
+
+/// Whether `expr` is `COUNT(*)` or `COUNT(1)`.
+fn is_row_count(expr: &Expression) -> bool {
+    match expr {
+        Expression::Count(count) if !count.distinct && count.filter.is_none() => {
+            count.star
+                || count
+                    .this
+                    .as_ref()
+                    .is_none_or(|arg| expr_to_sql(arg).is_ok_and(|sql| sql == "1"))
+        }
+        _ => false,
+    }
 }
 
 fn extract_aggregate_input(expr: &Expression) -> Option<Expression> {
@@ -1276,6 +2065,19 @@ fn dimension_to_expr(
     Expression::qualified_column(alias_name.to_string(), dimension.sql_expr().to_string())
 }
 
+/// A dimension as projected in SELECT: [`dimension_to_expr`], wrapped in the
+/// dimension's null label when it has one.
+fn dimension_select_expr(
+    alias_name: &str,
+    dimension: &crate::core::Dimension,
+    granularity: Option<&str>,
+) -> Expression {
+    dimension
+        .null_label_sql(&format!("{alias_name}.{}", dimension.sql_expr()))
+        .and_then(|sql| parse_select_expr(&sql))
+        .unwrap_or_else(|| dimension_to_expr(alias_name, dimension, granularity))
+}
+
 fn table_name_and_alias(source: &Expression) -> Option<(String, Option<String>)> {
     match source {
         Expression::Table(table) => Some((
@@ -1386,6 +2188,63 @@ mod tests {
         assert!(rewritten.contains("status"));
     }
 
+    #[test]
+    fn test_quoted_identifiers_are_preserved() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("Orders", "order_id")
+                    .with_table("Orders")
+                    .with_dimension(Dimension::categorical("Status"))
+                    .with_dimension(Dimension::categorical("Orders"))
+                    .with_metric(Metric::sum("Revenue", "amount")),
+            )
+            .unwrap();
+
+        let sql = QueryRewriter::new(&graph)
+            .rewrite(r#"SELECT "Orders"."Revenue", "Orders"."Status" FROM "Orders""#)
+            .unwrap();
+
+        assert!(sql.contains(r#"FROM "Orders""#), "{sql}");
+        assert!(sql.contains(r#"AS "Revenue""#), "{sql}");
+        assert!(sql.contains(r#""Orders"."Status""#), "{sql}");
+
+        // Quoting is keyed on the qualified reference: the unquoted `Orders`
+        // column keeps its spelling even though the table was quoted.
+        let sql = QueryRewriter::new(&graph)
+            .rewrite(r#"SELECT "Orders"."Revenue", "Orders".Orders FROM "Orders""#)
+            .unwrap();
+        assert!(sql.contains(r#""Orders".Orders AS Orders"#), "{sql}");
+    }
+
+    #[test]
+    fn test_count_distinct_matches_generator_rendering() {
+        let mut graph = create_test_graph();
+        let mut orders = graph.get_model("orders").unwrap().clone();
+        orders
+            .metrics
+            .push(Metric::count_distinct("unique_customers", "customer_id"));
+        graph.replace_model(orders).unwrap();
+
+        let rewritten = QueryRewriter::new(&graph)
+            .rewrite("SELECT orders.unique_customers, orders.status FROM orders")
+            .unwrap();
+        let generated = crate::sql::SqlGenerator::new(&graph)
+            .generate(
+                &crate::sql::SemanticQuery::new()
+                    .with_metrics(vec!["orders.unique_customers".into()])
+                    .with_dimensions(vec!["orders.status".into()]),
+            )
+            .unwrap();
+
+        // Both paths aggregate the same raw CTE column with the same call.
+        let expected = crate::core::Aggregation::CountDistinct
+            .call_sql("orders_cte.unique_customers_raw")
+            + " AS unique_customers";
+        assert!(rewritten.contains(&expected), "{rewritten}");
+        assert!(generated.contains(&expected), "{generated}");
+    }
+
     #[test]
     fn test_cross_model_join() {
         let graph = create_test_graph();
@@ -1454,6 +2313,164 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_in_subquery_over_semantic_model_is_rewritten() {
+        let graph = create_test_graph();
+        let rewriter = QueryRewriter::new(&graph);
+
+        let sql = "SELECT orders.revenue FROM orders \
+                   WHERE orders.status IN (SELECT orders.status FROM orders WHERE orders.order_date >= '2024-01-01')";
+        let rewritten = rewriter.rewrite(sql).unwrap();
+
+        assert_eq!(
+            rewritten.matches("public.orders").count(),
+            2,
+            "Expected both outer and inner FROM rewritten in: {rewritten}"
+        );
+        assert!(
+            rewritten.contains("created_at"),
+            "Expected inner dimension expanded in: {rewritten}"
+        );
+        assert!(
+            !rewritten.contains("order_date"),
+            "Unexpected raw semantic field in: {rewritten}"
+        );
+    }
+
+    #[test]
+    fn test_cte_and_derived_table_over_semantic_model_are_rewritten() {
+        let graph = create_test_graph();
+        let rewriter = QueryRewriter::new(&graph);
+
+        let cte = rewriter
+            .rewrite(
+                "WITH totals AS (SELECT orders.status, orders.revenue FROM orders) \
+                 SELECT totals.status, totals.revenue FROM totals",
+            )
+            .unwrap();
+        assert!(cte.contains("public.orders"), "{cte}");
+        assert!(cte.contains("SUM("), "{cte}");
+        assert!(cte.contains("FROM totals"), "{cte}");
+
+        let derived = rewriter
+            .rewrite(
+                "SELECT t.revenue FROM (SELECT orders.status, orders.revenue FROM orders) AS t \
+                 WHERE t.revenue > 100",
+            )
+            .unwrap();
+        assert!(derived.contains("public.orders"), "{derived}");
+        assert!(derived.contains("SUM("), "{derived}");
+        assert!(derived.contains("t.revenue > 100"), "{derived}");
+    }
+
+    #[test]
+    fn test_cte_shadowing_model_name_is_not_rewritten() {
+        let graph = create_test_graph();
+        let rewriter = QueryRewriter::new(&graph);
+
+        let sql = rewriter
+            .rewrite(
+                "WITH orders AS (SELECT orders.revenue FROM orders) \
+                 SELECT orders.revenue FROM orders",
+            )
+            .unwrap();
+
+        assert_eq!(sql.matches("public.orders").count(), 1, "{sql}");
+        assert!(sql.ends_with("SELECT orders.revenue FROM orders"), "{sql}");
+    }
+
+    #[test]
+    fn test_union_all_rewrites_each_branch() {
+        let graph = create_test_graph();
+        let rewriter = QueryRewriter::new(&graph);
+
+        let sql = rewriter
+            .rewrite(
+                "SELECT orders.status, orders.revenue FROM orders WHERE orders.status = 'open' \
+                 UNION ALL \
+                 SELECT orders.status, orders.revenue FROM orders WHERE orders.status = 'closed'",
+            )
+            .unwrap();
+
+        assert!(sql.contains("UNION ALL"), "{sql}");
+        assert_eq!(sql.matches("public.orders").count(), 2, "{sql}");
+        assert_eq!(sql.matches("SUM(").count(), 2, "{sql}");
+        assert_eq!(sql.matches("GROUP BY").count(), 2, "{sql}");
+    }
+
+    #[test]
+    fn test_null_label_wraps_projected_dimension_only() {
+        let mut graph = create_test_graph();
+        let mut orders = graph.get_model("orders").unwrap().clone();
+        orders.dimensions[0] = Dimension::categorical("status").with_null_label("Unknown");
+        graph.replace_model(orders).unwrap();
+        let rewriter = QueryRewriter::new(&graph);
+
+        let sql = rewriter
+            .rewrite(
+                "SELECT orders.status, orders.revenue FROM orders WHERE orders.status IS NOT NULL",
+            )
+            .unwrap();
+        assert!(
+            sql.contains("COALESCE(orders.status, 'Unknown') AS status"),
+            "{sql}"
+        );
+        assert_eq!(sql.matches("'Unknown'").count(), 1, "{sql}");
+
+        let star = rewriter.rewrite("SELECT orders.* FROM orders").unwrap();
+        assert!(
+            star.contains("COALESCE(orders.status, 'Unknown') AS status"),
+            "{star}"
+        );
+        assert_eq!(star.matches("'Unknown'").count(), 1, "{star}");
+    }
+
+    #[test]
+    fn test_user_group_by_is_merged_not_duplicated() {
+        let graph = create_test_graph();
+        let rewriter = QueryRewriter::new(&graph);
+
+        let positional = rewriter
+            .rewrite("SELECT orders.status, orders.revenue FROM orders GROUP BY 1")
+            .unwrap();
+        assert!(positional.contains("GROUP BY 1"), "{positional}");
+        assert!(!positional.contains("GROUP BY 1, 1"), "{positional}");
+        assert!(!positional.contains("GROUP BY 1, 2"), "{positional}");
+
+        let named = rewriter
+            .rewrite("SELECT orders.status, orders.revenue FROM orders GROUP BY orders.status")
+            .unwrap();
+        assert!(named.contains("GROUP BY 1"), "{named}");
+        assert!(!named.contains("GROUP BY 1,"), "{named}");
+        assert!(!named.contains("GROUP BY orders.status"), "{named}");
+
+        // Grouping by an unprojected dimension keeps it alongside the
+        // projected one.
+        let extra = rewriter
+            .rewrite("SELECT orders.status, orders.revenue FROM orders GROUP BY orders.order_date")
+            .unwrap();
+        assert!(extra.contains("GROUP BY orders.created_at, 1"), "{extra}");
+    }
+
+    #[test]
+    fn test_subquery_model_refs_do_not_join_outer_query() {
+        let graph = create_test_graph();
+        let rewriter = QueryRewriter::new(&graph);
+
+        let sql = "SELECT orders.revenue FROM orders \
+                   WHERE orders.status IN (SELECT customers.name FROM customers WHERE customers.country = 'US')";
+        let rewritten = rewriter.rewrite(sql).unwrap();
+
+        assert!(
+            rewritten.contains("public.customers"),
+            "Expected inner FROM rewritten in: {rewritten}"
+        );
+        assert!(
+            !rewritten.to_uppercase().contains("JOIN"),
+            "Unexpected JOIN in: {rewritten}"
+        );
+    }
+
     #[test]
     fn test_count_without_sql() {
         // Test COUNT metric without explicit sql (simulates parsed definition)
@@ -1486,6 +2503,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_count_style_applies_to_count_metrics() {
+        let mut graph = SemanticGraph::new();
+        let orders = Model::new("orders", "order_id")
+            .with_table("orders")
+            .with_dimension(Dimension::categorical("status"))
+            .with_metric(Metric::count("order_count"))
+            .with_metric(Metric::count("completed_count").with_filter("{model}.status = 'done'"));
+        graph.add_model(orders).unwrap();
+        let rewriter = QueryRewriter::new(&graph).with_count_style(CountStyle::One);
+
+        let rewritten = rewriter
+            .rewrite("SELECT orders.status, orders.order_count FROM orders")
+            .unwrap();
+        assert!(rewritten.contains("COUNT(1)"), "{rewritten}");
+        assert!(!rewritten.contains("COUNT(*)"), "{rewritten}");
+
+        let filtered = rewriter
+            .rewrite("SELECT orders.completed_count FROM orders")
+            .unwrap();
+        assert!(filtered.contains("COUNT(1) FILTER(WHERE"), "{filtered}");
+        assert!(!filtered.contains("COUNT(*)"), "{filtered}");
+    }
+
     #[test]
     fn test_source_uri_only_model_rejects_rewrite() {
         let mut graph = SemanticGraph::new();
@@ -1578,4 +2619,261 @@ mod tests {
             "semantic metric reference leaked into ORDER BY: {rewritten}"
         );
     }
+
+    #[test]
+    fn test_order_by_semantic_expressions_and_granular_dimensions() {
+        let graph = create_test_graph();
+        let rewriter = QueryRewriter::new(&graph);
+
+        let sql = "SELECT orders.status, orders.order_date__month FROM orders \
+                   ORDER BY orders.revenue * 2 DESC, orders.order_date__month";
+        let rewritten = rewriter.rewrite(sql).unwrap();
+
+        assert!(
+            rewritten.contains("ORDER BY SUM(orders.amount) * 2 DESC, order_date__month"),
+            "expected rewritten sort expressions, got: {rewritten}"
+        );
+    }
+
+    #[test]
+    fn test_wildcard_expands_model_fields() {
+        let graph = create_test_graph();
+        let rewriter = QueryRewriter::new(&graph);
+
+        let bare = rewriter.rewrite("SELECT * FROM orders").unwrap();
+        assert!(bare.contains("orders.status AS status"), "{bare}");
+        assert!(bare.contains("orders.created_at AS order_date"), "{bare}");
+        // Wildcards expand to dimensions only; metrics must be selected by name.
+        assert!(!bare.contains("AS revenue"), "{bare}");
+        assert!(!bare.contains("SELECT *"), "{bare}");
+
+        let with_metric = rewriter
+            .rewrite("SELECT *, orders.revenue FROM orders")
+            .unwrap();
+        assert_eq!(
+            with_metric.matches("AS revenue ").count(),
+            1,
+            "{with_metric}"
+        );
+
+        let qualified = rewriter.rewrite("SELECT o.* FROM orders AS o").unwrap();
+        assert!(qualified.contains("o.status AS status"), "{qualified}");
+        assert!(
+            qualified.contains("o.created_at AS order_date"),
+            "{qualified}"
+        );
+        assert!(!qualified.contains(".*"), "{qualified}");
+
+        let quoted = rewriter
+            .rewrite(r#"SELECT "o".* FROM orders AS "o""#)
+            .unwrap();
+        assert!(quoted.contains("AS status"), "{quoted}");
+
+        let err = rewriter
+            .rewrite("SELECT missing.* FROM orders")
+            .unwrap_err();
+        assert!(err.to_string().contains("Unknown table 'missing'"), "{err}");
+    }
+
+    #[test]
+    fn test_star_except_omits_excluded_fields() {
+        let graph = create_test_graph();
+        let rewriter = QueryRewriter::new(&graph);
+
+        let rewritten = rewriter
+            .rewrite("SELECT * EXCEPT (status) FROM orders")
+            .unwrap();
+        assert!(rewritten.contains("AS order_date"), "{rewritten}");
+        assert!(!rewritten.contains("AS status"), "{rewritten}");
+        assert!(!rewritten.contains("EXCEPT"), "{rewritten}");
+
+        let err = rewriter
+            .rewrite("SELECT * EXCEPT (missing) FROM orders")
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Cannot exclude 'orders.missing' from SELECT *: unknown field"),
+            "{err}"
+        );
+
+        let err = rewriter
+            .rewrite("SELECT * EXCLUDE (order_count) FROM orders")
+            .unwrap_err();
+        assert!(
+            err.to_string().contains(
+                "Cannot exclude 'orders.order_count' from SELECT *: not a wildcard-expanded field"
+            ),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_window_function_over_semantic_references() {
+        let graph = create_test_graph();
+        let rewriter = QueryRewriter::new(&graph);
+
+        let sql = rewriter
+            .rewrite(
+                "SELECT orders.status, SUM(orders.revenue) OVER (PARTITION BY orders.status) AS share FROM orders",
+            )
+            .unwrap();
+
+        assert!(
+            sql.contains("SUM(SUM(orders.amount)) OVER (PARTITION BY orders.status) AS share"),
+            "{sql}"
+        );
+        assert!(sql.contains("GROUP BY 1"), "{sql}");
+        assert!(!sql.contains("GROUP BY 1, 2"), "{sql}");
+
+        // Errors inside window arguments surface instead of passing through.
+        let err = QueryRewriter::new(&graph)
+            .with_strict(true)
+            .rewrite(
+                "SELECT orders.status, SUM(orders.reveneu) OVER (PARTITION BY orders.status) AS share FROM orders",
+            )
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("Field 'orders.reveneu' not found"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_unknown_field_of_known_model() {
+        let graph = create_test_graph();
+        let rewriter = QueryRewriter::new(&graph).with_strict(true);
+
+        let err = rewriter
+            .rewrite("SELECT orders.status FROM orders WHERE orders.statsu = 'shipped'")
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("Field 'orders.statsu' not found"),
+            "{err}"
+        );
+
+        let rewritten = rewriter
+            .rewrite(
+                "SELECT orders.status, orders.revenue FROM orders WHERE orders.status = 'shipped'",
+            )
+            .unwrap();
+        assert!(rewritten.contains("SUM("), "{rewritten}");
+    }
+
+    #[test]
+    fn test_cumulative_metric_wraps_generated_query() {
+        let mut graph = create_test_graph();
+        let orders = graph
+            .get_model("orders")
+            .unwrap()
+            .clone()
+            .with_metric(Metric::cumulative("running_revenue", "orders.revenue"));
+        graph.replace_model(orders).unwrap();
+        let rewriter = QueryRewriter::new(&graph);
+
+        let sql = "SELECT orders.order_date__month, orders.running_revenue AS total \
+                   FROM orders \
+                   WHERE orders.status = 'complete' AND orders.running_revenue > 1000 \
+                   ORDER BY orders.order_date__month";
+        let rewritten = rewriter.rewrite(sql).unwrap();
+
+        assert!(rewritten.contains("OVER (ORDER BY"), "{rewritten}");
+        assert!(rewritten.contains("AS windowed"), "{rewritten}");
+        assert!(
+            rewritten.contains("windowed.running_revenue AS total"),
+            "{rewritten}"
+        );
+        // The row-level filter is pushed into the generated query; the filter
+        // on the window metric applies to the windowed result.
+        let inner_filter = rewritten.find("'complete'").unwrap();
+        let wrapper = rewritten.find("AS windowed").unwrap();
+        assert!(inner_filter < wrapper, "{rewritten}");
+        assert!(
+            rewritten.contains("windowed.running_revenue > 1000"),
+            "{rewritten}"
+        );
+        assert!(
+            rewritten.contains("ORDER BY windowed.order_date__month"),
+            "{rewritten}"
+        );
+    }
+
+    #[test]
+    fn test_window_query_keeps_quoted_aliases_and_distinct() {
+        let mut graph = create_test_graph();
+        let orders = graph
+            .get_model("orders")
+            .unwrap()
+            .clone()
+            .with_metric(Metric::cumulative("running_revenue", "orders.revenue"));
+        graph.replace_model(orders).unwrap();
+        let rewriter = QueryRewriter::new(&graph);
+
+        let rewritten = rewriter
+            .rewrite(
+                "SELECT DISTINCT orders.order_date__month, orders.running_revenue AS \"Running Total\" \
+                 FROM orders",
+            )
+            .unwrap();
+        assert!(rewritten.starts_with("SELECT DISTINCT "), "{rewritten}");
+        assert!(
+            rewritten.contains("windowed.running_revenue AS \"Running Total\""),
+            "{rewritten}"
+        );
+    }
+
+    #[test]
+    fn test_window_query_fields_read_output_columns_from_projection() {
+        let fields = WindowQueryFields {
+            metrics: vec!["orders.running_revenue".into()],
+            dimensions: vec!["orders.status".into(), "customers.status".into()],
+            projection: Vec::new(),
+        };
+
+        // Names shared by several models come out prefixed with the model.
+        let columns = fields
+            .output_columns(
+                "SELECT base.orders_status, base.customers_status, \
+                 SUM(base.revenue) OVER () AS running_revenue FROM base",
+            )
+            .unwrap();
+        assert_eq!(columns["orders.status"], "orders_status");
+        assert_eq!(columns["customers.status"], "customers_status");
+        assert_eq!(columns["orders.running_revenue"], "running_revenue");
+
+        let err = fields
+            .output_columns("SELECT base.running_revenue FROM base")
+            .unwrap_err();
+        assert!(err.to_string().contains("'orders.status'"), "{err}");
+    }
+
+    #[test]
+    fn test_filtered_metric_expands_dimension_references_for_dialect() {
+        let mut graph = SemanticGraph::new();
+        graph
+            .add_model(
+                Model::new("orders", "order_id")
+                    .with_table("orders")
+                    .with_dimension(Dimension::categorical("state").with_sql("order_state"))
+                    .with_metric(
+                        Metric::sum("completed_revenue", "amount")
+                            .with_filter("{model}.state = 'completed'"),
+                    ),
+            )
+            .unwrap();
+        let sql = "SELECT orders.completed_revenue FROM orders";
+
+        let rewritten = QueryRewriter::new(&graph).rewrite(sql).unwrap();
+        assert!(
+            rewritten.contains("FILTER(WHERE orders.order_state = 'completed')"),
+            "{rewritten}"
+        );
+
+        graph.set_dialect(DialectType::BigQuery);
+        let rewritten = QueryRewriter::new(&graph).rewrite(sql).unwrap();
+        assert!(
+            rewritten
+                .contains("SUM(CASE WHEN orders.order_state = 'completed' THEN orders.amount END)"),
+            "{rewritten}"
+        );
+    }
 }
@@ -0,0 +1,297 @@
+//! Round-trip fuzzing for the query rewriter.
+//!
+//! Generates random-but-valid semantic queries against a fixed graph,
+//! rewrites them, re-parses the output and checks invariants that every
+//! rewrite must uphold. Cases are driven by a seeded PRNG so failures are
+//! reproducible from the printed seed.
+
+use std::collections::HashSet;
+
+use polyglot_sql::DialectType;
+use regex::Regex;
+use sidemantic::{Dimension, Metric, Model, QueryRewriter, Relationship, SemanticGraph};
+
+const CASES: u64 = 256;
+
+/// Small xorshift PRNG; deterministic and dependency-free.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.next_u64() % 100 < percent
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    /// Up to `max` distinct items, at least `min`, in a random order.
+    fn sample<'a, T>(&mut self, items: &'a [T], min: usize, max: usize) -> Vec<&'a T> {
+        let count = min + self.below(max - min + 1);
+        let mut indices: Vec<usize> = (0..items.len()).collect();
+        for i in (1..indices.len()).rev() {
+            indices.swap(i, self.below(i + 1));
+        }
+        indices.into_iter().take(count).map(|i| &items[i]).collect()
+    }
+}
+
+fn fuzz_graph() -> SemanticGraph {
+    let mut graph = SemanticGraph::new();
+    graph
+        .add_model(
+            Model::new("orders", "order_id")
+                .with_table("public.orders")
+                .with_dimension(Dimension::categorical("status"))
+                .with_dimension(Dimension::time("order_date").with_sql("created_at"))
+                .with_metric(Metric::sum("revenue", "amount"))
+                .with_metric(Metric::count("order_count"))
+                .with_metric(Metric::avg("avg_amount", "amount"))
+                .with_relationship(Relationship::many_to_one("customers")),
+        )
+        .unwrap();
+    graph
+        .add_model(
+            Model::new("customers", "id")
+                .with_table("public.customers")
+                .with_dimension(Dimension::categorical("name"))
+                .with_dimension(Dimension::categorical("country")),
+        )
+        .unwrap();
+    graph
+}
+
+/// A generated query plus what the rewrite is expected to preserve.
+struct FuzzCase {
+    sql: String,
+    output_aliases: Vec<String>,
+    limit: Option<usize>,
+}
+
+fn generate_case(rng: &mut Rng) -> FuzzCase {
+    // Only the base model takes a FROM alias; joined models are always
+    // referenced by name.
+    let orders = if rng.chance(40) { "o" } else { "orders" };
+    let from = if orders == "orders" {
+        "orders".to_string()
+    } else if rng.chance(50) {
+        "orders AS o".to_string()
+    } else {
+        "orders o".to_string()
+    };
+
+    let metrics = rng.sample(&["revenue", "order_count", "avg_amount"], 1, 2);
+    let dimensions: Vec<String> = rng
+        .sample(
+            &[
+                format!("{orders}.status"),
+                format!("{orders}.order_date__month"),
+                "customers.country".to_string(),
+            ],
+            0,
+            2,
+        )
+        .into_iter()
+        .cloned()
+        .collect();
+
+    let mut projection = Vec::new();
+    let mut output_aliases = Vec::new();
+    for (i, metric) in metrics.iter().enumerate() {
+        if rng.chance(50) {
+            let alias = format!("m{i}");
+            projection.push(format!("{orders}.{metric} AS {alias}"));
+            output_aliases.push(alias);
+        } else {
+            projection.push(format!("{orders}.{metric}"));
+            output_aliases.push(metric.to_string());
+        }
+    }
+    for dimension in &dimensions {
+        projection.push(dimension.clone());
+    }
+    // Interleave dimensions and metrics to exercise projection ordering.
+    if rng.chance(50) {
+        projection.reverse();
+    }
+
+    let mut sql = format!("SELECT {} FROM {from}", projection.join(", "));
+
+    let filters = [
+        format!("{orders}.status = 'completed'"),
+        format!("{orders}.order_date >= '2024-01-01'"),
+        "customers.country = 'US'".to_string(),
+        format!("{orders}.status IN ('completed', 'shipped')"),
+    ];
+    let chosen_filters = rng.sample(&filters, 0, 2);
+    if !chosen_filters.is_empty() {
+        let joined: Vec<&str> = chosen_filters.iter().map(|f| f.as_str()).collect();
+        sql.push_str(&format!(" WHERE {}", joined.join(" AND ")));
+    }
+
+    if rng.chance(50) {
+        let direction = rng.pick(&["ASC", "DESC"]);
+        let target = if rng.chance(50) {
+            rng.pick(&output_aliases).clone()
+        } else {
+            format!("{orders}.{}", rng.pick(&metrics))
+        };
+        sql.push_str(&format!(" ORDER BY {target} {direction}"));
+    }
+
+    let limit = rng.chance(40).then(|| 1 + rng.below(100));
+    if let Some(limit) = limit {
+        sql.push_str(&format!(" LIMIT {limit}"));
+    }
+
+    FuzzCase {
+        sql,
+        output_aliases,
+        limit,
+    }
+}
+
+/// Parse on a large stack, as the rewriter does, so deep ASTs don't overflow
+/// the test thread.
+fn parses(sql: &str) -> Result<(), String> {
+    let sql = sql.to_string();
+    std::thread::Builder::new()
+        .stack_size(16 * 1024 * 1024)
+        .spawn(move || {
+            polyglot_sql::parse(&sql, DialectType::Generic)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        })
+        .expect("parser thread should spawn")
+        .join()
+        .expect("parser thread should not panic")
+}
+
+/// Names in FROM/JOIN position, excluding `EXTRACT(... FROM ...)` operands.
+fn referenced_relations(sql: &str) -> Vec<String> {
+    let extract_re = Regex::new(r"(?i)\bEXTRACT\s*\([^)]*\)").unwrap();
+    let relation_re = Regex::new(r"(?i)\b(?:FROM|JOIN)\s+([A-Za-z_][A-Za-z0-9_.]*)").unwrap();
+    let stripped = extract_re.replace_all(sql, "");
+    relation_re
+        .captures_iter(&stripped)
+        .map(|cap| cap[1].to_string())
+        .collect()
+}
+
+fn defined_ctes(sql: &str) -> HashSet<String> {
+    let cte_re = Regex::new(r"(?i)\b([A-Za-z_][A-Za-z0-9_]*)\s+AS\s+\(\s*SELECT\b").unwrap();
+    cte_re
+        .captures_iter(sql)
+        .map(|cap| cap[1].to_string())
+        .collect()
+}
+
+fn check_case(
+    rewriter: &QueryRewriter<'_>,
+    physical_tables: &HashSet<String>,
+    seed: u64,
+    case: &FuzzCase,
+) -> Result<(), String> {
+    let rewritten = rewriter
+        .rewrite(&case.sql)
+        .map_err(|e| format!("rewrite failed: {e}"))?;
+
+    // The output is valid SQL.
+    parses(&rewritten).map_err(|e| format!("rewritten SQL does not parse: {e}\n{rewritten}"))?;
+
+    // Only physical tables and CTEs defined by the rewrite are read.
+    let ctes = defined_ctes(&rewritten);
+    for relation in referenced_relations(&rewritten) {
+        if !physical_tables.contains(&relation) && !ctes.contains(&relation) {
+            return Err(format!(
+                "rewritten SQL reads non-physical relation '{relation}'\n{rewritten}"
+            ));
+        }
+    }
+
+    // Output column names survive, including explicit aliases.
+    for alias in &case.output_aliases {
+        if !Regex::new(&format!(r"(?i)\bAS\s+\W?{alias}\b"))
+            .unwrap()
+            .is_match(&rewritten)
+        {
+            return Err(format!("output alias '{alias}' was lost\n{rewritten}"));
+        }
+    }
+
+    // LIMIT is preserved verbatim.
+    if let Some(limit) = case.limit {
+        if !rewritten.contains(&format!("LIMIT {limit}")) {
+            return Err(format!("LIMIT {limit} was lost\n{rewritten}"));
+        }
+    }
+
+    // Rewriting is deterministic.
+    let again = rewriter
+        .rewrite(&case.sql)
+        .map_err(|e| format!("second rewrite failed: {e}"))?;
+    if again != rewritten {
+        return Err(format!(
+            "rewrite is not deterministic (seed {seed})\n{rewritten}\n---\n{again}"
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn rewriter_round_trip_fuzz() {
+    let graph = fuzz_graph();
+    let rewriter = QueryRewriter::new(&graph);
+    let physical_tables: HashSet<String> = graph
+        .models()
+        .map(|model| model.table_name().to_string())
+        .collect();
+
+    let mut failures = Vec::new();
+    for seed in 0..CASES {
+        let case = generate_case(&mut Rng::new(seed));
+        if let Err(reason) = check_case(&rewriter, &physical_tables, seed, &case) {
+            failures.push(format!("seed {seed}: {}\n  {reason}", case.sql));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {CASES} fuzz cases failed:\n{}",
+        failures.len(),
+        failures.join("\n\n")
+    );
+}
+
+#[test]
+fn fuzz_generator_is_reproducible() {
+    let first: Vec<String> = (0..16)
+        .map(|seed| generate_case(&mut Rng::new(seed)).sql)
+        .collect();
+    let second: Vec<String> = (0..16)
+        .map(|seed| generate_case(&mut Rng::new(seed)).sql)
+        .collect();
+    assert_eq!(first, second);
+    assert!(
+        first.iter().collect::<HashSet<_>>().len() > 8,
+        "generator should produce varied queries: {first:#?}"
+    );
+}